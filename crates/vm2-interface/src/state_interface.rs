@@ -1,10 +1,12 @@
+use std::ops::Range;
+
 use primitive_types::{H160, U256};
 
 /// Public interface of the VM state. Encompasses both read and write methods.
 pub trait StateInterface {
     /// Reads a register with the specified zero-based index. Returns a value together with a pointer flag.
     fn read_register(&self, register: u8) -> (U256, bool);
-    /// Sets a register with the specified zero-based index
+    /// Sets a register with the specified zero-based index. Writes to register 0 are ignored since it always contains zero.
     fn set_register(&mut self, register: u8, value: U256, is_pointer: bool);
 
     /// Returns a mutable handle to the current call frame.
@@ -14,12 +16,45 @@ pub trait StateInterface {
     /// Returns a mutable handle to a call frame with the specified index, where
     /// zero is the current frame, one is the frame before that etc.
     fn callframe(&mut self, n: usize) -> impl CallframeInterface + '_;
+    /// Returns the remaining amount of gas of the call frame with the specified index (see [`Self::callframe()`]).
+    fn callframe_gas(&mut self, n: usize) -> u32 {
+        self.callframe(n).gas()
+    }
+    /// Returns the total amount of gas remaining in all call frames, i.e., the sum of [`CallframeInterface::gas()`]
+    /// over all frames. Saturates at `u32::MAX`.
+    ///
+    /// The default implementation iterates over call frames; VM implementations should override it with a more efficient one.
+    fn gas_remaining(&mut self) -> u32 {
+        (0..self.number_of_callframes())
+            .map(|n| self.callframe_gas(n))
+            .fold(0, u32::saturating_add)
+    }
+    /// Marks the current [total remaining gas](Self::gas_remaining()), so that gas spent afterwards can be measured
+    /// using [`Self::gas_used_since()`].
+    fn gas_mark(&mut self) -> GasMark {
+        GasMark {
+            gas_remaining: self.gas_remaining(),
+        }
+    }
+    /// Returns the amount of gas spent since `mark` was obtained via [`Self::gas_mark()`], e.g. to check
+    /// the exact gas consumption of executed code. Returns 0 if more gas is remaining than before (e.g., after a refund).
+    fn gas_used_since(&mut self, mark: &GasMark) -> u32 {
+        mark.gas_remaining.saturating_sub(self.gas_remaining())
+    }
 
     /// Reads a single byte from the specified heap at the specified 0-based offset.
     fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8;
     /// Reads an entire `U256` word in the big-endian order from the specified heap / `offset`
     /// (which is the index of the most significant byte of the read value).
     fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256;
+    /// Reads bytes in the specified `range` of the specified heap, e.g. to extract returndata.
+    ///
+    /// The default implementation reads bytes one by one; VM implementations should override it with a more efficient one.
+    fn read_heap_range(&self, heap: HeapId, range: Range<u32>) -> Vec<u8> {
+        range
+            .map(|offset| self.read_heap_byte(heap, offset))
+            .collect()
+    }
     /// Writes an entire `U256` word in the big-endian order to the specified heap at the specified `offset`
     /// (which is the index of the most significant byte of the written value).
     fn write_heap_u256(&mut self, heap: HeapId, offset: u32, value: U256);
@@ -137,18 +172,21 @@ pub trait CallframeInterface {
     /// Returns ID of the main heap used in this call.
     fn heap(&self) -> HeapId;
     /// Returns the main heap boundary (number of paid bytes).
+    ///
+    /// The boundary is tracked for each far call and shared by its near calls. It starts at the memory stipend
+    /// of the frame and grows when the frame accesses the heap beyond it, charging gas for the growth.
     fn heap_bound(&self) -> u32;
     /// Sets the main heap boundary.
     fn set_heap_bound(&mut self, value: u32);
 
     /// Returns ID of the auxiliary heap used in this call.
     fn aux_heap(&self) -> HeapId;
-    /// Returns the auxiliary heap boundary (number of paid bytes).
+    /// Returns the auxiliary heap boundary (number of paid bytes). Tracked in the same way as [`Self::heap_bound()`].
     fn aux_heap_bound(&self) -> u32;
     /// Sets the auxiliary heap boundary.
     fn set_aux_heap_bound(&mut self, value: u32);
 
-    /// Reads a word from the bytecode of the executing contract.
+    /// Reads a word from the bytecode of the executing contract. Returns zero if `slot` is out of bounds.
     fn read_contract_code(&self, slot: u16) -> U256;
 }
 
@@ -178,6 +216,12 @@ impl HeapId {
     }
 }
 
+/// Total remaining gas at a certain point of execution, returned by [`StateInterface::gas_mark()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasMark {
+    gas_remaining: u32,
+}
+
 /// Event emitted by EraVM.
 ///
 /// There is no address field because nobody is interested in events that don't come
@@ -213,6 +257,52 @@ pub struct L2ToL1Log {
     pub tx_number: u16,
 }
 
+impl L2ToL1Log {
+    /// Length of the [leaf encoding](Self::to_leaf_bytes()) of a log in bytes.
+    pub const LEAF_ENCODING_LEN: usize = 88;
+
+    /// Serializes this log into its canonical encoding used as a leaf of the L2-to-L1 logs Merkle tree.
+    ///
+    /// The encoding is a concatenation of the shard ID (1 byte), the service flag (1 byte), the transaction number
+    /// (2 bytes, big-endian), the sender address (20 bytes), the key and the value (32 bytes each, big-endian).
+    pub fn to_leaf_bytes(&self) -> [u8; Self::LEAF_ENCODING_LEN] {
+        let mut bytes = [0_u8; Self::LEAF_ENCODING_LEN];
+        bytes[0] = self.shard_id;
+        bytes[1] = self.is_service.into();
+        bytes[2..4].copy_from_slice(&self.tx_number.to_be_bytes());
+        bytes[4..24].copy_from_slice(self.address.as_bytes());
+        self.key.to_big_endian(&mut bytes[24..56]);
+        self.value.to_big_endian(&mut bytes[56..]);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::{H160, U256};
+
+    use super::L2ToL1Log;
+
+    #[test]
+    fn l2_to_l1_log_leaf_encoding() {
+        let log = L2ToL1Log {
+            key: U256::from(0x_0102),
+            value: U256::MAX,
+            is_service: true,
+            address: H160::repeat_byte(0x23),
+            shard_id: 0,
+            tx_number: 0x_0a0b,
+        };
+        let bytes = log.to_leaf_bytes();
+
+        assert_eq!(bytes[..4], [0, 1, 0x0a, 0x0b]);
+        assert_eq!(bytes[4..24], [0x23; 20]);
+        assert_eq!(bytes[24..54], [0; 30]);
+        assert_eq!(bytes[54..56], [1, 2]);
+        assert_eq!(bytes[56..], [0xff; 32]);
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod testonly {
     use primitive_types::{H160, U256};
@@ -280,6 +280,12 @@ pub trait Tracer {
     ///
     /// The default implementation does nothing.
     fn on_extra_prover_cycles(&mut self, _stats: CycleStats) {}
+
+    /// Called when the current frame panics, just before the VM returns from it. This includes both explicit panics
+    /// (`ret.panic`) and spontaneous ones, e.g. running out of gas.
+    ///
+    /// The default implementation does nothing.
+    fn on_panic(&mut self, _reason: PanicReason) {}
 }
 
 /// Returned from [`Tracer::after_instruction`] to indicate if the VM should stop.
@@ -329,6 +335,28 @@ pub enum CycleStats {
     StorageWrite,
 }
 
+/// Reason of a frame panic supplied to [`Tracer::on_panic()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PanicReason {
+    /// The frame didn't have enough gas to execute an instruction, pay for memory growth or pubdata.
+    OutOfGas,
+    /// An invalid instruction was executed. Such an instruction burns all gas left in the frame.
+    InvalidInstruction,
+    /// A fat pointer was expected but a non-pointer value was provided, or the pointer was malformed
+    /// (e.g., its offset exceeded its length).
+    PointerMisuse,
+    /// A kernel-only instruction was executed in user mode, or an instruction changing state
+    /// was executed in a static context.
+    PrivilegeViolation,
+    /// A heap access was out of the addressable range.
+    HeapOutOfBounds,
+    /// The frame panicked explicitly using `ret.panic`.
+    Explicit,
+    /// Other reasons, e.g. calling an unsupported precompile or failing to decommit a called contract.
+    Other,
+}
+
 /// No-op tracer implementation.
 impl Tracer for () {}
 
@@ -352,12 +380,17 @@ impl<A: Tracer, B: Tracer> Tracer for (A, B) {
         self.0.on_extra_prover_cycles(stats);
         self.1.on_extra_prover_cycles(stats);
     }
+
+    fn on_panic(&mut self, reason: PanicReason) {
+        self.0.on_panic(reason);
+        self.1.on_panic(reason);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{CallingMode, OpcodeType};
-    use crate::{opcodes, testonly::DummyState, GlobalStateInterface, Tracer};
+    use crate::{opcodes, testonly::DummyState, GlobalStateInterface, PanicReason, Tracer};
 
     struct FarCallCounter(usize);
 
@@ -397,4 +430,21 @@ mod tests {
         assert_eq!(tracer.1 .0 .0, 1);
         assert_eq!(tracer.1 .1 .0, 1);
     }
+
+    #[derive(Default)]
+    struct PanicRecorder(Vec<PanicReason>);
+
+    impl Tracer for PanicRecorder {
+        fn on_panic(&mut self, reason: PanicReason) {
+            self.0.push(reason);
+        }
+    }
+
+    #[test]
+    fn aggregate_tracer_forwards_panics() {
+        let mut tracer = (PanicRecorder::default(), FarCallCounter(0));
+        tracer.on_panic(PanicReason::OutOfGas);
+        tracer.on_panic(PanicReason::Explicit);
+        assert_eq!(tracer.0 .0, [PanicReason::OutOfGas, PanicReason::Explicit]);
+    }
 }
@@ -0,0 +1,26 @@
+//! Benchmarks for decoding programs.
+
+use divan::Bencher;
+use primitive_types::U256;
+use zksync_vm2::{testonly::TestWorld, Program};
+
+/// Decodes a program with `1 << 16` words, which is truncated to the maximum number of instructions the VM
+/// can address. Compare runs with and without the `rayon` feature to see the effect of parallel decoding.
+#[divan::bench]
+fn decoding_large_program(bencher: Bencher) {
+    let bytecode = include_bytes!("../src/tests/bytecodes/call_far");
+    let words: Vec<_> = bytecode
+        .chunks_exact(32)
+        .map(U256::from_big_endian)
+        .cycle()
+        .take(1 << 16)
+        .collect();
+
+    bencher
+        .with_inputs(|| words.clone())
+        .bench_values(|words| Program::<(), TestWorld<()>>::from_words(words, false));
+}
+
+fn main() {
+    divan::main();
+}
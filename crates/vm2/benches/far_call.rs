@@ -0,0 +1,117 @@
+//! Benchmarks for far calls. Allocations are profiled to check that frames reuse buffers of exited frames.
+
+use divan::{black_box, AllocProfiler, Bencher};
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    testonly::{initial_decommit, TestWorld},
+    Instruction, ModeRequirements,
+    Predicate::Always,
+    Program, Settings, VirtualMachine,
+};
+use zksync_vm2_interface::opcodes;
+
+#[global_allocator]
+static ALLOC: AllocProfiler = AllocProfiler::system();
+
+#[divan::bench]
+fn repeated_far_call(bencher: Bencher) {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let called_address = Address::from_low_u64_be(0x_abe1_23ff);
+    let main_address = Address::from_low_u64_be(0x_abe1_2400);
+
+    let mut abi = U256::zero();
+    abi.0[3] = 10_000.into();
+    // Calls the other contract until running out of gas.
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 1,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r2).into(),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            Instruction::from_far_call::<opcodes::Normal>(
+                Register1(r1),
+                Register2(r2),
+                Immediate1(0),
+                false,
+                false,
+                Arguments::new(Always, 200, ModeRequirements::none()),
+            ),
+            Instruction::from_jump(
+                Immediate1(0).into(),
+                Register1(r0),
+                Arguments::new(Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![abi, called_address.to_low_u64_be().into()],
+    );
+    // Writes to its heap and returns.
+    let called_program = Program::from_raw(
+        vec![
+            Instruction::from_heap_write(
+                Register1(r0).into(),
+                Register2(r0),
+                None,
+                Arguments::new(Always, 5, ModeRequirements::none()),
+                false,
+            ),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[
+            (called_address, called_program.clone()),
+            (main_address, main_program.clone()),
+        ]);
+        let program = initial_decommit(&mut world, main_address);
+        let mut vm = VirtualMachine::new(
+            main_address,
+            program,
+            Address::zero(),
+            &[],
+            10_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
+fn main() {
+    divan::main();
+}
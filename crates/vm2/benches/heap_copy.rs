@@ -0,0 +1,76 @@
+//! Benchmarks for heap reads and writes.
+
+use divan::{black_box, Bencher};
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    Instruction, ModeRequirements,
+    Predicate::Always,
+    Program, Settings, VirtualMachine,
+};
+
+/// Copies heap words to unaligned addresses (so that some of them cross page boundaries) until running out of gas.
+#[divan::bench]
+fn heap_copy(bencher: Bencher) {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r3 = Register::new(3);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                Immediate1(1).into(),
+                Register2(r0),
+                Register1(r3).into(),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            Instruction::from_heap_read(
+                Register1(r1).into(),
+                Register1(r2),
+                Some(Register2(r1)),
+                Arguments::new(Always, 7, ModeRequirements::none()),
+            ),
+            Instruction::from_heap_write(
+                Register1(r3).into(),
+                Register2(r2),
+                Some(Register1(r3)),
+                Arguments::new(Always, 7, ModeRequirements::none()),
+                false,
+            ),
+            Instruction::from_jump(
+                Immediate1(1).into(),
+                Register1(r0),
+                Arguments::new(Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_abe1_23ff);
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            2_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
+fn main() {
+    divan::main();
+}
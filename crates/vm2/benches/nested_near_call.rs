@@ -1,15 +1,213 @@
-//! Low-level benchmarks.
+//! Low-level benchmarks. Allocations are profiled to check that storage writes don't cause allocator churn
+//! beyond growing the world diff.
 
-use divan::{black_box, Bencher};
+use std::sync::Arc;
+
+use divan::{black_box, AllocProfiler, Bencher};
 use zkevm_opcode_defs::ethereum_types::Address;
 use zksync_vm2::{
     addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1, Register2},
+    allocator::PoolingAllocator,
     testonly::{initial_decommit, TestWorld},
-    Instruction, ModeRequirements,
+    Instruction, ModeRequirements, Predicate,
     Predicate::Always,
     Program, Settings, VirtualMachine,
 };
 
+#[global_allocator]
+static ALLOC: AllocProfiler = AllocProfiler::system();
+
+/// Increments a register in a loop until running out of gas. Since the loop body is trivial, this mostly measures
+/// instruction dispatch, which depends on how many instructions fit into the CPU cache.
+#[divan::bench]
+fn tight_loop(bencher: Bencher) {
+    let r1 = Register::new(1);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                Immediate1(1).into(),
+                Register2(r1),
+                Register1(r1).into(),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            Instruction::from_jump(
+                Immediate1(0).into(),
+                Register1(Register::new(0)),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_abe1_23ff);
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
+/// Like [`tight_loop()`], but with conditional instructions, half of which are skipped.
+#[divan::bench]
+fn conditional_loop(bencher: Bencher) {
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let add = |out, predicate, set_flags| {
+        Instruction::from_add(
+            Immediate1(1).into(),
+            Register2(r1),
+            Register1(out).into(),
+            Arguments::new(predicate, 6, ModeRequirements::none()),
+            false,
+            set_flags,
+        )
+    };
+    let program = Program::from_raw(
+        vec![
+            add(r1, Always, true),
+            add(r2, Predicate::IfEQ, false),
+            add(r2, Predicate::IfGT, false),
+            add(r2, Predicate::IfLT, false),
+            add(r2, Predicate::IfGE, false),
+            Instruction::from_jump(
+                Immediate1(0).into(),
+                Register1(Register::new(0)),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_abe1_23ff);
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
+/// Reads the heap in a loop. Heap reads check gas, mode requirements and heap bounds, which would panic, before doing
+/// their work. Compare with a build without `#[cold]` on the panic path to see its effect on the success path.
+#[divan::bench]
+fn heap_read_loop(bencher: Bencher) {
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_heap_read(
+                Immediate1(64).into(),
+                Register1(Register::new(1)),
+                None,
+                Arguments::new(Always, 7, ModeRequirements::none()),
+            ),
+            Instruction::from_jump(
+                Immediate1(0).into(),
+                Register1(Register::new(0)),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_abe1_23ff);
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
+/// Repeatedly near calls a function consisting of an invalid instruction, so that every iteration goes through
+/// the out-of-line panic path. Complements [`heap_read_loop()`], which only takes the success path.
+#[divan::bench]
+fn panicking_near_call_loop(bencher: Bencher) {
+    let r1 = Register::new(1);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                Immediate1(10).into(),
+                Register2(Register::new(0)),
+                Register1(r1).into(),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            // The exception handler is the near call itself, so the loop runs until the gas is exhausted.
+            Instruction::from_near_call(
+                Register1(r1),
+                Immediate1(2),
+                Immediate2(1),
+                Arguments::new(Always, 25, ModeRequirements::none()),
+            ),
+            Instruction::from_invalid(),
+        ],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_abe1_23ff);
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
 #[divan::bench]
 fn nested_near_call(bencher: Bencher) {
     let program = Program::from_raw(
@@ -45,6 +243,39 @@ fn nested_near_call(bencher: Bencher) {
     });
 }
 
+/// Like [`nested_near_call()`], but with VMs sharing a [`PoolingAllocator`]. Near call frames are stored
+/// in a buffer reused across VMs, so unlike in [`nested_near_call()`], it isn't reallocated as the nesting grows.
+#[divan::bench]
+fn nested_near_call_pooled(bencher: Bencher) {
+    let program = Program::from_raw(
+        vec![Instruction::from_near_call(
+            // zero means pass all gas
+            Register1(Register::new(0)),
+            Immediate1(0),
+            Immediate2(0),
+            Arguments::new(Always, 10, ModeRequirements::none()),
+        )],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_abe1_23ff);
+    let allocator = Arc::new(PoolingAllocator::new(64, 1));
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .gas(10_000_000)
+            .allocator(allocator.clone())
+            .build()
+            .unwrap();
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
 #[divan::bench]
 fn nested_near_call_with_storage_write(bencher: Bencher) {
     let program = Program::from_raw(
@@ -0,0 +1,79 @@
+//! Benchmarks for precompiles.
+
+use divan::{black_box, Bencher};
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2::{
+    addressing_modes::{Arguments, CodePage, Register, Register1, Register2, RegisterAndImmediate},
+    precompiles::KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+    testonly::{initial_decommit, TestWorld},
+    Instruction, ModeRequirements,
+    Predicate::Always,
+    Program, Settings, VirtualMachine,
+};
+
+/// Hashes `len` bytes of the heap using the keccak256 precompile.
+#[divan::bench(args = [1_024, 65_536, 1 << 20])]
+fn keccak256(bencher: Bencher, len: u64) {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+
+    // Input offset and length, output offset and length, default heaps.
+    let mut abi = U256::zero();
+    abi.0[0] = len << 32;
+    abi.0[1] = 1 << 32;
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            Instruction::from_precompile_call(
+                Register1(r1),
+                Register2(r0),
+                Register1(r2),
+                Arguments::new(Always, 6, ModeRequirements::none()),
+            ),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![abi],
+    );
+
+    let address = Address::from_low_u64_be(KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS.into());
+
+    bencher.bench(|| {
+        let mut world = TestWorld::new(&[(address, program.clone())]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        vm.run(black_box(&mut world), &mut ());
+    });
+}
+
+fn main() {
+    divan::main();
+}
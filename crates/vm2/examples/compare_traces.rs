@@ -0,0 +1,143 @@
+//! Compares instruction-level traces of two EraVM bytecodes and reports the first differing step.
+//!
+//! ```text
+//! cargo run --example compare_traces -- <LEFT> <RIGHT> [--gas <GAS>] [--calldata <HEX>]
+//! ```
+//!
+//! `LEFT` and `RIGHT` are paths to bytecode files, either raw or hex-encoded (with an optional `0x` prefix).
+//! Each bytecode is executed as the only contract in a [`TestWorld`]. The process exits with code 1
+//! if the traces differ.
+//!
+//! To compare two builds of the VM rather than two bytecodes, implement [`ReplayTarget`] for a previous version
+//! of the crate added as a renamed dependency and pass it to [`compare_targets()`] instead of a `BytecodeTarget`.
+
+use std::{env, fs, process};
+
+use primitive_types::H160;
+use zksync_vm2::{
+    replay::{compare_targets, ReplayTarget},
+    testonly::{initial_decommit, TestWorld},
+    tracers::{StructLog, StructLogTracer},
+    Program, VirtualMachine,
+};
+
+const USAGE: &str = "usage: compare_traces <LEFT> <RIGHT> [--gas <GAS>] [--calldata <HEX>]";
+
+const ADDRESS: H160 = H160::repeat_byte(0x23);
+
+/// Runs a bytecode with the provided calldata and gas.
+struct BytecodeTarget {
+    name: String,
+    bytecode: Vec<u8>,
+    calldata: Vec<u8>,
+    gas: u32,
+}
+
+impl ReplayTarget for BytecodeTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&mut self) -> Vec<StructLog> {
+        let program = Program::new(&self.bytecode, false);
+        let mut world = TestWorld::new(&[(ADDRESS, program)]);
+        let program = initial_decommit(&mut world, ADDRESS);
+        let mut vm = VirtualMachine::builder()
+            .address(ADDRESS)
+            .program(program)
+            .calldata(self.calldata.clone())
+            .gas(self.gas)
+            .build()
+            .expect("address and program are set");
+
+        let mut tracer = StructLogTracer::default();
+        vm.run(&mut world, &mut tracer);
+        tracer.take_logs()
+    }
+}
+
+struct Args {
+    paths: [String; 2],
+    gas: u32,
+    calldata: Vec<u8>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut paths = vec![];
+        let mut gas = u32::MAX;
+        let mut calldata = vec![];
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--gas" => {
+                    let value = args.next().ok_or("missing value for `--gas`")?;
+                    gas = value
+                        .parse()
+                        .map_err(|err| format!("invalid gas `{value}`: {err}"))?;
+                }
+                "--calldata" => {
+                    let value = args.next().ok_or("missing value for `--calldata`")?;
+                    calldata = decode_hex(value.as_bytes())
+                        .ok_or_else(|| format!("invalid calldata `{value}`"))?;
+                }
+                "-h" | "--help" => return Err(USAGE.to_owned()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+                _ => paths.push(arg),
+            }
+        }
+
+        let paths = <[String; 2]>::try_from(paths)
+            .map_err(|paths| format!("expected 2 bytecode paths, got {}", paths.len()))?;
+        Ok(Self {
+            paths,
+            gas,
+            calldata,
+        })
+    }
+}
+
+/// Decodes hex digits with an optional `0x` prefix, ignoring surrounding whitespace.
+fn decode_hex(text: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(text).ok()?.trim();
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    if digits.len() % 2 != 0 || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn read_bytecode(path: &str) -> Result<Vec<u8>, String> {
+    let contents = fs::read(path).map_err(|err| format!("cannot read `{path}`: {err}"))?;
+    Ok(decode_hex(&contents).unwrap_or(contents))
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        process::exit(2);
+    });
+
+    let [mut left, mut right] = args.paths.map(|path| {
+        let bytecode = read_bytecode(&path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(2);
+        });
+        BytecodeTarget {
+            name: path,
+            bytecode,
+            calldata: args.calldata.clone(),
+            gas: args.gas,
+        }
+    });
+
+    match compare_targets(&mut left, &mut right) {
+        None => println!("traces are equal"),
+        Some((divergence, [left_name, right_name])) => {
+            println!("--- {left_name}\n+++ {right_name}\n{divergence}");
+            process::exit(1);
+        }
+    }
+}
@@ -4,9 +4,10 @@
 use arbitrary::{Arbitrary, Unstructured};
 use enum_dispatch::enum_dispatch;
 use primitive_types::U256;
-use zkevm_opcode_defs::erase_fat_pointer_metadata;
 
-use crate::{mode_requirements::ModeRequirements, predication::Predicate};
+use crate::{
+    isa::erase_fat_pointer_metadata, mode_requirements::ModeRequirements, predication::Predicate,
+};
 
 pub(crate) trait Source {
     /// Get a word's value for non-pointer operations. (Pointers are erased.)
@@ -43,21 +44,34 @@ pub(crate) trait Destination {
     fn set_fat_ptr(args: &Arguments, state: &mut impl Addressable, value: U256);
 }
 
-/// The part of VM state that addressing modes need to operate on
-pub(crate) trait Addressable {
+/// The part of VM state that addressing modes need to operate on.
+///
+/// Implemented by the VM state; can be implemented by other types to resolve operands
+/// (e.g., via [`AnySource::read()`] and [`AnyDestination::write()`]) outside the VM.
+pub trait Addressable {
+    /// Returns mutable access to all 16 registers. Register 0 must always contain zero.
     fn registers(&mut self) -> &mut [U256; 16];
+    /// Returns mutable access to register pointer flags; the bit `i` corresponds to register `i`.
     fn register_pointer_flags(&mut self) -> &mut u16;
 
+    /// Reads the current frame's stack at the specified slot.
     fn read_stack(&mut self, slot: u16) -> U256;
+    /// Writes the current frame's stack at the specified slot. Doesn't change the slot pointer flag.
     fn write_stack(&mut self, slot: u16, value: U256);
+    /// Returns mutable access to the current frame's stack pointer.
     fn stack_pointer(&mut self) -> &mut u16;
 
+    /// Reads the pointer flag for the specified stack slot.
     fn read_stack_pointer_flag(&mut self, slot: u16) -> bool;
+    /// Sets the pointer flag for the specified stack slot.
     fn set_stack_pointer_flag(&mut self, slot: u16);
+    /// Clears the pointer flag for the specified stack slot.
     fn clear_stack_pointer_flag(&mut self, slot: u16);
 
+    /// Returns the code page of the currently executing program.
     fn code_page(&self) -> &[U256];
 
+    /// Checks whether the current frame is executed in kernel mode.
     fn in_kernel_mode(&self) -> bool;
 }
 
@@ -99,6 +113,10 @@ pub struct Arguments {
     static_gas_cost: u8,
 }
 
+// Immediates are stored inline since EraVM immediates are 16-bit; referencing them by an index into a pool
+// would take as much space as the immediates themselves.
+const _: () = assert!(std::mem::size_of::<Arguments>() == 8);
+
 pub(crate) const L1_MESSAGE_COST: u32 = 156_250;
 pub(crate) const SSTORE_COST: u32 = 5_511;
 pub(crate) const SLOAD_COST: u32 = 2_008;
@@ -127,7 +145,7 @@ impl Arguments {
     }
 
     #[allow(clippy::cast_possible_truncation)] // checked
-    const fn encode_static_gas_cost(x: u32) -> u8 {
+    pub(crate) const fn encode_static_gas_cost(x: u32) -> u8 {
         match x {
             L1_MESSAGE_COST => 1,
             SSTORE_COST => 2,
@@ -355,7 +373,7 @@ fn source_stack_address(args: &Arguments, state: &mut impl Addressable) -> u16 {
     compute_stack_address(state, args.source_registers.register1(), args.immediate1)
 }
 
-pub(crate) fn destination_stack_address(args: &Arguments, state: &mut impl Addressable) -> u16 {
+fn destination_stack_address(args: &Arguments, state: &mut impl Addressable) -> u16 {
     compute_stack_address(
         state,
         args.destination_registers.register1(),
@@ -418,6 +436,9 @@ impl StackAddressing for RelativeStack {
 
 /// Same as [`RelativeStack`], but moves the stack pointer on access (decreases it when reading data;
 /// increases when writing data).
+///
+/// Like all stack addressing, stack pointer arithmetic is performed modulo 2^16, so moving the pointer
+/// past either end of the stack wraps around instead of panicking. This matches the reference VM.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
 pub struct AdvanceStackPointer(pub RegisterAndImmediate);
@@ -445,6 +466,15 @@ impl StackAddressing for AdvanceStackPointer {
     }
 }
 
+impl AdvanceStackPointer {
+    /// Moves the stack pointer as encoded by the source and destination of a `nop` instruction,
+    /// i.e., decreases it by the source offset and then increases it by the destination offset.
+    pub(crate) fn adjust_stack_pointer(args: &Arguments, state: &mut impl Addressable) {
+        Self::address_for_get(args, state);
+        Self::address_for_set(args, state);
+    }
+}
+
 /// Absolute addressing into the code page of the currently executing program.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
@@ -482,6 +512,11 @@ impl Register {
         Self(n)
     }
 
+    /// Returns the 0-based index of this register.
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
     fn value(self, state: &mut impl Addressable) -> U256 {
         unsafe { *state.registers().get_unchecked(self.0 as usize) }
     }
@@ -552,6 +587,26 @@ pub enum AnySource {
     CodePage,
 }
 
+impl AnySource {
+    /// Reads the value and the pointer flag of this operand from `state`, the same way
+    /// an instruction using it as its first source would.
+    ///
+    /// Pointers are returned as is; [`AdvanceStackPointer`] moves the stack pointer in `state`.
+    pub fn read(self, state: &mut impl Addressable) -> (U256, bool) {
+        let args = operand_arguments().write_source(&self);
+        match self {
+            Self::Register1(_) => Register1::get_with_pointer_flag(&args, state),
+            Self::Immediate1(_) => Immediate1::get_with_pointer_flag(&args, state),
+            Self::AbsoluteStack(_) => AbsoluteStack::get_with_pointer_flag(&args, state),
+            Self::RelativeStack(_) => RelativeStack::get_with_pointer_flag(&args, state),
+            Self::AdvanceStackPointer(_) => {
+                AdvanceStackPointer::get_with_pointer_flag(&args, state)
+            }
+            Self::CodePage(_) => CodePage::get_with_pointer_flag(&args, state),
+        }
+    }
+}
+
 /// Register or immediate addressing modes required by some VM instructions.
 #[enum_dispatch(SourceWriter)]
 #[derive(Debug, Clone, Copy)]
@@ -593,3 +648,275 @@ pub enum AnyDestination {
     /// Relative stack addressing that updates the stack pointer on access.
     AdvanceStackPointer,
 }
+
+impl AnyDestination {
+    /// Writes `value` to this operand in `state` and sets or clears its pointer flag, the same way
+    /// an instruction using it as its first destination would.
+    ///
+    /// Writes to register 0 are discarded; [`AdvanceStackPointer`] moves the stack pointer in `state`.
+    pub fn write(self, state: &mut impl Addressable, value: U256, is_pointer: bool) {
+        fn write_to<D: Destination>(
+            args: &Arguments,
+            state: &mut impl Addressable,
+            value: U256,
+            is_pointer: bool,
+        ) {
+            if is_pointer {
+                D::set_fat_ptr(args, state, value);
+            } else {
+                D::set(args, state, value);
+            }
+        }
+
+        let args = operand_arguments().write_destination(&self);
+        match self {
+            Self::Register1(_) => write_to::<Register1>(&args, state, value, is_pointer),
+            Self::AbsoluteStack(_) => write_to::<AbsoluteStack>(&args, state, value, is_pointer),
+            Self::RelativeStack(_) => write_to::<RelativeStack>(&args, state, value, is_pointer),
+            Self::AdvanceStackPointer(_) => {
+                write_to::<AdvanceStackPointer>(&args, state, value, is_pointer);
+            }
+        }
+    }
+}
+
+/// Arguments with no operands encoded, used to resolve standalone operands.
+fn operand_arguments() -> Arguments {
+    Arguments::new(Predicate::Always, 0, ModeRequirements::none())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct MockState {
+        registers: [U256; 16],
+        register_pointer_flags: u16,
+        stack: HashMap<u16, U256>,
+        stack_pointer_flags: HashSet<u16>,
+        sp: u16,
+        code_page: Vec<U256>,
+        is_kernel: bool,
+    }
+
+    impl Addressable for MockState {
+        fn registers(&mut self) -> &mut [U256; 16] {
+            &mut self.registers
+        }
+
+        fn register_pointer_flags(&mut self) -> &mut u16 {
+            &mut self.register_pointer_flags
+        }
+
+        fn read_stack(&mut self, slot: u16) -> U256 {
+            self.stack.get(&slot).copied().unwrap_or_default()
+        }
+
+        fn write_stack(&mut self, slot: u16, value: U256) {
+            self.stack.insert(slot, value);
+        }
+
+        fn stack_pointer(&mut self) -> &mut u16 {
+            &mut self.sp
+        }
+
+        fn read_stack_pointer_flag(&mut self, slot: u16) -> bool {
+            self.stack_pointer_flags.contains(&slot)
+        }
+
+        fn set_stack_pointer_flag(&mut self, slot: u16) {
+            self.stack_pointer_flags.insert(slot);
+        }
+
+        fn clear_stack_pointer_flag(&mut self, slot: u16) {
+            self.stack_pointer_flags.remove(&slot);
+        }
+
+        fn code_page(&self) -> &[U256] {
+            &self.code_page
+        }
+
+        fn in_kernel_mode(&self) -> bool {
+            self.is_kernel
+        }
+    }
+
+    fn reg_and_imm(register: u8, immediate: u16) -> RegisterAndImmediate {
+        RegisterAndImmediate {
+            immediate,
+            register: Register::new(register),
+        }
+    }
+
+    #[test]
+    fn register_operands() {
+        let mut state = MockState::default();
+        let r3 = Register::new(3);
+        AnyDestination::Register1(Register1(r3)).write(&mut state, 42.into(), false);
+        assert_eq!(state.registers[3], 42.into());
+        assert_eq!(
+            AnySource::Register1(Register1(r3)).read(&mut state),
+            (42.into(), false)
+        );
+
+        AnyDestination::Register1(Register1(r3)).write(&mut state, 7.into(), true);
+        assert_eq!(state.register_pointer_flags, 1 << 3);
+        assert_eq!(
+            AnySource::Register1(Register1(r3)).read(&mut state),
+            (7.into(), true)
+        );
+    }
+
+    #[test]
+    fn zero_register_ignores_writes() {
+        let mut state = MockState::default();
+        let r0 = Register::new(0);
+        for is_pointer in [false, true] {
+            AnyDestination::Register1(Register1(r0)).write(&mut state, 42.into(), is_pointer);
+            <Register2 as Destination>::set_fat_ptr(
+                &operand_arguments().write_destination(&Register2(r0)),
+                &mut state,
+                42.into(),
+            );
+            assert_eq!(state.registers[0], U256::zero());
+            assert_eq!(state.register_pointer_flags, 0);
+            assert_eq!(
+                AnySource::Register1(Register1(r0)).read(&mut state),
+                (U256::zero(), false)
+            );
+        }
+    }
+
+    #[test]
+    fn immediate_operand() {
+        let mut state = MockState::default();
+        let source = AnySource::Immediate1(Immediate1(u16::MAX));
+        assert_eq!(source.read(&mut state), (u16::MAX.into(), false));
+    }
+
+    #[test]
+    fn absolute_stack_operands() {
+        let mut state = MockState {
+            sp: 100,
+            ..MockState::default()
+        };
+        state.registers[1] = 10.into();
+        AnyDestination::AbsoluteStack(AbsoluteStack(reg_and_imm(1, 5))).write(
+            &mut state,
+            42.into(),
+            true,
+        );
+        assert_eq!(state.stack[&15], 42.into());
+        assert!(state.stack_pointer_flags.contains(&15));
+        assert_eq!(
+            AnySource::AbsoluteStack(AbsoluteStack(reg_and_imm(0, 15))).read(&mut state),
+            (42.into(), true)
+        );
+        assert_eq!(state.sp, 100);
+    }
+
+    #[test]
+    fn absolute_stack_address_wraps() {
+        let mut state = MockState::default();
+        state.registers[1] = U256::from(u16::MAX) + 1 + 3;
+        AnyDestination::AbsoluteStack(AbsoluteStack(reg_and_imm(1, u16::MAX))).write(
+            &mut state,
+            1.into(),
+            false,
+        );
+        assert_eq!(state.stack[&2], 1.into());
+    }
+
+    #[test]
+    fn relative_stack_operands() {
+        let mut state = MockState {
+            sp: 100,
+            ..MockState::default()
+        };
+        state.registers[2] = 3.into();
+        AnyDestination::RelativeStack(RelativeStack(reg_and_imm(2, 4))).write(
+            &mut state,
+            42.into(),
+            false,
+        );
+        assert_eq!(state.stack[&93], 42.into());
+        assert_eq!(
+            AnySource::RelativeStack(RelativeStack(reg_and_imm(0, 7))).read(&mut state),
+            (42.into(), false)
+        );
+        assert_eq!(state.sp, 100);
+    }
+
+    #[test]
+    fn advance_stack_pointer_operands() {
+        let mut state = MockState {
+            sp: 100,
+            ..MockState::default()
+        };
+        let push = AnyDestination::AdvanceStackPointer(AdvanceStackPointer(reg_and_imm(0, 1)));
+        push.write(&mut state, 1.into(), false);
+        push.write(&mut state, 2.into(), false);
+        assert_eq!(state.sp, 102);
+        assert_eq!(state.stack[&100], 1.into());
+        assert_eq!(state.stack[&101], 2.into());
+
+        let pop = AnySource::AdvanceStackPointer(AdvanceStackPointer(reg_and_imm(0, 1)));
+        assert_eq!(pop.read(&mut state), (2.into(), false));
+        assert_eq!(pop.read(&mut state), (1.into(), false));
+        assert_eq!(state.sp, 100);
+    }
+
+    #[test]
+    fn advance_stack_pointer_wraps_around() {
+        let mut state = MockState {
+            sp: u16::MAX,
+            ..MockState::default()
+        };
+        let push = AnyDestination::AdvanceStackPointer(AdvanceStackPointer(reg_and_imm(0, 2)));
+        push.write(&mut state, 42.into(), false);
+        assert_eq!(state.stack[&u16::MAX], 42.into());
+        assert_eq!(state.sp, 1);
+
+        let pop = AnySource::AdvanceStackPointer(AdvanceStackPointer(reg_and_imm(0, 3)));
+        state.stack.insert(u16::MAX - 1, 23.into());
+        assert_eq!(pop.read(&mut state), (23.into(), false));
+        assert_eq!(state.sp, u16::MAX - 1);
+
+        // Only the low 16 bits of the register are used as an offset.
+        state.registers[1] = U256::from(u16::MAX) + 1;
+        let pop = AnySource::AdvanceStackPointer(AdvanceStackPointer(reg_and_imm(1, 0)));
+        pop.read(&mut state);
+        assert_eq!(state.sp, u16::MAX - 1);
+    }
+
+    #[test]
+    fn code_page_operand() {
+        let mut state = MockState {
+            code_page: vec![1.into(), 2.into(), 3.into()],
+            ..MockState::default()
+        };
+        state.registers[1] = 1.into();
+        let source = AnySource::CodePage(CodePage(reg_and_imm(1, 1)));
+        assert_eq!(source.read(&mut state), (3.into(), false));
+        let out_of_range = AnySource::CodePage(CodePage(reg_and_imm(1, 2)));
+        assert_eq!(out_of_range.read(&mut state), (U256::zero(), false));
+    }
+
+    #[test]
+    fn pointer_flag_is_hidden_outside_kernel_mode() {
+        let mut state = MockState::default();
+        state.registers[1] = 1.into();
+        state.register_pointer_flags = 1 << 1;
+        let args = operand_arguments().write_source(&Register1(Register::new(1)));
+        assert!(!Register1::get_with_pointer_flag_and_erasing(&args, &mut state).1);
+
+        state.is_kernel = true;
+        assert_eq!(
+            Register1::get_with_pointer_flag_and_erasing(&args, &mut state),
+            (1.into(), true)
+        );
+    }
+}
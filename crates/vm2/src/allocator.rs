@@ -0,0 +1,304 @@
+//! Allocation of memory buffers used by VMs.
+//!
+//! Each VM needs memory for its heaps and a large (2 MiB, or 128 KiB with the `small_stack` feature) stack for each
+//! active callframe, and buffers for near call frames of each callframe. While a VM reuses buffers freed during
+//! its execution, by default, buffers are obtained
+//! from the global allocator when a VM is created and released when it's dropped. An [`Allocator`] specified via
+//! [`VirtualMachineBuilder::allocator()`](crate::VirtualMachineBuilder::allocator()) allows to reuse buffers
+//! across VMs instead; e.g., [`PoolingAllocator`] keeps buffers released by dropped VMs to reuse them in new VMs.
+
+use std::{
+    fmt,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{callframe::NearCallFrame, stack::Stack};
+
+/// Heap page size in bytes. With the `small_heap_pages` feature, pages are 1 KiB instead of 4 KiB, which reduces
+/// memory used by VMs with many small heaps at the cost of more page lookups. Heap contents don't depend
+/// on the page size.
+pub const HEAP_PAGE_SIZE: usize = if cfg!(feature = "small_heap_pages") {
+    1 << 10
+} else {
+    1 << 12
+};
+
+/// Heap page buffer.
+pub type HeapPageBuffer = Box<[u8; HEAP_PAGE_SIZE]>;
+
+/// Allocates a zeroed heap page using the global allocator.
+pub(crate) fn zeroed_heap_page() -> HeapPageBuffer {
+    let boxed_slice: Box<[u8]> = vec![0_u8; HEAP_PAGE_SIZE].into();
+    boxed_slice.try_into().unwrap()
+}
+
+/// Opaque buffer for a callframe stack.
+pub struct StackBuffer(pub(crate) Box<Stack>);
+
+impl fmt::Debug for StackBuffer {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("StackBuffer")
+            .finish_non_exhaustive()
+    }
+}
+
+impl StackBuffer {
+    /// Allocates a zeroed stack using the global allocator.
+    #[allow(clippy::new_without_default)] // allocating a large buffer shouldn't be implicit
+    pub fn new() -> Self {
+        Self(Stack::new())
+    }
+}
+
+/// Opaque buffer for near call frames of a callframe. Deeply nested near calls grow the buffer, so reusing it
+/// avoids reallocating it for each VM.
+#[derive(Default)]
+pub struct NearCallBuffer(pub(crate) Vec<NearCallFrame>);
+
+impl fmt::Debug for NearCallBuffer {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("NearCallBuffer")
+            .field("capacity", &self.0.capacity())
+            .finish()
+    }
+}
+
+/// Allocator of memory buffers for VMs. The VM may call its methods from any thread.
+///
+/// The default method implementations use the global allocator.
+pub trait Allocator: fmt::Debug + Send + Sync {
+    /// Allocates a heap page. The page may contain arbitrary data; the VM zeroes it before use.
+    fn allocate_heap_page(&self) -> HeapPageBuffer {
+        zeroed_heap_page()
+    }
+
+    /// Releases a heap page that is no longer used by a VM.
+    fn free_heap_page(&self, page: HeapPageBuffer) {
+        drop(page);
+    }
+
+    /// Allocates a stack. The stack may be returned from [`Self::free_stack()`] without modifications;
+    /// the VM zeroes it before use.
+    fn allocate_stack(&self) -> StackBuffer {
+        StackBuffer::new()
+    }
+
+    /// Releases a stack that is no longer used by a VM.
+    fn free_stack(&self, stack: StackBuffer) {
+        drop(stack);
+    }
+
+    /// Allocates a buffer for near call frames. The buffer may be returned from [`Self::free_near_call_buffer()`]
+    /// without modifications; the VM clears it before use. The default implementation returns an empty buffer,
+    /// which doesn't allocate until the first near call.
+    fn allocate_near_call_buffer(&self) -> NearCallBuffer {
+        NearCallBuffer::default()
+    }
+
+    /// Releases a near call buffer that is no longer used by a VM.
+    fn free_near_call_buffer(&self, buffer: NearCallBuffer) {
+        drop(buffer);
+    }
+}
+
+/// [`Allocator`] keeping released buffers in a pool to reuse them for the following allocations.
+///
+/// The number of retained buffers is capped; buffers released when the pool is full are returned
+/// to the global allocator. Since each callframe has a stack and a near call buffer, near call buffers are capped
+/// by the same number as stacks.
+#[derive(Debug)]
+pub struct PoolingAllocator {
+    max_heap_pages: usize,
+    max_stacks: usize,
+    heap_pages: Mutex<Vec<HeapPageBuffer>>,
+    stacks: Mutex<Vec<StackBuffer>>,
+    near_call_buffers: Mutex<Vec<NearCallBuffer>>,
+}
+
+impl PoolingAllocator {
+    /// Creates an allocator retaining up to the specified number of heap pages and stacks.
+    pub fn new(max_heap_pages: usize, max_stacks: usize) -> Self {
+        Self {
+            max_heap_pages,
+            max_stacks,
+            heap_pages: Mutex::default(),
+            stacks: Mutex::default(),
+            near_call_buffers: Mutex::default(),
+        }
+    }
+
+    /// Returns the number of heap pages and stacks currently retained by this allocator.
+    pub fn pooled_buffers(&self) -> (usize, usize) {
+        (lock(&self.heap_pages).len(), lock(&self.stacks).len())
+    }
+
+    /// Returns the number of near call buffers currently retained by this allocator.
+    pub fn pooled_near_call_buffers(&self) -> usize {
+        lock(&self.near_call_buffers).len()
+    }
+}
+
+/// Locks a pool ignoring poisoning; pools are always in a consistent state.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Allocator for PoolingAllocator {
+    fn allocate_heap_page(&self) -> HeapPageBuffer {
+        lock(&self.heap_pages)
+            .pop()
+            .unwrap_or_else(zeroed_heap_page)
+    }
+
+    fn free_heap_page(&self, page: HeapPageBuffer) {
+        let mut pages = lock(&self.heap_pages);
+        if pages.len() < self.max_heap_pages {
+            pages.push(page);
+        }
+    }
+
+    fn allocate_stack(&self) -> StackBuffer {
+        lock(&self.stacks).pop().unwrap_or_else(StackBuffer::new)
+    }
+
+    fn free_stack(&self, stack: StackBuffer) {
+        let mut stacks = lock(&self.stacks);
+        if stacks.len() < self.max_stacks {
+            stacks.push(stack);
+        }
+    }
+
+    fn allocate_near_call_buffer(&self) -> NearCallBuffer {
+        lock(&self.near_call_buffers).pop().unwrap_or_default()
+    }
+
+    fn free_near_call_buffer(&self, buffer: NearCallBuffer) {
+        let mut buffers = lock(&self.near_call_buffers);
+        if buffers.len() < self.max_stacks {
+            buffers.push(buffer);
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use std::sync::Arc;
+
+    use primitive_types::U256;
+    use zkevm_opcode_defs::ethereum_types::Address;
+    use zksync_vm2_interface::{HeapId, StateInterface};
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1, Register2},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+    };
+
+    /// Runs a program writing a non-zero value to the specified heap offset.
+    fn run_vm(allocator: &Arc<PoolingAllocator>, offset: u16) -> VirtualMachine<(), TestWorld<()>> {
+        let r1 = Register::new(1);
+        let program = Program::from_raw(
+            vec![
+                Instruction::from_heap_write(
+                    Immediate1(offset).into(),
+                    Register2(r1),
+                    None,
+                    Arguments::new(Predicate::Always, 7, ModeRequirements::none()),
+                    false,
+                ),
+                Instruction::from_ret(
+                    Register1(Register::new(0)),
+                    None,
+                    Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+                ),
+            ],
+            vec![],
+        );
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .gas(100_000)
+            .allocator(allocator.clone())
+            .build()
+            .unwrap();
+        assert_eq!(
+            vm.run(&mut world, &mut ()),
+            ExecutionEnd::ProgramFinished(vec![])
+        );
+        vm
+    }
+
+    #[test]
+    fn reusing_buffers_across_vms() {
+        let allocator = Arc::new(PoolingAllocator::new(16, 4));
+        let vm = run_vm(&allocator, 10_000);
+        assert_eq!(allocator.pooled_buffers(), (0, 0));
+        assert_ne!(vm.read_heap_u256(HeapId::FIRST, 10_000), U256::zero());
+        drop(vm);
+        assert_eq!(allocator.pooled_buffers(), (1, 1));
+
+        // Writing to the same heap page reuses the page, which must be zeroed.
+        let vm = run_vm(&allocator, 10_032);
+        assert_eq!(allocator.pooled_buffers(), (0, 0));
+        assert_eq!(vm.read_heap_u256(HeapId::FIRST, 10_000), U256::zero());
+        drop(vm);
+        assert_eq!(allocator.pooled_buffers(), (1, 1));
+    }
+
+    #[test]
+    fn capping_pooled_buffers() {
+        let allocator = PoolingAllocator::new(1, 0);
+        allocator.free_heap_page(zeroed_heap_page());
+        allocator.free_heap_page(zeroed_heap_page());
+        allocator.free_stack(StackBuffer::new());
+        allocator.free_near_call_buffer(NearCallBuffer(Vec::with_capacity(1)));
+        assert_eq!(allocator.pooled_buffers(), (1, 0));
+        assert_eq!(allocator.pooled_near_call_buffers(), 0);
+    }
+
+    #[test]
+    fn reusing_near_call_buffers_across_vms() {
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let program = Program::from_raw(
+            vec![
+                Instruction::from_near_call(
+                    Register1(Register::new(0)),
+                    Immediate1(2),
+                    Immediate2(2),
+                    arguments(25),
+                ),
+                Instruction::from_ret(Register1(Register::new(0)), None, arguments(5)),
+                // Near call body
+                Instruction::from_ret(Register1(Register::new(0)), None, arguments(5)),
+            ],
+            vec![],
+        );
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let allocator = Arc::new(PoolingAllocator::new(16, 4));
+
+        for _ in 0..2 {
+            let program = initial_decommit(&mut world, address);
+            let mut vm: VirtualMachine<(), _> = VirtualMachine::builder()
+                .address(address)
+                .program(program)
+                .gas(100_000)
+                .allocator(allocator.clone())
+                .build()
+                .unwrap();
+            assert_eq!(allocator.pooled_near_call_buffers(), 0);
+            assert_eq!(
+                vm.run(&mut world, &mut ()),
+                ExecutionEnd::ProgramFinished(vec![])
+            );
+            drop(vm);
+            assert_eq!(allocator.pooled_near_call_buffers(), 1);
+        }
+    }
+}
@@ -0,0 +1,445 @@
+//! Static analysis of decoded programs.
+//!
+//! The analysis works on [`InstructionInfo`]s, e.g. ones obtained using [`InstructionInfo::decode_bytecode()`].
+//! It splits a program into basic blocks and computes a conservative stack effect and a worst-case static gas cost
+//! for each block. This is useful for contract audits and for precharging gas on the block level.
+
+use std::{collections::BTreeSet, fmt, ops::Range};
+
+use zksync_vm2_interface::Opcode;
+
+use crate::{
+    addressing_modes::{AnyDestination, AnySource},
+    InstructionInfo, Predicate,
+};
+
+/// Change of the stack pointer during execution of a [`BasicBlock`], relative to its value at the block start.
+///
+/// For simplicity, stack pointer arithmetic is not performed modulo 2^16, unlike in the VM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StackEffect {
+    /// Change of the stack pointer after the block is executed.
+    pub net: i32,
+    /// Minimum change of the stack pointer (i.e., how many stack slots below the initial stack pointer
+    /// may be popped); always non-positive.
+    pub min: i32,
+    /// Maximum change of the stack pointer; always non-negative.
+    pub max: i32,
+}
+
+/// Sequence of instructions that is only entered at the first instruction and only left after the last one
+/// (not counting panics, which can happen at any instruction).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Index of the first instruction in the block.
+    pub start: usize,
+    /// Index after the last instruction in the block.
+    pub end: usize,
+    /// Indices of blocks (i.e., their first instructions) that can be executed after this one,
+    /// including exception handlers of calls. Sorted and deduplicated.
+    pub successors: Vec<usize>,
+    /// Whether the block ends with a jump to an address known only at runtime, so that [`Self::successors`]
+    /// may be incomplete.
+    pub has_dynamic_successors: bool,
+    /// Stack pointer change, or `None` if it depends on register values.
+    pub stack_effect: Option<StackEffect>,
+    /// Sum of static gas costs of all instructions in the block. Instructions skipped because of their predicate
+    /// are still charged their static cost, so this is the exact static cost of executing the block.
+    pub static_gas: u32,
+    /// Whether any of the instructions in the block may charge gas in addition to its static cost
+    /// (e.g., for heap growth or pubdata) or pass gas to a callee. If so, `static_gas` is a lower bound.
+    pub has_dynamic_gas: bool,
+}
+
+/// Effect of an instruction on control flow.
+struct ControlFlow {
+    targets: Vec<usize>,
+    is_dynamic: bool,
+    falls_through: bool,
+}
+
+impl ControlFlow {
+    /// Returns `None` if the instruction doesn't end a basic block.
+    fn new(info: &InstructionInfo) -> Option<Self> {
+        // Conditional instructions may be skipped, in which case execution continues with the next instruction.
+        let is_conditional = !matches!(info.predicate, Predicate::Always);
+        let Some(opcode) = info.opcode else {
+            // Invalid instructions always panic.
+            return Some(Self {
+                targets: vec![],
+                is_dynamic: false,
+                falls_through: false,
+            });
+        };
+        let (targets, is_dynamic, falls_through) = match opcode {
+            Opcode::Jump => match info.src1 {
+                AnySource::Immediate1(target) => (vec![target.0], false, is_conditional),
+                _ => (vec![], true, is_conditional),
+            },
+            Opcode::Ret(_) if info.flags.to_label => (vec![info.imm1], false, is_conditional),
+            Opcode::Ret(_) => (vec![], false, is_conditional),
+            // Near calls return to the next instruction or to the exception handler.
+            Opcode::NearCall => (vec![info.imm1, info.imm2], false, true),
+            // Far calls return to the next instruction or to the exception handler.
+            Opcode::FarCall(_) => (vec![info.imm1], false, true),
+            _ => return None,
+        };
+        Some(Self {
+            targets: targets.into_iter().map(usize::from).collect(),
+            is_dynamic,
+            falls_through,
+        })
+    }
+}
+
+/// Checks whether the instruction may spend more gas than its static cost.
+fn has_dynamic_gas(info: &InstructionInfo) -> bool {
+    let Some(opcode) = info.opcode else {
+        // Invalid instructions burn all gas
+        return true;
+    };
+    matches!(
+        opcode,
+        Opcode::HeapRead
+            | Opcode::HeapWrite
+            | Opcode::AuxHeapRead
+            | Opcode::AuxHeapWrite
+            | Opcode::NearCall
+            | Opcode::FarCall(_)
+            | Opcode::Ret(_)
+            | Opcode::Decommit
+            | Opcode::PrecompileCall
+            | Opcode::StorageRead
+            | Opcode::StorageWrite
+            | Opcode::L2ToL1Message
+    )
+}
+
+/// Computes the stack effect of a sequence of instructions.
+fn stack_effect(instructions: &[InstructionInfo]) -> Option<StackEffect> {
+    let mut effect = StackEffect::default();
+    for info in instructions {
+        // Sources are read before destinations are written.
+        if let AnySource::AdvanceStackPointer(operand) = info.src1 {
+            if operand.0.register.index() != 0 {
+                return None;
+            }
+            effect.net -= i32::from(operand.0.immediate);
+            effect.min = effect.min.min(effect.net);
+        }
+        if let Some(AnyDestination::AdvanceStackPointer(operand)) = info.out {
+            if operand.0.register.index() != 0 {
+                return None;
+            }
+            effect.net += i32::from(operand.0.immediate);
+            effect.max = effect.max.max(effect.net);
+        }
+    }
+    Some(effect)
+}
+
+/// Splits a program into basic blocks ordered by their start.
+///
+/// Targets of jumps, returns to labels and calls outside the program are ignored. An empty program has no blocks.
+pub fn basic_blocks(instructions: &[InstructionInfo]) -> Vec<BasicBlock> {
+    let len = instructions.len();
+    let control_flows: Vec<_> = instructions.iter().map(ControlFlow::new).collect();
+
+    let mut leaders = BTreeSet::from([0]);
+    for (i, control_flow) in control_flows.iter().enumerate() {
+        if let Some(control_flow) = control_flow {
+            leaders.insert(i + 1);
+            leaders.extend(control_flow.targets.iter().copied());
+        }
+    }
+    let leaders: Vec<_> = leaders.into_iter().filter(|&i| i < len).collect();
+
+    let mut blocks = Vec::with_capacity(leaders.len());
+    for (i, &start) in leaders.iter().enumerate() {
+        let end = leaders.get(i + 1).copied().unwrap_or(len);
+        let block_instructions = &instructions[start..end];
+
+        let (mut successors, has_dynamic_successors) = match &control_flows[end - 1] {
+            Some(control_flow) => {
+                let mut successors = control_flow.targets.clone();
+                if control_flow.falls_through {
+                    successors.push(end);
+                }
+                (successors, control_flow.is_dynamic)
+            }
+            None => (vec![end], false),
+        };
+        successors.retain(|&successor| successor < len);
+        successors.sort_unstable();
+        successors.dedup();
+
+        blocks.push(BasicBlock {
+            start,
+            end,
+            successors,
+            has_dynamic_successors,
+            stack_effect: stack_effect(block_instructions),
+            static_gas: block_instructions
+                .iter()
+                .fold(0_u32, |acc, info| acc.saturating_add(info.gas_cost)),
+            has_dynamic_gas: block_instructions.iter().any(has_dynamic_gas),
+        });
+    }
+    blocks
+}
+
+/// Returns ranges of instruction indices that are unreachable from the program start, sorted and merged.
+///
+/// `blocks` must be obtained from [`basic_blocks()`]. Only static control flow is considered, i.e. targets
+/// of dynamic jumps are not followed; a report for a program using such jumps may contain reachable code.
+pub fn unreachable_code(blocks: &[BasicBlock]) -> Vec<Range<usize>> {
+    let mut is_reachable = vec![false; blocks.len()];
+    let mut queue = if blocks.is_empty() { vec![] } else { vec![0] };
+    while let Some(i) = queue.pop() {
+        if is_reachable[i] {
+            continue;
+        }
+        is_reachable[i] = true;
+        for successor in &blocks[i].successors {
+            if let Ok(j) = blocks.binary_search_by_key(successor, |block| block.start) {
+                queue.push(j);
+            }
+        }
+    }
+
+    let mut unreachable: Vec<Range<usize>> = vec![];
+    for (block, _) in blocks.iter().zip(is_reachable).filter(|(_, r)| !r) {
+        match unreachable.last_mut() {
+            Some(range) if range.end == block.start => range.end = block.end,
+            _ => unreachable.push(block.start..block.end),
+        }
+    }
+    unreachable
+}
+
+/// Set of instruction indices that are static targets of control flow, i.e. targets of jumps with an immediate
+/// destination, labels of returns, as well as destinations and exception handlers of calls. Obtained using
+/// [`jump_targets()`] or [`Program::jump_targets()`](crate::Program::jump_targets()).
+///
+/// The set is a bitmap covering all indices addressable by the program counter, so membership checks are O(1).
+#[derive(Clone, PartialEq, Eq)]
+pub struct JumpTargets(Box<[u64; 1 << 10]>);
+
+impl JumpTargets {
+    fn insert(&mut self, index: u16) {
+        self.0[usize::from(index >> 6)] |= 1 << (index & 63);
+    }
+
+    /// Checks whether the instruction with the specified index is a jump target.
+    pub fn contains(&self, index: u16) -> bool {
+        self.0[usize::from(index >> 6)] & (1 << (index & 63)) != 0
+    }
+
+    /// Iterates over jump targets in the ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..=u16::MAX).filter(|&index| self.contains(index))
+    }
+}
+
+impl fmt::Debug for JumpTargets {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Collects static control flow targets of a program. Like in [`basic_blocks()`], targets outside the program
+/// are ignored; targets of dynamic jumps are not known statically and thus are not included either.
+#[allow(clippy::cast_possible_truncation)] // targets are created from `u16` immediates
+pub fn jump_targets(instructions: &[InstructionInfo]) -> JumpTargets {
+    let mut targets = JumpTargets(Box::new([0; 1 << 10]));
+    let len = instructions.len();
+    for control_flow in instructions.iter().filter_map(ControlFlow::new) {
+        for &target in &control_flow.targets {
+            if target < len {
+                targets.insert(target as u16);
+            }
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use zksync_vm2_interface::ReturnType;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{
+            AdvanceStackPointer, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+        },
+        InstructionFlags, ModeRequirements,
+    };
+
+    fn info(opcode: Opcode, gas_cost: u32) -> InstructionInfo {
+        let r0 = Register::new(0);
+        InstructionInfo {
+            opcode: Some(opcode),
+            predicate: Predicate::Always,
+            mode_requirements: ModeRequirements::none(),
+            gas_cost,
+            src1: Register1(r0).into(),
+            src2: Register2(r0),
+            out: Some(Register1(r0).into()),
+            out2: Register2(r0),
+            imm1: 0,
+            imm2: 0,
+            flags: InstructionFlags::default(),
+        }
+    }
+
+    fn jump(target: u16, predicate: Predicate) -> InstructionInfo {
+        InstructionInfo {
+            src1: Immediate1(target).into(),
+            predicate,
+            ..info(Opcode::Jump, 6)
+        }
+    }
+
+    fn stack_operand(register: u8, immediate: u16) -> AdvanceStackPointer {
+        AdvanceStackPointer(RegisterAndImmediate {
+            immediate,
+            register: Register::new(register),
+        })
+    }
+
+    #[test]
+    fn splitting_into_blocks() {
+        let instructions = [
+            info(Opcode::Add, 6),
+            jump(4, Predicate::IfEQ),
+            info(Opcode::Sub, 6),
+            jump(0, Predicate::Always),
+            info(Opcode::HeapWrite, 7),
+            info(Opcode::Ret(ReturnType::Normal), 5),
+        ];
+        let blocks = basic_blocks(&instructions);
+
+        let ranges: Vec<_> = blocks
+            .iter()
+            .map(|block| (block.start, block.end))
+            .collect();
+        assert_eq!(ranges, [(0, 2), (2, 4), (4, 6)]);
+        let successors: Vec<_> = blocks
+            .iter()
+            .map(|block| block.successors.clone())
+            .collect();
+        assert_eq!(successors, [vec![2, 4], vec![0], vec![]]);
+
+        assert_eq!(blocks[0].static_gas, 12);
+        assert!(!blocks[0].has_dynamic_gas);
+        assert_eq!(blocks[2].static_gas, 12);
+        assert!(blocks[2].has_dynamic_gas);
+        assert!(blocks.iter().all(|block| !block.has_dynamic_successors));
+    }
+
+    #[test]
+    fn collecting_jump_targets() {
+        let instructions = [
+            jump(3, Predicate::IfEQ),
+            // Dynamic jump
+            info(Opcode::Jump, 6),
+            jump(100, Predicate::Always),
+            InstructionInfo {
+                imm1: 1,
+                imm2: 4,
+                ..info(Opcode::NearCall, 25)
+            },
+            info(Opcode::Ret(ReturnType::Normal), 5),
+        ];
+        let targets = jump_targets(&instructions);
+        assert!(targets.contains(3));
+        assert!(!targets.contains(0));
+        assert!(!targets.contains(100));
+        assert_eq!(targets.iter().collect::<Vec<_>>(), [1, 3, 4]);
+        assert_eq!(format!("{targets:?}"), "{1, 3, 4}");
+    }
+
+    #[test]
+    fn reporting_unreachable_code() {
+        let instructions = [
+            jump(3, Predicate::Always),
+            info(Opcode::Add, 6),
+            info(Opcode::Ret(ReturnType::Normal), 5),
+            jump(6, Predicate::IfGT),
+            info(Opcode::Ret(ReturnType::Normal), 5),
+            info(Opcode::Sub, 6),
+            info(Opcode::Ret(ReturnType::Revert), 5),
+            info(Opcode::Add, 6),
+        ];
+        let blocks = basic_blocks(&instructions);
+        assert_eq!(unreachable_code(&blocks), [1..3, 5..6, 7..8]);
+
+        assert!(unreachable_code(&[]).is_empty());
+    }
+
+    #[test]
+    fn dynamic_jumps_and_invalid_instructions() {
+        let instructions = [
+            info(Opcode::Jump, 6),
+            InstructionInfo {
+                opcode: None,
+                ..info(Opcode::Nop, 6)
+            },
+            info(Opcode::Add, 6),
+        ];
+        let blocks = basic_blocks(&instructions);
+
+        assert_eq!(blocks.len(), 3);
+        assert!(blocks[0].has_dynamic_successors);
+        assert!(blocks[0].successors.is_empty());
+        assert!(blocks[1].successors.is_empty());
+        assert!(blocks[1].has_dynamic_gas);
+        assert!(blocks[2].successors.is_empty());
+    }
+
+    #[test]
+    fn storage_accesses_have_dynamic_gas() {
+        let instructions = [
+            info(Opcode::Add, 6),
+            jump(3, Predicate::IfEQ),
+            info(Opcode::StorageRead, 2_008),
+            info(Opcode::StorageWrite, 5_511),
+        ];
+        let blocks = basic_blocks(&instructions);
+
+        assert_eq!(blocks.len(), 3);
+        assert!(!blocks[0].has_dynamic_gas);
+        assert!(blocks[1].has_dynamic_gas);
+        assert!(blocks[2].has_dynamic_gas);
+    }
+
+    #[test]
+    fn computing_stack_effect() {
+        let instructions = [
+            InstructionInfo {
+                src1: stack_operand(0, 2).into(),
+                out: Some(stack_operand(0, 5).into()),
+                ..info(Opcode::Nop, 6)
+            },
+            InstructionInfo {
+                src1: stack_operand(0, 4).into(),
+                ..info(Opcode::Add, 6)
+            },
+        ];
+        let blocks = basic_blocks(&instructions);
+        assert_eq!(
+            blocks[0].stack_effect,
+            Some(StackEffect {
+                net: -1,
+                min: -2,
+                max: 3,
+            })
+        );
+
+        let instructions = [InstructionInfo {
+            out: Some(stack_operand(1, 0).into()),
+            ..info(Opcode::Nop, 6)
+        }];
+        assert_eq!(basic_blocks(&instructions)[0].stack_effect, None);
+    }
+}
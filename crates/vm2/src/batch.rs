@@ -0,0 +1,263 @@
+//! Parallel execution of independent transactions.
+//!
+//! [`execute_batch()`] runs each transaction of a batch on a separate [`VirtualMachine`] instance. Transactions are
+//! first executed speculatively across several threads against the initial state of the world. Then, they are
+//! committed in order; a transaction that accessed a storage slot changed by a previously committed transaction
+//! is re-executed sequentially against the updated world. Thus, the outcome is the same as if all transactions
+//! were executed one after another.
+//!
+//! Only storage accesses are checked for conflicts; other state shared between transactions (e.g., bytecodes
+//! deployed by a transaction) must be provided by the world upfront.
+
+use std::{collections::BTreeSet, mem, thread};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::Tracer;
+
+use crate::{ExecutionEnd, VirtualMachine, World, WorldDiff};
+
+/// [`World`] that can be shared by transactions in a batch.
+pub trait BatchWorld<T: Tracer>: World<T> + Clone {
+    /// Persists a storage change made by a committed transaction, so that it's visible to the following transactions.
+    fn apply_storage_change(&mut self, contract: H160, key: U256, value: U256);
+}
+
+/// Outcome of executing a single transaction in a batch.
+#[derive(Debug)]
+pub struct TransactionResult<T> {
+    /// Changes made by the transaction.
+    pub world_diff: WorldDiff,
+    /// Tracer used for the execution.
+    pub tracer: T,
+    /// How the execution has ended.
+    pub end: ExecutionEnd,
+    /// Whether the transaction was re-executed sequentially because of a conflict with a previous transaction.
+    pub reexecuted: bool,
+}
+
+/// Executes a batch of transactions, using up to `threads` threads, and applies their storage changes to `world`.
+///
+/// Each transaction is represented by a closure creating a VM and a tracer; it receives the world the transaction
+/// will be executed against, e.g. to load the initial program. The VM is then [run](VirtualMachine::run()) until
+/// the first [`ExecutionEnd`] on the executing thread; only its [`WorldDiff`] is returned. Since a transaction may be executed twice (speculatively and after a conflict),
+/// the closure should not have side effects. Results are returned in the order of `transactions`.
+///
+/// Only storage changes of transactions ending with [`ExecutionEnd::ProgramFinished`] are applied to `world`.
+/// The returned [`WorldDiff`] of a reverted, panicked or suspended transaction still contains its changes
+/// for inspection, but they are not visible to the following transactions.
+///
+/// # Panics
+///
+/// Panics if `threads` is zero, or if executing any transaction panics.
+pub fn execute_batch<T, W, F>(
+    world: &mut W,
+    transactions: &[F],
+    threads: usize,
+) -> Vec<TransactionResult<T>>
+where
+    T: Tracer + Send,
+    W: BatchWorld<T> + Send,
+    F: Fn(&mut W) -> (VirtualMachine<T, W>, T) + Sync,
+{
+    assert!(threads > 0, "number of threads must be positive");
+    if transactions.is_empty() {
+        return vec![];
+    }
+
+    let chunk_size = transactions.len().div_ceil(threads);
+    let speculative: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = transactions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                // The VM never writes to the world directly, so transactions in a chunk can share its copy.
+                let mut world = world.clone();
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|transaction| execute(&mut world, transaction))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("transaction execution panicked"))
+            .collect()
+    });
+
+    let mut changed_slots = BTreeSet::new();
+    let mut results = Vec::with_capacity(transactions.len());
+    for ((world_diff, tracer, end), transaction) in speculative.into_iter().zip(transactions) {
+        let reexecuted = world_diff
+            .accessed_storage_slots()
+            .any(|slot| changed_slots.contains(slot));
+        let (world_diff, tracer, end) = if reexecuted {
+            execute(world, transaction)
+        } else {
+            (world_diff, tracer, end)
+        };
+
+        if matches!(end, ExecutionEnd::ProgramFinished(_)) {
+            for ((contract, key), change) in world_diff.get_storage_changes() {
+                world.apply_storage_change(contract, key, change.after);
+                changed_slots.insert((contract, key));
+            }
+        }
+        results.push(TransactionResult {
+            world_diff,
+            tracer,
+            end,
+            reexecuted,
+        });
+    }
+    results
+}
+
+fn execute<T: Tracer, W: World<T>>(
+    world: &mut W,
+    transaction: &impl Fn(&mut W) -> (VirtualMachine<T, W>, T),
+) -> (WorldDiff, T, ExecutionEnd) {
+    let (mut vm, mut tracer) = transaction(world);
+    let end = vm.run(world, &mut tracer);
+    (mem::take(&mut vm.world_diff), tracer, end)
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{
+            Arguments, Immediate1, Register, Register1, Register2, SLOAD_COST, SSTORE_COST,
+        },
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program, Settings, StorageInterface,
+    };
+
+    /// Program incrementing the storage slot 0 of the executing contract, and then reverting if `reverts` is set.
+    fn counter_program(reverts: bool) -> Program<(), TestWorld<()>> {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        Program::from_raw(
+            vec![
+                Instruction::from_storage_read(Register1(r0), Register1(r1), arguments(SLOAD_COST)),
+                Instruction::from_add(
+                    Immediate1(1).into(),
+                    Register2(r1),
+                    Register1(r1).into(),
+                    arguments(6),
+                    false,
+                    false,
+                ),
+                Instruction::from_storage_write(
+                    Register1(r0),
+                    Register2(r1),
+                    arguments(SSTORE_COST),
+                ),
+                if reverts {
+                    Instruction::from_revert(Register1(r0), None, arguments(5))
+                } else {
+                    Instruction::from_ret(Register1(r0), None, arguments(5))
+                },
+            ],
+            vec![],
+        )
+    }
+
+    fn transaction(
+        address: Address,
+    ) -> impl Fn(&mut TestWorld<()>) -> (VirtualMachine<(), TestWorld<()>>, ()) {
+        move |world| {
+            let program = initial_decommit(world, address);
+            let vm = VirtualMachine::new(
+                address,
+                program,
+                Address::zero(),
+                &[],
+                100_000,
+                Settings {
+                    default_aa_code_hash: [0; 32],
+                    evm_interpreter_code_hash: [0; 32],
+                    hook_address: 0,
+                },
+            );
+            (vm, ())
+        }
+    }
+
+    #[test]
+    fn executing_independent_transactions() {
+        let addresses: Vec<_> = (1..=4)
+            .map(|i| Address::from_low_u64_be(0x_1234_5678_0000 + i))
+            .collect();
+        let contracts: Vec<_> = addresses
+            .iter()
+            .map(|&address| (address, counter_program(false)))
+            .collect();
+        let mut world = TestWorld::new(&contracts);
+        let transactions: Vec<_> = addresses.iter().copied().map(transaction).collect();
+
+        let results = execute_batch(&mut world, &transactions, 2);
+        assert_eq!(results.len(), 4);
+        for (result, &address) in results.iter().zip(&addresses) {
+            assert_eq!(result.end, ExecutionEnd::ProgramFinished(vec![]));
+            assert!(!result.reexecuted);
+            assert_eq!(world.read_storage_value(address, U256::zero()), U256::one());
+        }
+    }
+
+    #[test]
+    fn reexecuting_conflicting_transactions() {
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, counter_program(false))]);
+        let transactions = vec![transaction(address); 3];
+
+        let results = execute_batch(&mut world, &transactions, 3);
+        let reexecuted: Vec<_> = results.iter().map(|result| result.reexecuted).collect();
+        assert_eq!(reexecuted, [false, true, true]);
+        assert_eq!(
+            results[2].world_diff.get_storage_state()[&(address, U256::zero())],
+            3.into()
+        );
+        assert_eq!(world.read_storage_value(address, U256::zero()), 3.into());
+    }
+
+    #[test]
+    fn reverted_transactions_dont_change_storage() {
+        let reverting_address = Address::from_low_u64_be(0x_1234_5678_0001);
+        let address = Address::from_low_u64_be(0x_1234_5678_0002);
+        let mut world = TestWorld::new(&[
+            (reverting_address, counter_program(true)),
+            (address, counter_program(false)),
+        ]);
+        let transactions = vec![
+            transaction(reverting_address),
+            transaction(address),
+            transaction(reverting_address),
+        ];
+
+        let results = execute_batch(&mut world, &transactions, 2);
+        let ends: Vec<_> = results.iter().map(|result| &result.end).collect();
+        assert_eq!(
+            ends,
+            [
+                &ExecutionEnd::Reverted(vec![]),
+                &ExecutionEnd::ProgramFinished(vec![]),
+                &ExecutionEnd::Reverted(vec![]),
+            ]
+        );
+        // The second execution of the reverting transaction doesn't observe the write made by the first one.
+        assert!(!results[2].reexecuted);
+        assert_eq!(
+            results[2].world_diff.get_storage_state()[&(reverting_address, U256::zero())],
+            U256::one()
+        );
+        assert_eq!(
+            world.read_storage_value(reverting_address, U256::zero()),
+            U256::zero()
+        );
+        assert_eq!(world.read_storage_value(address, U256::zero()), U256::one());
+    }
+}
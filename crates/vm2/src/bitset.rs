@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 /// Bitset with `1 << 16` elements. Used to store pointer flags for VM [`Stack`](crate::stack::Stack).
 #[derive(Clone, PartialEq, Debug, Hash)]
 pub(crate) struct Bitset([u64; 1 << 10]);
@@ -20,6 +22,12 @@ impl Bitset {
         let (slot, bit) = slot_and_bit(i);
         self.0[slot] &= !bit;
     }
+
+    /// Clears all elements in the specified range. Range bounds must be multiples of 64.
+    pub(crate) fn clear_range(&mut self, range: Range<usize>) {
+        debug_assert!(range.start % 64 == 0 && range.end % 64 == 0);
+        self.0[range.start / 64..range.end / 64].fill(0);
+    }
 }
 
 #[inline(always)]
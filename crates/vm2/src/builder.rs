@@ -0,0 +1,303 @@
+//! Builder for [`VirtualMachine`]s.
+
+use std::{error, fmt, sync::Arc};
+
+use primitive_types::H160;
+use zksync_vm2_interface::Tracer;
+
+use crate::{
+    allocator::Allocator,
+    metrics::Metrics,
+    precompiles::{Precompiles, PrecompilesOverride},
+    Program, RefundPolicy, Settings, VirtualMachine, World,
+};
+
+/// Error building a [`VirtualMachine`] using [`VirtualMachineBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// Address of the executed contract was not specified.
+    MissingAddress,
+    /// Executed program was not specified.
+    MissingProgram,
+    /// Calldata length doesn't fit into 32 bits.
+    CalldataTooLarge(usize),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingAddress => formatter.write_str("contract address is not specified"),
+            Self::MissingProgram => formatter.write_str("program is not specified"),
+            Self::CalldataTooLarge(len) => {
+                write!(formatter, "calldata length {len} doesn't fit into 32 bits")
+            }
+        }
+    }
+}
+
+impl error::Error for BuildError {}
+
+/// Builder for [`VirtualMachine`]s. Created using [`VirtualMachine::builder()`].
+///
+/// The contract address and program must be specified; other params have defaults:
+///
+/// - Caller: zero address
+/// - Calldata: empty
+/// - Gas: `u32::MAX`
+/// - Default AA and EVM interpreter code hashes: zeros
+/// - Hook address: 0
+/// - Refund policy: [immediate](RefundPolicy::immediate())
+/// - Pubdata charging: disabled
+/// - Memory limit: none
+/// - Allocator: global allocator
+/// - Precompiles: provided by the world
+/// - Metrics: not reported
+pub struct VirtualMachineBuilder<T, W> {
+    address: Option<H160>,
+    program: Option<Program<T, W>>,
+    caller: H160,
+    calldata: Vec<u8>,
+    gas: u32,
+    settings: Settings,
+    refund_policy: RefundPolicy,
+    charge_for_pubdata: bool,
+    memory_limit: Option<usize>,
+    allocator: Option<Arc<dyn Allocator>>,
+    precompiles: Option<PrecompilesOverride>,
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+impl<T, W> fmt::Debug for VirtualMachineBuilder<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("VirtualMachineBuilder")
+            .field("address", &self.address)
+            .field("program", &self.program)
+            .field("caller", &self.caller)
+            .field("calldata.len", &self.calldata.len())
+            .field("gas", &self.gas)
+            .field("settings", &self.settings)
+            .field("refund_policy", &self.refund_policy)
+            .field("charge_for_pubdata", &self.charge_for_pubdata)
+            .field("memory_limit", &self.memory_limit)
+            .field("allocator", &self.allocator)
+            .field("precompiles", &self.precompiles)
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
+impl<T, W> Default for VirtualMachineBuilder<T, W> {
+    fn default() -> Self {
+        Self {
+            address: None,
+            program: None,
+            caller: H160::zero(),
+            calldata: vec![],
+            gas: u32::MAX,
+            settings: Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+            refund_policy: RefundPolicy::immediate(),
+            charge_for_pubdata: false,
+            memory_limit: None,
+            allocator: None,
+            precompiles: None,
+            metrics: None,
+        }
+    }
+}
+
+impl<T: Tracer, W: World<T>> VirtualMachineBuilder<T, W> {
+    /// Sets the address of the executed contract.
+    #[must_use]
+    pub fn address(mut self, address: H160) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Sets the executed program.
+    #[must_use]
+    pub fn program(mut self, program: Program<T, W>) -> Self {
+        self.program = Some(program);
+        self
+    }
+
+    /// Sets the caller of the executed contract.
+    #[must_use]
+    pub fn caller(mut self, caller: H160) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    /// Sets calldata passed to the executed contract.
+    #[must_use]
+    pub fn calldata(mut self, calldata: impl Into<Vec<u8>>) -> Self {
+        self.calldata = calldata.into();
+        self
+    }
+
+    /// Sets gas available to the executed contract.
+    #[must_use]
+    pub fn gas(mut self, gas: u32) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    /// Sets the bytecode hash of the default account abstraction contract.
+    #[must_use]
+    pub fn default_aa_code_hash(mut self, hash: [u8; 32]) -> Self {
+        self.settings.default_aa_code_hash = hash;
+        self
+    }
+
+    /// Sets the bytecode hash of the EVM interpreter.
+    #[must_use]
+    pub fn evm_interpreter_code_hash(mut self, hash: [u8; 32]) -> Self {
+        self.settings.evm_interpreter_code_hash = hash;
+        self
+    }
+
+    /// Sets the bootloader heap address writing to which suspends execution.
+    #[must_use]
+    pub fn hook_address(mut self, address: u32) -> Self {
+        self.settings.hook_address = address;
+        self
+    }
+
+    /// Sets all settings at once, overriding previously set code hashes and hook address.
+    #[must_use]
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the rules for crediting gas refunds.
+    #[must_use]
+    pub fn refund_policy(mut self, policy: RefundPolicy) -> Self {
+        self.refund_policy = policy;
+        self
+    }
+
+    /// Enables or disables charging storage writes and L2-to-L1 messages for the pubdata they produce, at the rate
+    /// set by [`VirtualMachine::set_ergs_per_pubdata_byte()`]. Charging is disabled by default, matching `zk_evm`,
+    /// which leaves charging for pubdata to the bootloader.
+    #[must_use]
+    pub fn charge_for_pubdata(mut self, enabled: bool) -> Self {
+        self.charge_for_pubdata = enabled;
+        self
+    }
+
+    /// Sets the limit of memory usage in bytes; see [`VirtualMachine::set_memory_limit()`].
+    #[must_use]
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Sets the allocator for heap pages and stacks used by the VM.
+    #[must_use]
+    pub fn allocator(mut self, allocator: Arc<dyn Allocator>) -> Self {
+        self.allocator = Some(allocator);
+        self
+    }
+
+    /// Sets precompiles used by the VM instead of ones returned by [`World::precompiles()`]. This allows to offload
+    /// precompile computations, e.g. to native or GPU implementations. Parsing the precompile call ABI, charging gas
+    /// and writing the output to the heap is still performed by the VM.
+    #[must_use]
+    pub fn precompiles(mut self, precompiles: Arc<dyn Precompiles + Send + Sync>) -> Self {
+        self.precompiles = Some(PrecompilesOverride(precompiles));
+        self
+    }
+
+    /// Sets the receiver of metrics reported by the VM; see [`Metrics`] for details.
+    #[must_use]
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Validates the provided params and builds a VM.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required params are not specified, or the params are invalid.
+    pub fn build(self) -> Result<VirtualMachine<T, W>, BuildError> {
+        let address = self.address.ok_or(BuildError::MissingAddress)?;
+        let program = self.program.ok_or(BuildError::MissingProgram)?;
+        if u32::try_from(self.calldata.len()).is_err() {
+            return Err(BuildError::CalldataTooLarge(self.calldata.len()));
+        }
+
+        let mut vm = VirtualMachine::with_allocator(
+            address,
+            program,
+            self.caller,
+            &self.calldata,
+            self.gas,
+            self.settings,
+            self.allocator,
+        );
+        vm.refund_policy = self.refund_policy;
+        vm.charge_for_pubdata = self.charge_for_pubdata;
+        vm.set_memory_limit(self.memory_limit);
+        vm.precompiles = self.precompiles;
+        vm.metrics = self.metrics;
+        Ok(vm)
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+    use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate,
+    };
+
+    #[test]
+    fn building_vm() {
+        let program = Program::from_raw(
+            vec![Instruction::from_ret(
+                Register1(Register::new(0)),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            )],
+            vec![],
+        );
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let caller = Address::repeat_byte(1);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+
+        let builder = VirtualMachine::builder().program(program.clone());
+        assert_eq!(builder.build().err(), Some(BuildError::MissingAddress));
+        let builder = VirtualMachine::builder().address(address);
+        assert_eq!(builder.build().err(), Some(BuildError::MissingProgram));
+
+        let mut vm = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .caller(caller)
+            .calldata([1, 2, 3])
+            .gas(1_000)
+            .build()
+            .unwrap();
+        assert_eq!(vm.current_frame().address(), address);
+        assert_eq!(vm.current_frame().caller(), caller);
+        assert_eq!(vm.current_frame().gas(), 1_000);
+        assert_eq!(vm.settings.hook_address, 0);
+        assert_eq!(
+            vm.run(&mut world, &mut ()),
+            ExecutionEnd::ProgramFinished(vec![])
+        );
+    }
+}
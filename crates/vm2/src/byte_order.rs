@@ -0,0 +1,130 @@
+//! Conversions between [`U256`] and its 32-byte big-endian representation used by VM heaps.
+//!
+//! On `x86_64` with SSSE3 enabled at compile time (e.g., via `-C target-cpu=native`), bytes are reversed using two
+//! 16-byte shuffles; otherwise, a portable implementation converting each 64-bit limb separately is used.
+
+use primitive_types::U256;
+
+#[inline(always)]
+pub(crate) fn u256_from_be_bytes(bytes: &[u8; 32]) -> U256 {
+    imp::u256_from_be_bytes(bytes)
+}
+
+#[inline(always)]
+pub(crate) fn u256_to_be_bytes(value: &U256, bytes: &mut [u8; 32]) {
+    imp::u256_to_be_bytes(value, bytes);
+}
+
+#[cfg_attr(
+    all(target_arch = "x86_64", target_feature = "ssse3", not(test)),
+    allow(dead_code)
+)]
+mod portable {
+    use primitive_types::U256;
+
+    #[inline(always)]
+    pub(super) fn u256_from_be_bytes(bytes: &[u8; 32]) -> U256 {
+        let limb = |i: usize| {
+            let chunk = &bytes[24 - 8 * i..32 - 8 * i];
+            u64::from_be_bytes(chunk.try_into().unwrap())
+        };
+        U256([limb(0), limb(1), limb(2), limb(3)])
+    }
+
+    #[inline(always)]
+    pub(super) fn u256_to_be_bytes(value: &U256, bytes: &mut [u8; 32]) {
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(value.0.iter().rev()) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "ssse3"))]
+mod ssse3 {
+    use std::arch::x86_64::{
+        __m128i, _mm_loadu_si128, _mm_set_epi8, _mm_shuffle_epi8, _mm_storeu_si128,
+    };
+
+    use primitive_types::U256;
+
+    /// Writes 32 bytes at `src` to `dst` in the reverse byte order. Since `x86_64` is little-endian, reversing
+    /// the big-endian representation of a `U256` yields its in-memory representation (limbs from least to most
+    /// significant), and vice versa.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid for reading 32 bytes, and `dst` must be valid for writing 32 bytes. No alignment is required.
+    #[inline(always)]
+    unsafe fn reverse_bytes(src: *const u8, dst: *mut u8) {
+        let mask = _mm_set_epi8(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+        let low = _mm_loadu_si128(src.cast::<__m128i>());
+        let high = _mm_loadu_si128(src.add(16).cast::<__m128i>());
+        _mm_storeu_si128(dst.cast::<__m128i>(), _mm_shuffle_epi8(high, mask));
+        _mm_storeu_si128(dst.add(16).cast::<__m128i>(), _mm_shuffle_epi8(low, mask));
+    }
+
+    #[inline(always)]
+    pub(super) fn u256_from_be_bytes(bytes: &[u8; 32]) -> U256 {
+        let mut value = U256::zero();
+        // SAFETY: both buffers have 32 bytes.
+        unsafe { reverse_bytes(bytes.as_ptr(), value.0.as_mut_ptr().cast::<u8>()) };
+        value
+    }
+
+    #[inline(always)]
+    pub(super) fn u256_to_be_bytes(value: &U256, bytes: &mut [u8; 32]) {
+        // SAFETY: both buffers have 32 bytes.
+        unsafe { reverse_bytes(value.0.as_ptr().cast::<u8>(), bytes.as_mut_ptr()) };
+    }
+}
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "ssse3")))]
+use self::portable as imp;
+#[cfg(all(target_arch = "x86_64", target_feature = "ssse3"))]
+use self::ssse3 as imp;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_values() -> impl Iterator<Item = U256> {
+        let mut pattern = [0_u8; 32];
+        for (byte, value) in pattern.iter_mut().zip(1..) {
+            *byte = value;
+        }
+        let patterned = U256::from_big_endian(&pattern);
+        [
+            U256::zero(),
+            U256::one(),
+            U256::MAX,
+            patterned,
+            patterned << 8,
+            U256::from(u64::MAX) << 100,
+        ]
+        .into_iter()
+    }
+
+    #[test]
+    fn converting_u256_to_and_from_bytes() {
+        for value in sample_values() {
+            let mut bytes = [0; 32];
+            u256_to_be_bytes(&value, &mut bytes);
+            let mut expected = [0; 32];
+            value.to_big_endian(&mut expected);
+            assert_eq!(bytes, expected);
+            assert_eq!(u256_from_be_bytes(&bytes), value);
+        }
+    }
+
+    #[test]
+    fn portable_implementation_matches_primitive_types() {
+        for value in sample_values() {
+            let mut bytes = [0; 32];
+            portable::u256_to_be_bytes(&value, &mut bytes);
+            let mut expected = [0; 32];
+            value.to_big_endian(&mut expected);
+            assert_eq!(bytes, expected);
+            assert_eq!(portable::u256_from_be_bytes(&bytes), value);
+        }
+    }
+}
@@ -1,18 +1,20 @@
-use std::{mem, ptr};
+use std::{mem, ptr, sync::Arc};
 
 use primitive_types::H160;
-use zkevm_opcode_defs::system_params::{
-    NEW_EVM_FRAME_MEMORY_STIPEND, NEW_FRAME_MEMORY_STIPEND, NEW_KERNEL_FRAME_MEMORY_STIPEND,
-};
 use zksync_vm2_interface::{HeapId, Tracer};
 
 use crate::{
+    allocator::{Allocator, NearCallBuffer},
     decommit::is_kernel,
+    instruction::CompactInstruction,
     instruction_handlers::invalid_instruction,
+    isa::system_params::{
+        NEW_EVM_FRAME_MEMORY_STIPEND, NEW_FRAME_MEMORY_STIPEND, NEW_KERNEL_FRAME_MEMORY_STIPEND,
+    },
     program::Program,
-    stack::{Stack, StackSnapshot},
+    stack::{StackBox, StackSnapshot},
     world_diff::Snapshot,
-    Instruction, World,
+    World,
 };
 
 #[derive(Debug)]
@@ -24,11 +26,24 @@ pub(crate) struct Callframe<T, W> {
     pub(crate) context_u128: u128,
     pub(crate) is_static: bool,
     pub(crate) is_kernel: bool,
-    pub(crate) stack: Box<Stack>,
+    /// Shard of the executed contract. Since far calls to other shards always panic, this is currently always 0.
+    pub(crate) shard_id: u8,
+    /// Shard of the caller.
+    pub(crate) caller_shard_id: u8,
+    pub(crate) stack: StackBox,
     pub(crate) sp: u16,
     pub(crate) gas: u32,
+    /// Gas the frame has started with, including stipends.
+    pub(crate) initial_gas: u32,
+    /// Refunds accumulated by the frame that will be credited to the caller on return. Always zero
+    /// unless refunds are [deferred](crate::RefundPolicy::deferred()).
+    pub(crate) pending_refund: u32,
     pub(crate) near_calls: Vec<NearCallFrame>,
-    pub(crate) pc: *const Instruction<T, W>,
+    /// Pointer to the next instruction to execute. Always points either into `program` or to one of the static
+    /// panic / invalid instructions (see `spontaneous_panic()` and `invalid_instruction()`).
+    /// Every program ends with an instruction that doesn't advance the pointer, so advancing it by one
+    /// after executing an instruction keeps this invariant.
+    pub(crate) pc: *const CompactInstruction,
     pub(crate) program: Program<T, W>,
     pub(crate) heap: HeapId,
     pub(crate) aux_heap: HeapId,
@@ -48,15 +63,83 @@ pub(crate) struct Callframe<T, W> {
     pub(crate) world_before_this_frame: Snapshot,
 }
 
+// `pc` is the only field preventing `Send` and `Sync` from being derived automatically. It points either into
+// the instructions of `program`, which are kept alive by `program` on whichever thread the frame is,
+// or to `const` instructions. In both cases, the instruction is never mutated, so the pointer can be shared
+// and sent to another thread as freely as `program` itself.
+unsafe impl<T, W> Send for Callframe<T, W> where Program<T, W>: Send {}
+unsafe impl<T, W> Sync for Callframe<T, W> where Program<T, W>: Sync {}
+
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) struct NearCallFrame {
     pub(crate) exception_handler: u16,
     pub(crate) previous_frame_sp: u16,
     pub(crate) previous_frame_gas: u32,
     pub(crate) previous_frame_pc: u16,
+    /// Pending refund of the far call frame before the near call; it's restored if the near call fails.
+    pub(crate) previous_pending_refund: u32,
     world_before_this_frame: Snapshot,
 }
 
+/// Buffers of exited callframes retained to be reused by new frames, so that far calls don't allocate them anew.
+/// If an allocator is specified, near call buffers are obtained from it when the pool is empty, and are released
+/// to it when the pool is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct FrameBufferPool {
+    near_calls: Vec<Vec<NearCallFrame>>,
+    heap_lists: Vec<Vec<HeapId>>,
+    allocator: Option<Arc<dyn Allocator>>,
+}
+
+impl Drop for FrameBufferPool {
+    fn drop(&mut self) {
+        if let Some(allocator) = &self.allocator {
+            for near_calls in self.near_calls.drain(..) {
+                allocator.free_near_call_buffer(NearCallBuffer(near_calls));
+            }
+        }
+    }
+}
+
+impl FrameBufferPool {
+    pub(crate) fn new(allocator: Option<Arc<dyn Allocator>>) -> Self {
+        Self {
+            near_calls: vec![],
+            heap_lists: vec![],
+            allocator,
+        }
+    }
+
+    /// Replaces the (empty) buffers of a new frame with recycled ones, if any.
+    pub(crate) fn fill<T, W>(&mut self, frame: &mut Callframe<T, W>) {
+        if let Some(near_calls) = self.near_calls.pop() {
+            frame.near_calls = near_calls;
+        } else if let Some(allocator) = &self.allocator {
+            let mut near_calls = allocator.allocate_near_call_buffer().0;
+            near_calls.clear();
+            frame.near_calls = near_calls;
+        }
+        if let Some(heaps) = self.heap_lists.pop() {
+            frame.heaps_i_am_keeping_alive = heaps;
+        }
+    }
+
+    pub(crate) fn recycle(
+        &mut self,
+        mut near_calls: Vec<NearCallFrame>,
+        mut heaps_i_am_keeping_alive: Vec<HeapId>,
+    ) {
+        if near_calls.capacity() > 0 {
+            near_calls.clear();
+            self.near_calls.push(near_calls);
+        }
+        if heaps_i_am_keeping_alive.capacity() > 0 {
+            heaps_i_am_keeping_alive.clear();
+            self.heap_lists.push(heaps_i_am_keeping_alive);
+        }
+    }
+}
+
 impl<T, W> Callframe<T, W> {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
@@ -64,7 +147,7 @@ impl<T, W> Callframe<T, W> {
         code_address: H160,
         caller: H160,
         program: Program<T, W>,
-        stack: Box<Stack>,
+        stack: StackBox,
         heap: HeapId,
         aux_heap: HeapId,
         calldata_heap: HeapId,
@@ -93,6 +176,8 @@ impl<T, W> Callframe<T, W> {
             context_u128,
             is_static,
             is_kernel,
+            shard_id: 0,
+            caller_shard_id: 0,
             stack,
             heap,
             aux_heap,
@@ -102,6 +187,8 @@ impl<T, W> Callframe<T, W> {
             heaps_i_am_keeping_alive: vec![],
             sp: 0,
             gas,
+            initial_gas: gas,
+            pending_refund: 0,
             exception_handler,
             near_calls: vec![],
             world_before_this_frame,
@@ -121,15 +208,20 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
             previous_frame_sp: self.sp,
             previous_frame_gas: self.gas - gas_to_call,
             previous_frame_pc: self.get_pc_as_u16(),
+            previous_pending_refund: self.pending_refund,
             world_before_this_frame,
         });
         self.gas = gas_to_call;
     }
 
-    pub(crate) fn pop_near_call(&mut self) -> Option<FrameRemnant> {
+    /// Pops the innermost near call. If the call has failed, refunds accumulated during it are dropped.
+    pub(crate) fn pop_near_call(&mut self, is_failure: bool) -> Option<FrameRemnant> {
         self.near_calls.pop().map(|f| {
             self.sp = f.previous_frame_sp;
             self.gas = f.previous_frame_gas;
+            if is_failure {
+                self.pending_refund = f.previous_pending_refund;
+            }
             self.set_pc_from_u16(f.previous_frame_pc);
 
             FrameRemnant {
@@ -140,13 +232,13 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
     }
 
     /// Gets a raw inferred program counter. This value can be garbage if the frame is on an invalid instruction or free panic.
-    #[allow(clippy::cast_possible_wrap)] // false positive: `Instruction` isn't that large
+    #[allow(clippy::cast_possible_wrap)] // false positive: `CompactInstruction` isn't that large
     pub(crate) fn get_raw_pc(&self) -> isize {
         // We cannot use `<*const _>::offset_from` because `self.pc` isn't guaranteed to be allocated within `self.program`
         // (invalid instructions and free panics aren't).
         let offset_in_bytes =
             self.pc as isize - ptr::from_ref(self.program.instruction(0).unwrap()) as isize;
-        offset_in_bytes / mem::size_of::<Instruction<T, W>>() as isize
+        offset_in_bytes / mem::size_of::<CompactInstruction>() as isize
     }
 
     // TODO: can overflow / underflow after an invalid instruction or free panic. Ordinarily, this will lead to VM termination (for an invalid instruction)
@@ -167,12 +259,10 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
 
     /// The total amount of gas in this frame, including gas currently inaccessible because of a near call.
     pub(crate) fn contained_gas(&self) -> u32 {
-        self.gas
-            + self
-                .near_calls
-                .iter()
-                .map(|f| f.previous_frame_gas)
-                .sum::<u32>()
+        self.near_calls
+            .iter()
+            .map(|f| f.previous_frame_gas)
+            .fold(self.gas, u32::saturating_add)
     }
 
     pub(crate) fn snapshot(&self) -> CallframeSnapshot {
@@ -183,6 +273,7 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
             sp: self.sp,
             pc: self.get_pc_as_u16(),
             gas: self.gas,
+            pending_refund: self.pending_refund,
             near_calls: self.near_calls.clone(),
             heap_size: self.heap_size,
             aux_heap_size: self.aux_heap_size,
@@ -202,6 +293,7 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
             sp,
             pc,
             gas,
+            pending_refund,
             near_calls,
             heap_size,
             aux_heap_size,
@@ -214,6 +306,7 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
         self.sp = sp;
         self.set_pc_from_u16(pc);
         self.gas = gas;
+        self.pending_refund = pending_refund;
         self.near_calls = near_calls;
         self.heap_size = heap_size;
         self.aux_heap_size = aux_heap_size;
@@ -229,13 +322,14 @@ pub(crate) struct FrameRemnant {
 }
 
 /// Only contains the fields that can change (other than via tracer).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct CallframeSnapshot {
     stack: StackSnapshot,
     context_u128: u128,
     sp: u16,
     pc: u16,
     gas: u32,
+    pending_refund: u32,
     near_calls: Vec<NearCallFrame>,
     heap_size: u32,
     aux_heap_size: u32,
@@ -252,9 +346,13 @@ impl<T, W> Clone for Callframe<T, W> {
             context_u128: self.context_u128,
             is_static: self.is_static,
             is_kernel: self.is_kernel,
+            shard_id: self.shard_id,
+            caller_shard_id: self.caller_shard_id,
             stack: self.stack.clone(),
             sp: self.sp,
             gas: self.gas,
+            initial_gas: self.initial_gas,
+            pending_refund: self.pending_refund,
             near_calls: self.near_calls.clone(),
             pc: self.pc,
             program: self.program.clone(),
@@ -277,9 +375,13 @@ impl<T, W> PartialEq for Callframe<T, W> {
             && self.exception_handler == other.exception_handler
             && self.context_u128 == other.context_u128
             && self.is_static == other.is_static
+            && self.shard_id == other.shard_id
+            && self.caller_shard_id == other.caller_shard_id
             && self.stack == other.stack
             && self.sp == other.sp
             && self.gas == other.gas
+            && self.initial_gas == other.initial_gas
+            && self.pending_refund == other.pending_refund
             && self.near_calls == other.near_calls
             && std::ptr::eq(self.pc, other.pc)
             && self.program == other.program
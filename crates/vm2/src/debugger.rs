@@ -0,0 +1,319 @@
+//! Debugger supporting reverse execution.
+
+use zksync_vm2_interface::Tracer;
+
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::StateDelta;
+use crate::{ExecutionEnd, VirtualMachine, World};
+
+/// Copy of the debugged state.
+#[derive(Debug)]
+struct Checkpoint<T, W> {
+    position: u64,
+    vm: VirtualMachine<T, W>,
+    world: W,
+    tracer: T,
+}
+
+/// Debugger executing a [`VirtualMachine`] instruction by instruction, both forward and backward.
+///
+/// Stepping back restores the latest checkpoint (a copy of the VM, the world and the tracer) made before the target
+/// position and re-executes instructions from it. Checkpoints are made automatically every `checkpoint_interval`
+/// instructions, so stepping back re-executes less than `checkpoint_interval` instructions; a smaller interval
+/// makes stepping back faster at the cost of memory. Re-execution leads to the same state as long as the world and
+/// the tracer behave deterministically.
+#[derive(Debug)]
+pub struct Debugger<T, W> {
+    vm: VirtualMachine<T, W>,
+    world: W,
+    tracer: T,
+    /// Number of instructions executed so far (including ones skipped because of their predicate).
+    position: u64,
+    end: Option<ExecutionEnd>,
+    checkpoint_interval: u64,
+    /// Checkpoints ordered by their position.
+    checkpoints: Vec<Checkpoint<T, W>>,
+}
+
+impl<T: Tracer + Clone, W: World<T> + Clone> Debugger<T, W> {
+    /// Creates a debugger for a VM that will be executed with the specified world and tracer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `checkpoint_interval` is zero.
+    pub fn new(vm: VirtualMachine<T, W>, world: W, tracer: T, checkpoint_interval: u64) -> Self {
+        assert!(
+            checkpoint_interval > 0,
+            "checkpoint interval must be positive"
+        );
+        Self {
+            vm,
+            world,
+            tracer,
+            position: 0,
+            end: None,
+            checkpoint_interval,
+            checkpoints: vec![],
+        }
+    }
+
+    /// Returns the debugged VM.
+    pub fn vm(&self) -> &VirtualMachine<T, W> {
+        &self.vm
+    }
+
+    /// Returns the world used by the debugged VM.
+    pub fn world(&self) -> &W {
+        &self.world
+    }
+
+    /// Returns the tracer used by the debugged VM.
+    pub fn tracer(&self) -> &T {
+        &self.tracer
+    }
+
+    /// Returns the number of instructions executed so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the end of execution if it was reached at the current position.
+    pub fn end(&self) -> Option<&ExecutionEnd> {
+        self.end.as_ref()
+    }
+
+    /// Executes a single instruction. Returns the end of execution if it was reached, either by this instruction
+    /// or before; in the latter case, no instruction is executed.
+    ///
+    /// If execution was [suspended on a hook](ExecutionEnd::SuspendedOnHook) or stopped by the tracer,
+    /// it's not resumed by further steps; use [`Self::into_parts()`] to resume it.
+    pub fn step(&mut self) -> Option<ExecutionEnd> {
+        if self.end.is_some() {
+            return self.end.clone();
+        }
+
+        self.make_checkpoint_if_needed();
+        let mut instruction_budget = 1;
+        let end = self.vm.run_with_instruction_limit(
+            &mut self.world,
+            &mut self.tracer,
+            &mut instruction_budget,
+        );
+        self.finish_step(end, instruction_budget);
+        self.end.clone()
+    }
+
+    /// Same as [`Self::step()`], but also returns the changes of the VM state made by the executed instruction.
+    /// If no instruction is executed, the returned delta is empty.
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub fn step_with_delta(&mut self) -> (Option<ExecutionEnd>, StateDelta) {
+        if self.end.is_some() {
+            return (self.end.clone(), StateDelta::default());
+        }
+
+        self.make_checkpoint_if_needed();
+        let mut instruction_budget = 1;
+        let (end, delta) =
+            self.vm
+                .run_with_delta(&mut self.world, &mut self.tracer, &mut instruction_budget);
+        self.finish_step(end, instruction_budget);
+        (self.end.clone(), delta)
+    }
+
+    /// Updates the debugger after running the VM with the budget of a single instruction.
+    fn finish_step(&mut self, end: ExecutionEnd, instruction_budget: u64) {
+        // The budget is only spent if an instruction was executed. The VM may stop before executing anything,
+        // e.g. if it exceeds its memory limit when resumed.
+        if instruction_budget == 0 {
+            self.position += 1;
+        }
+        if end != ExecutionEnd::InstructionLimit {
+            self.end = Some(end);
+        }
+    }
+
+    fn make_checkpoint_if_needed(&mut self) {
+        if self.position % self.checkpoint_interval == 0 {
+            if let Err(index) = self
+                .checkpoints
+                .binary_search_by_key(&self.position, |checkpoint| checkpoint.position)
+            {
+                let checkpoint = Checkpoint {
+                    position: self.position,
+                    vm: self.vm.clone(),
+                    world: self.world.clone(),
+                    tracer: self.tracer.clone(),
+                };
+                self.checkpoints.insert(index, checkpoint);
+            }
+        }
+    }
+
+    /// Executes instructions until the end of execution.
+    pub fn run(&mut self) -> ExecutionEnd {
+        loop {
+            if let Some(end) = self.step() {
+                return end;
+            }
+        }
+    }
+
+    /// Returns to the state just before the last executed instruction. Returns `false` if no instructions were executed.
+    #[allow(clippy::missing_panics_doc)] // the checkpoint at position 0 is always made by the first step
+    pub fn step_back(&mut self) -> bool {
+        let Some(target) = self.position.checked_sub(1) else {
+            return false;
+        };
+
+        let checkpoint = self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.position <= target)
+            .expect("no checkpoint at start");
+        self.vm = checkpoint.vm.clone();
+        self.world = checkpoint.world.clone();
+        self.tracer = checkpoint.tracer.clone();
+        self.position = checkpoint.position;
+        self.end = None;
+
+        while self.position < target {
+            self.step();
+        }
+        true
+    }
+
+    /// Returns the debugged VM, world and tracer, e.g. to resume execution without the debugger.
+    pub fn into_parts(self) -> (VirtualMachine<T, W>, W, T) {
+        (self.vm, self.world, self.tracer)
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+    use zksync_vm2_interface::StateInterface;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program, Settings,
+    };
+
+    fn debugger(checkpoint_interval: u64) -> Debugger<(), TestWorld<()>> {
+        let r1 = Register::new(1);
+        let add_to_r1 = |value, r1_value| {
+            Instruction::from_add(
+                Immediate1(value).into(),
+                Register2(r1_value),
+                Register1(r1).into(),
+                Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            )
+        };
+        // Set r1 to 0, increment it 5 times and panic.
+        let mut instructions = vec![add_to_r1(0, Register::new(0))];
+        instructions.extend((0..5).map(|_| add_to_r1(1, r1)));
+        instructions.push(Instruction::from_invalid());
+        let program = Program::from_raw(instructions, vec![]);
+
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            1000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+        Debugger::new(vm, world, (), checkpoint_interval)
+    }
+
+    #[test]
+    fn stepping_back_before_panic() {
+        for checkpoint_interval in [1, 3, 100] {
+            let mut debugger = debugger(checkpoint_interval);
+            assert_eq!(debugger.run(), ExecutionEnd::Panicked);
+            assert_eq!(debugger.position(), 7);
+
+            assert!(debugger.step_back());
+            assert_eq!(debugger.position(), 6);
+            assert_eq!(debugger.end(), None);
+            assert_eq!(debugger.vm().read_register(1).0, 5.into());
+
+            assert!(debugger.step_back());
+            assert!(debugger.step_back());
+            assert_eq!(debugger.position(), 4);
+            assert_eq!(debugger.vm().read_register(1).0, 3.into());
+
+            assert_eq!(debugger.step(), None);
+            assert_eq!(debugger.vm().read_register(1).0, 4.into());
+            assert_eq!(debugger.run(), ExecutionEnd::Panicked);
+        }
+    }
+
+    #[test]
+    fn stepping_with_deltas() {
+        let mut debugger = debugger(2);
+        let (end, delta) = debugger.step_with_delta();
+        assert_eq!(end, None);
+        // The first instruction sets r1 to 0, which it already is.
+        assert!(delta.registers.is_empty());
+        assert_eq!(delta.gas_used, 6);
+
+        let (end, delta) = debugger.step_with_delta();
+        assert_eq!(end, None);
+        assert_eq!(delta.registers[&1], (1.into(), false));
+
+        assert!(debugger.step_back());
+        let (_, replayed_delta) = debugger.step_with_delta();
+        assert_eq!(replayed_delta, delta);
+        assert_eq!(debugger.run(), ExecutionEnd::Panicked);
+        assert_eq!(
+            debugger.step_with_delta(),
+            (Some(ExecutionEnd::Panicked), StateDelta::default())
+        );
+    }
+
+    #[test]
+    fn stopping_before_first_instruction() {
+        let (mut vm, world, tracer) = debugger(1).into_parts();
+        vm.set_memory_limit(Some(0));
+        let mut debugger = Debugger::new(vm, world, tracer, 1);
+
+        assert_eq!(debugger.step(), Some(ExecutionEnd::MemoryLimitExceeded));
+        assert_eq!(debugger.position(), 0);
+        assert_eq!(debugger.end(), Some(&ExecutionEnd::MemoryLimitExceeded));
+        assert!(!debugger.step_back());
+        assert_eq!(
+            debugger.step_with_delta(),
+            (
+                Some(ExecutionEnd::MemoryLimitExceeded),
+                StateDelta::default()
+            )
+        );
+        assert_eq!(debugger.position(), 0);
+    }
+
+    #[test]
+    fn stepping_back_to_start() {
+        let mut debugger = debugger(2);
+        let initial_state = debugger.vm().dump_state();
+        assert!(!debugger.step_back());
+
+        for _ in 0..3 {
+            debugger.step();
+        }
+        while debugger.step_back() {}
+        assert_eq!(debugger.position(), 0);
+        assert_eq!(debugger.vm().dump_state(), initial_state);
+    }
+}
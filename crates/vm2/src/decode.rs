@@ -1,11 +1,3 @@
-use zkevm_opcode_defs::{
-    decoding::{EncodingModeProduction, VmEncodingMode},
-    ImmMemHandlerFlags, Opcode,
-    Operand::{Full, RegOnly, RegOrImm},
-    RegOrImmFlags, FAR_CALL_SHARD_FLAG_IDX, FAR_CALL_STATIC_FLAG_IDX, FIRST_MESSAGE_FLAG_IDX,
-    RET_TO_LABEL_BIT_IDX, SET_FLAGS_FLAG_IDX, SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES,
-    SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE, UMA_INCREMENT_FLAG_IDX,
-};
 use zksync_vm2_interface::{
     opcodes::{
         self, Add, And, Div, Mul, Or, PointerAdd, PointerPack, PointerShrink, PointerSub,
@@ -21,8 +13,17 @@ use crate::{
         RelativeStack, SourceWriter,
     },
     instruction::{ExecutionEnd, ExecutionStatus},
+    instruction_info::decode_opcode,
+    isa::{
+        self, EncodingModeProduction, ImmMemHandlerFlags, Opcode,
+        Operand::{self, Full, RegOnly, RegOrImm},
+        RegOrImmFlags, VmEncodingMode, FAR_CALL_SHARD_FLAG_IDX, FAR_CALL_STATIC_FLAG_IDX,
+        FIRST_MESSAGE_FLAG_IDX, RET_TO_LABEL_BIT_IDX, SET_FLAGS_FLAG_IDX,
+        SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES, SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE,
+        UMA_INCREMENT_FLAG_IDX,
+    },
     mode_requirements::ModeRequirements,
-    Instruction, Predicate, VirtualMachine, World,
+    GasCosts, Instruction, Predicate, VirtualMachine, World,
 };
 
 fn unimplemented_instruction<T, W>(variant: Opcode) -> Instruction<T, W> {
@@ -47,53 +48,50 @@ fn unimplemented_handler<T, W>(
     ExecutionStatus::Stopped(ExecutionEnd::Panicked)
 }
 
-#[allow(clippy::too_many_lines)]
-pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> Instruction<T, W> {
-    let (parsed, _) = EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
-
-    let predicate = match parsed.condition {
-        zkevm_opcode_defs::Condition::Always => Predicate::Always,
-        zkevm_opcode_defs::Condition::Gt => Predicate::IfGT,
-        zkevm_opcode_defs::Condition::Lt => Predicate::IfLT,
-        zkevm_opcode_defs::Condition::Eq => Predicate::IfEQ,
-        zkevm_opcode_defs::Condition::Ge => Predicate::IfGE,
-        zkevm_opcode_defs::Condition::Le => Predicate::IfLE,
-        zkevm_opcode_defs::Condition::Ne => Predicate::IfNotEQ,
-        zkevm_opcode_defs::Condition::GtOrLt => Predicate::IfGTOrLT,
-    };
-    let arguments = Arguments::new(
-        predicate,
-        parsed.variant.ergs_price(),
-        ModeRequirements::new(
-            parsed.variant.requires_kernel_mode(),
-            !parsed.variant.can_be_used_in_static_context(),
-        ),
-    );
+pub(crate) fn decode_predicate(condition: isa::Condition) -> Predicate {
+    match condition {
+        isa::Condition::Always => Predicate::Always,
+        isa::Condition::Gt => Predicate::IfGT,
+        isa::Condition::Lt => Predicate::IfLT,
+        isa::Condition::Eq => Predicate::IfEQ,
+        isa::Condition::Ge => Predicate::IfGE,
+        isa::Condition::Le => Predicate::IfLE,
+        isa::Condition::Ne => Predicate::IfNotEQ,
+        isa::Condition::GtOrLt => Predicate::IfGTOrLT,
+    }
+}
 
+pub(crate) fn decode_source(operand_type: Operand, register: u8, immediate: u16) -> AnySource {
     let stack_in = RegisterAndImmediate {
-        immediate: parsed.imm_0,
-        register: Register::new(parsed.src0_reg_idx),
+        immediate,
+        register: Register::new(register),
     };
-    let src1: AnySource = match parsed.variant.src0_operand_type {
+    match operand_type {
         RegOnly | RegOrImm(RegOrImmFlags::UseRegOnly) | Full(ImmMemHandlerFlags::UseRegOnly) => {
-            Register1(Register::new(parsed.src0_reg_idx)).into()
+            Register1(Register::new(register)).into()
         }
         RegOrImm(RegOrImmFlags::UseImm16Only) | Full(ImmMemHandlerFlags::UseImm16Only) => {
-            Immediate1(parsed.imm_0).into()
+            Immediate1(immediate).into()
         }
         Full(ImmMemHandlerFlags::UseAbsoluteOnStack) => AbsoluteStack(stack_in).into(),
         Full(ImmMemHandlerFlags::UseStackWithPushPop) => AdvanceStackPointer(stack_in).into(),
         Full(ImmMemHandlerFlags::UseStackWithOffset) => RelativeStack(stack_in).into(),
         Full(ImmMemHandlerFlags::UseCodePage) => CodePage(stack_in).into(),
-    };
+    }
+}
 
+pub(crate) fn decode_destination(
+    operand_type: Operand,
+    register: u8,
+    immediate: u16,
+) -> AnyDestination {
     let stack_out = RegisterAndImmediate {
-        immediate: parsed.imm_1,
-        register: Register::new(parsed.dst0_reg_idx),
+        immediate,
+        register: Register::new(register),
     };
-    let out: AnyDestination = match parsed.variant.dst0_operand_type {
+    match operand_type {
         RegOnly | RegOrImm(RegOrImmFlags::UseRegOnly) | Full(ImmMemHandlerFlags::UseRegOnly) => {
-            Register1(Register::new(parsed.dst0_reg_idx)).into()
+            Register1(Register::new(register)).into()
         }
         RegOrImm(RegOrImmFlags::UseImm16Only) | Full(ImmMemHandlerFlags::UseImm16Only) => {
             panic!("Parser wants to output to immediate")
@@ -102,7 +100,40 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
         Full(ImmMemHandlerFlags::UseStackWithPushPop) => AdvanceStackPointer(stack_out).into(),
         Full(ImmMemHandlerFlags::UseStackWithOffset) => RelativeStack(stack_out).into(),
         Full(ImmMemHandlerFlags::UseCodePage) => panic!("Parser wants to write to code page"),
-    };
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+pub(crate) fn decode<T: Tracer, W: World<T>>(
+    raw: u64,
+    is_bootloader: bool,
+    gas_costs: &GasCosts,
+) -> Instruction<T, W> {
+    let (parsed, _) = EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
+
+    let predicate = decode_predicate(parsed.condition);
+    let gas_cost = decode_opcode(parsed.variant.opcode)
+        .and_then(|opcode| gas_costs.cost(opcode))
+        .unwrap_or_else(|| parsed.variant.ergs_price());
+    let arguments = Arguments::new(
+        predicate,
+        gas_cost,
+        ModeRequirements::new(
+            parsed.variant.requires_kernel_mode(),
+            !parsed.variant.can_be_used_in_static_context(),
+        ),
+    );
+
+    let src1 = decode_source(
+        parsed.variant.src0_operand_type,
+        parsed.src0_reg_idx,
+        parsed.imm_0,
+    );
+    let out = decode_destination(
+        parsed.variant.dst0_operand_type,
+        parsed.dst0_reg_idx,
+        parsed.imm_1,
+    );
 
     let src2 = Register2(Register::new(parsed.src1_reg_idx));
     let out2 = Register2(Register::new(parsed.dst1_reg_idx));
@@ -139,54 +170,50 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
         Opcode::Mul(_) => binop!(Mul, out2),
         Opcode::Div(_) => binop!(Div, out2),
         Opcode::Binop(x) => match x {
-            zkevm_opcode_defs::BinopOpcode::Xor => binop!(Xor, ()),
-            zkevm_opcode_defs::BinopOpcode::And => binop!(And, ()),
-            zkevm_opcode_defs::BinopOpcode::Or => binop!(Or, ()),
+            isa::BinopOpcode::Xor => binop!(Xor, ()),
+            isa::BinopOpcode::And => binop!(And, ()),
+            isa::BinopOpcode::Or => binop!(Or, ()),
         },
         Opcode::Shift(x) => match x {
-            zkevm_opcode_defs::ShiftOpcode::Shl => binop!(ShiftLeft, ()),
-            zkevm_opcode_defs::ShiftOpcode::Shr => binop!(ShiftRight, ()),
-            zkevm_opcode_defs::ShiftOpcode::Rol => binop!(RotateLeft, ()),
-            zkevm_opcode_defs::ShiftOpcode::Ror => binop!(RotateRight, ()),
+            isa::ShiftOpcode::Shl => binop!(ShiftLeft, ()),
+            isa::ShiftOpcode::Shr => binop!(ShiftRight, ()),
+            isa::ShiftOpcode::Rol => binop!(RotateLeft, ()),
+            isa::ShiftOpcode::Ror => binop!(RotateRight, ()),
         },
         Opcode::Jump(_) => Instruction::from_jump(src1, out.try_into().unwrap(), arguments),
         Opcode::Context(x) => match x {
-            zkevm_opcode_defs::ContextOpcode::This => {
-                Instruction::from_this(out.try_into().unwrap(), arguments)
-            }
-            zkevm_opcode_defs::ContextOpcode::Caller => {
+            isa::ContextOpcode::This => Instruction::from_this(out.try_into().unwrap(), arguments),
+            isa::ContextOpcode::Caller => {
                 Instruction::from_caller(out.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::CodeAddress => {
+            isa::ContextOpcode::CodeAddress => {
                 Instruction::from_code_address(out.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::ErgsLeft => {
+            isa::ContextOpcode::ErgsLeft => {
                 Instruction::from_ergs_left(out.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::GetContextU128 => {
+            isa::ContextOpcode::GetContextU128 => {
                 Instruction::from_context_u128(out.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::SetContextU128 => {
+            isa::ContextOpcode::SetContextU128 => {
                 Instruction::from_set_context_u128(src1.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::Sp => {
+            isa::ContextOpcode::Sp => {
                 Instruction::from_context_sp(out.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::Meta => {
+            isa::ContextOpcode::Meta => {
                 Instruction::from_context_meta(out.try_into().unwrap(), arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::IncrementTxNumber => {
+            isa::ContextOpcode::IncrementTxNumber => {
                 Instruction::from_increment_tx_number(arguments)
             }
-            zkevm_opcode_defs::ContextOpcode::AuxMutating0 => {
-                Instruction::from_aux_mutating(arguments)
-            }
+            isa::ContextOpcode::AuxMutating0 => Instruction::from_aux_mutating(arguments),
         },
         Opcode::Ptr(x) => match x {
-            zkevm_opcode_defs::PtrOpcode::Add => ptr!(PointerAdd),
-            zkevm_opcode_defs::PtrOpcode::Sub => ptr!(PointerSub),
-            zkevm_opcode_defs::PtrOpcode::Pack => ptr!(PointerPack),
-            zkevm_opcode_defs::PtrOpcode::Shrink => ptr!(PointerShrink),
+            isa::PtrOpcode::Add => ptr!(PointerAdd),
+            isa::PtrOpcode::Sub => ptr!(PointerSub),
+            isa::PtrOpcode::Pack => ptr!(PointerPack),
+            isa::PtrOpcode::Shrink => ptr!(PointerShrink),
         },
         Opcode::NearCall(_) => Instruction::from_near_call(
             Register1(Register::new(parsed.src0_reg_idx)),
@@ -196,15 +223,9 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
         ),
         Opcode::FarCall(kind) => {
             let constructor = match kind {
-                zkevm_opcode_defs::FarCallOpcode::Normal => {
-                    Instruction::from_far_call::<opcodes::Normal>
-                }
-                zkevm_opcode_defs::FarCallOpcode::Delegate => {
-                    Instruction::from_far_call::<opcodes::Delegate>
-                }
-                zkevm_opcode_defs::FarCallOpcode::Mimic => {
-                    Instruction::from_far_call::<opcodes::Mimic>
-                }
+                isa::FarCallOpcode::Normal => Instruction::from_far_call::<opcodes::Normal>,
+                isa::FarCallOpcode::Delegate => Instruction::from_far_call::<opcodes::Delegate>,
+                isa::FarCallOpcode::Mimic => Instruction::from_far_call::<opcodes::Mimic>,
             };
             constructor(
                 src1.try_into().unwrap(),
@@ -223,56 +244,54 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
                 None
             };
             match kind {
-                zkevm_opcode_defs::RetOpcode::Ok => {
+                isa::RetOpcode::Ok => {
                     Instruction::from_ret(src1.try_into().unwrap(), label, arguments)
                 }
-                zkevm_opcode_defs::RetOpcode::Revert => {
+                isa::RetOpcode::Revert => {
                     Instruction::from_revert(src1.try_into().unwrap(), label, arguments)
                 }
-                zkevm_opcode_defs::RetOpcode::Panic => Instruction::from_panic(label, arguments),
+                isa::RetOpcode::Panic => Instruction::from_panic(label, arguments),
             }
         }
         Opcode::Log(x) => match x {
-            zkevm_opcode_defs::LogOpcode::StorageRead => Instruction::from_storage_read(
+            isa::LogOpcode::StorageRead => Instruction::from_storage_read(
+                src1.try_into().unwrap(),
+                out.try_into().unwrap(),
+                arguments,
+            ),
+            isa::LogOpcode::TransientStorageRead => Instruction::from_transient_storage_read(
                 src1.try_into().unwrap(),
                 out.try_into().unwrap(),
                 arguments,
             ),
-            zkevm_opcode_defs::LogOpcode::TransientStorageRead => {
-                Instruction::from_transient_storage_read(
-                    src1.try_into().unwrap(),
-                    out.try_into().unwrap(),
-                    arguments,
-                )
-            }
 
-            zkevm_opcode_defs::LogOpcode::StorageWrite => {
+            isa::LogOpcode::StorageWrite => {
                 Instruction::from_storage_write(src1.try_into().unwrap(), src2, arguments)
             }
 
-            zkevm_opcode_defs::LogOpcode::TransientStorageWrite => {
+            isa::LogOpcode::TransientStorageWrite => {
                 Instruction::from_transient_storage_write(src1.try_into().unwrap(), src2, arguments)
             }
 
-            zkevm_opcode_defs::LogOpcode::ToL1Message => Instruction::from_l2_to_l1_message(
+            isa::LogOpcode::ToL1Message => Instruction::from_l2_to_l1_message(
                 src1.try_into().unwrap(),
                 src2,
                 parsed.variant.flags[FIRST_MESSAGE_FLAG_IDX],
                 arguments,
             ),
-            zkevm_opcode_defs::LogOpcode::Event => Instruction::from_event(
+            isa::LogOpcode::Event => Instruction::from_event(
                 src1.try_into().unwrap(),
                 src2,
                 parsed.variant.flags[FIRST_MESSAGE_FLAG_IDX],
                 arguments,
             ),
-            zkevm_opcode_defs::LogOpcode::PrecompileCall => Instruction::from_precompile_call(
+            isa::LogOpcode::PrecompileCall => Instruction::from_precompile_call(
                 src1.try_into().unwrap(),
                 src2,
                 out.try_into().unwrap(),
                 arguments,
             ),
-            zkevm_opcode_defs::LogOpcode::Decommit => Instruction::from_decommit(
+            isa::LogOpcode::Decommit => Instruction::from_decommit(
                 src1.try_into().unwrap(),
                 src2,
                 out.try_into().unwrap(),
@@ -282,43 +301,43 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
         Opcode::UMA(x) => {
             let increment = parsed.variant.flags[UMA_INCREMENT_FLAG_IDX];
             match x {
-                zkevm_opcode_defs::UMAOpcode::HeapRead => Instruction::from_heap_read(
+                isa::UMAOpcode::HeapRead => Instruction::from_heap_read(
                     src1.try_into().unwrap(),
                     out.try_into().unwrap(),
                     increment.then_some(out2),
                     arguments,
                 ),
-                zkevm_opcode_defs::UMAOpcode::HeapWrite => Instruction::from_heap_write(
+                isa::UMAOpcode::HeapWrite => Instruction::from_heap_write(
                     src1.try_into().unwrap(),
                     src2,
                     increment.then_some(out.try_into().unwrap()),
                     arguments,
                     is_bootloader,
                 ),
-                zkevm_opcode_defs::UMAOpcode::AuxHeapRead => Instruction::from_aux_heap_read(
+                isa::UMAOpcode::AuxHeapRead => Instruction::from_aux_heap_read(
                     src1.try_into().unwrap(),
                     out.try_into().unwrap(),
                     increment.then_some(out2),
                     arguments,
                 ),
-                zkevm_opcode_defs::UMAOpcode::AuxHeapWrite => Instruction::from_aux_heap_store(
+                isa::UMAOpcode::AuxHeapWrite => Instruction::from_aux_heap_store(
                     src1.try_into().unwrap(),
                     src2,
                     increment.then_some(out.try_into().unwrap()),
                     arguments,
                 ),
-                zkevm_opcode_defs::UMAOpcode::FatPointerRead => Instruction::from_pointer_read(
+                isa::UMAOpcode::FatPointerRead => Instruction::from_pointer_read(
                     src1.try_into().unwrap(),
                     out.try_into().unwrap(),
                     increment.then_some(out2),
                     arguments,
                 ),
-                zkevm_opcode_defs::UMAOpcode::StaticMemoryRead => unimplemented_instruction(
-                    Opcode::UMA(zkevm_opcode_defs::UMAOpcode::StaticMemoryRead),
-                ),
-                zkevm_opcode_defs::UMAOpcode::StaticMemoryWrite => unimplemented_instruction(
-                    Opcode::UMA(zkevm_opcode_defs::UMAOpcode::StaticMemoryWrite),
-                ),
+                isa::UMAOpcode::StaticMemoryRead => {
+                    unimplemented_instruction(Opcode::UMA(isa::UMAOpcode::StaticMemoryRead))
+                }
+                isa::UMAOpcode::StaticMemoryWrite => {
+                    unimplemented_instruction(Opcode::UMA(isa::UMAOpcode::StaticMemoryWrite))
+                }
             }
         }
         Opcode::Invalid(_) => Instruction::from_invalid(),
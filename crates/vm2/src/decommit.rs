@@ -1,10 +1,14 @@
 use primitive_types::{H160, U256};
-use zkevm_opcode_defs::{
-    ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
-};
+use zkevm_opcode_defs::ethereum_types::Address;
 use zksync_vm2_interface::{CycleStats, Tracer};
 
-use crate::{program::Program, world_diff::WorldDiff, World};
+use crate::{
+    instruction_handlers::address_into_u256,
+    isa::{self, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW},
+    program::Program,
+    world_diff::WorldDiff,
+    World,
+};
 
 impl WorldDiff {
     pub(crate) fn decommit<T: Tracer>(
@@ -18,57 +22,33 @@ impl WorldDiff {
     ) -> Option<(UnpaidDecommit, bool)> {
         let deployer_system_contract_address =
             Address::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        let code_info = self.read_storage_without_refund(
+            world,
+            tracer,
+            deployer_system_contract_address,
+            address,
+        );
+        let mut code_info_bytes = [0; 32];
+        code_info.to_big_endian(&mut code_info_bytes);
 
-        let mut is_evm = false;
+        let try_default_aa = if is_kernel(u256_into_address(address)) {
+            None
+        } else {
+            Some(default_aa_code_hash)
+        };
 
-        let mut code_info = {
-            let code_info = self.read_storage_without_refund(
-                world,
-                tracer,
-                deployer_system_contract_address,
-                address,
-            );
-            let mut code_info_bytes = [0; 32];
-            code_info.to_big_endian(&mut code_info_bytes);
-
-            // Note that EOAs are considered constructed because their code info is all zeroes.
-            let is_constructed = match code_info_bytes[1] {
-                0 => true,
-                1 => false,
-                _ => {
-                    return None;
-                }
-            };
-
-            let try_default_aa = if is_kernel(u256_into_address(address)) {
-                None
-            } else {
-                Some(default_aa_code_hash)
-            };
-
-            // The address aliasing contract implements Ethereum-like behavior of calls to EOAs
-            // returning successfully (and address aliasing when called from the bootloader).
-            // It makes sense that unconstructed code is treated as an EOA but for some reason
-            // a constructor call to constructed code is also treated as EOA.
-            match code_info_bytes[0] {
-                1 => {
-                    if is_constructed == is_constructor_call {
-                        try_default_aa?
-                    } else {
-                        code_info_bytes
-                    }
-                }
-                2 => {
-                    if is_constructed == is_constructor_call {
-                        try_default_aa?
-                    } else {
-                        is_evm = true;
-                        evm_interpreter_code_hash
-                    }
-                }
-                _ if code_info == U256::zero() => try_default_aa?,
-                _ => return None,
-            }
+        // The address aliasing contract implements Ethereum-like behavior of calls to EOAs
+        // returning successfully (and address aliasing when called from the bootloader).
+        // It makes sense that unconstructed code is treated as an EOA but for some reason
+        // a constructor call to constructed code is also treated as EOA. Malformed hashes don't point to any code,
+        // so they are treated as EOAs as well.
+        let (mut code_info, is_evm) = match VersionedHash::parse(&code_info_bytes) {
+            None | Some(VersionedHash::Empty) => (try_default_aa?, false),
+            Some(
+                VersionedHash::EraVm { is_constructed } | VersionedHash::Evm { is_constructed },
+            ) if is_constructed == is_constructor_call => (try_default_aa?, false),
+            Some(VersionedHash::EraVm { .. }) => (code_info_bytes, false),
+            Some(VersionedHash::Evm { .. }) => (evm_interpreter_code_hash, true),
         };
 
         code_info[1] = 0;
@@ -79,7 +59,7 @@ impl WorldDiff {
             0
         } else {
             let code_length_in_words = u16::from_be_bytes([code_info[2], code_info[3]]);
-            u32::from(code_length_in_words) * zkevm_opcode_defs::ERGS_PER_CODE_WORD_DECOMMITTMENT
+            u32::from(code_length_in_words) * isa::ERGS_PER_CODE_WORD_DECOMMITTMENT
         };
 
         Some((UnpaidDecommit { cost, code_key }, is_evm))
@@ -134,6 +114,38 @@ impl WorldDiff {
     }
 }
 
+/// Versioned bytecode hash stored in the deployer system contract for each address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VersionedHash {
+    /// All-zero hash, i.e., no code is deployed (e.g., the address is an EOA).
+    Empty,
+    /// EraVM bytecode hash (version byte 1). Bytes `2..4` contain the bytecode length in words.
+    EraVm { is_constructed: bool },
+    /// EVM bytecode hash (version byte 2); the bytecode is executed by the EVM interpreter.
+    /// Bytes `2..4` contain the bytecode length in bytes.
+    Evm { is_constructed: bool },
+}
+
+impl VersionedHash {
+    /// Parses a hash, returning `None` if the version byte, the construction marker or the length is invalid.
+    /// EraVM bytecodes must have an odd number of words; bytecodes of either version must not be empty.
+    fn parse(bytes: &[u8; 32]) -> Option<Self> {
+        // Note that EOAs are considered constructed because their code info is all zeroes.
+        let is_constructed = match bytes[1] {
+            0 => true,
+            1 => false,
+            _ => return None,
+        };
+        let len = u16::from_be_bytes([bytes[2], bytes[3]]);
+        match bytes[0] {
+            1 if len % 2 == 1 => Some(Self::EraVm { is_constructed }),
+            2 if len > 0 => Some(Self::Evm { is_constructed }),
+            _ if bytes.iter().all(|&byte| byte == 0) => Some(Self::Empty),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct UnpaidDecommit {
     cost: u32,
@@ -151,3 +163,160 @@ pub(crate) fn u256_into_address(source: U256) -> H160 {
 pub(crate) fn is_kernel(address: H160) -> bool {
     address.0[..18].iter().all(|&byte| byte == 0)
 }
+
+/// May be used to load code when the VM first starts up.
+/// Doesn't check for any errors.
+/// Doesn't cost anything but also doesn't make the code free in future decommits.
+// Not re-exported from the crate root; only available via `testonly` for low-level testing / benches.
+#[doc(hidden)]
+pub fn initial_decommit<T: Tracer, W: World<T>>(world: &mut W, address: H160) -> Program<T, W> {
+    let deployer_system_contract_address =
+        Address::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+    let code_info =
+        world.read_storage_value(deployer_system_contract_address, address_into_u256(address));
+
+    let mut code_info_bytes = [0; 32];
+    code_info.to_big_endian(&mut code_info_bytes);
+
+    code_info_bytes[1] = 0;
+    let code_key: U256 = U256::from_big_endian(&code_info_bytes);
+
+    world.decommit(code_key)
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use super::*;
+    use crate::testonly::TestWorld;
+
+    const DEFAULT_AA_CODE_HASH: [u8; 32] = code_hash(0xaa);
+    const EVM_INTERPRETER_CODE_HASH: [u8; 32] = code_hash(0xee);
+
+    const fn code_hash(tag: u8) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[0] = 1;
+        bytes[3] = 1;
+        bytes[31] = tag;
+        bytes
+    }
+
+    fn versioned_hash(version: u8, marker: u8) -> [u8; 32] {
+        with_len(version, marker, 5)
+    }
+
+    fn with_len(version: u8, marker: u8, len: u16) -> [u8; 32] {
+        let mut bytes = [0x11; 32];
+        bytes[0] = version;
+        bytes[1] = marker;
+        bytes[2..4].copy_from_slice(&len.to_be_bytes());
+        bytes
+    }
+
+    /// Returns the resolved code hash and whether it's executed by the EVM interpreter.
+    fn resolve(
+        code_info: [u8; 32],
+        address: U256,
+        is_constructor_call: bool,
+    ) -> Option<([u8; 32], bool)> {
+        let mut world = TestWorld::<()>::new(&[]);
+        world
+            .address_to_hash
+            .insert(address, U256::from_big_endian(&code_info));
+        let (decommit, is_evm) = WorldDiff::default().decommit(
+            &mut world,
+            &mut (),
+            address,
+            DEFAULT_AA_CODE_HASH,
+            EVM_INTERPRETER_CODE_HASH,
+            is_constructor_call,
+        )?;
+        let mut code_key = [0; 32];
+        decommit.code_key.to_big_endian(&mut code_key);
+        Some((code_key, is_evm))
+    }
+
+    #[test]
+    fn parsing_versioned_hashes() {
+        assert_eq!(VersionedHash::parse(&[0; 32]), Some(VersionedHash::Empty));
+        assert_eq!(
+            VersionedHash::parse(&versioned_hash(1, 0)),
+            Some(VersionedHash::EraVm {
+                is_constructed: true
+            })
+        );
+        assert_eq!(
+            VersionedHash::parse(&versioned_hash(1, 1)),
+            Some(VersionedHash::EraVm {
+                is_constructed: false
+            })
+        );
+        assert_eq!(
+            VersionedHash::parse(&versioned_hash(2, 1)),
+            Some(VersionedHash::Evm {
+                is_constructed: false
+            })
+        );
+        assert_eq!(VersionedHash::parse(&versioned_hash(1, 2)), None);
+        assert_eq!(VersionedHash::parse(&versioned_hash(0, 0)), None);
+        assert_eq!(VersionedHash::parse(&versioned_hash(3, 0)), None);
+    }
+
+    #[test]
+    fn parsing_versioned_hashes_with_malformed_length() {
+        for len in [0, 2, 4, u16::MAX - 1] {
+            assert_eq!(VersionedHash::parse(&with_len(1, 0, len)), None, "{len}");
+        }
+        assert_eq!(VersionedHash::parse(&with_len(2, 0, 0)), None);
+        // EVM bytecode lengths are measured in bytes, so they may be even.
+        assert_eq!(
+            VersionedHash::parse(&with_len(2, 0, 4)),
+            Some(VersionedHash::Evm {
+                is_constructed: true
+            })
+        );
+    }
+
+    #[test]
+    fn resolving_called_code() {
+        let address = U256::from(0x_1234_5678_u64) << 64;
+        let constructed = versioned_hash(1, 0);
+        assert_eq!(
+            resolve(constructed, address, false),
+            Some((constructed, false))
+        );
+        // The construction marker is cleared in the decommitted hash.
+        let mut expected = versioned_hash(1, 1);
+        expected[1] = 0;
+        assert_eq!(
+            resolve(versioned_hash(1, 1), address, true),
+            Some((expected, false))
+        );
+        assert_eq!(
+            resolve(versioned_hash(2, 0), address, false),
+            Some((EVM_INTERPRETER_CODE_HASH, true))
+        );
+
+        // Calls to addresses without code or with code in the wrong construction state go to the default account.
+        for (code_info, is_constructor_call) in [
+            ([0; 32], false),
+            ([0; 32], true),
+            (constructed, true),
+            (versioned_hash(1, 1), false),
+            (versioned_hash(2, 1), false),
+            // Malformed hashes
+            (versioned_hash(1, 2), false),
+            (versioned_hash(3, 0), false),
+            (with_len(1, 0, 0), false),
+            (with_len(1, 0, 2), false),
+            (with_len(2, 0, 0), false),
+        ] {
+            assert_eq!(
+                resolve(code_info, address, is_constructor_call),
+                Some((DEFAULT_AA_CODE_HASH, false))
+            );
+        }
+        // ...unless the called address is a kernel address.
+        assert_eq!(resolve([0; 32], U256::from(0x8001), false), None);
+        assert_eq!(resolve(with_len(1, 0, 2), U256::from(0x8001), false), None);
+    }
+}
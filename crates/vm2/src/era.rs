@@ -0,0 +1,137 @@
+//! Conversions of VM outputs into [`zksync_types`] structures used by the ZKsync Era server.
+//!
+//! Era events are located in an L1 batch, which is not known to the VM, so the batch number is passed
+//! to [`vm_event()`] / [`vm_events()`] by the caller.
+
+use primitive_types::{H160, H256, U256};
+use zksync_types::{
+    l2_to_l1_log, AccountTreeId, L1BatchNumber, StorageKey, StorageLog, StorageLogKind, VmEvent,
+};
+use zksync_vm2_interface::{Event, L2ToL1Log};
+
+use crate::{merge_events, MergedEvent, StorageChange, WorldDiff};
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut hash = H256::zero();
+    value.to_big_endian(hash.as_bytes_mut());
+    hash
+}
+
+/// Converts a storage change into a write log.
+pub fn storage_log((contract, key): (H160, U256), change: &StorageChange) -> StorageLog {
+    StorageLog {
+        kind: if change.is_initial {
+            StorageLogKind::InitialWrite
+        } else {
+            StorageLogKind::RepeatedWrite
+        },
+        key: StorageKey::new(AccountTreeId::new(contract), u256_to_h256(key)),
+        value: u256_to_h256(change.after),
+    }
+}
+
+/// Returns write logs for all storage changes in `world_diff` (see [`WorldDiff::get_storage_changes()`]).
+pub fn storage_logs(world_diff: &WorldDiff) -> Vec<StorageLog> {
+    world_diff
+        .get_storage_changes()
+        .map(|(key, change)| storage_log(key, &change))
+        .collect()
+}
+
+/// Converts an L2-to-L1 log.
+pub fn l2_to_l1_log(log: &L2ToL1Log) -> l2_to_l1_log::L2ToL1Log {
+    l2_to_l1_log::L2ToL1Log {
+        shard_id: log.shard_id,
+        is_service: log.is_service,
+        tx_number_in_block: log.tx_number,
+        sender: log.address,
+        key: u256_to_h256(log.key),
+        value: u256_to_h256(log.value),
+    }
+}
+
+/// Returns all L2-to-L1 logs emitted during VM execution.
+pub fn l2_to_l1_logs(world_diff: &WorldDiff) -> Vec<l2_to_l1_log::L2ToL1Log> {
+    world_diff
+        .l2_to_l1_logs()
+        .iter()
+        .map(l2_to_l1_log)
+        .collect()
+}
+
+/// Converts a merged event emitted in the specified L1 batch.
+pub fn vm_event(event: &MergedEvent, l1_batch_number: L1BatchNumber) -> VmEvent {
+    VmEvent {
+        location: (l1_batch_number, event.tx_number.into()),
+        address: event.address,
+        indexed_topics: event.topics.iter().copied().map(u256_to_h256).collect(),
+        value: event.data.clone(),
+    }
+}
+
+/// Merges raw events (see [`merge_events()`]) emitted in the specified L1 batch and converts them.
+pub fn vm_events(
+    events: impl IntoIterator<Item = Event>,
+    l1_batch_number: L1BatchNumber,
+) -> Vec<VmEvent> {
+    merge_events(events)
+        .iter()
+        .map(|event| vm_event(event, l1_batch_number))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converting_storage_change() {
+        let contract = H160::repeat_byte(1);
+        let change = StorageChange {
+            before: 1.into(),
+            after: 0x1234.into(),
+            is_initial: true,
+        };
+        let log = storage_log((contract, 5.into()), &change);
+        assert_eq!(log.kind, StorageLogKind::InitialWrite);
+        assert_eq!(*log.key.address(), contract);
+        assert_eq!(*log.key.key(), H256::from_low_u64_be(5));
+        assert_eq!(log.value, H256::from_low_u64_be(0x1234));
+    }
+
+    #[test]
+    fn converting_l2_to_l1_log() {
+        let log = L2ToL1Log {
+            key: 1.into(),
+            value: 2.into(),
+            is_service: true,
+            address: H160::repeat_byte(0x80),
+            shard_id: 0,
+            tx_number: 3,
+        };
+        let converted = l2_to_l1_log(&log);
+        assert_eq!(converted.sender, log.address);
+        assert_eq!(converted.tx_number_in_block, 3);
+        assert!(converted.is_service);
+        assert_eq!(converted.key, H256::from_low_u64_be(1));
+        assert_eq!(converted.value, H256::from_low_u64_be(2));
+    }
+
+    #[test]
+    fn converting_event() {
+        let event = MergedEvent {
+            address: H160::repeat_byte(0x23),
+            topics: vec![1.into(), 2.into()],
+            data: vec![0xab; 40],
+            tx_number: 3,
+        };
+        let converted = vm_event(&event, L1BatchNumber(5));
+        assert_eq!(converted.location, (L1BatchNumber(5), 3));
+        assert_eq!(converted.address, event.address);
+        assert_eq!(
+            converted.indexed_topics,
+            [H256::from_low_u64_be(1), H256::from_low_u64_be(2)]
+        );
+        assert_eq!(converted.value, event.data);
+    }
+}
@@ -0,0 +1,162 @@
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::Event;
+
+use crate::decommit::u256_into_address;
+
+/// Ethereum-style event assembled from a chain of raw EraVM [`Event`]s by [`merge_events()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedEvent {
+    /// Address of the contract that emitted the event.
+    pub address: H160,
+    /// Indexed topics of the event.
+    pub topics: Vec<U256>,
+    /// Non-indexed event data.
+    pub data: Vec<u8>,
+    /// 0-based index of a transaction that has emitted this event.
+    pub tx_number: u16,
+}
+
+/// Event being assembled by [`merge_events()`].
+struct PendingEvent {
+    event: MergedEvent,
+    remaining_topics: u32,
+    remaining_data_len: usize,
+}
+
+impl PendingEvent {
+    fn new(first: &Event) -> Self {
+        // The event writer packs the number of topics and the data length into the first event key.
+        let remaining_topics = first.key.low_u32();
+        #[allow(clippy::cast_possible_truncation)] // truncation is intentional
+        let remaining_data_len = (first.key.0[0] >> 32) as u32 as usize;
+        Self {
+            event: MergedEvent {
+                address: u256_into_address(first.value),
+                topics: vec![],
+                data: vec![],
+                tx_number: first.tx_number,
+            },
+            remaining_topics,
+            remaining_data_len,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining_topics == 0 && self.remaining_data_len == 0
+    }
+
+    fn push_word(&mut self, word: U256) {
+        if self.remaining_topics > 0 {
+            self.event.topics.push(word);
+            self.remaining_topics -= 1;
+        } else if self.remaining_data_len > 0 {
+            let mut bytes = [0_u8; 32];
+            word.to_big_endian(&mut bytes);
+            let len = self.remaining_data_len.min(32);
+            self.event.data.extend_from_slice(&bytes[..len]);
+            self.remaining_data_len -= len;
+        }
+    }
+}
+
+/// Merges raw events recorded by the VM into Ethereum-style events.
+///
+/// The event writer system contract emits each event as a chain of raw events. The first one has
+/// [`Event::is_first`] set; its key packs the number of topics (low 32 bits) and the data length in bytes (next 32 bits),
+/// and its value is the emitter address. Each subsequent raw event carries two words (key first); topics come first,
+/// followed by data split into 32-byte chunks. Chains that are interrupted before all topics and data are received
+/// are skipped.
+pub fn merge_events(events: impl IntoIterator<Item = Event>) -> Vec<MergedEvent> {
+    let mut merged = vec![];
+    let mut pending: Option<PendingEvent> = None;
+    for event in events {
+        if event.is_first {
+            if let Some(complete) = pending.take().filter(PendingEvent::is_complete) {
+                merged.push(complete.event);
+            }
+            pending = Some(PendingEvent::new(&event));
+        } else if let Some(current) = &mut pending {
+            current.push_word(event.key);
+            current.push_word(event.value);
+        }
+    }
+    if let Some(complete) = pending.filter(PendingEvent::is_complete) {
+        merged.push(complete.event);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction_handlers::address_into_u256;
+
+    fn first_event(address: H160, topics: u64, data_len: u64) -> Event {
+        Event {
+            key: (topics | (data_len << 32)).into(),
+            value: address_into_u256(address),
+            is_first: true,
+            shard_id: 0,
+            tx_number: 1,
+        }
+    }
+
+    fn continuation(key: U256, value: U256) -> Event {
+        Event {
+            key,
+            value,
+            is_first: false,
+            shard_id: 0,
+            tx_number: 1,
+        }
+    }
+
+    #[test]
+    fn merging_events() {
+        let address = H160::repeat_byte(0x23);
+        let data_word = U256::from_big_endian(&[0xab; 32]);
+        let events = [
+            first_event(address, 3, 40),
+            continuation(1.into(), 2.into()),
+            continuation(3.into(), data_word),
+            continuation(data_word, data_word),
+            // event without topics and data
+            first_event(address, 0, 0),
+        ];
+
+        let merged = merge_events(events);
+        assert_eq!(
+            merged,
+            [
+                MergedEvent {
+                    address,
+                    topics: vec![1.into(), 2.into(), 3.into()],
+                    data: vec![0xab; 40],
+                    tx_number: 1,
+                },
+                MergedEvent {
+                    address,
+                    topics: vec![],
+                    data: vec![],
+                    tx_number: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_events_are_skipped() {
+        let address = H160::repeat_byte(0x23);
+        let events = [
+            first_event(address, 3, 0),
+            continuation(1.into(), 2.into()),
+            first_event(address, 1, 0),
+            continuation(1.into(), 0.into()),
+            first_event(address, 0, 64),
+        ];
+
+        let merged = merge_events(events);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].topics, [U256::one()]);
+    }
+}
@@ -0,0 +1,784 @@
+//! High-level execution of transactions without a bootloader.
+//!
+//! [`execute_transaction()`] calls a single contract deployed in the world, runs the VM to completion and collects
+//! the outcome into a [`TxResult`]. Unlike in the full protocol, there is no bootloader: no fees are charged, no nonces
+//! are checked and no account abstraction is involved. This is mostly useful for tools and tests that need to run
+//! contract code against a world state. [`simulate_call()`] is a read-only counterpart suitable for view calls,
+//! and [`estimate_gas()`] finds the minimum gas limit for which a transaction succeeds.
+
+use std::{collections::BTreeMap, error, fmt};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::L2ToL1Log;
+
+use crate::{
+    decommit::initial_decommit, instruction_handlers::address_into_u256,
+    isa::system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW, merge_events, AccessList, BuildError,
+    ExecutionEnd, MergedEvent, PanicInfo, RefundPolicy, Settings, StorageChange, StorageInterface,
+    VirtualMachine, World,
+};
+
+/// Call of a single contract executed by [`execute_transaction()`].
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    /// Address of the called contract. Its code is decommitted from the world.
+    pub to: H160,
+    /// Caller of the contract.
+    pub caller: H160,
+    /// Calldata passed to the contract.
+    pub calldata: Vec<u8>,
+    /// Gas available to the transaction.
+    pub gas: u32,
+}
+
+/// Configuration of the VM executing a [`Transaction`].
+#[derive(Debug, Clone)]
+pub struct ExecutionConfig {
+    /// VM settings.
+    pub settings: Settings,
+    /// Rules for crediting gas refunds.
+    pub refund_policy: RefundPolicy,
+    /// Limit of memory usage in bytes.
+    pub memory_limit: Option<usize>,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            settings: Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+            refund_policy: RefundPolicy::immediate(),
+            memory_limit: None,
+        }
+    }
+}
+
+/// Final status of an executed [`Transaction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    /// The called contract has returned successfully.
+    Success,
+    /// The called contract has reverted.
+    Reverted,
+    /// The called contract has panicked, e.g. by running out of gas.
+    Panicked,
+    /// Memory used by the VM exceeded [`ExecutionConfig::memory_limit`].
+    MemoryLimitExceeded,
+    /// The called contract has called a precompile not supported by the VM; see
+    /// [`ExecutionEnd::UnsupportedPrecompile`].
+    UnsupportedPrecompile(u16),
+}
+
+/// Outcome of a [`Transaction`] executed by [`execute_transaction()`].
+#[derive(Debug)]
+pub struct TxResult {
+    /// Final status of the transaction.
+    pub status: TxStatus,
+    /// Data returned by the called contract on success or revert; empty otherwise.
+    pub returndata: Vec<u8>,
+    /// Why the called contract has panicked if the status is [`TxStatus::Panicked`].
+    pub panic: Option<PanicInfo>,
+    /// Events emitted by the transaction. Empty unless the status is [`TxStatus::Success`].
+    pub events: Vec<MergedEvent>,
+    /// L2-to-L1 logs emitted by the transaction. Empty unless the status is [`TxStatus::Success`].
+    pub l2_to_l1_logs: Vec<L2ToL1Log>,
+    /// Gas spent by the transaction.
+    pub gas_used: u32,
+    /// Storage changes made by the transaction. Empty unless the status is [`TxStatus::Success`].
+    pub storage_changes: BTreeMap<(H160, U256), StorageChange>,
+    /// Contracts and storage slots accessed by the transaction. Unlike changes, accesses are reported regardless
+    /// of the status, since they affect gas costs.
+    pub access_list: AccessList,
+}
+
+/// Error executing a [`Transaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExecutionError {
+    /// No contract is deployed at the called address.
+    MissingContract(H160),
+    /// The VM couldn't be created.
+    Build(BuildError),
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingContract(address) => {
+                write!(formatter, "no contract is deployed at {address:?}")
+            }
+            Self::Build(err) => write!(formatter, "failed building VM: {err}"),
+        }
+    }
+}
+
+impl error::Error for ExecutionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::MissingContract(_) => None,
+            Self::Build(err) => Some(err),
+        }
+    }
+}
+
+/// Outcome of a read-only call executed by [`simulate_call()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallResult {
+    /// Final status of the call.
+    pub status: TxStatus,
+    /// Data returned by the called contract on success or revert; empty otherwise.
+    pub returndata: Vec<u8>,
+    /// Why the called contract has panicked if the status is [`TxStatus::Panicked`].
+    pub panic: Option<PanicInfo>,
+    /// Gas spent by the call.
+    pub gas_used: u32,
+    /// Contracts and storage slots accessed by the call.
+    pub access_list: AccessList,
+}
+
+/// Executes a single [`Transaction`] against the provided world.
+///
+/// Changes made by the transaction are not applied to the world; they are returned in [`TxResult`] instead.
+/// The VM doesn't roll back changes made by the initial frame when it reverts or panics, so changes are only returned
+/// for successful transactions; failed transactions are reported as having no effect besides spent gas.
+/// The execution is resumed after each [hook](ExecutionEnd::SuspendedOnHook), so it always runs to completion.
+///
+/// # Errors
+///
+/// Returns an error if the called contract is not deployed, or the VM cannot be created from the provided params.
+pub fn execute_transaction<W: World<()>>(
+    world: &mut W,
+    tx: &Transaction,
+    config: &ExecutionConfig,
+) -> Result<TxResult, ExecutionError> {
+    let mut vm = create_vm(world, tx, config)?;
+    let (status, returndata, panic) = run_to_completion(&mut vm, world);
+
+    let world_diff = vm.world_diff();
+    let (events, l2_to_l1_logs, storage_changes) = if status == TxStatus::Success {
+        (
+            merge_events(world_diff.events().iter().copied()),
+            world_diff.l2_to_l1_logs().to_vec(),
+            world_diff.get_storage_changes().collect(),
+        )
+    } else {
+        Default::default()
+    };
+    Ok(TxResult {
+        status,
+        returndata,
+        panic,
+        events,
+        l2_to_l1_logs,
+        gas_used: tx.gas.saturating_sub(vm.state.total_unspent_gas()),
+        storage_changes,
+        access_list: access_list(&vm, tx),
+    })
+}
+
+/// Executes a read-only call, e.g. to serve `eth_call` RPC requests for view functions.
+///
+/// The called contract is executed in static mode, so it and its callees cannot change state; instructions that
+/// would do so panic. Pubdata is not charged for. The VM and its [`WorldDiff`](crate::WorldDiff) are discarded after
+/// the call, so only its output is returned.
+///
+/// # Errors
+///
+/// Returns an error if the called contract is not deployed, or the VM cannot be created from the provided params.
+pub fn simulate_call<W: World<()>>(
+    world: &mut W,
+    tx: &Transaction,
+    config: &ExecutionConfig,
+) -> Result<CallResult, ExecutionError> {
+    let mut vm = create_vm(world, tx, config)?;
+    vm.state.current_frame.is_static = true;
+    vm.set_ergs_per_pubdata_byte(0);
+    let (status, returndata, panic) = run_to_completion(&mut vm, world);
+    Ok(CallResult {
+        status,
+        returndata,
+        panic,
+        gas_used: tx.gas.saturating_sub(vm.state.total_unspent_gas()),
+        access_list: access_list(&vm, tx),
+    })
+}
+
+/// Bounds of the gas limit searched by [`estimate_gas()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasBounds {
+    /// Minimum gas limit to try.
+    pub min: u32,
+    /// Maximum gas limit to try.
+    pub max: u32,
+}
+
+impl Default for GasBounds {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: u32::MAX,
+        }
+    }
+}
+
+/// Gas estimate returned by [`estimate_gas()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    /// Minimum gas limit for which the transaction succeeds.
+    pub gas_limit: u32,
+    /// Gas spent by the transaction with this limit.
+    pub gas_used: u32,
+    /// Number of times the transaction was executed.
+    pub attempts: u32,
+}
+
+/// Error estimating gas using [`estimate_gas()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GasEstimationError {
+    /// The transaction couldn't be executed.
+    Execution(ExecutionError),
+    /// The transaction doesn't succeed even with the maximum gas limit.
+    Failed(CallResult),
+}
+
+impl fmt::Display for GasEstimationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Execution(err) => fmt::Display::fmt(err, formatter),
+            Self::Failed(result) => write!(
+                formatter,
+                "transaction fails with the maximum gas limit (status: {:?})",
+                result.status
+            ),
+        }
+    }
+}
+
+impl error::Error for GasEstimationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Execution(err) => Some(err),
+            Self::Failed(_) => None,
+        }
+    }
+}
+
+impl From<ExecutionError> for GasEstimationError {
+    fn from(err: ExecutionError) -> Self {
+        Self::Execution(err)
+    }
+}
+
+/// Estimates the minimum gas limit within `bounds` for which a [`Transaction`] succeeds, i.e. returns without
+/// reverting or panicking. The gas limit specified in the transaction is ignored.
+///
+/// The transaction is executed repeatedly, binary-searching the gas limit, so it's assumed that the transaction
+/// succeeding with some gas limit also succeeds with any larger one. All attempts are executed by the same VM,
+/// which is rolled back to its initial state after each attempt; thus, programs decoded during the first attempt
+/// and VM memory buffers are reused.
+///
+/// # Errors
+///
+/// Returns an error if the transaction cannot be executed, or fails with the maximum gas limit.
+///
+/// # Panics
+///
+/// Panics if the minimum bound exceeds the maximum one.
+pub fn estimate_gas<W: World<()>>(
+    world: &mut W,
+    tx: &Transaction,
+    bounds: GasBounds,
+    config: &ExecutionConfig,
+) -> Result<GasEstimate, GasEstimationError> {
+    assert!(bounds.min <= bounds.max, "invalid gas bounds: {bounds:?}");
+
+    let mut vm = create_vm(world, tx, config)?;
+    let mut attempts = 0;
+    let mut execute = |world: &mut W, gas| {
+        attempts += 1;
+        vm.make_snapshot();
+        vm.state.current_frame.gas = gas;
+        let (status, returndata, panic) = run_to_completion(&mut vm, world);
+        let result = CallResult {
+            status,
+            returndata,
+            panic,
+            gas_used: gas.saturating_sub(vm.state.total_unspent_gas()),
+            access_list: access_list(&vm, tx),
+        };
+        if vm.state.previous_frames.is_empty() {
+            vm.rollback();
+        } else {
+            // Exceeding the memory limit can stop the VM in a nested frame, in which case it cannot be rolled back.
+            vm = create_vm(world, tx, config)?;
+        }
+        Ok::<_, ExecutionError>(result)
+    };
+
+    let mut result = execute(world, bounds.max)?;
+    if result.status != TxStatus::Success {
+        return Err(GasEstimationError::Failed(result));
+    }
+    // Invariant: the transaction succeeds with `succeeding_gas` and fails with all limits below `min_gas`.
+    let mut succeeding_gas = bounds.max;
+    let mut min_gas = bounds.min;
+    while min_gas < succeeding_gas {
+        let gas = min_gas + (succeeding_gas - min_gas) / 2;
+        let attempt = execute(world, gas)?;
+        if attempt.status == TxStatus::Success {
+            succeeding_gas = gas;
+            result = attempt;
+        } else {
+            min_gas = gas + 1;
+        }
+    }
+
+    Ok(GasEstimate {
+        gas_limit: succeeding_gas,
+        gas_used: result.gas_used,
+        attempts,
+    })
+}
+
+fn create_vm<W: World<()>>(
+    world: &mut W,
+    tx: &Transaction,
+    config: &ExecutionConfig,
+) -> Result<VirtualMachine<(), W>, ExecutionError> {
+    let deployer_system_contract_address =
+        H160::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+    let code_info =
+        world.read_storage_value(deployer_system_contract_address, address_into_u256(tx.to));
+    if code_info.is_zero() {
+        return Err(ExecutionError::MissingContract(tx.to));
+    }
+    let program = initial_decommit(world, tx.to);
+
+    let mut builder = VirtualMachine::builder()
+        .address(tx.to)
+        .program(program)
+        .caller(tx.caller)
+        .calldata(tx.calldata.as_slice())
+        .gas(tx.gas)
+        .settings(config.settings.clone())
+        .refund_policy(config.refund_policy);
+    if let Some(limit) = config.memory_limit {
+        builder = builder.memory_limit(limit);
+    }
+    builder.build().map_err(ExecutionError::Build)
+}
+
+/// Unlike [`WorldDiff::access_list()`](crate::WorldDiff::access_list()), includes the called contract.
+fn access_list<W: World<()>>(vm: &VirtualMachine<(), W>, tx: &Transaction) -> AccessList {
+    let mut access_list = vm.world_diff().access_list();
+    access_list.contracts.insert(tx.to);
+    access_list
+}
+
+fn run_to_completion<W: World<()>>(
+    vm: &mut VirtualMachine<(), W>,
+    world: &mut W,
+) -> (TxStatus, Vec<u8>, Option<PanicInfo>) {
+    let end = loop {
+        match vm.run(world, &mut ()) {
+            ExecutionEnd::SuspendedOnHook(_) => {}
+            end => break end,
+        }
+    };
+    match end {
+        ExecutionEnd::ProgramFinished(output) => (TxStatus::Success, output, None),
+        ExecutionEnd::Reverted(output) => (TxStatus::Reverted, output, None),
+        // The initial frame is the last one to panic.
+        ExecutionEnd::Panicked => (TxStatus::Panicked, vec![], vm.last_panic()),
+        ExecutionEnd::MemoryLimitExceeded => (TxStatus::MemoryLimitExceeded, vec![], None),
+        ExecutionEnd::UnsupportedPrecompile(address_low) => {
+            (TxStatus::UnsupportedPrecompile(address_low), vec![], None)
+        }
+        ExecutionEnd::SuspendedOnHook(_)
+        | ExecutionEnd::StoppedByTracer
+        | ExecutionEnd::InstructionLimit => {
+            unreachable!("execution cannot be stopped without a tracer or instruction limit")
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::{ethereum_types::Address, ADDRESS_EVENT_WRITER};
+    use zksync_vm2_interface::{opcodes, PanicReason};
+
+    use super::*;
+    use crate::{
+        addressing_modes::{
+            Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+            SSTORE_COST,
+        },
+        testonly::TestWorld,
+        Instruction, ModeRequirements, Predicate, Program,
+    };
+
+    /// Program writing calldata length to the storage slot 0, emitting an empty event and an L2-to-L1 log,
+    /// and ending with the specified status.
+    fn program(status: TxStatus) -> Program<(), TestWorld<()>> {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let r2 = Register::new(2);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let end = match status {
+            TxStatus::Success => Instruction::from_ret(Register1(r0), None, arguments(5)),
+            TxStatus::Reverted => Instruction::from_revert(Register1(r0), None, arguments(5)),
+            TxStatus::Panicked => Instruction::from_panic(None, arguments(5)),
+            TxStatus::MemoryLimitExceeded | TxStatus::UnsupportedPrecompile(_) => unreachable!(),
+        };
+        Program::from_raw(
+            vec![
+                // The calldata length is stored in the high 32 bits of the second fat pointer word.
+                Instruction::from_shift_right(
+                    Immediate1(96).into(),
+                    Register2(r1),
+                    Register1(r2).into(),
+                    arguments(6),
+                    true,
+                    false,
+                ),
+                Instruction::from_storage_write(
+                    Register1(r0),
+                    Register2(r2),
+                    arguments(SSTORE_COST),
+                ),
+                Instruction::from_event(Register1(r0), Register2(r0), true, arguments(5)),
+                Instruction::from_l2_to_l1_message(
+                    Register1(r0),
+                    Register2(r2),
+                    false,
+                    arguments(5),
+                ),
+                end,
+            ],
+            vec![],
+        )
+    }
+
+    fn transaction(to: Address) -> Transaction {
+        Transaction {
+            to,
+            caller: Address::repeat_byte(1),
+            calldata: vec![0; 40],
+            gas: 100_000,
+        }
+    }
+
+    #[test]
+    fn executing_transaction() {
+        // Events are only recorded for the event writer contract.
+        let address = Address::from_low_u64_be(ADDRESS_EVENT_WRITER.into());
+        let mut world = TestWorld::new(&[(address, program(TxStatus::Success))]);
+        let result = execute_transaction(
+            &mut world,
+            &transaction(address),
+            &ExecutionConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(result.status, TxStatus::Success);
+        assert!(result.returndata.is_empty());
+        assert_eq!(
+            result.events,
+            [MergedEvent {
+                address: Address::zero(),
+                topics: vec![],
+                data: vec![],
+                tx_number: 0,
+            }]
+        );
+        assert_eq!(result.l2_to_l1_logs.len(), 1);
+        assert_eq!(result.l2_to_l1_logs[0].value, 40.into());
+        assert!(result.gas_used > 5000 && result.gas_used < 100_000);
+        let change = &result.storage_changes[&(address, U256::zero())];
+        assert_eq!(change.after, 40.into());
+        assert_eq!(result.access_list.contracts, [address].into());
+        assert_eq!(
+            result.access_list.written_slots,
+            [(address, U256::zero())].into()
+        );
+        assert!(result.access_list.read_slots.is_empty());
+    }
+
+    #[test]
+    fn executing_failed_transactions() {
+        let address = Address::from_low_u64_be(ADDRESS_EVENT_WRITER.into());
+        for status in [TxStatus::Reverted, TxStatus::Panicked] {
+            let mut world = TestWorld::new(&[(address, program(status))]);
+            let result = execute_transaction(
+                &mut world,
+                &transaction(address),
+                &ExecutionConfig::default(),
+            )
+            .unwrap();
+
+            assert_eq!(result.status, status);
+            // Changes in the initial frame are not rolled back by the VM, but must not be reported.
+            assert!(result.events.is_empty(), "{status:?}");
+            assert!(result.l2_to_l1_logs.is_empty(), "{status:?}");
+            assert!(result.storage_changes.is_empty(), "{status:?}");
+            assert!(result.gas_used > 0, "{status:?}");
+            assert_eq!(
+                result.access_list.written_slots,
+                [(address, U256::zero())].into(),
+                "{status:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn exceeding_memory_limit_in_far_call() {
+        let r0 = Register::new(0);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let load_word = |immediate, register| {
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(register).into(),
+                arguments(6),
+                false,
+                false,
+            )
+        };
+        let address = Address::repeat_byte(0x23);
+        let called_address = Address::repeat_byte(0x42);
+        // Pass all gas to the called contract, and use no calldata.
+        let far_call_abi = U256([0, 0, 0, u32::MAX.into()]);
+        let main_program = Program::from_raw(
+            vec![
+                load_word(0, Register::new(1)),
+                load_word(1, Register::new(2)),
+                Instruction::from_far_call::<opcodes::Normal>(
+                    Register1(Register::new(1)),
+                    Register2(Register::new(2)),
+                    Immediate1(4),
+                    false,
+                    false,
+                    arguments(200),
+                ),
+                Instruction::from_ret(Register1(r0), None, arguments(5)),
+                // 4: exception handler
+                Instruction::from_revert(Register1(r0), None, arguments(5)),
+            ],
+            vec![far_call_abi, address_into_u256(called_address)],
+        );
+        let called_program = Program::from_raw(
+            vec![Instruction::from_ret(Register1(r0), None, arguments(5))],
+            vec![],
+        );
+        let mut world =
+            TestWorld::new(&[(address, main_program), (called_address, called_program)]);
+        let tx = transaction(address);
+
+        let result = execute_transaction(&mut world, &tx, &ExecutionConfig::default()).unwrap();
+        assert_eq!(result.status, TxStatus::Success);
+
+        // The new frame and the decommitted program exceed the limit, so the VM stops in the called frame.
+        let initial_usage = create_vm(&mut world, &tx, &ExecutionConfig::default())
+            .unwrap()
+            .memory_usage();
+        let config = ExecutionConfig {
+            memory_limit: Some(initial_usage + 1),
+            ..ExecutionConfig::default()
+        };
+        let limited_result = execute_transaction(&mut world, &tx, &config).unwrap();
+        assert_eq!(limited_result.status, TxStatus::MemoryLimitExceeded);
+        // Gas passed to the called frame is not spent.
+        assert!(limited_result.gas_used > 0);
+        assert!(limited_result.gas_used <= result.gas_used);
+    }
+
+    #[test]
+    fn executing_transaction_for_missing_contract() {
+        let mut world = TestWorld::new(&[]);
+        let address = Address::repeat_byte(0x23);
+        let err = execute_transaction(
+            &mut world,
+            &transaction(address),
+            &ExecutionConfig::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ExecutionError::MissingContract(address));
+    }
+
+    /// Program returning 42 as a single word, optionally writing to storage beforehand.
+    fn view_program(write_storage: bool) -> Program<(), TestWorld<()>> {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let mut instructions = vec![Instruction::from_add(
+            Immediate1(42).into(),
+            Register2(r0),
+            Register1(r1).into(),
+            arguments(6),
+            false,
+            false,
+        )];
+        if write_storage {
+            instructions.push(Instruction::from_storage_write(
+                Register1(r0),
+                Register2(r1),
+                Arguments::new(
+                    Predicate::Always,
+                    SSTORE_COST,
+                    ModeRequirements::new(false, true),
+                ),
+            ));
+        }
+        instructions.extend([
+            Instruction::from_heap_write(
+                Immediate1(0).into(),
+                Register2(r1),
+                None,
+                arguments(7),
+                false,
+            ),
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                arguments(6),
+                false,
+                false,
+            ),
+            Instruction::from_ret(Register1(r1), None, arguments(5)),
+        ]);
+
+        // Return the first heap word.
+        let mut abi = U256::zero();
+        abi.0[1] = 32 << 32;
+        Program::from_raw(instructions, vec![abi])
+    }
+
+    #[test]
+    fn simulating_call() {
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, view_program(false))]);
+        let result = simulate_call(
+            &mut world,
+            &transaction(address),
+            &ExecutionConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(result.status, TxStatus::Success);
+        let mut expected_output = [0; 32];
+        expected_output[31] = 42;
+        assert_eq!(result.returndata, expected_output);
+        assert!(result.gas_used > 0);
+    }
+
+    #[test]
+    fn simulated_call_cannot_change_state() {
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, view_program(true))]);
+        let result = simulate_call(
+            &mut world,
+            &transaction(address),
+            &ExecutionConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(result.status, TxStatus::Panicked);
+        assert!(result.returndata.is_empty());
+        // The storage write is the second instruction.
+        let panic = result.panic.unwrap();
+        assert_eq!(panic.reason, PanicReason::PrivilegeViolation);
+        assert_eq!((panic.address, panic.pc), (address, Some(1)));
+
+        // The same program executed as a transaction changes storage.
+        let result = execute_transaction(
+            &mut world,
+            &transaction(address),
+            &ExecutionConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(result.status, TxStatus::Success);
+        assert_eq!(result.panic, None);
+        assert_eq!(result.storage_changes.len(), 1);
+    }
+
+    /// Program doing some arithmetic and returning if it has at least 17 gas.
+    fn gas_dependent_program(revert: bool) -> Program<(), TestWorld<()>> {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let add = || {
+            Instruction::from_add(
+                Immediate1(1).into(),
+                Register2(r1),
+                Register1(r1).into(),
+                arguments(6),
+                false,
+                false,
+            )
+        };
+        let end = if revert {
+            Instruction::from_revert(Register1(r0), None, arguments(5))
+        } else {
+            Instruction::from_ret(Register1(r0), None, arguments(5))
+        };
+        Program::from_raw(vec![add(), add(), end], vec![])
+    }
+
+    #[test]
+    fn estimating_gas() {
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, gas_dependent_program(false))]);
+        let tx = transaction(address);
+        let config = ExecutionConfig::default();
+
+        let estimate = estimate_gas(&mut world, &tx, GasBounds::default(), &config).unwrap();
+        assert_eq!(estimate.gas_limit, 17);
+        assert_eq!(estimate.gas_used, 17);
+        // One attempt with the maximum gas, and then 32 binary search iterations.
+        assert_eq!(estimate.attempts, 33);
+
+        let bounds = GasBounds { min: 16, max: 20 };
+        let estimate = estimate_gas(&mut world, &tx, bounds, &config).unwrap();
+        assert_eq!(estimate.gas_limit, 17);
+
+        let bounds = GasBounds { min: 100, max: 200 };
+        let estimate = estimate_gas(&mut world, &tx, bounds, &config).unwrap();
+        assert_eq!(estimate.gas_limit, 100);
+
+        let bounds = GasBounds { min: 0, max: 16 };
+        let err = estimate_gas(&mut world, &tx, bounds, &config).unwrap_err();
+        let GasEstimationError::Failed(result) = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(result.status, TxStatus::Panicked);
+    }
+
+    #[test]
+    fn estimating_gas_for_reverting_transaction() {
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, gas_dependent_program(true))]);
+        let err = estimate_gas(
+            &mut world,
+            &transaction(address),
+            GasBounds::default(),
+            &ExecutionConfig::default(),
+        )
+        .unwrap_err();
+        let GasEstimationError::Failed(result) = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(result.status, TxStatus::Reverted);
+    }
+}
@@ -0,0 +1,361 @@
+//! Shared executor of VM runs, e.g. for serving many concurrent `eth_call`s on an RPC node.
+//!
+//! An [`Executor`] owns a fixed set of worker threads, together with resources shared by all VMs it runs:
+//! a [`ProgramCache`] for decoded programs and a [`PoolingAllocator`] for heap pages and stacks. Each submitted job
+//! receives an [`ExecutorContext`] providing these resources; it creates the world and the VM for its call,
+//! runs the VM and returns an arbitrary result, which can be awaited via the returned [`JobHandle`].
+//!
+//! Since VMs created via [`ExecutorContext::builder()`] obtain their buffers from the shared allocator,
+//! starting a VM on a warmed-up executor mostly reuses memory released by previous jobs instead of allocating it.
+
+use std::{
+    any::Any,
+    error, fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread,
+};
+
+use zksync_vm2_interface::Tracer;
+
+use crate::{
+    allocator::{Allocator, PoolingAllocator},
+    ProgramCache, VirtualMachine, VirtualMachineBuilder, World,
+};
+
+/// Resources shared by all jobs run by an [`Executor`].
+pub struct ExecutorContext<T, W> {
+    programs: Arc<ProgramCache<T, W>>,
+    allocator: Arc<PoolingAllocator>,
+}
+
+impl<T, W> fmt::Debug for ExecutorContext<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ExecutorContext")
+            .field("programs.len", &self.programs.len())
+            .field("allocator", &self.allocator)
+            .finish()
+    }
+}
+
+impl<T, W> ExecutorContext<T, W> {
+    /// Returns the shared program cache. It can be cloned into [`World`]s created by jobs, so that
+    /// [`World::decommit()`] decodes each bytecode only once across all jobs.
+    pub fn programs(&self) -> &Arc<ProgramCache<T, W>> {
+        &self.programs
+    }
+
+    /// Returns the shared allocator of heap pages and stacks.
+    pub fn allocator(&self) -> &Arc<PoolingAllocator> {
+        &self.allocator
+    }
+}
+
+impl<T: Tracer, W: World<T>> ExecutorContext<T, W> {
+    /// Creates a VM builder using the shared allocator.
+    pub fn builder(&self) -> VirtualMachineBuilder<T, W> {
+        VirtualMachine::builder().allocator(self.allocator.clone())
+    }
+}
+
+type Job<T, W> = Box<dyn FnOnce(&ExecutorContext<T, W>) + Send>;
+
+/// Error awaiting a job result via [`JobHandle::wait()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JobError {
+    /// The job has panicked. Contains the panic message if it's a string.
+    Panicked(Option<String>),
+    /// The job was dropped without being run.
+    Cancelled,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Panicked(Some(message)) => write!(formatter, "job panicked: {message}"),
+            Self::Panicked(None) => formatter.write_str("job panicked"),
+            Self::Cancelled => formatter.write_str("job was cancelled"),
+        }
+    }
+}
+
+impl error::Error for JobError {}
+
+impl JobError {
+    fn panicked(payload: &(dyn Any + Send)) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|&message| message.to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+        Self::Panicked(message)
+    }
+}
+
+/// Handle to a job submitted to an [`Executor`].
+#[derive(Debug)]
+pub struct JobHandle<R> {
+    receiver: mpsc::Receiver<Result<R, JobError>>,
+}
+
+impl<R> JobHandle<R> {
+    /// Blocks the current thread until the job is finished, and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the job has panicked or was not run.
+    pub fn wait(self) -> Result<R, JobError> {
+        self.receiver.recv().unwrap_or(Err(JobError::Cancelled))
+    }
+
+    /// Returns the job result if the job is finished, or `None` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the job has panicked or was not run.
+    pub fn try_wait(&self) -> Result<Option<R>, JobError> {
+        match self.receiver.try_recv() {
+            Ok(result) => result.map(Some),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(JobError::Cancelled),
+        }
+    }
+}
+
+/// Pool of worker threads running jobs that create and execute VMs. See the [module docs](self) for details.
+///
+/// Jobs are run in the order they are submitted. Dropping the executor waits for all submitted jobs to finish.
+pub struct Executor<T, W> {
+    context: Arc<ExecutorContext<T, W>>,
+    sender: Option<mpsc::Sender<Job<T, W>>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl<T, W> fmt::Debug for Executor<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Executor")
+            .field("context", &self.context)
+            .field("workers", &self.workers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: 'static, W: 'static> Executor<T, W> {
+    /// Starts an executor with the specified number of worker threads. The allocator is warmed up
+    /// with a stack for each worker.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero, or if spawning a worker thread fails.
+    pub fn new(
+        threads: usize,
+        programs: Arc<ProgramCache<T, W>>,
+        allocator: Arc<PoolingAllocator>,
+    ) -> Self {
+        assert!(threads > 0, "number of threads must be positive");
+        let stacks: Vec<_> = (0..threads).map(|_| allocator.allocate_stack()).collect();
+        for stack in stacks {
+            allocator.free_stack(stack);
+        }
+
+        let context = Arc::new(ExecutorContext {
+            programs,
+            allocator,
+        });
+        let (sender, receiver) = mpsc::channel::<Job<T, W>>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads)
+            .map(|i| {
+                let context = context.clone();
+                let receiver = receiver.clone();
+                thread::Builder::new()
+                    .name(format!("vm2-executor-{i}"))
+                    .spawn(move || loop {
+                        // The lock is released before running the job, so that other workers can receive jobs.
+                        let job = receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        let Ok(job) = job else {
+                            break; // The executor is dropped and all jobs are finished.
+                        };
+                        job(&context);
+                    })
+                    .expect("failed spawning executor thread")
+            })
+            .collect();
+
+        Self {
+            context,
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Returns resources shared by jobs, e.g. to pre-populate the program cache.
+    pub fn context(&self) -> &ExecutorContext<T, W> {
+        &self.context
+    }
+
+    /// Submits a job to the executor. A panic in the job is caught and returned from [`JobHandle::wait()`];
+    /// it doesn't affect other jobs.
+    pub fn submit<R, F>(&self, job: F) -> JobHandle<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&ExecutorContext<T, W>) -> R + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let job: Job<T, W> = Box::new(move |context| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| job(context)))
+                .map_err(|payload| JobError::panicked(&*payload));
+            // The handle may be dropped if the job result is not needed.
+            sender.send(result).ok();
+        });
+        // The sender is only removed on drop, and workers only stop after that, so sending cannot fail.
+        if let Some(sender) = &self.sender {
+            sender.send(job).ok();
+        }
+        JobHandle { receiver }
+    }
+}
+
+impl<T, W> Drop for Executor<T, W> {
+    fn drop(&mut self) {
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            // Jobs never panic on workers, so this can only fail if the thread panicked while panicking.
+            worker.join().ok();
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use primitive_types::U256;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{
+            Arguments, Immediate1, Register, Register1, Register2, SLOAD_COST, SSTORE_COST,
+        },
+        testonly::{world_with_program, TestWorld, TEST_PROGRAM_ADDRESS},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, StorageInterface,
+    };
+
+    type TestExecutor = Executor<(), TestWorld<()>>;
+
+    /// Program incrementing the storage slot 0 of the executing contract and returning the new value in `r1`.
+    fn counter_program() -> Program<(), TestWorld<()>> {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        Program::from_raw(
+            vec![
+                Instruction::from_storage_read(Register1(r0), Register1(r1), arguments(SLOAD_COST)),
+                Instruction::from_add(
+                    Immediate1(1).into(),
+                    Register2(r1),
+                    Register1(r1).into(),
+                    arguments(6),
+                    false,
+                    false,
+                ),
+                Instruction::from_storage_write(
+                    Register1(r0),
+                    Register2(r1),
+                    arguments(SSTORE_COST),
+                ),
+                Instruction::from_ret(Register1(r0), None, arguments(5)),
+            ],
+            vec![],
+        )
+    }
+
+    fn executor() -> TestExecutor {
+        let allocator = Arc::new(PoolingAllocator::new(16, 4));
+        Executor::new(2, Arc::default(), allocator)
+    }
+
+    #[test]
+    fn executing_calls_concurrently() {
+        let executor = executor();
+        assert_eq!(executor.context().allocator().pooled_buffers(), (0, 2));
+
+        let address = TEST_PROGRAM_ADDRESS;
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                executor.submit(move |context| {
+                    // Each call is executed against its own copy of the world.
+                    let (mut world, program) = world_with_program(counter_program());
+                    let mut vm = context
+                        .builder()
+                        .address(address)
+                        .program(program)
+                        .gas(100_000)
+                        .build()
+                        .unwrap();
+                    let end = vm.run(&mut world, &mut ());
+                    let slot = vm.world_diff.get_storage_state()[&(address, U256::zero())];
+                    (end, slot, world.read_storage_value(address, U256::zero()))
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let (end, slot, persisted) = handle.wait().unwrap();
+            assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+            assert_eq!(slot, U256::one());
+            assert_eq!(persisted, U256::zero());
+        }
+        let (_, stacks) = executor.context().allocator().pooled_buffers();
+        assert!(stacks > 0);
+    }
+
+    #[test]
+    fn sharing_program_cache() {
+        let executor = executor();
+        let hash = U256::from(42);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                executor.submit(move |context| {
+                    context.programs().get_or_insert_with(hash, counter_program);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.wait().unwrap();
+        }
+        assert_eq!(executor.context().programs().len(), 1);
+        assert!(executor.context().programs().get(hash).is_some());
+    }
+
+    #[test]
+    fn catching_job_panics() {
+        let executor = executor();
+        let handle = executor.submit::<(), _>(|_| panic!("oops"));
+        assert_eq!(
+            handle.wait().unwrap_err(),
+            JobError::Panicked(Some("oops".to_owned()))
+        );
+
+        // The worker keeps running jobs after a panic.
+        let handles: Vec<_> = (0..4).map(|i| executor.submit(move |_| i * 2)).collect();
+        let results: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.wait().unwrap())
+            .collect();
+        assert_eq!(results, [0, 2, 4, 6]);
+    }
+
+    #[test]
+    fn dropping_executor_finishes_jobs() {
+        let executor = executor();
+        let handles: Vec<_> = (0..4).map(|i| executor.submit(move |_| i)).collect();
+        drop(executor);
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.try_wait(), Ok(Some(i)));
+        }
+    }
+}
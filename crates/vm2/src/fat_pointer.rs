@@ -1,9 +1,14 @@
-use std::ptr;
+use std::{ops::Range, ptr};
 
 use primitive_types::U256;
-use zksync_vm2_interface::HeapId;
+use zksync_vm2_interface::{HeapId, StateInterface};
 
 /// Fat pointer to a heap location.
+///
+/// A pointer occupies the lower 128 bits of a `U256` word: `offset` is stored in bits 0..32, `memory_page`
+/// in bits 32..64, `start` in bits 64..96, and `length` in bits 96..128. On little-endian targets, this coincides
+/// with the in-memory layout of this struct, so conversions are no-ops; on big-endian targets, fields are extracted
+/// from the word explicitly.
 #[derive(Debug)]
 #[repr(C)]
 pub struct FatPointer {
@@ -31,10 +36,164 @@ impl From<U256> for FatPointer {
     }
 }
 
+#[cfg(not(target_endian = "little"))]
+impl From<U256> for FatPointer {
+    fn from(value: U256) -> Self {
+        portable::from_u256(value)
+    }
+}
+
+#[cfg_attr(all(target_endian = "little", not(test)), allow(dead_code))]
+mod portable {
+    use primitive_types::U256;
+    use zksync_vm2_interface::HeapId;
+
+    use super::FatPointer;
+
+    #[allow(clippy::cast_possible_truncation)] // truncation is intentional
+    pub(super) fn from_u256(value: U256) -> FatPointer {
+        let [low, high, ..] = value.0;
+        FatPointer {
+            offset: low as u32,
+            memory_page: HeapId::from_u32_unchecked((low >> 32) as u32),
+            start: high as u32,
+            length: (high >> 32) as u32,
+        }
+    }
+
+    pub(super) fn into_u256(pointer: &FatPointer) -> U256 {
+        let low = u64::from(pointer.offset) | (u64::from(pointer.memory_page.as_u32()) << 32);
+        let high = u64::from(pointer.start) | (u64::from(pointer.length) << 32);
+        U256([low, high, 0, 0])
+    }
+}
+
 impl FatPointer {
+    /// Returns the heap range this pointer refers to, i.e. `(start + offset)..(start + length)`.
+    /// The offset is clamped to the pointer length.
+    pub fn range(&self) -> Range<u32> {
+        let end = self.start.saturating_add(self.length);
+        self.start.saturating_add(self.offset.min(self.length))..end
+    }
+
+    /// Reads the bytes this pointer refers to (see [`Self::range()`]) from the VM state. This can be used
+    /// to get returndata from a pointer returned by a far call.
+    pub fn read_from(&self, state: &impl StateInterface) -> Vec<u8> {
+        state.read_heap_range(self.memory_page, self.range())
+    }
+
     /// Converts this pointer into a `U256` word.
     #[cfg(target_endian = "little")]
     pub fn into_u256(self) -> U256 {
         U256::zero() + unsafe { std::mem::transmute::<FatPointer, u128>(self) }
     }
+
+    /// Converts this pointer into a `U256` word.
+    #[cfg(not(target_endian = "little"))]
+    pub fn into_u256(self) -> U256 {
+        portable::into_u256(&self)
+    }
+
+    /// Reads a pointer from `word`, modifies it and writes it back, keeping the upper 128 bits of `word` intact.
+    #[inline(always)]
+    pub(crate) fn update<R>(word: &mut U256, action: impl FnOnce(&mut Self) -> R) -> R {
+        #[cfg(target_endian = "little")]
+        {
+            action(word.into())
+        }
+        #[cfg(not(target_endian = "little"))]
+        {
+            let mut pointer = Self::from(*word);
+            let output = action(&mut pointer);
+            let low = pointer.into_u256();
+            word.0[..2].copy_from_slice(&low.0[..2]);
+            output
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+    };
+
+    #[test]
+    fn reading_pointed_bytes() {
+        let ret = Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+        );
+        let program = Program::from_raw(vec![ret], vec![]);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let calldata: Vec<u8> = (0..100).collect();
+        let vm: VirtualMachine<(), TestWorld<()>> = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .calldata(calldata.clone())
+            .build()
+            .unwrap();
+
+        let (calldata_ptr, is_pointer) = vm.read_register(1);
+        assert!(is_pointer);
+        let mut pointer = FatPointer::from(calldata_ptr);
+        assert_eq!(pointer.range(), 0..100);
+        assert_eq!(pointer.read_from(&vm), calldata);
+
+        pointer.offset = 90;
+        assert_eq!(pointer.read_from(&vm), calldata[90..]);
+        pointer.offset = 200;
+        assert_eq!(pointer.range(), 100..100);
+        assert!(pointer.read_from(&vm).is_empty());
+    }
+
+    #[test]
+    fn portable_conversions_match_native_ones() {
+        let values = [
+            U256::zero(),
+            U256::MAX,
+            U256([0x0001_0002_0003_0004, 0x0005_0006_0007_0008, 1, 2]),
+        ];
+        for value in values {
+            let native = FatPointer::from(value);
+            let portable = portable::from_u256(value);
+            assert_eq!(
+                (
+                    native.offset,
+                    native.memory_page,
+                    native.start,
+                    native.length
+                ),
+                (
+                    portable.offset,
+                    portable.memory_page,
+                    portable.start,
+                    portable.length
+                )
+            );
+            assert_eq!(portable::into_u256(&portable), native.into_u256());
+        }
+    }
+
+    #[test]
+    fn updating_pointer_in_place() {
+        let mut word = U256([0x0001_0002_0003_0004, 0x0005_0006_0007_0008, 1, 2]);
+        let old_offset = FatPointer::update(&mut word, |pointer| {
+            pointer.length += 1;
+            std::mem::replace(&mut pointer.offset, 0)
+        });
+        assert_eq!(old_offset, 0x0003_0004);
+        assert_eq!(
+            word,
+            U256([0x0001_0002_0000_0000, 0x0005_0007_0007_0008, 1, 2])
+        );
+    }
 }
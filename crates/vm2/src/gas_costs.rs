@@ -0,0 +1,204 @@
+//! Custom static gas costs of instructions.
+
+use std::fmt;
+
+use zksync_vm2_interface::{CallingMode, Opcode, ReturnType};
+
+use crate::addressing_modes::Arguments;
+
+/// Table of static gas costs overriding the default ones for select [`Opcode`]s.
+///
+/// Costs are applied when decoding programs using [`Program::new_with_gas_costs()`](crate::Program::new_with_gas_costs())
+/// or [`Program::from_words_with_gas_costs()`](crate::Program::from_words_with_gas_costs()), so executing instructions
+/// is no slower than with the default costs. Opcodes missing from the table are charged their default cost taken from
+/// `zkevm_opcode_defs`. Only static costs are affected; dynamic costs (e.g., for heap growth, decommitment or pubdata)
+/// stay the same.
+///
+/// This is useful to replay protocol versions with different pricing, or to experiment with repricing.
+#[derive(Clone, PartialEq, Eq)]
+pub struct GasCosts {
+    // Indexed by `opcode_index()`.
+    costs: [Option<u32>; OPCODES.len()],
+}
+
+impl Default for GasCosts {
+    fn default() -> Self {
+        Self {
+            costs: [None; OPCODES.len()],
+        }
+    }
+}
+
+impl fmt::Debug for GasCosts {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("GasCosts")
+            .field("costs", &Overrides(self))
+            .finish()
+    }
+}
+
+/// Outputs overridden costs as a map.
+struct Overrides<'a>(&'a GasCosts);
+
+impl fmt::Debug for Overrides<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let overridden = OPCODES
+            .iter()
+            .zip(&self.0.costs)
+            .filter_map(|(opcode, cost)| Some((opcode, (*cost)?)));
+        formatter.debug_map().entries(overridden).finish()
+    }
+}
+
+impl GasCosts {
+    /// Creates a table without overrides.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the cost of the specified opcode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cost cannot be stored in a decoded instruction; see [`Self::set_cost()`].
+    #[must_use]
+    pub fn with_cost(mut self, opcode: Opcode, cost: u32) -> Self {
+        self.set_cost(opcode, cost);
+        self
+    }
+
+    /// Overrides the cost of the specified opcode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cost cannot be stored in a decoded instruction. Like for instructions constructed
+    /// with [`Arguments::new()`], the cost must fit into 8 bits and not be in `1..=4`, unless it is one of the few costs
+    /// of the default table not satisfying these constraints (e.g., the cost of storage writes).
+    pub fn set_cost(&mut self, opcode: Opcode, cost: u32) {
+        // Panics if the cost cannot be encoded.
+        Arguments::encode_static_gas_cost(cost);
+        self.costs[opcode_index(opcode)] = Some(cost);
+    }
+
+    /// Returns the overridden cost of the specified opcode, or `None` if it has the default cost.
+    pub fn cost(&self, opcode: Opcode) -> Option<u32> {
+        self.costs[opcode_index(opcode)]
+    }
+}
+
+/// All opcodes in the order of their [indices](opcode_index()).
+const OPCODES: [Opcode; 47] = [
+    Opcode::Nop,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::And,
+    Opcode::Or,
+    Opcode::Xor,
+    Opcode::ShiftLeft,
+    Opcode::ShiftRight,
+    Opcode::RotateLeft,
+    Opcode::RotateRight,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::NearCall,
+    Opcode::FarCall(CallingMode::Normal),
+    Opcode::FarCall(CallingMode::Delegate),
+    Opcode::FarCall(CallingMode::Mimic),
+    Opcode::Ret(ReturnType::Normal),
+    Opcode::Ret(ReturnType::Revert),
+    Opcode::Ret(ReturnType::Panic),
+    Opcode::Jump,
+    Opcode::Event,
+    Opcode::L2ToL1Message,
+    Opcode::Decommit,
+    Opcode::This,
+    Opcode::Caller,
+    Opcode::CodeAddress,
+    Opcode::ErgsLeft,
+    Opcode::SP,
+    Opcode::ContextMeta,
+    Opcode::ContextU128,
+    Opcode::SetContextU128,
+    Opcode::IncrementTxNumber,
+    Opcode::AuxMutating0,
+    Opcode::PrecompileCall,
+    Opcode::HeapRead,
+    Opcode::HeapWrite,
+    Opcode::AuxHeapRead,
+    Opcode::AuxHeapWrite,
+    Opcode::PointerRead,
+    Opcode::PointerAdd,
+    Opcode::PointerSub,
+    Opcode::PointerPack,
+    Opcode::PointerShrink,
+    Opcode::StorageRead,
+    Opcode::StorageWrite,
+    Opcode::TransientStorageRead,
+    Opcode::TransientStorageWrite,
+];
+
+/// Maps opcodes to dense indices.
+const fn opcode_index(opcode: Opcode) -> usize {
+    match opcode {
+        Opcode::Nop => 0,
+        Opcode::Add => 1,
+        Opcode::Sub => 2,
+        Opcode::And => 3,
+        Opcode::Or => 4,
+        Opcode::Xor => 5,
+        Opcode::ShiftLeft => 6,
+        Opcode::ShiftRight => 7,
+        Opcode::RotateLeft => 8,
+        Opcode::RotateRight => 9,
+        Opcode::Mul => 10,
+        Opcode::Div => 11,
+        Opcode::NearCall => 12,
+        Opcode::FarCall(CallingMode::Normal) => 13,
+        Opcode::FarCall(CallingMode::Delegate) => 14,
+        Opcode::FarCall(CallingMode::Mimic) => 15,
+        Opcode::Ret(ReturnType::Normal) => 16,
+        Opcode::Ret(ReturnType::Revert) => 17,
+        Opcode::Ret(ReturnType::Panic) => 18,
+        Opcode::Jump => 19,
+        Opcode::Event => 20,
+        Opcode::L2ToL1Message => 21,
+        Opcode::Decommit => 22,
+        Opcode::This => 23,
+        Opcode::Caller => 24,
+        Opcode::CodeAddress => 25,
+        Opcode::ErgsLeft => 26,
+        Opcode::SP => 27,
+        Opcode::ContextMeta => 28,
+        Opcode::ContextU128 => 29,
+        Opcode::SetContextU128 => 30,
+        Opcode::IncrementTxNumber => 31,
+        Opcode::AuxMutating0 => 32,
+        Opcode::PrecompileCall => 33,
+        Opcode::HeapRead => 34,
+        Opcode::HeapWrite => 35,
+        Opcode::AuxHeapRead => 36,
+        Opcode::AuxHeapWrite => 37,
+        Opcode::PointerRead => 38,
+        Opcode::PointerAdd => 39,
+        Opcode::PointerSub => 40,
+        Opcode::PointerPack => 41,
+        Opcode::PointerShrink => 42,
+        Opcode::StorageRead => 43,
+        Opcode::StorageWrite => 44,
+        Opcode::TransientStorageRead => 45,
+        Opcode::TransientStorageWrite => 46,
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcode_indices_match_opcode_list() {
+        for (i, &opcode) in OPCODES.iter().enumerate() {
+            assert_eq!(opcode_index(opcode), i, "{opcode:?}");
+        }
+    }
+}
@@ -1,22 +1,45 @@
 use std::{
+    collections::HashSet,
     fmt, mem,
     ops::{Index, Range},
+    sync::Arc,
 };
 
 use primitive_types::U256;
 use zksync_vm2_interface::HeapId;
 
-/// Heap page size in bytes.
-const HEAP_PAGE_SIZE: usize = 1 << 12;
+use crate::{
+    allocator::{zeroed_heap_page, Allocator, HeapPageBuffer, HEAP_PAGE_SIZE},
+    byte_order::{u256_from_be_bytes, u256_to_be_bytes},
+    state_delta::HeapWrite,
+};
 
-/// Heap page.
+/// Heap page. Pages are shared among heap clones (e.g., [`HeapSnapshot`]s) and copied on write.
 #[derive(Debug, Clone, PartialEq)]
-struct HeapPage(Box<[u8; HEAP_PAGE_SIZE]>);
+struct HeapPage(Arc<HeapPageBuffer>);
 
 impl Default for HeapPage {
     fn default() -> Self {
-        let boxed_slice: Box<[u8]> = vec![0_u8; HEAP_PAGE_SIZE].into();
-        Self(boxed_slice.try_into().unwrap())
+        Self(Arc::new(zeroed_heap_page()))
+    }
+}
+
+impl HeapPage {
+    fn bytes(&self) -> &[u8; HEAP_PAGE_SIZE] {
+        &self.0
+    }
+
+    fn is_shared(&mut self) -> bool {
+        Arc::get_mut(&mut self.0).is_none()
+    }
+
+    /// Returns mutable page bytes. Must only be called on pages that are not shared.
+    fn bytes_mut(&mut self) -> &mut [u8; HEAP_PAGE_SIZE] {
+        Arc::get_mut(&mut self.0).expect("heap page is shared")
+    }
+
+    fn into_buffer(self) -> Option<HeapPageBuffer> {
+        Arc::into_inner(self.0)
     }
 }
 
@@ -39,7 +62,7 @@ impl PartialEq for Heap {
                     }
                 }
                 (Some(page), None) | (None, Some(page)) => {
-                    if page.0.iter().any(|&byte| byte != 0) {
+                    if page.bytes().iter().any(|&byte| byte != 0) {
                         return false;
                     }
                 }
@@ -52,20 +75,19 @@ impl PartialEq for Heap {
 
 impl Heap {
     fn from_bytes(bytes: &[u8], pagepool: &mut PagePool) -> Self {
-        let pages = bytes
-            .chunks(HEAP_PAGE_SIZE)
-            .map(|bytes| {
-                Some(if let Some(mut page) = pagepool.get_dirty_page() {
-                    page.0[..bytes.len()].copy_from_slice(bytes);
-                    page.0[bytes.len()..].fill(0);
-                    page
-                } else {
-                    let mut page = HeapPage::default();
-                    page.0[..bytes.len()].copy_from_slice(bytes);
-                    page
-                })
+        let mut pages = pagepool.get_page_table();
+        let new_pages = bytes.chunks(HEAP_PAGE_SIZE).map(|bytes| {
+            Some(if let Some(mut page) = pagepool.get_dirty_page() {
+                page.bytes_mut()[..bytes.len()].copy_from_slice(bytes);
+                page.bytes_mut()[bytes.len()..].fill(0);
+                page
+            } else {
+                let mut page = pagepool.new_page();
+                page.bytes_mut()[..bytes.len()].copy_from_slice(bytes);
+                page
             })
-            .collect();
+        });
+        pages.extend(new_pages);
         Self { pages }
     }
 
@@ -75,23 +97,19 @@ impl Heap {
 
         if bytes_in_page >= 32 {
             if let Some(page) = self.page(page_idx) {
-                U256::from_big_endian(&page.0[offset_in_page..offset_in_page + 32])
+                u256_from_be_bytes(page_chunk(page, offset_in_page))
             } else {
                 U256::zero()
             }
         } else {
             let mut result = [0u8; 32];
             if let Some(page) = self.page(page_idx) {
-                for (res, src) in result.iter_mut().zip(&page.0[offset_in_page..]) {
-                    *res = *src;
-                }
+                result[..bytes_in_page].copy_from_slice(&page.bytes()[offset_in_page..]);
             }
             if let Some(page) = self.page(page_idx + 1) {
-                for (res, src) in result[bytes_in_page..].iter_mut().zip(&*page.0) {
-                    *res = *src;
-                }
+                result[bytes_in_page..].copy_from_slice(&page.bytes()[..32 - bytes_in_page]);
             }
-            U256::from_big_endian(&result)
+            u256_from_be_bytes(&result)
         }
     }
 
@@ -102,19 +120,13 @@ impl Heap {
 
         let mut result = [0u8; 32];
         if let Some(page) = self.page(page_idx) {
-            for (res, src) in result[..bytes_in_page]
-                .iter_mut()
-                .zip(&page.0[offset_in_page..])
-            {
-                *res = *src;
-            }
+            result[..bytes_in_page]
+                .copy_from_slice(&page.bytes()[offset_in_page..offset_in_page + bytes_in_page]);
         }
         if let Some(page) = self.page(page_idx + 1) {
-            for (res, src) in result[bytes_in_page..length].iter_mut().zip(&*page.0) {
-                *res = *src;
-            }
+            result[bytes_in_page..length].copy_from_slice(&page.bytes()[..length - bytes_in_page]);
         }
-        U256::from_big_endian(&result)
+        u256_from_be_bytes(&result)
     }
 
     pub(crate) fn read_range_big_endian(&self, range: Range<u32>) -> Vec<u8> {
@@ -125,7 +137,9 @@ impl Heap {
         while result.len() < length {
             let len_in_page = (length - result.len()).min(HEAP_PAGE_SIZE - offset_in_page);
             if let Some(page) = self.page(page_idx) {
-                result.extend_from_slice(&page.0[offset_in_page..(offset_in_page + len_in_page)]);
+                result.extend_from_slice(
+                    &page.bytes()[offset_in_page..(offset_in_page + len_in_page)],
+                );
             } else {
                 result.resize(result.len() + len_in_page, 0);
             }
@@ -135,21 +149,52 @@ impl Heap {
         result
     }
 
+    /// Fills `buffer` with heap bytes starting from `start_address`.
+    pub(crate) fn read_into(&self, start_address: u32, buffer: &mut [u8]) {
+        let (mut page_idx, mut offset_in_page) = address_to_page_offset(start_address);
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let len_in_page = (buffer.len() - filled).min(HEAP_PAGE_SIZE - offset_in_page);
+            let dst = &mut buffer[filled..filled + len_in_page];
+            if let Some(page) = self.page(page_idx) {
+                dst.copy_from_slice(&page.bytes()[offset_in_page..offset_in_page + len_in_page]);
+            } else {
+                dst.fill(0);
+            }
+            filled += len_in_page;
+            page_idx += 1;
+            offset_in_page = 0;
+        }
+    }
+
     /// Needed only by tracers
     pub(crate) fn read_byte(&self, address: u32) -> u8 {
         let (page, offset) = address_to_page_offset(address);
-        self.page(page).map_or(0, |page| page.0[offset])
+        self.page(page).map_or(0, |page| page.bytes()[offset])
     }
 
     fn page(&self, idx: usize) -> Option<&HeapPage> {
         self.pages.get(idx)?.as_ref()
     }
 
-    fn get_or_insert_page(&mut self, idx: usize, pagepool: &mut PagePool) -> &mut HeapPage {
+    /// Returns the page for writing, allocating it if necessary, or copying it if it's shared with a snapshot.
+    fn get_or_insert_page(
+        &mut self,
+        idx: usize,
+        pagepool: &mut PagePool,
+    ) -> &mut [u8; HEAP_PAGE_SIZE] {
         if self.pages.len() <= idx {
             self.pages.resize(idx + 1, None);
         }
-        self.pages[idx].get_or_insert_with(|| pagepool.allocate_page())
+        let page = self.pages[idx].get_or_insert_with(|| pagepool.allocate_page());
+        if page.is_shared() {
+            let mut copy = pagepool
+                .get_dirty_page()
+                .unwrap_or_else(|| pagepool.new_page());
+            copy.bytes_mut().copy_from_slice(page.bytes());
+            pagepool.release_shared_page(mem::replace(page, copy));
+        }
+        page.bytes_mut()
     }
 
     fn write_u256(&mut self, start_address: u32, value: U256, pagepool: &mut PagePool) {
@@ -158,28 +203,101 @@ impl Heap {
         let page = self.get_or_insert_page(page_idx, pagepool);
 
         if bytes_in_page >= 32 {
-            value.to_big_endian(&mut page.0[offset_in_page..offset_in_page + 32]);
+            let chunk: &mut [u8; 32] = (&mut page[offset_in_page..offset_in_page + 32])
+                .try_into()
+                .unwrap();
+            u256_to_be_bytes(&value, chunk);
         } else {
             let mut bytes = [0; 32];
-            value.to_big_endian(&mut bytes);
-            let mut bytes_iter = bytes.into_iter();
-
-            for (dst, src) in page.0[offset_in_page..].iter_mut().zip(bytes_iter.by_ref()) {
-                *dst = src;
-            }
+            u256_to_be_bytes(&value, &mut bytes);
+            page[offset_in_page..].copy_from_slice(&bytes[..bytes_in_page]);
 
             let page = self.get_or_insert_page(page_idx + 1, pagepool);
-            for (dst, src) in page.0.iter_mut().zip(bytes_iter) {
-                *dst = src;
-            }
+            page[..32 - bytes_in_page].copy_from_slice(&bytes[bytes_in_page..]);
         }
     }
 }
 
+/// Rollbacks restore entries in the reverse order, so each byte ends up with the value from the earliest entry covering it.
+/// Thus, entries for an address that already has an earlier entry can be dropped without changing rollback results,
+/// as long as no snapshot points between these entries.
+fn compact_rollback_info(rollback_info: &mut Vec<(u32, U256)>, start: usize) {
+    let mut seen_addresses = HashSet::new();
+    let mut index = 0;
+    rollback_info.retain(|&(address, _)| {
+        let keep = index < start || seen_addresses.insert(address);
+        index += 1;
+        keep
+    });
+    rollback_info.shrink_to_fit();
+}
+
+/// Statistics of the history retained by a [`VirtualMachine`](crate::VirtualMachine) to support rolling back
+/// its snapshot. Obtained via [`VirtualMachine::history_stats()`](crate::VirtualMachine::history_stats()).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HistoryStats {
+    /// Number of recorded writes to the bootloader heap and aux heap.
+    pub heap_entries: usize,
+    /// Number of bytes allocated to store recorded bootloader heap writes.
+    pub heap_bytes: usize,
+}
+
+/// Copy of the contents of a single heap.
+///
+/// Taking a snapshot doesn't copy heap pages: they are shared with the VM until it writes to them, at which point
+/// the VM copies the written page.
+///
+/// Obtained via [`VirtualMachine::heap_snapshot()`](crate::VirtualMachine::heap_snapshot()) and compared via [`heap_diff()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeapSnapshot(pub(crate) Heap);
+
+/// Returns all bytes that differ between two heap snapshots as `(offset, old, new)` tuples ordered by offset.
+///
+/// Snapshots are compared page by page, so only pages that were allocated in either of the snapshots and aren't shared
+/// by them are inspected byte by byte; bytes in missing pages are treated as zero. Since pages are only copied
+/// when written to, diffing snapshots of the same heap only inspects pages written to between the snapshots.
+pub fn heap_diff(old: &HeapSnapshot, new: &HeapSnapshot) -> Vec<(u32, u8, u8)> {
+    const ZERO_PAGE: [u8; HEAP_PAGE_SIZE] = [0; HEAP_PAGE_SIZE];
+
+    let (old, new) = (&old.0, &new.0);
+    let mut diff = vec![];
+    for page_idx in 0..old.pages.len().max(new.pages.len()) {
+        let (old_page, new_page) = match (old.page(page_idx), new.page(page_idx)) {
+            (None, None) => continue,
+            (Some(old_page), Some(new_page)) if Arc::ptr_eq(&old_page.0, &new_page.0) => continue,
+            (old_page, new_page) => (
+                old_page.map_or(&ZERO_PAGE, HeapPage::bytes),
+                new_page.map_or(&ZERO_PAGE, HeapPage::bytes),
+            ),
+        };
+        if old_page == new_page {
+            continue;
+        }
+
+        #[allow(clippy::cast_possible_truncation)] // heap addresses fit into `u32` by construction
+        let page_start = (page_idx * HEAP_PAGE_SIZE) as u32;
+        let changed_bytes = (page_start..)
+            .zip(old_page.iter().zip(new_page.iter()))
+            .filter(|(_, (old_byte, new_byte))| old_byte != new_byte)
+            .map(|(offset, (&old_byte, &new_byte))| (offset, old_byte, new_byte));
+        diff.extend(changed_bytes);
+    }
+    diff
+}
+
+/// Returns 32 bytes of a page starting from the specified offset, which must be at most `HEAP_PAGE_SIZE - 32`.
+#[inline(always)]
+fn page_chunk(page: &HeapPage, offset_in_page: usize) -> &[u8; 32] {
+    page.bytes()[offset_in_page..offset_in_page + 32]
+        .try_into()
+        .unwrap()
+}
+
 #[inline(always)]
 fn address_to_page_offset(address: u32) -> (usize, usize) {
     let offset = address as usize;
-    (offset >> 12, offset & (HEAP_PAGE_SIZE - 1))
+    (offset / HEAP_PAGE_SIZE, offset % HEAP_PAGE_SIZE)
 }
 
 #[derive(Debug, Clone)]
@@ -188,13 +306,20 @@ pub(crate) struct Heaps {
     pagepool: PagePool,
     bootloader_heap_rollback_info: Vec<(u32, U256)>,
     bootloader_aux_rollback_info: Vec<(u32, U256)>,
+    /// Writes recorded for [`StateDelta`](crate::StateDelta)s; `None` if writes are not recorded.
+    recorded_writes: Option<Vec<HeapWrite>>,
 }
 
 impl Heaps {
     pub(crate) fn new(calldata: &[u8]) -> Self {
+        Self::with_allocator(calldata, None)
+    }
+
+    pub(crate) fn with_allocator(calldata: &[u8], allocator: Option<Arc<dyn Allocator>>) -> Self {
         // The first heap can never be used because heap zero
         // means the current heap in precompile calls
         let mut pagepool = PagePool::default();
+        pagepool.allocator = allocator;
         Self {
             heaps: vec![
                 Heap::default(),
@@ -205,6 +330,7 @@ impl Heaps {
             pagepool,
             bootloader_heap_rollback_info: vec![],
             bootloader_aux_rollback_info: vec![],
+            recorded_writes: None,
         }
     }
 
@@ -224,11 +350,24 @@ impl Heaps {
         id
     }
 
+    /// Checks whether the specified heap was allocated. Deallocated heaps are still considered allocated.
+    pub(crate) fn contains(&self, heap: HeapId) -> bool {
+        (heap.as_u32() as usize) < self.heaps.len()
+    }
+
+    /// Replaces the contents of the calldata heap of the initial frame.
+    pub(crate) fn replace_calldata(&mut self, calldata: &[u8]) {
+        let heap = Heap::from_bytes(calldata, &mut self.pagepool);
+        let old_heap = mem::replace(
+            &mut self.heaps[HeapId::FIRST_CALLDATA.as_u32() as usize],
+            heap,
+        );
+        self.pagepool.recycle_page_table(old_heap.pages);
+    }
+
     pub(crate) fn deallocate(&mut self, heap: HeapId) {
         let heap = mem::take(&mut self.heaps[heap.as_u32() as usize]);
-        for page in heap.pages.into_iter().flatten() {
-            self.pagepool.recycle_page(page);
-        }
+        self.pagepool.recycle_page_table(heap.pages);
     }
 
     pub(crate) fn write_u256(&mut self, heap: HeapId, start_address: u32, value: U256) {
@@ -241,9 +380,24 @@ impl Heaps {
             self.bootloader_aux_rollback_info
                 .push((start_address, prev_value));
         }
+        if let Some(writes) = &mut self.recorded_writes {
+            writes.push(HeapWrite {
+                heap,
+                offset: start_address,
+                value,
+            });
+        }
         self.heaps[heap.as_u32() as usize].write_u256(start_address, value, &mut self.pagepool);
     }
 
+    pub(crate) fn start_recording_writes(&mut self) {
+        self.recorded_writes = Some(vec![]);
+    }
+
+    pub(crate) fn stop_recording_writes(&mut self) -> Vec<HeapWrite> {
+        self.recorded_writes.take().unwrap_or_default()
+    }
+
     pub(crate) fn snapshot(&self) -> (usize, usize) {
         (
             self.bootloader_heap_rollback_info.len(),
@@ -273,6 +427,44 @@ impl Heaps {
         self.bootloader_heap_rollback_info.clear();
         self.bootloader_aux_rollback_info.clear();
     }
+
+    /// Removes rollback entries recorded after `snapshot` that are redundant because of earlier entries
+    /// for the same address.
+    pub(crate) fn compact_history(&mut self, (heap_snap, aux_snap): (usize, usize)) {
+        compact_rollback_info(&mut self.bootloader_heap_rollback_info, heap_snap);
+        compact_rollback_info(&mut self.bootloader_aux_rollback_info, aux_snap);
+    }
+
+    pub(crate) fn history_stats(&self) -> HistoryStats {
+        let entries =
+            self.bootloader_heap_rollback_info.len() + self.bootloader_aux_rollback_info.len();
+        let capacity = self.bootloader_heap_rollback_info.capacity()
+            + self.bootloader_aux_rollback_info.capacity();
+        HistoryStats {
+            heap_entries: entries,
+            heap_bytes: capacity * mem::size_of::<(u32, U256)>(),
+        }
+    }
+
+    /// Returns the number of bytes allocated for heap pages, including pages that are not used by any heap currently,
+    /// but are retained for reuse.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.pagepool.allocated_pages * HEAP_PAGE_SIZE
+    }
+}
+
+impl Drop for Heaps {
+    fn drop(&mut self) {
+        if self.pagepool.allocator.is_none() {
+            return;
+        }
+        // Move all pages to the pool, so that they are released to the allocator.
+        for heap in &mut self.heaps {
+            for page in mem::take(&mut heap.pages).into_iter().flatten() {
+                self.pagepool.recycle_page(page);
+            }
+        }
+    }
 }
 
 impl Index<HeapId> for Heaps {
@@ -298,34 +490,102 @@ impl PartialEq for Heaps {
     }
 }
 
-#[derive(Default, Clone)]
-struct PagePool(Vec<HeapPage>);
+#[derive(Default)]
+struct PagePool {
+    /// Recycled pages. These are never shared, so they can be reused without copying.
+    pages: Vec<HeapPage>,
+    /// Emptied page tables of deallocated heaps. Reusing them saves an allocation for each heap of a far call.
+    page_tables: Vec<Vec<Option<HeapPage>>>,
+    /// Number of pages created by this pool, i.e., pages held by heaps and recycled pages.
+    allocated_pages: usize,
+    allocator: Option<Arc<dyn Allocator>>,
+}
+
+// Recycled pages are not cloned since they can be shared neither between pools nor with snapshots.
+impl Clone for PagePool {
+    fn clone(&self) -> Self {
+        Self {
+            pages: vec![],
+            page_tables: vec![],
+            allocated_pages: self.allocated_pages - self.pages.len(),
+            allocator: self.allocator.clone(),
+        }
+    }
+}
 
 impl fmt::Debug for PagePool {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter
             .debug_struct("PagePool")
-            .field("len", &self.0.len())
+            .field("len", &self.pages.len())
+            .field("page_tables", &self.page_tables.len())
+            .field("allocated_pages", &self.allocated_pages)
             .finish_non_exhaustive()
     }
 }
 
+impl Drop for PagePool {
+    fn drop(&mut self) {
+        if let Some(allocator) = &self.allocator {
+            for page in self.pages.drain(..).filter_map(HeapPage::into_buffer) {
+                allocator.free_heap_page(page);
+            }
+        }
+    }
+}
+
 impl PagePool {
     fn allocate_page(&mut self) -> HeapPage {
-        self.get_dirty_page()
-            .map(|mut page| {
-                page.0.fill(0);
+        self.get_dirty_page().map_or_else(
+            || self.new_page(),
+            |mut page| {
+                page.bytes_mut().fill(0);
                 page
-            })
-            .unwrap_or_default()
+            },
+        )
+    }
+
+    fn new_page(&mut self) -> HeapPage {
+        self.allocated_pages += 1;
+        if let Some(allocator) = &self.allocator {
+            let mut buffer = allocator.allocate_heap_page();
+            buffer.fill(0);
+            HeapPage(Arc::new(buffer))
+        } else {
+            HeapPage::default()
+        }
     }
 
     fn get_dirty_page(&mut self) -> Option<HeapPage> {
-        self.0.pop()
+        self.pages.pop()
+    }
+
+    fn recycle_page(&mut self, mut page: HeapPage) {
+        if page.is_shared() {
+            self.release_shared_page(page);
+        } else {
+            self.pages.push(page);
+        }
+    }
+
+    /// Releases a page shared with a snapshot. The page is freed once the snapshot is dropped.
+    fn release_shared_page(&mut self, page: HeapPage) {
+        self.allocated_pages -= 1;
+        drop(page);
+    }
+
+    fn get_page_table(&mut self) -> Vec<Option<HeapPage>> {
+        self.page_tables.pop().unwrap_or_default()
     }
 
-    fn recycle_page(&mut self, page: HeapPage) {
-        self.0.push(page);
+    /// Recycles all pages in the table and retains the emptied table.
+    fn recycle_page_table(&mut self, mut pages: Vec<Option<HeapPage>>) {
+        for page in pages.drain(..).flatten() {
+            self.recycle_page(page);
+        }
+        if pages.capacity() > 0 {
+            self.page_tables.push(pages);
+        }
     }
 }
 
@@ -395,7 +655,7 @@ mod tests {
         for _ in 0..10 {
             let mut page = HeapPage::default();
             // Fill pages with 0xff bytes to detect not clearing pages
-            page.0.fill(0xff);
+            page.bytes_mut().fill(0xff);
             pagepool.recycle_page(page);
         }
         pagepool
@@ -461,6 +721,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn diffing_heap_snapshots() {
+        let mut pagepool = PagePool::default();
+        let mut heap = Heap::from_bytes(&[1, 2, 3], &mut pagepool);
+        let old = HeapSnapshot(heap.clone());
+        assert!(heap_diff(&old, &old).is_empty());
+
+        heap.write_u256(0, U256::from_big_endian(&[1, 2, 3]), &mut pagepool);
+        assert_eq!(
+            heap_diff(&old, &HeapSnapshot(heap)),
+            [
+                (0, 1, 0),
+                (1, 2, 0),
+                (2, 3, 0),
+                (29, 0, 1),
+                (30, 0, 2),
+                (31, 0, 3)
+            ]
+        );
+
+        // Write across a page boundary and into a page missing in the old snapshot.
+        let mut heap = old.0.clone();
+        heap.write_u256(HEAP_PAGE_SIZE as u32 - 1, U256::from(0xabcd), &mut pagepool);
+        let new = HeapSnapshot(heap.clone());
+        let boundary = HEAP_PAGE_SIZE as u32 + 29;
+        assert_eq!(
+            heap_diff(&old, &new),
+            [(boundary, 0, 0xab), (boundary + 1, 0, 0xcd)]
+        );
+        assert_eq!(
+            heap_diff(&new, &old),
+            [(boundary, 0xab, 0), (boundary + 1, 0xcd, 0)]
+        );
+
+        // Pages allocated with zeros are not reported.
+        heap.write_u256(1 << 20, U256::zero(), &mut pagepool);
+        assert!(heap_diff(&new, &HeapSnapshot(heap)).is_empty());
+    }
+
+    #[test]
+    fn snapshot_pages_are_copied_on_write() {
+        let mut pagepool = PagePool::default();
+        let bytes = vec![1; HEAP_PAGE_SIZE * 2];
+        let mut heap = Heap::from_bytes(&bytes, &mut pagepool);
+        let snapshot = HeapSnapshot(heap.clone());
+        assert_eq!(pagepool.allocated_pages, 2);
+
+        heap.write_u256(0, U256::zero(), &mut pagepool);
+        let [(old0, new0), (old1, new1)] = [0, 1].map(|idx| {
+            let old = &snapshot.0.page(idx).unwrap().0;
+            let new = &heap.page(idx).unwrap().0;
+            (old.clone(), new.clone())
+        });
+        assert!(!Arc::ptr_eq(&old0, &new0));
+        assert!(Arc::ptr_eq(&old1, &new1));
+        // The snapshot page is owned by the snapshot now.
+        assert_eq!(pagepool.allocated_pages, 2);
+        assert_eq!(snapshot.0.read_u256(0), repeat_byte(1));
+        assert_eq!(heap_diff(&snapshot, &HeapSnapshot(heap)).len(), 32);
+    }
+
     #[test]
     fn heap_read_out_of_bounds() {
         let heap = Heap::default();
@@ -546,4 +867,52 @@ mod tests {
         assert_eq!(heaps.bootloader_heap_rollback_info.len(), 1);
         assert_eq!(heaps.bootloader_aux_rollback_info.len(), 1);
     }
+
+    #[test]
+    fn compacting_heap_history() {
+        let mut heaps = Heaps::new(&[]);
+        heaps.write_u256(HeapId::FIRST, 0, 1.into());
+        heaps.write_u256(HeapId::FIRST, 0, 2.into());
+        let snapshot = heaps.snapshot();
+        let value_before_snapshot = heaps[HeapId::FIRST].read_u256(0);
+
+        for i in 0..10_u64 {
+            heaps.write_u256(HeapId::FIRST, 0, (i + 10).into());
+            heaps.write_u256(HeapId::FIRST, 1, U256::MAX - i);
+            heaps.write_u256(HeapId::FIRST_AUX, 64, i.into());
+        }
+        assert_eq!(heaps.history_stats().heap_entries, 32);
+        heaps.compact_history(snapshot);
+        // Entries before the snapshot are retained.
+        assert_eq!(heaps.history_stats().heap_entries, 5);
+
+        heaps.rollback(snapshot);
+        assert_eq!(heaps[HeapId::FIRST].read_u256(0), value_before_snapshot);
+        assert_eq!(heaps[HeapId::FIRST].read_u256(32), U256::zero());
+        assert_eq!(heaps[HeapId::FIRST_AUX].read_u256(64), U256::zero());
+    }
+
+    #[test]
+    fn reusing_page_tables_of_deallocated_heaps() {
+        let mut heaps = Heaps::new(&[]);
+        let heap = heaps.allocate();
+        heaps.write_u256(heap, 3 * HEAP_PAGE_SIZE as u32, 42.into());
+        let page_table_capacity = heaps[heap].pages.capacity();
+        assert!(page_table_capacity >= 4);
+        heaps.deallocate(heap);
+        assert_eq!(heaps.pagepool.pages.len(), 1);
+        assert_eq!(heaps.pagepool.page_tables.len(), 1);
+
+        let new_heap = heaps.allocate();
+        assert!(heaps[new_heap].pages.is_empty());
+        assert_eq!(heaps[new_heap].pages.capacity(), page_table_capacity);
+        assert!(heaps.pagepool.page_tables.is_empty());
+        assert_eq!(
+            heaps[new_heap].read_u256(3 * HEAP_PAGE_SIZE as u32),
+            0.into()
+        );
+
+        heaps.write_u256(new_heap, 0, 1.into());
+        assert!(heaps.pagepool.pages.is_empty());
+    }
 }
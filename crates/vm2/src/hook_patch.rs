@@ -0,0 +1,246 @@
+//! Checked modification of the bootloader state after the VM is suspended on a hook.
+
+use std::{error, fmt};
+
+use primitive_types::U256;
+use zksync_vm2_interface::{HeapId, Tracer};
+
+use crate::{VirtualMachine, World};
+
+/// Error modifying VM state using [`HookPatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatchError {
+    /// The VM doesn't execute the bootloader frame, so its heap is not the bootloader heap.
+    NotInBootloader,
+    /// The register is not among the registers designated when creating the patch, or is `r0`.
+    RegisterNotAllowed(u8),
+    /// The written heap range doesn't fit into 32-bit addresses.
+    HeapRangeOverflow {
+        /// Start of the range.
+        offset: u32,
+        /// Length of the range.
+        len: usize,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInBootloader => {
+                formatter.write_str("VM is not executing the bootloader frame")
+            }
+            Self::RegisterNotAllowed(register) => {
+                write!(
+                    formatter,
+                    "register r{register} is not allowed to be modified"
+                )
+            }
+            Self::HeapRangeOverflow { offset, len } => write!(
+                formatter,
+                "heap range of {len} bytes starting at {offset} doesn't fit into 32-bit addresses"
+            ),
+        }
+    }
+}
+
+impl error::Error for PatchError {}
+
+/// Modifies the bootloader heap and designated registers of a VM suspended on a [hook](crate::ExecutionEnd::SuspendedOnHook),
+/// e.g. to provide the results of hook processing before resuming execution. Created using
+/// [`VirtualMachine::patch_after_hook()`].
+///
+/// Modifications are undone by [rolling back](VirtualMachine::rollback()) a snapshot made before them, like changes
+/// made by the bootloader itself.
+#[derive(Debug)]
+pub struct HookPatch<'a, T, W> {
+    vm: &'a mut VirtualMachine<T, W>,
+    /// Bit mask of registers that may be modified.
+    allowed_registers: u16,
+}
+
+impl<T, W> HookPatch<'_, T, W> {
+    /// Writes `value` as a big-endian word to the bootloader heap at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the word doesn't fit into 32-bit addresses.
+    pub fn write_heap_u256(&mut self, offset: u32, value: U256) -> Result<(), PatchError> {
+        let mut bytes = [0; 32];
+        value.to_big_endian(&mut bytes);
+        self.write_heap(offset, &bytes)
+    }
+
+    /// Writes `bytes` to the bootloader heap starting from `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the written range doesn't fit into 32-bit addresses.
+    #[allow(clippy::cast_possible_truncation)] // word addresses are checked to fit into `u32`
+    pub fn write_heap(&mut self, offset: u32, bytes: &[u8]) -> Result<(), PatchError> {
+        let end = u32::try_from(bytes.len())
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .ok_or(PatchError::HeapRangeOverflow {
+                offset,
+                len: bytes.len(),
+            })?;
+
+        // The heap is written by whole words so that writes are recorded for rollbacks.
+        let heaps = &mut self.vm.state.heaps;
+        let mut word_start = u64::from(offset & !31);
+        while word_start < u64::from(end) {
+            let word_address = word_start as u32;
+            let mut word = [0; 32];
+            heaps[HeapId::FIRST]
+                .read_u256(word_address)
+                .to_big_endian(&mut word);
+            for (i, byte) in word.iter_mut().enumerate() {
+                let address = word_start + i as u64;
+                if (u64::from(offset)..u64::from(end)).contains(&address) {
+                    *byte = bytes[(address - u64::from(offset)) as usize];
+                }
+            }
+            heaps.write_u256(HeapId::FIRST, word_address, U256::from_big_endian(&word));
+            word_start += 32;
+        }
+        Ok(())
+    }
+
+    /// Sets a designated register to a non-pointer `value`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the register was not designated when creating the patch.
+    pub fn set_register(&mut self, register: u8, value: U256) -> Result<(), PatchError> {
+        if register == 0 || register >= 16 || self.allowed_registers & (1 << register) == 0 {
+            return Err(PatchError::RegisterNotAllowed(register));
+        }
+        let state = &mut self.vm.state;
+        state.registers[usize::from(register)] = value;
+        state.register_pointer_flags &= !(1 << register);
+        Ok(())
+    }
+}
+
+impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
+    /// Starts modifying the bootloader state after the VM is suspended on a hook. Only the bootloader heap and
+    /// `allowed_registers` can be modified using the returned patch; other registers are left intact, and registers
+    /// outside `1..16` are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the VM is not executing the bootloader frame, i.e. if it is not the initial frame
+    /// or its heap is not the first heap.
+    pub fn patch_after_hook(
+        &mut self,
+        allowed_registers: &[u8],
+    ) -> Result<HookPatch<'_, T, W>, PatchError> {
+        if !self.state.previous_frames.is_empty() || self.state.current_frame.heap != HeapId::FIRST
+        {
+            return Err(PatchError::NotInBootloader);
+        }
+        let allowed_registers = allowed_registers
+            .iter()
+            .filter(|&&register| (1..16).contains(&register))
+            .fold(0_u16, |mask, &register| mask | (1 << register));
+        Ok(HookPatch {
+            vm: self,
+            allowed_registers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+    use zksync_vm2_interface::StateInterface;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program,
+    };
+
+    fn test_vm() -> VirtualMachine<(), TestWorld<()>> {
+        let ret = Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+        );
+        let program = Program::from_raw(vec![ret], vec![]);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .gas(1_000)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn patching_bootloader_heap() {
+        let mut vm = test_vm();
+        vm.write_heap_u256(HeapId::FIRST, 64, U256::MAX);
+        vm.make_snapshot();
+
+        let mut patch = vm.patch_after_hook(&[]).unwrap();
+        patch.write_heap(70, &[1, 2, 3]).unwrap();
+        patch.write_heap_u256(128, 42.into()).unwrap();
+        assert_eq!(
+            vm.read_heap_range(HeapId::FIRST, 68..74),
+            [0xff, 0xff, 1, 2, 3, 0xff]
+        );
+        assert_eq!(vm.read_heap_u256(HeapId::FIRST, 128), 42.into());
+
+        vm.rollback();
+        assert_eq!(vm.read_heap_u256(HeapId::FIRST, 64), U256::MAX);
+        assert_eq!(vm.read_heap_u256(HeapId::FIRST, 128), U256::zero());
+    }
+
+    #[test]
+    fn patch_modifications_are_rolled_back() {
+        let mut vm = test_vm();
+        vm.make_snapshot();
+        let mut patch = vm.patch_after_hook(&[1]).unwrap();
+        patch.set_register(1, 5.into()).unwrap();
+        vm.rollback();
+        assert_eq!(vm.state.registers[1], U256::zero());
+    }
+
+    #[test]
+    fn patching_designated_registers() {
+        let mut vm = test_vm();
+        vm.set_register(2, U256::one(), true);
+
+        let mut patch = vm.patch_after_hook(&[2]).unwrap();
+        patch.set_register(2, 123.into()).unwrap();
+        assert_eq!(
+            patch.set_register(3, 1.into()),
+            Err(PatchError::RegisterNotAllowed(3))
+        );
+        assert_eq!(
+            patch.set_register(0, 1.into()),
+            Err(PatchError::RegisterNotAllowed(0))
+        );
+        assert_eq!(vm.state.registers[2], 123.into());
+        assert_eq!(vm.state.register_pointer_flags & (1 << 2), 0);
+        assert_eq!(vm.state.registers[3], U256::zero());
+    }
+
+    #[test]
+    fn rejecting_overflowing_heap_range() {
+        let mut vm = test_vm();
+        let mut patch = vm.patch_after_hook(&[]).unwrap();
+        assert_eq!(
+            patch.write_heap(u32::MAX - 1, &[0; 3]),
+            Err(PatchError::HeapRangeOverflow {
+                offset: u32::MAX - 1,
+                len: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Typed representation of bootloader hooks.
+//!
+//! The bootloader suspends the VM by writing a hook number to its heap at the [hook address](crate::Settings),
+//! which results in [`ExecutionEnd::SuspendedOnHook`](crate::ExecutionEnd::SuspendedOnHook). Hook parameters
+//! are written to the bootloader heap before that. [`HookLayout`] describes where the parameters are located
+//! for a particular bootloader version and decodes them into a [`HookCall`].
+
+use primitive_types::U256;
+use zksync_vm2_interface::{HeapId, StateInterface};
+
+/// Hook used by the ZKsync Era bootloader. Hooks are numbered in the order of variants starting from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum Hook {
+    /// Account validation has started.
+    AccountValidationEntered = 0,
+    /// Paymaster validation has started.
+    PaymasterValidationEntered,
+    /// Account or paymaster validation has finished.
+    ValidationExited,
+    /// A validation step has finished.
+    ValidationStepEnded,
+    /// Transaction execution has finished.
+    TxHasEnded,
+    /// Debug message from the bootloader; see [`HookCall::debug_log()`].
+    DebugLog,
+    /// Debug output of the return data of a failed transaction.
+    DebugReturnData,
+    /// A near call has panicked and is being caught.
+    NearCallCatch,
+    /// The bootloader asks the operator for a refund.
+    AskOperatorForRefund,
+    /// The bootloader notifies the operator about the refund it has computed.
+    NotifyAboutRefund,
+    /// The bootloader reports a transaction execution result.
+    PostResult,
+    /// The bootloader asks the operator to provide final batch info.
+    FinalBatchInfo,
+    /// The bootloader asks the operator to provide pubdata.
+    PubdataRequested,
+}
+
+impl Hook {
+    const ALL: [Self; 13] = [
+        Self::AccountValidationEntered,
+        Self::PaymasterValidationEntered,
+        Self::ValidationExited,
+        Self::ValidationStepEnded,
+        Self::TxHasEnded,
+        Self::DebugLog,
+        Self::DebugReturnData,
+        Self::NearCallCatch,
+        Self::AskOperatorForRefund,
+        Self::NotifyAboutRefund,
+        Self::PostResult,
+        Self::FinalBatchInfo,
+        Self::PubdataRequested,
+    ];
+
+    /// Converts a raw hook number into a hook. Returns `None` for unknown hook numbers.
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Self::ALL.get(usize::try_from(value).ok()?).copied()
+    }
+
+    /// Returns the raw hook number.
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Location of hook parameters in the bootloader heap, which depends on the bootloader version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookLayout {
+    /// Heap offset of the first parameter.
+    pub params_offset: u32,
+    /// Number of 32-byte parameter words.
+    pub params_count: u32,
+}
+
+impl HookLayout {
+    /// Number of parameter words used by ZKsync Era bootloaders.
+    pub const DEFAULT_PARAMS_COUNT: u32 = 3;
+
+    /// Creates a layout with [`Self::DEFAULT_PARAMS_COUNT`] parameter words immediately preceding the hook address,
+    /// which is how ZKsync Era bootloaders place hook parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hook_address` is too small to be preceded by the parameters.
+    pub fn preceding(hook_address: u32) -> Self {
+        let params_offset = hook_address
+            .checked_sub(Self::DEFAULT_PARAMS_COUNT * 32)
+            .expect("hook address is too small");
+        Self {
+            params_offset,
+            params_count: Self::DEFAULT_PARAMS_COUNT,
+        }
+    }
+
+    /// Decodes the hook the VM is suspended on, reading its parameters from the bootloader heap.
+    /// Returns `None` if `hook` is not a known hook number.
+    pub fn decode(&self, hook: u32, state: &impl StateInterface) -> Option<HookCall> {
+        let hook = Hook::from_u32(hook)?;
+        let params = (0..self.params_count)
+            .map(|i| {
+                let offset = self.params_offset.wrapping_add(i.wrapping_mul(32));
+                state.read_heap_u256(HeapId::FIRST, offset)
+            })
+            .collect();
+        Some(HookCall { hook, params })
+    }
+}
+
+/// Hook together with its parameters decoded by [`HookLayout::decode()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookCall {
+    /// Called hook.
+    pub hook: Hook,
+    /// Hook parameters; their meaning depends on the hook.
+    pub params: Vec<U256>,
+}
+
+impl HookCall {
+    /// For [`Hook::DebugLog`], returns the logged message and the logged value. The message is a string
+    /// of up to 32 bytes stored in the first parameter; the value is the second parameter.
+    /// Returns `None` for other hooks.
+    pub fn debug_log(&self) -> Option<(String, U256)> {
+        if self.hook != Hook::DebugLog {
+            return None;
+        }
+        let mut message = [0; 32];
+        self.params.first()?.to_big_endian(&mut message);
+        let len = message
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(0, |i| i + 1);
+        let message = String::from_utf8_lossy(&message[..len]).into_owned();
+        Some((message, self.params.get(1).copied().unwrap_or_default()))
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+    };
+
+    #[test]
+    fn converting_hook_numbers() {
+        for (i, hook) in (0..).zip(Hook::ALL) {
+            assert_eq!(hook.as_u32(), i);
+            assert_eq!(Hook::from_u32(i), Some(hook));
+        }
+        assert_eq!(Hook::from_u32(5), Some(Hook::DebugLog));
+        assert_eq!(Hook::from_u32(13), None);
+    }
+
+    #[test]
+    fn decoding_debug_log() {
+        let ret = Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+        );
+        let program = Program::from_raw(vec![ret], vec![]);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm: VirtualMachine<(), TestWorld<()>> = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .build()
+            .unwrap();
+
+        let layout = HookLayout::preceding(1_024);
+        assert_eq!(layout.params_offset, 1_024 - 96);
+        let mut message = [0; 32];
+        message[..5].copy_from_slice(b"Hello");
+        vm.write_to_bootloader_heap(
+            layout.params_offset,
+            &[U256::from_big_endian(&message), 42.into()],
+        );
+
+        let call = layout.decode(5, &vm).unwrap();
+        assert_eq!(call.hook, Hook::DebugLog);
+        assert_eq!(call.params.len(), 3);
+        assert_eq!(call.debug_log(), Some(("Hello".to_owned(), 42.into())));
+        assert_eq!(layout.decode(100, &vm), None);
+
+        let call = layout.decode(Hook::TxHasEnded.as_u32(), &vm).unwrap();
+        assert_eq!(call.debug_log(), None);
+    }
+}
@@ -1,18 +1,109 @@
-use std::fmt;
+use std::{collections::HashMap, error, fmt};
 
-use zksync_vm2_interface::ShouldStop;
+use zksync_vm2_interface::{ShouldStop, Tracer};
 
-use crate::{addressing_modes::Arguments, vm::VirtualMachine};
+use crate::{addressing_modes::Arguments, vm::VirtualMachine, World};
 
 /// Single EraVM instruction (an opcode + [`Arguments`]).
 ///
 /// Managing instructions is warranted for low-level tests; prefer using [`Program`](crate::Program)s to decode instructions
-/// from EraVM bytecodes.
+/// from EraVM bytecodes. Programs store instructions in a more compact form, so instructions are only used
+/// to construct programs.
+///
+/// # Constructors
+///
+/// Every opcode supported by the VM has a typed `from_*` constructor, so programs can be built without encoding them:
+///
+/// - Arithmetic / bitwise ops: [`Self::from_add()`], [`Self::from_sub()`], [`Self::from_mul()`], [`Self::from_div()`],
+///   [`Self::from_and()`], [`Self::from_or()`], [`Self::from_xor()`], [`Self::from_shift_left()`],
+///   [`Self::from_shift_right()`], [`Self::from_rotate_left()`], [`Self::from_rotate_right()`]
+/// - Pointer ops: [`Self::from_pointer_add()`], [`Self::from_pointer_sub()`], [`Self::from_pointer_pack()`],
+///   [`Self::from_pointer_shrink()`]
+/// - Heap access (UMA): [`Self::from_heap_read()`], [`Self::from_heap_write()`], [`Self::from_aux_heap_read()`],
+///   [`Self::from_aux_heap_store()`], [`Self::from_pointer_read()`]
+/// - Storage and the rest of the log family: [`Self::from_storage_read()`], [`Self::from_storage_write()`],
+///   [`Self::from_transient_storage_read()`], [`Self::from_transient_storage_write()`], [`Self::from_event()`],
+///   [`Self::from_l2_to_l1_message()`], [`Self::from_precompile_call()`], [`Self::from_decommit()`]
+/// - Context: [`Self::from_this()`], [`Self::from_caller()`], [`Self::from_code_address()`], [`Self::from_ergs_left()`],
+///   [`Self::from_context_u128()`], [`Self::from_set_context_u128()`], [`Self::from_context_sp()`],
+///   [`Self::from_context_meta()`], [`Self::from_increment_tx_number()`],
+///   [`Self::from_set_ergs_per_pubdata_byte()`], [`Self::from_aux_mutating()`]
+/// - Control flow: [`Self::from_jump()`], [`Self::from_near_call()`], [`Self::from_far_call()`], [`Self::from_ret()`],
+///   [`Self::from_revert()`], [`Self::from_panic()`], [`Self::from_nop()`], [`Self::from_invalid()`]
+///
+/// Static memory reads / writes are not supported by the VM and thus have no constructors.
 pub struct Instruction<T, W> {
     pub(crate) handler: Handler<T, W>,
     pub(crate) arguments: Arguments,
 }
 
+/// Instruction as stored in a [`Program`](crate::Program). Instead of a handler pointer, it contains an index
+/// into the [`HandlerTable`] of the program.
+#[derive(Debug)]
+pub(crate) struct CompactInstruction {
+    pub(crate) handler: u16,
+    pub(crate) arguments: Arguments,
+}
+
+// Programs hold up to `1 << 16` instructions, so keeping instructions small lets hot loops stay in the CPU cache.
+// A handler index instead of a handler pointer shrinks an instruction from 16 to 10 bytes on 64-bit targets.
+const _: () = assert!(std::mem::size_of::<CompactInstruction>() == 10);
+
+/// Handler index of [`Instruction::from_invalid()`] in every [`HandlerTable`].
+pub(crate) const INVALID_HANDLER: u16 = 0;
+/// Handler index of [`Instruction::from_spontaneous_panic()`] in every [`HandlerTable`].
+pub(crate) const SPONTANEOUS_PANIC_HANDLER: u16 = 1;
+
+/// Deduplicated handlers of the instructions of a program.
+///
+/// The table always starts with the handlers of the invalid instruction and the spontaneous panic, so that
+/// the static instructions the program counter is set to on errors can be executed in any program.
+pub(crate) struct HandlerTable<T, W> {
+    handlers: Vec<Handler<T, W>>,
+    indices: HashMap<usize, u16>,
+}
+
+impl<T: Tracer, W: World<T>> HandlerTable<T, W> {
+    pub(crate) fn new() -> Self {
+        let mut this = Self {
+            handlers: vec![],
+            indices: HashMap::new(),
+        };
+        let invalid = this.compact(Instruction::from_invalid());
+        let panic = this.compact(Instruction::from_spontaneous_panic());
+        debug_assert_eq!(
+            (invalid.handler, panic.handler),
+            (INVALID_HANDLER, SPONTANEOUS_PANIC_HANDLER)
+        );
+        this
+    }
+}
+
+impl<T, W> HandlerTable<T, W> {
+    /// Replaces the handler of `instruction` with its index in this table, adding the handler if necessary.
+    pub(crate) fn compact(&mut self, instruction: Instruction<T, W>) -> CompactInstruction {
+        // Handlers may be deduplicated imperfectly if the same function has several addresses (or several
+        // identical functions share an address). This only affects the table size, not which code is executed.
+        let handlers = &mut self.handlers;
+        let handler = *self
+            .indices
+            .entry(instruction.handler as usize)
+            .or_insert_with(|| {
+                let index = u16::try_from(handlers.len()).expect("too many distinct handlers");
+                handlers.push(instruction.handler);
+                index
+            });
+        CompactInstruction {
+            handler,
+            arguments: instruction.arguments,
+        }
+    }
+
+    pub(crate) fn into_handlers(self) -> Vec<Handler<T, W>> {
+        self.handlers
+    }
+}
+
 impl<T, W> fmt::Debug for Instruction<T, W> {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter
@@ -51,7 +142,8 @@ impl From<ShouldStop> for ExecutionStatus {
 }
 
 /// VM stop reason returned from [`VirtualMachine::run()`].
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum ExecutionEnd {
     /// The executed program has finished and returned the specified data.
     ProgramFinished(Vec<u8>),
@@ -63,4 +155,61 @@ pub enum ExecutionEnd {
     SuspendedOnHook(u32),
     /// One of the tracers decided it is time to stop the VM.
     StoppedByTracer,
+    /// The instruction budget passed to [`VirtualMachine::run_with_instruction_limit()`] was exhausted.
+    InstructionLimit,
+    /// Memory used by the VM exceeded the [limit](VirtualMachine::set_memory_limit()).
+    MemoryLimitExceeded,
+    /// The program called a precompile (identified by the lower 16 bits of its address) that is not supported
+    /// by the used [`Precompiles`](crate::precompiles::Precompiles), e.g. because this crate is compiled
+    /// without the `precompiles` feature. Unlike other stops, the VM cannot be resumed afterwards,
+    /// since the precompile call has already charged gas.
+    UnsupportedPrecompile(u16),
+}
+
+impl ExecutionEnd {
+    /// Converts this end into a result, treating all ends other than [`Self::ProgramFinished`] as errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` if the program hasn't finished, including the cases when the VM can be resumed.
+    pub fn into_result(self) -> Result<Vec<u8>, Self> {
+        match self {
+            Self::ProgramFinished(output) => Ok(output),
+            _ => Err(self),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionEnd {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProgramFinished(output) => {
+                write!(
+                    formatter,
+                    "program finished with {} bytes of output",
+                    output.len()
+                )
+            }
+            Self::Reverted(output) => {
+                write!(
+                    formatter,
+                    "program reverted with {} bytes of output",
+                    output.len()
+                )
+            }
+            Self::Panicked => formatter.write_str("program panicked"),
+            Self::SuspendedOnHook(hook) => write!(formatter, "execution suspended on hook {hook}"),
+            Self::StoppedByTracer => formatter.write_str("execution stopped by tracer"),
+            Self::InstructionLimit => formatter.write_str("instruction limit exceeded"),
+            Self::MemoryLimitExceeded => formatter.write_str("memory limit exceeded"),
+            Self::UnsupportedPrecompile(address_low) => {
+                write!(
+                    formatter,
+                    "unsupported precompile {address_low:#06x} called"
+                )
+            }
+        }
+    }
 }
+
+impl error::Error for ExecutionEnd {}
@@ -41,6 +41,7 @@ where
         let (result, out2, flags) = Op::perform(&a, &b);
         Out::set(args, &mut vm.state, result);
         out2.write(args, &mut vm.state);
+        // Resolved during monomorphization, so handlers without `set_flags` never touch flags.
         if SET_FLAGS {
             vm.state.flags = flags;
         }
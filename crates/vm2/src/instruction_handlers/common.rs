@@ -1,9 +1,11 @@
-use zksync_vm2_interface::{opcodes, OpcodeType, Tracer};
+use zksync_vm2_interface::{opcodes, OpcodeType, PanicReason, Tracer};
 
 use super::ret::free_panic;
 use crate::{
-    addressing_modes::Arguments, instruction::ExecutionStatus, tracing::VmAndWorld, VirtualMachine,
-    World,
+    addressing_modes::Arguments,
+    instruction::{ExecutionEnd, ExecutionStatus},
+    tracing::VmAndWorld,
+    VirtualMachine, World,
 };
 
 #[inline(always)]
@@ -45,14 +47,14 @@ pub(crate) fn full_boilerplate<Opcode: OpcodeType, T: Tracer, W: World<T>>(
     ) -> ExecutionStatus,
 ) -> ExecutionStatus {
     let args = unsafe { &(*vm.state.current_frame.pc).arguments };
-
-    if vm.state.use_gas(args.get_static_gas_cost()).is_err()
-        || !args.mode_requirements().met(
-            vm.state.current_frame.is_kernel,
-            vm.state.current_frame.is_static,
-        )
-    {
-        return free_panic(vm, world, tracer);
+    if vm.state.use_gas(args.get_static_gas_cost()).is_err() {
+        return free_panic(vm, world, tracer, PanicReason::OutOfGas);
+    }
+    if !args.mode_requirements().met(
+        vm.state.current_frame.is_kernel,
+        vm.state.current_frame.is_static,
+    ) {
+        return free_panic(vm, world, tracer, PanicReason::PrivilegeViolation);
     }
 
     if args.predicate().satisfied(&vm.state.flags) {
@@ -68,3 +70,26 @@ pub(crate) fn full_boilerplate<Opcode: OpcodeType, T: Tracer, W: World<T>>(
             .into()
     }
 }
+
+/// Same as [`full_boilerplate()`], but also stops the VM if an instruction that may allocate memory (e.g., by writing
+/// to a heap or creating a frame) makes the VM exceed its memory limit. Memory usage cannot grow in other instructions,
+/// so the limit is only checked here.
+#[inline(always)]
+pub(crate) fn allocating_boilerplate<Opcode: OpcodeType, T: Tracer, W: World<T>>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+    business_logic: impl FnOnce(
+        &mut VirtualMachine<T, W>,
+        &Arguments,
+        &mut W,
+        &mut T,
+    ) -> ExecutionStatus,
+) -> ExecutionStatus {
+    match full_boilerplate::<Opcode, T, W>(vm, world, tracer, business_logic) {
+        ExecutionStatus::Running if vm.is_memory_limit_exceeded() => {
+            ExecutionStatus::Stopped(ExecutionEnd::MemoryLimitExceeded)
+        }
+        status => status,
+    }
+}
@@ -1,5 +1,4 @@
 use primitive_types::{H160, U256};
-use zkevm_opcode_defs::VmMetaParameters;
 use zksync_vm2_interface::{
     opcodes::{self, Caller, CodeAddress, ContextU128, ErgsLeft, This, SP},
     OpcodeType, Tracer,
@@ -9,6 +8,7 @@ use super::common::boilerplate;
 use crate::{
     addressing_modes::{Arguments, Destination, Register1, Source},
     instruction::ExecutionStatus,
+    isa::VmMetaParameters,
     state::State,
     Instruction, VirtualMachine, World,
 };
@@ -83,9 +83,10 @@ fn context_meta<T: Tracer, W: World<T>>(
         let result = VmMetaParameters {
             heap_size: vm.state.current_frame.heap_size,
             aux_heap_size: vm.state.current_frame.aux_heap_size,
-            this_shard_id: 0, // TODO properly implement shards
-            caller_shard_id: 0,
-            code_shard_id: 0,
+            this_shard_id: vm.state.current_frame.shard_id,
+            caller_shard_id: vm.state.current_frame.caller_shard_id,
+            // Code is always loaded from the shard of the executed contract.
+            code_shard_id: vm.state.current_frame.shard_id,
             // This field is actually pubdata!
             aux_field_0: if vm.state.current_frame.is_kernel {
                 #[allow(clippy::cast_sign_loss)] // wrapping conversion is intentional
@@ -123,6 +124,16 @@ fn increment_tx_number<T: Tracer, W: World<T>>(
     })
 }
 
+fn set_ergs_per_pubdata_byte<T: Tracer, W: World<T>>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+) -> ExecutionStatus {
+    boilerplate::<opcodes::AuxMutating0, _, _>(vm, world, tracer, |vm, args| {
+        vm.state.ergs_per_pubdata_byte = Register1::get(args, &mut vm.state).low_u32();
+    })
+}
+
 fn aux_mutating<T: Tracer, W: World<T>>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
@@ -196,7 +207,21 @@ impl<T: Tracer, W: World<T>> Instruction<T, W> {
         }
     }
 
-    /// Creates an [`AuxMutating0`](opcodes::AuxMutating0) instruction with the provided params.
+    /// Creates an [`AuxMutating0`](opcodes::AuxMutating0) instruction that sets the gas charged per byte of pubdata
+    /// to the low 32 bits of `src`. The instruction should be restricted to kernel mode with the provided `arguments`.
+    ///
+    /// The decoder interprets the opcode as [a no-op](Self::from_aux_mutating()) to match `zk_evm`, so this
+    /// instruction can only be constructed explicitly. The rate only has an effect if pubdata charging is enabled
+    /// with [`VirtualMachineBuilder::charge_for_pubdata()`](crate::VirtualMachineBuilder::charge_for_pubdata()).
+    pub fn from_set_ergs_per_pubdata_byte(src: Register1, arguments: Arguments) -> Self {
+        Self {
+            handler: set_ergs_per_pubdata_byte,
+            arguments: arguments.write_source(&src),
+        }
+    }
+
+    /// Creates an [`AuxMutating0`](opcodes::AuxMutating0) instruction with the provided params
+    /// that doesn't do anything besides charging gas.
     pub fn from_aux_mutating(arguments: Arguments) -> Self {
         Self {
             handler: aux_mutating,
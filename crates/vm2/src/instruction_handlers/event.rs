@@ -1,14 +1,18 @@
 use primitive_types::H160;
-use zkevm_opcode_defs::ADDRESS_EVENT_WRITER;
-use zksync_vm2_interface::{opcodes, Event, L2ToL1Log, Tracer};
+use zksync_vm2_interface::{opcodes, Event, L2ToL1Log, PanicReason, Tracer};
 
 use super::common::boilerplate_ext;
 use crate::{
     addressing_modes::{Arguments, Immediate1, Register1, Register2, Source},
     instruction::ExecutionStatus,
+    isa::ADDRESS_EVENT_WRITER,
     Instruction, VirtualMachine, World,
 };
 
+/// An L2-to-L1 message contributes its serialized log to pubdata.
+#[allow(clippy::cast_possible_truncation)] // the length is a small constant
+const L2_TO_L1_LOG_PUBDATA_BYTES: u32 = L2ToL1Log::LEAF_ENCODING_LEN as u32;
+
 fn event<T: Tracer, W: World<T>>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
@@ -24,7 +28,7 @@ fn event<T: Tracer, W: World<T>>(
                 key,
                 value,
                 is_first,
-                shard_id: 0, // shards currently aren't supported
+                shard_id: vm.state.current_frame.shard_id,
                 tx_number: vm.state.transaction_number,
             });
         }
@@ -40,12 +44,22 @@ fn l2_to_l1<T: Tracer, W: World<T>>(
         let key = Register1::get(args, &mut vm.state);
         let value = Register2::get(args, &mut vm.state);
         let is_service = Immediate1::get(args, &mut vm.state).low_u32() == 1;
+        if vm.charge_for_pubdata
+            && vm
+                .state
+                .use_gas_for_pubdata(L2_TO_L1_LOG_PUBDATA_BYTES)
+                .is_err()
+        {
+            vm.panic_spontaneously(PanicReason::OutOfGas);
+            return;
+        }
+
         vm.world_diff.record_l2_to_l1_log(L2ToL1Log {
             key,
             value,
             is_service,
             address: vm.state.current_frame.address,
-            shard_id: 0,
+            shard_id: vm.state.current_frame.shard_id,
             tx_number: vm.state.transaction_number,
         });
     })
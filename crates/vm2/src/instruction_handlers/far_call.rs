@@ -1,12 +1,11 @@
 use primitive_types::U256;
-use zkevm_opcode_defs::{system_params::MSG_VALUE_SIMULATOR_ADDITIVE_COST, ADDRESS_MSG_VALUE};
 use zksync_vm2_interface::{
     opcodes::{FarCall, TypeLevelCallingMode},
-    Tracer,
+    PanicReason, Tracer,
 };
 
 use super::{
-    common::full_boilerplate,
+    common::allocating_boilerplate,
     heap_access::grow_heap,
     monomorphization::{match_boolean, monomorphize, parameterize},
     AuxHeap, Heap,
@@ -16,6 +15,7 @@ use crate::{
     decommit::{is_kernel, u256_into_address},
     fat_pointer::FatPointer,
     instruction::ExecutionStatus,
+    isa::{system_params::MSG_VALUE_SIMULATOR_ADDITIVE_COST, ADDRESS_MSG_VALUE},
     predication::Flags,
     Instruction, Program, VirtualMachine, World,
 };
@@ -26,7 +26,8 @@ use crate::{
 /// or and existing one is forwarded. Costs for decommitting and memory growth are paid
 /// at this point.
 ///
-/// A new stack frame is pushed. At most 63/64 of the *remaining* gas is passed to the called contract.
+/// A new stack frame is pushed. At most 63/64 of the *remaining* gas is passed to the called contract;
+/// see [`max_passable_gas()`].
 ///
 /// Even though all errors happen before the new stack frame, they cause a panic in the new frame,
 /// not in the caller!
@@ -40,7 +41,7 @@ where
     W: World<T>,
     M: TypeLevelCallingMode,
 {
-    full_boilerplate::<FarCall<M>, _, _>(vm, world, tracer, |vm, args, world, tracer| {
+    allocating_boilerplate::<FarCall<M>, _, _>(vm, world, tracer, |vm, args, world, tracer| {
         let (raw_abi, raw_abi_is_pointer) = Register1::get_with_pointer_flag(args, &mut vm.state);
 
         let address_mask: U256 = U256::MAX >> (256 - 160);
@@ -82,34 +83,59 @@ where
                 // If the gas is insufficient, the rest is burned
                 vm.state.current_frame.gas = 0;
                 mandated_gas = 0;
-                return None;
+                return Err(PanicReason::OutOfGas);
             }
 
             if IS_SHARD && abi.shard_id != 0 {
-                return None;
+                return Err(PanicReason::Other);
             }
             let calldata = maybe_calldata?;
-            let (unpaid_decommit, is_evm) = decommit_result?;
-            let program = vm.world_diff.pay_for_decommit(
-                world,
-                tracer,
-                unpaid_decommit,
-                &mut vm.state.current_frame.gas,
-            )?;
-
-            Some((calldata, program, is_evm))
+            let (unpaid_decommit, is_evm) = decommit_result.ok_or(PanicReason::Other)?;
+            let program = vm
+                .world_diff
+                .pay_for_decommit(
+                    world,
+                    tracer,
+                    unpaid_decommit,
+                    &mut vm.state.current_frame.gas,
+                )
+                .ok_or(PanicReason::OutOfGas)?;
+
+            Ok((calldata, program, is_evm))
         })();
 
-        let maximum_gas = vm.state.current_frame.gas / 64 * 63;
+        let maximum_gas = max_passable_gas(vm.state.current_frame.gas);
         let normally_passed_gas = abi.gas_to_pass.min(maximum_gas);
         vm.state.current_frame.gas -= normally_passed_gas;
-        let new_frame_gas = normally_passed_gas + mandated_gas;
+        // Stipends are small, but the sum can still overflow if the caller has near-maximum gas.
+        let new_frame_gas = normally_passed_gas.saturating_add(mandated_gas);
+
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(
+            caller = ?vm.state.current_frame.address,
+            address = ?u256_into_address(destination_address),
+            mode = ?M::VALUE,
+            gas = new_frame_gas,
+            pc = vm.state.current_frame.get_pc_as_u16(),
+            is_static = IS_STATIC || vm.state.current_frame.is_static,
+            is_system_call = abi.is_system_call,
+            failed = fallible_part.is_err(),
+            "far call"
+        );
 
         // A far call pushes a new frame and returns from it in the next instruction if it panics.
-        let (calldata, program, is_evm_interpreter) =
-            fallible_part.unwrap_or_else(|| (U256::zero().into(), Program::new_panicking(), false));
+        // The panic is recorded here because the far call instruction is the one that has failed.
+        let (calldata, program, is_evm_interpreter) = fallible_part.unwrap_or_else(|reason| {
+            vm.record_panic(reason);
+            (U256::zero().into(), Program::new_panicking(), false)
+        });
 
         let new_frame_is_static = IS_STATIC || vm.state.current_frame.is_static;
+        let new_frame_shard_id = if IS_SHARD {
+            abi.shard_id
+        } else {
+            vm.state.current_frame.shard_id
+        };
         vm.push_frame::<M>(
             u256_into_address(destination_address),
             program,
@@ -117,6 +143,7 @@ where
             exception_handler,
             new_frame_is_static && !is_evm_interpreter,
             is_evm_interpreter,
+            new_frame_shard_id,
             calldata.memory_page,
             vm.world_diff.snapshot(),
         );
@@ -147,6 +174,20 @@ where
     })
 }
 
+/// A far call passes at most `(FAR_CALL_GAS_DIVISOR - 1) / FAR_CALL_GAS_DIVISOR` of the caller's gas
+/// to the callee (the "63/64 rule").
+const FAR_CALL_GAS_DIVISOR: u32 = 64;
+
+/// Returns the maximum gas that can be passed to a far call, given the gas left in the caller after paying
+/// for decommitment and memory growth for the calldata. Mandated gas (e.g., for calls to the `MsgValueSimulator`)
+/// is passed on top of this amount.
+///
+/// Unlike the EVM, the remaining gas is rounded down to a multiple of [`FAR_CALL_GAS_DIVISOR`] before
+/// computing the share, so the caller keeps `gas_left / 64 + gas_left % 64`.
+fn max_passable_gas(gas_left: u32) -> u32 {
+    gas_left / FAR_CALL_GAS_DIVISOR * (FAR_CALL_GAS_DIVISOR - 1)
+}
+
 #[derive(Debug)]
 pub(crate) struct FarCallABI {
     pub(crate) gas_to_pass: u32,
@@ -178,7 +219,7 @@ pub(crate) fn get_calldata<T, W>(
     is_pointer: bool,
     vm: &mut VirtualMachine<T, W>,
     already_failed: bool,
-) -> Option<FatPointer> {
+) -> Result<FatPointer, PanicReason> {
     let mut pointer = FatPointer::from(raw_abi);
     #[allow(clippy::cast_possible_truncation)]
     // intentional: the source is encoded in the lower byte of the extracted value
@@ -187,7 +228,7 @@ pub(crate) fn get_calldata<T, W>(
     match FatPointerSource::from_abi(raw_source) {
         FatPointerSource::ForwardFatPointer => {
             if !is_pointer || pointer.offset > pointer.length {
-                return None;
+                return Err(PanicReason::PointerMisuse);
             }
 
             pointer.narrow();
@@ -196,32 +237,38 @@ pub(crate) fn get_calldata<T, W>(
             let mut grow = |size| {
                 match target {
                     FatPointerTarget::ToHeap => {
-                        grow_heap::<_, _, Heap>(&mut vm.state, size).ok()?;
+                        grow_heap::<_, _, Heap>(&mut vm.state, size)
+                            .map_err(|()| PanicReason::OutOfGas)?;
                         pointer.memory_page = vm.state.current_frame.heap;
                     }
                     FatPointerTarget::ToAuxHeap => {
-                        grow_heap::<_, _, AuxHeap>(&mut vm.state, size).ok()?;
+                        grow_heap::<_, _, AuxHeap>(&mut vm.state, size)
+                            .map_err(|()| PanicReason::OutOfGas)?;
                         pointer.memory_page = vm.state.current_frame.aux_heap;
                     }
                 }
-                Some(())
+                Ok(())
             };
 
             // A pointer whose start + length > u32::MAX always causes the heap to grow,
             // even if it doesn't fullfill any other validity criteria.
             if let Some(bound) = pointer.start.checked_add(pointer.length) {
-                if is_pointer || pointer.offset != 0 || already_failed {
-                    return None;
+                if is_pointer || pointer.offset != 0 {
+                    return Err(PanicReason::PointerMisuse);
+                }
+                if already_failed {
+                    return Err(PanicReason::Other);
                 }
                 grow(bound)?;
             } else {
-                grow(u32::MAX);
-                return None;
+                // The pointer is out of bounds regardless of whether growing succeeds.
+                let _ = grow(u32::MAX);
+                return Err(PanicReason::HeapOutOfBounds);
             }
         }
     }
 
-    Some(pointer)
+    Ok(pointer)
 }
 
 #[derive(Debug)]
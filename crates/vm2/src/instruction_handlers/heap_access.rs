@@ -1,10 +1,9 @@
 use primitive_types::U256;
-use zksync_vm2_interface::{opcodes, HeapId, OpcodeType, Tracer};
+use zksync_vm2_interface::{opcodes, HeapId, OpcodeType, PanicReason, Tracer};
 
 use super::{
-    common::{boilerplate, full_boilerplate},
+    common::{allocating_boilerplate, boilerplate},
     monomorphization::{match_boolean, match_reg_imm, monomorphize, parameterize},
-    ret::spontaneous_panic,
 };
 use crate::{
     addressing_modes::{
@@ -76,14 +75,14 @@ fn load<T: Tracer, W: World<T>, H: HeapFromState, In: Source, const INCREMENT: b
 
         if bigger_than_last_address(pointer) {
             let _ = vm.state.use_gas(u32::MAX);
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::HeapOutOfBounds);
             return;
         }
 
         let address = pointer.low_u32();
         let new_bound = address.wrapping_add(32);
         if grow_heap::<_, _, H>(&mut vm.state, new_bound).is_err() {
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::OutOfGas);
             return;
         }
 
@@ -107,14 +106,14 @@ where
     H: HeapFromState,
     In: Source,
 {
-    full_boilerplate::<H::Write, _, _>(vm, world, tracer, |vm, args, _, _| {
+    allocating_boilerplate::<H::Write, _, _>(vm, world, tracer, |vm, args, _, _| {
         // Pointers need not be masked here even though we do not care about them being pointers.
         // They will panic, though because they are larger than 2^32.
         let (pointer, _) = In::get_with_pointer_flag(args, &mut vm.state);
 
         if bigger_than_last_address(pointer) {
             let _ = vm.state.use_gas(u32::MAX);
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::HeapOutOfBounds);
             return ExecutionStatus::Running;
         }
 
@@ -123,7 +122,7 @@ where
 
         let new_bound = address.wrapping_add(32);
         if grow_heap::<_, _, H>(&mut vm.state, new_bound).is_err() {
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::OutOfGas);
             return ExecutionStatus::Running;
         }
 
@@ -135,6 +134,13 @@ where
         }
 
         if HOOKING_ENABLED && address == vm.settings.hook_address {
+            #[cfg(feature = "tracing")]
+            ::tracing::debug!(
+                hook = value.as_u32(),
+                pc = vm.state.current_frame.get_pc_as_u16(),
+                gas = vm.state.current_frame.gas,
+                "suspended on hook"
+            );
             ExecutionStatus::Stopped(ExecutionEnd::SuspendedOnHook(value.as_u32()))
         } else {
             ExecutionStatus::Running
@@ -164,7 +170,7 @@ fn load_pointer<T: Tracer, W: World<T>, const INCREMENT: bool>(
     boilerplate::<opcodes::PointerRead, _, _>(vm, world, tracer, |vm, args| {
         let (input, input_is_pointer) = Register1::get_with_pointer_flag(args, &mut vm.state);
         if !input_is_pointer {
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::PointerMisuse);
             return;
         }
         let pointer = FatPointer::from(input);
@@ -173,7 +179,7 @@ fn load_pointer<T: Tracer, W: World<T>, const INCREMENT: bool>(
         // but if offset + 32 is not representable, we panic, even if we could've read some bytes.
         // This is not a bug, this is how it must work to be backwards compatible.
         if pointer.offset > LAST_ADDRESS {
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::HeapOutOfBounds);
             return;
         }
 
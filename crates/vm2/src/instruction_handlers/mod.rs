@@ -1,10 +1,7 @@
-#[cfg(feature = "single_instruction_test")]
-pub(crate) use ret::spontaneous_panic;
-
 pub(crate) use self::{
     context::address_into_u256,
     heap_access::{AuxHeap, Heap},
-    ret::invalid_instruction,
+    ret::{invalid_instruction, spontaneous_panic},
 };
 
 mod binop;
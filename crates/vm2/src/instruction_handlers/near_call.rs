@@ -37,6 +37,10 @@ fn near_call<T: Tracer, W: World<T>>(
 
 impl<T: Tracer, W: World<T>> Instruction<T, W> {
     /// Creates a [`NearCall`](opcodes::NearCall) instruction with the provided params.
+    ///
+    /// The callee gets the gas specified by the low 32 bits of `gas`; zero or an amount exceeding
+    /// the gas left in the caller passes all of it. Once the call returns, reverts or panics, gas left in
+    /// the callee is returned to the caller.
     pub fn from_near_call(
         gas: Register1,
         destination: Immediate1,
@@ -2,7 +2,7 @@ use zksync_vm2_interface::{opcodes, Tracer};
 
 use super::common::boilerplate;
 use crate::{
-    addressing_modes::{destination_stack_address, AdvanceStackPointer, Arguments, Source},
+    addressing_modes::{AdvanceStackPointer, Arguments},
     instruction::ExecutionStatus,
     Instruction, VirtualMachine, World,
 };
@@ -14,12 +14,7 @@ fn nop<T: Tracer, W: World<T>>(
 ) -> ExecutionStatus {
     boilerplate::<opcodes::Nop, _, _>(vm, world, tracer, |vm, args| {
         // nop's addressing modes can move the stack pointer!
-        AdvanceStackPointer::get(args, &mut vm.state);
-        vm.state.current_frame.sp = vm
-            .state
-            .current_frame
-            .sp
-            .wrapping_add(destination_stack_address(args, &mut vm.state));
+        AdvanceStackPointer::adjust_stack_pointer(args, &mut vm.state);
     })
 }
 
@@ -1,7 +1,7 @@
 use primitive_types::U256;
 use zksync_vm2_interface::{
     opcodes::{PointerAdd, PointerPack, PointerShrink, PointerSub},
-    OpcodeType, Tracer,
+    OpcodeType, PanicReason, Tracer,
 };
 
 use super::{
@@ -9,7 +9,6 @@ use super::{
     monomorphization::{
         match_boolean, match_destination, match_source, monomorphize, parameterize,
     },
-    ret::spontaneous_panic,
 };
 use crate::{
     addressing_modes::{
@@ -40,12 +39,12 @@ fn ptr<T: Tracer, W: World<T>, Op: PtrOp, In1: Source, Out: Destination, const S
         };
 
         if !a_is_pointer || b_is_pointer {
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::PointerMisuse);
             return;
         }
 
         let Some(result) = Op::perform(a, b) else {
-            vm.state.current_frame.pc = spontaneous_panic();
+            vm.panic_spontaneously(PanicReason::PointerMisuse);
             return;
         };
 
@@ -75,16 +74,14 @@ fn ptr_add_sub<const IS_ADD: bool>(mut in1: U256, in2: U256) -> Option<U256> {
     if in2 > u32::MAX.into() {
         return None;
     }
-    let pointer: &mut FatPointer = (&mut in1).into();
-
-    let new_offset = if IS_ADD {
-        pointer.offset.checked_add(in2.low_u32())
-    } else {
-        pointer.offset.checked_sub(in2.low_u32())
-    }?;
-
-    pointer.offset = new_offset;
-
+    FatPointer::update(&mut in1, |pointer| {
+        pointer.offset = if IS_ADD {
+            pointer.offset.checked_add(in2.low_u32())
+        } else {
+            pointer.offset.checked_sub(in2.low_u32())
+        }?;
+        Some(())
+    })?;
     Some(in1)
 }
 
@@ -102,8 +99,10 @@ impl PtrOp for PointerPack {
 impl PtrOp for PointerShrink {
     #[inline(always)]
     fn perform(mut in1: U256, in2: U256) -> Option<U256> {
-        let pointer: &mut FatPointer = (&mut in1).into();
-        pointer.length = pointer.length.checked_sub(in2.low_u32())?;
+        FatPointer::update(&mut in1, |pointer| {
+            pointer.length = pointer.length.checked_sub(in2.low_u32())?;
+            Some(())
+        })?;
         Some(in1)
     }
 }
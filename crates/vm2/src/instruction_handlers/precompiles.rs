@@ -1,10 +1,10 @@
 use primitive_types::U256;
-use zksync_vm2_interface::{opcodes, HeapId, Tracer};
+use zksync_vm2_interface::{opcodes, HeapId, PanicReason, Tracer};
 
-use super::{common::boilerplate_ext, ret::spontaneous_panic};
+use super::common::allocating_boilerplate;
 use crate::{
     addressing_modes::{Arguments, Destination, Register1, Register2, Source},
-    instruction::ExecutionStatus,
+    instruction::{ExecutionEnd, ExecutionStatus},
     precompiles::{PrecompileMemoryReader, Precompiles},
     Instruction, VirtualMachine, World,
 };
@@ -69,7 +69,7 @@ fn precompile_call<T: Tracer, W: World<T>>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate_ext::<opcodes::PrecompileCall, _, _>(
+    allocating_boilerplate::<opcodes::PrecompileCall, _, _>(
         vm,
         world,
         tracer,
@@ -78,13 +78,17 @@ fn precompile_call<T: Tracer, W: World<T>>(
             // This is safe because system contracts are trusted
             let aux_data = PrecompileAuxData::from_u256(Register2::get(args, &mut vm.state));
             let Ok(()) = vm.state.use_gas(aux_data.extra_ergs_cost) else {
-                vm.state.current_frame.pc = spontaneous_panic();
-                return;
+                vm.panic_spontaneously(PanicReason::OutOfGas);
+                return ExecutionStatus::Running;
             };
 
             #[allow(clippy::cast_possible_wrap)]
             {
-                vm.world_diff.pubdata.0 += aux_data.extra_pubdata_cost as i32;
+                vm.world_diff.pubdata.0 = vm
+                    .world_diff
+                    .pubdata
+                    .0
+                    .wrapping_add(aux_data.extra_pubdata_cost as i32);
             }
 
             let mut abi = PrecompileCallAbi::from_u256(Register1::get(args, &mut vm.state));
@@ -94,6 +98,13 @@ fn precompile_call<T: Tracer, W: World<T>>(
             if abi.memory_page_to_write.as_u32() == 0 {
                 abi.memory_page_to_write = vm.state.current_frame.heap;
             }
+            // Heap IDs are provided by the caller, so they must be checked to not panic on an unallocated heap.
+            if !vm.state.heaps.contains(abi.memory_page_to_read)
+                || !vm.state.heaps.contains(abi.memory_page_to_write)
+            {
+                vm.panic_spontaneously(PanicReason::Other);
+                return ExecutionStatus::Running;
+            }
 
             let address_bytes = vm.state.current_frame.address.0;
             let address_low = u16::from_le_bytes([address_bytes[19], address_bytes[18]]);
@@ -103,26 +114,37 @@ fn precompile_call<T: Tracer, W: World<T>>(
                 abi.input_memory_offset,
                 abi.input_memory_length,
             );
-            let output = world.precompiles().call_precompile(
-                address_low,
-                memory,
-                abi.precompile_interpreted_data,
-            );
+            let output = if let Some(precompiles) = &vm.precompiles {
+                precompiles
+                    .0
+                    .call_precompile(address_low, memory, abi.precompile_interpreted_data)
+            } else {
+                world.precompiles().call_precompile(
+                    address_low,
+                    memory,
+                    abi.precompile_interpreted_data,
+                )
+            };
 
+            if output.is_unsupported {
+                return ExecutionStatus::Stopped(ExecutionEnd::UnsupportedPrecompile(address_low));
+            }
             if let Some(cycle_stats) = output.cycle_stats {
                 tracer.on_extra_prover_cycles(cycle_stats);
             }
 
-            let mut write_offset = abi.output_memory_offset * 32;
+            // Offsets are provided by the caller and wrap around on overflow.
+            let mut write_offset = abi.output_memory_offset.wrapping_mul(32);
             for i in 0..output.len.min(abi.output_memory_length) {
                 vm.state.heaps.write_u256(
                     abi.memory_page_to_write,
                     write_offset,
                     output.buffer[i as usize],
                 );
-                write_offset += 32;
+                write_offset = write_offset.wrapping_add(32);
             }
             Register1::set(args, &mut vm.state, 1.into());
+            ExecutionStatus::Running
         },
     )
 }
@@ -1,7 +1,7 @@
 use primitive_types::U256;
 use zksync_vm2_interface::{
     opcodes::{self, Normal, Panic, Revert, TypeLevelReturnType},
-    ReturnType, Tracer,
+    PanicReason, ReturnType, Tracer,
 };
 
 use super::{
@@ -12,7 +12,10 @@ use super::{
 use crate::{
     addressing_modes::{Arguments, Immediate1, Register1, Source, INVALID_INSTRUCTION_COST},
     callframe::FrameRemnant,
-    instruction::{ExecutionEnd, ExecutionStatus},
+    instruction::{
+        CompactInstruction, ExecutionEnd, ExecutionStatus, INVALID_HANDLER,
+        SPONTANEOUS_PANIC_HANDLER,
+    },
     mode_requirements::ModeRequirements,
     predication::Flags,
     tracing::VmAndWorld,
@@ -29,7 +32,10 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
     let (snapshot, leftover_gas) = if let Some(FrameRemnant {
         exception_handler,
         snapshot,
-    }) = vm.state.current_frame.pop_near_call()
+    }) = vm
+        .state
+        .current_frame
+        .pop_near_call(return_type.is_failure())
     {
         if TO_LABEL {
             let pc = Immediate1::get_u16(args);
@@ -44,18 +50,43 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
             None
         } else {
             let (raw_abi, is_pointer) = Register1::get_with_pointer_flag(args, &mut vm.state);
-            let result = get_calldata(raw_abi, is_pointer, vm, false).filter(|pointer| {
-                vm.state.current_frame.is_kernel
+            let result = get_calldata(raw_abi, is_pointer, vm, false).and_then(|pointer| {
+                // Only the kernel can return its calldata.
+                if vm.state.current_frame.is_kernel
                     || pointer.memory_page != vm.state.current_frame.calldata_heap
+                {
+                    Ok(pointer)
+                } else {
+                    Err(PanicReason::PointerMisuse)
+                }
             });
 
-            if result.is_none() {
-                return_type = ReturnType::Panic;
+            match result {
+                Ok(pointer) => Some(pointer),
+                Err(reason) => {
+                    vm.record_panic(reason);
+                    return_type = ReturnType::Panic;
+                    None
+                }
             }
-            result
         };
 
-        let leftover_gas = vm.state.current_frame.gas;
+        let frame = &vm.state.current_frame;
+        let refund = if return_type.is_failure() {
+            0
+        } else {
+            let gas_spent = frame.initial_gas.saturating_sub(frame.gas);
+            vm.refund_policy
+                .credited_refund(frame.pending_refund, gas_spent)
+        };
+        let leftover_gas = frame.gas.saturating_add(refund);
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(
+            address = ?vm.state.current_frame.address,
+            ?return_type,
+            gas_left = leftover_gas,
+            "far return"
+        );
 
         let Some(FrameRemnant {
             exception_handler,
@@ -74,13 +105,11 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
             // But to continue execution would be nonsensical and can cause UB because there
             // is no next instruction after a panic arising from some other instruction.
             vm.state.current_frame.pc = invalid_instruction();
+            vm.state.current_frame.gas = leftover_gas;
 
             return if let Some(return_value) = return_value_or_panic {
                 let output = vm.state.heaps[return_value.memory_page]
-                    .read_range_big_endian(
-                        return_value.start..return_value.start + return_value.length,
-                    )
-                    .clone();
+                    .read_range_big_endian(return_value.range());
                 if return_type == ReturnType::Revert {
                     ExecutionStatus::Stopped(ExecutionEnd::Reverted(output))
                 } else {
@@ -94,6 +123,7 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
         vm.state.set_context_u128(0);
         vm.state.registers = [U256::zero(); 16];
 
+        // `r1` is always a pointer; on panic, it's the empty fat pointer (all zeros).
         if let Some(return_value) = return_value_or_panic {
             vm.state.registers[1] = return_value.into_u256();
         }
@@ -111,7 +141,8 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
     }
 
     vm.state.flags = Flags::new(return_type == ReturnType::Panic, false, false);
-    vm.state.current_frame.gas += leftover_gas;
+    // Leftover gas may include a stipend, so the sum is not bounded by the gas available before the call.
+    vm.state.current_frame.gas = vm.state.current_frame.gas.saturating_add(leftover_gas);
 
     ExecutionStatus::Running
 }
@@ -121,8 +152,14 @@ fn ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bool>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    full_boilerplate::<opcodes::Ret<RT>, _, _>(vm, world, tracer, |vm, args, _, _| {
-        naked_ret::<T, W, RT, TO_LABEL>(vm, args)
+    full_boilerplate::<opcodes::Ret<RT>, _, _>(vm, world, tracer, |vm, args, _, tracer| {
+        if RT::VALUE == ReturnType::Panic {
+            // Doesn't override the reason of a spontaneous panic, which is executed as an explicit one.
+            vm.record_panic(PanicReason::Explicit);
+        }
+        let status = naked_ret::<T, W, RT, TO_LABEL>(vm, args);
+        vm.report_panic(tracer);
+        status
     })
 }
 
@@ -135,18 +172,38 @@ fn ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bool>(
 /// - the far call stack overflows
 ///
 /// For all other panics, point the instruction pointer at [PANIC] instead.
+///
+/// `reason` is reported to the tracer, unless another reason was already recorded for the current instruction.
+// Panics are rare, so this is kept out of line to not bloat the hot path of every handler, which checks gas
+// and mode requirements before executing the instruction.
+#[cold]
+#[inline(never)]
 pub(crate) fn free_panic<T: Tracer, W: World<T>>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
     tracer: &mut T,
+    reason: PanicReason,
 ) -> ExecutionStatus {
+    #[cfg(feature = "tracing")]
+    ::tracing::debug!(
+        address = ?vm.state.current_frame.address,
+        pc = vm.state.current_frame.get_pc_as_u16(),
+        gas = vm.state.current_frame.gas,
+        ?reason,
+        "panic"
+    );
+    let pc = vm.state.current_frame.get_raw_pc();
+    vm.record_panic_at(reason, pc);
     tracer.before_instruction::<opcodes::Ret<Panic>, _>(&mut VmAndWorld { vm, world });
     // args aren't used for panics unless TO_LABEL
-    naked_ret::<T, W, Panic, false>(
+    let status = naked_ret::<T, W, Panic, false>(
         vm,
         &Arguments::new(Predicate::Always, 0, ModeRequirements::none()),
+    );
+    vm.report_panic(tracer);
+    status.merge_tracer(
+        tracer.after_instruction::<opcodes::Ret<Panic>, _>(&mut VmAndWorld { vm, world }),
     )
-    .merge_tracer(tracer.after_instruction::<opcodes::Ret<Panic>, _>(&mut VmAndWorld { vm, world }))
 }
 
 fn invalid<T: Tracer, W: World<T>>(
@@ -155,30 +212,37 @@ fn invalid<T: Tracer, W: World<T>>(
     tracer: &mut T,
 ) -> ExecutionStatus {
     vm.state.current_frame.gas = 0;
-    free_panic(vm, world, tracer)
+    free_panic(vm, world, tracer, PanicReason::InvalidInstruction)
 }
 
-trait GenericStatics<T, W> {
-    const PANIC: Instruction<T, W>;
-    const INVALID: Instruction<T, W>;
-}
+// Handlers of these instructions are present in every program at fixed indices, so the instructions
+// don't depend on the program (or its tracer and world types) and can be static.
 
-impl<T: Tracer, W: World<T>> GenericStatics<T, W> for () {
-    const PANIC: Instruction<T, W> = Instruction::from_spontaneous_panic();
-    const INVALID: Instruction<T, W> = Instruction::from_invalid();
-}
+static SPONTANEOUS_PANIC: CompactInstruction = CompactInstruction {
+    handler: SPONTANEOUS_PANIC_HANDLER,
+    arguments: Arguments::new(Predicate::Always, RETURN_COST, ModeRequirements::none()),
+};
+
+static INVALID: CompactInstruction = CompactInstruction {
+    handler: INVALID_HANDLER,
+    arguments: Arguments::new(
+        Predicate::Always,
+        INVALID_INSTRUCTION_COST,
+        ModeRequirements::none(),
+    ),
+};
 
 // The following functions return references that live for 'static.
 // They aren't marked as such because returning any lifetime is more ergonomic.
 
 /// Point the program counter at this instruction when a panic occurs during the logic of and instruction.
-pub(crate) fn spontaneous_panic<'a, T: Tracer, W: World<T>>() -> &'a Instruction<T, W> {
-    &<()>::PANIC
+pub(crate) fn spontaneous_panic<'a>() -> &'a CompactInstruction {
+    &SPONTANEOUS_PANIC
 }
 
 /// Panics, burning all available gas.
-pub(crate) fn invalid_instruction<'a, T: Tracer, W: World<T>>() -> &'a Instruction<T, W> {
-    &<()>::INVALID
+pub(crate) fn invalid_instruction<'a>() -> &'a CompactInstruction {
+    &INVALID
 }
 
 pub(crate) const RETURN_COST: u32 = 5;
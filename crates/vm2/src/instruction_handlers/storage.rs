@@ -1,4 +1,4 @@
-use zksync_vm2_interface::{opcodes, Tracer};
+use zksync_vm2_interface::{opcodes, PanicReason, Tracer};
 
 use super::common::{boilerplate, boilerplate_ext};
 use crate::{
@@ -18,12 +18,20 @@ fn sstore<T: Tracer, W: World<T>>(
         let key = Register1::get(args, &mut vm.state);
         let value = Register2::get(args, &mut vm.state);
 
+        let pubdata_before = vm.world_diff.pubdata();
         let refund =
             vm.world_diff
                 .write_storage(world, tracer, vm.state.current_frame.address, key, value);
 
         assert!(refund <= SSTORE_COST);
-        vm.state.current_frame.gas += refund;
+        vm.credit_refund(refund);
+
+        // Writes that decrease pubdata (e.g., reverting a slot to its initial value) are not refunded.
+        let pubdata_diff = i64::from(vm.world_diff.pubdata()) - i64::from(pubdata_before);
+        let new_pubdata = u32::try_from(pubdata_diff).unwrap_or(0);
+        if vm.charge_for_pubdata && vm.state.use_gas_for_pubdata(new_pubdata).is_err() {
+            vm.panic_spontaneously(PanicReason::OutOfGas);
+        }
     })
 }
 
@@ -54,7 +62,7 @@ fn sload<T: Tracer, W: World<T>>(
                 .read_storage(world, tracer, vm.state.current_frame.address, key);
 
         assert!(refund <= SLOAD_COST);
-        vm.state.current_frame.gas += refund;
+        vm.credit_refund(refund);
 
         Register1::set(args, &mut vm.state, value);
     })
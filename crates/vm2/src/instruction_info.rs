@@ -0,0 +1,542 @@
+//! Inspectable representation of decoded instructions.
+
+use std::fmt;
+
+use zksync_vm2_interface::{CallingMode, Opcode, ReturnType};
+
+use crate::{
+    addressing_modes::{AnyDestination, AnySource, Register, Register2, RegisterAndImmediate},
+    decode::{decode_destination, decode_predicate, decode_source},
+    isa::{
+        self, BinopOpcode, ContextOpcode, EncodingModeProduction, FarCallOpcode, LogOpcode,
+        PtrOpcode, RetOpcode, ShiftOpcode, UMAOpcode, VmEncodingMode, FAR_CALL_SHARD_FLAG_IDX,
+        FAR_CALL_STATIC_FLAG_IDX, FIRST_MESSAGE_FLAG_IDX, RET_TO_LABEL_BIT_IDX, SET_FLAGS_FLAG_IDX,
+        SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES, SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE,
+        UMA_INCREMENT_FLAG_IDX,
+    },
+    ModeRequirements, Predicate,
+};
+
+/// Opcode-specific flags of a decoded instruction. Flags not applicable to the instruction opcode are always `false`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InstructionFlags {
+    /// Binary and pointer operations: whether source operands are swapped.
+    pub swap_operands: bool,
+    /// Binary operations: whether the instruction sets the VM flags.
+    pub set_flags: bool,
+    /// Events and L2-to-L1 messages: whether this is the first event / message in a chain.
+    pub is_first: bool,
+    /// Returns: whether the return jumps to the label specified in the first immediate.
+    pub to_label: bool,
+    /// Far calls: whether the call is static.
+    pub is_static: bool,
+    /// Far calls: whether the shard ID is taken from the ABI.
+    pub is_shard: bool,
+    /// Heap accesses: whether the incremented pointer is returned.
+    pub increment: bool,
+}
+
+/// Information about a decoded instruction intended for static analysis tools.
+///
+/// Unlike [`Instruction`](crate::Instruction), which only contains what is necessary to execute it, this describes
+/// all decoded fields. Operands are provided as is; which of them are used depends on the opcode
+/// (e.g., `out2` is only used by multiplication, division and heap reads with increment).
+#[derive(Debug, Clone, Copy)]
+pub struct InstructionInfo {
+    /// Decoded opcode, or `None` for invalid instructions and instructions not supported by the VM
+    /// (i.e., static memory accesses).
+    pub opcode: Option<Opcode>,
+    /// Predicate on VM flags that must hold for the instruction to execute.
+    pub predicate: Predicate,
+    /// Execution mode requirements.
+    pub mode_requirements: ModeRequirements,
+    /// Static gas cost of the instruction.
+    pub gas_cost: u32,
+    /// First source operand.
+    pub src1: AnySource,
+    /// Second source operand.
+    pub src2: Register2,
+    /// First destination operand, or `None` if the encoded addressing mode cannot be used for writing.
+    pub out: Option<AnyDestination>,
+    /// Second destination operand.
+    pub out2: Register2,
+    /// First immediate. Apart from being used in `src1`, it's used e.g. as the near call destination or the return label.
+    pub imm1: u16,
+    /// Second immediate. Apart from being used in `out`, it's used e.g. as the near call exception handler.
+    pub imm2: u16,
+    /// Opcode-specific flags.
+    pub flags: InstructionFlags,
+}
+
+impl InstructionInfo {
+    /// Decodes information about a single encoded instruction.
+    pub fn decode(raw: u64) -> Self {
+        let (parsed, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
+        let variant = parsed.variant;
+        let opcode = decode_opcode(variant.opcode);
+
+        let mut flags = InstructionFlags::default();
+        match variant.opcode {
+            isa::Opcode::Add(_)
+            | isa::Opcode::Sub(_)
+            | isa::Opcode::Mul(_)
+            | isa::Opcode::Div(_)
+            | isa::Opcode::Binop(_)
+            | isa::Opcode::Shift(_) => {
+                flags.swap_operands = variant.flags[SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES];
+                flags.set_flags = variant.flags[SET_FLAGS_FLAG_IDX];
+            }
+            isa::Opcode::Ptr(_) => {
+                flags.swap_operands = variant.flags[SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE];
+            }
+            isa::Opcode::Log(LogOpcode::Event | LogOpcode::ToL1Message) => {
+                flags.is_first = variant.flags[FIRST_MESSAGE_FLAG_IDX];
+            }
+            isa::Opcode::Ret(_) => {
+                flags.to_label = variant.flags[RET_TO_LABEL_BIT_IDX];
+            }
+            isa::Opcode::FarCall(_) => {
+                flags.is_static = variant.flags[FAR_CALL_STATIC_FLAG_IDX];
+                flags.is_shard = variant.flags[FAR_CALL_SHARD_FLAG_IDX];
+            }
+            isa::Opcode::UMA(_) => {
+                flags.increment = variant.flags[UMA_INCREMENT_FLAG_IDX];
+            }
+            _ => {}
+        }
+
+        let out = match variant.dst0_operand_type {
+            isa::Operand::RegOrImm(isa::RegOrImmFlags::UseImm16Only)
+            | isa::Operand::Full(
+                isa::ImmMemHandlerFlags::UseImm16Only | isa::ImmMemHandlerFlags::UseCodePage,
+            ) => None,
+            operand_type => Some(decode_destination(
+                operand_type,
+                parsed.dst0_reg_idx,
+                parsed.imm_1,
+            )),
+        };
+
+        Self {
+            opcode,
+            predicate: decode_predicate(parsed.condition),
+            mode_requirements: ModeRequirements::new(
+                variant.requires_kernel_mode(),
+                !variant.can_be_used_in_static_context(),
+            ),
+            gas_cost: variant.ergs_price(),
+            src1: decode_source(variant.src0_operand_type, parsed.src0_reg_idx, parsed.imm_0),
+            src2: Register2(Register::new(parsed.src1_reg_idx)),
+            out,
+            out2: Register2(Register::new(parsed.dst1_reg_idx)),
+            imm1: parsed.imm_0,
+            imm2: parsed.imm_1,
+            flags,
+        }
+    }
+
+    /// Decodes information about all instructions in the provided bytecode. Like [`Program::new()`](crate::Program::new()),
+    /// this splits the bytecode into big-endian 8-byte words; unlike it, the code page is not processed in any way,
+    /// so its words are decoded as instructions as well.
+    #[allow(clippy::missing_panics_doc)] // false positive
+    pub fn decode_bytecode(bytecode: &[u8]) -> Vec<Self> {
+        bytecode
+            .chunks_exact(8)
+            .map(|chunk| Self::decode(u64::from_be_bytes(chunk.try_into().unwrap())))
+            .collect()
+    }
+}
+
+/// Operands of an instruction in the order they are displayed.
+#[derive(Debug, Clone, Copy)]
+enum DisplayedOperand {
+    Src1,
+    Src2,
+    Out,
+    Out2,
+    Label1,
+    Label2,
+}
+
+impl InstructionInfo {
+    fn displayed_operands(&self) -> &'static [DisplayedOperand] {
+        use DisplayedOperand::{Label1, Label2, Out, Out2, Src1, Src2};
+
+        let Some(opcode) = self.opcode else {
+            return &[];
+        };
+        let increment = self.flags.increment;
+        match opcode {
+            // Nops are only used to move the stack pointer.
+            Opcode::Nop => match (self.src1, self.out) {
+                (
+                    AnySource::AdvanceStackPointer(_),
+                    Some(AnyDestination::AdvanceStackPointer(_)),
+                ) => &[Src1, Out],
+                (AnySource::AdvanceStackPointer(_), _) => &[Src1],
+                (_, Some(AnyDestination::AdvanceStackPointer(_))) => &[Out],
+                _ => &[],
+            },
+            Opcode::HeapRead | Opcode::AuxHeapRead | Opcode::PointerRead if increment => {
+                &[Src1, Out, Out2]
+            }
+            Opcode::HeapWrite | Opcode::AuxHeapWrite if increment => &[Src1, Src2, Out],
+            Opcode::Ret(_) if self.flags.to_label => &[Src1, Label1],
+            Opcode::Add
+            | Opcode::Sub
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::ShiftLeft
+            | Opcode::ShiftRight
+            | Opcode::RotateLeft
+            | Opcode::RotateRight
+            | Opcode::PointerAdd
+            | Opcode::PointerSub
+            | Opcode::PointerPack
+            | Opcode::PointerShrink
+            | Opcode::Decommit
+            | Opcode::PrecompileCall => &[Src1, Src2, Out],
+            Opcode::Mul | Opcode::Div => &[Src1, Src2, Out, Out2],
+            Opcode::Jump
+            | Opcode::StorageRead
+            | Opcode::TransientStorageRead
+            | Opcode::HeapRead
+            | Opcode::AuxHeapRead
+            | Opcode::PointerRead => &[Src1, Out],
+            Opcode::Event
+            | Opcode::L2ToL1Message
+            | Opcode::StorageWrite
+            | Opcode::TransientStorageWrite
+            | Opcode::HeapWrite
+            | Opcode::AuxHeapWrite => &[Src1, Src2],
+            Opcode::Ret(_) | Opcode::SetContextU128 => &[Src1],
+            Opcode::This
+            | Opcode::Caller
+            | Opcode::CodeAddress
+            | Opcode::ErgsLeft
+            | Opcode::SP
+            | Opcode::ContextMeta
+            | Opcode::ContextU128 => &[Out],
+            Opcode::NearCall => &[Src1, Label1, Label2],
+            Opcode::FarCall(_) => &[Src1, Src2, Label1],
+            Opcode::IncrementTxNumber | Opcode::AuxMutating0 => &[],
+        }
+    }
+}
+
+/// Formats instructions in an assembly-like syntax, e.g. `sub.s.lt! r1, stack[r2 + 3], r3`.
+///
+/// Modifiers follow the mnemonic: opcode flags (`.s` for swapped operands, `.first`, `.to_label`, `.static`, `.shard`,
+/// `.inc`), then the predicate, then `!` if the instruction sets flags. Only operands used by the opcode are output;
+/// labels (i.e., immediates used as instruction indices) are prefixed with `@`.
+impl fmt::Display for InstructionInfo {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(opcode) = self.opcode else {
+            return formatter.write_str("invalid");
+        };
+        formatter.write_str(mnemonic(opcode))?;
+
+        let flags = self.flags;
+        let modifiers = [
+            (flags.swap_operands, ".s"),
+            (flags.is_first, ".first"),
+            (flags.to_label, ".to_label"),
+            (flags.is_static, ".static"),
+            (flags.is_shard, ".shard"),
+            (flags.increment, ".inc"),
+        ];
+        for (is_set, modifier) in modifiers {
+            if is_set {
+                formatter.write_str(modifier)?;
+            }
+        }
+        formatter.write_str(match self.predicate {
+            Predicate::Always => "",
+            Predicate::IfGT => ".gt",
+            Predicate::IfEQ => ".eq",
+            Predicate::IfLT => ".lt",
+            Predicate::IfGE => ".ge",
+            Predicate::IfLE => ".le",
+            Predicate::IfNotEQ => ".ne",
+            Predicate::IfGTOrLT => ".gtlt",
+        })?;
+        if flags.set_flags {
+            formatter.write_str("!")?;
+        }
+
+        for (i, operand) in self.displayed_operands().iter().enumerate() {
+            formatter.write_str(if i == 0 { " " } else { ", " })?;
+            match operand {
+                DisplayedOperand::Src1 => write_source(formatter, self.src1)?,
+                DisplayedOperand::Src2 => write_register(formatter, self.src2.0)?,
+                DisplayedOperand::Out => write_destination(formatter, self.out)?,
+                DisplayedOperand::Out2 => write_register(formatter, self.out2.0)?,
+                DisplayedOperand::Label1 => write!(formatter, "@{}", self.imm1)?,
+                DisplayedOperand::Label2 => write!(formatter, "@{}", self.imm2)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn mnemonic(opcode: Opcode) -> &'static str {
+    match opcode {
+        Opcode::Nop => "nop",
+        Opcode::Add => "add",
+        Opcode::Sub => "sub",
+        Opcode::And => "and",
+        Opcode::Or => "or",
+        Opcode::Xor => "xor",
+        Opcode::ShiftLeft => "shl",
+        Opcode::ShiftRight => "shr",
+        Opcode::RotateLeft => "rol",
+        Opcode::RotateRight => "ror",
+        Opcode::Mul => "mul",
+        Opcode::Div => "div",
+        Opcode::NearCall => "near_call",
+        Opcode::FarCall(CallingMode::Normal) => "far_call",
+        Opcode::FarCall(CallingMode::Delegate) => "far_call.delegate",
+        Opcode::FarCall(CallingMode::Mimic) => "far_call.mimic",
+        Opcode::Ret(ReturnType::Normal) => "ret",
+        Opcode::Ret(ReturnType::Revert) => "ret.revert",
+        Opcode::Ret(ReturnType::Panic) => "ret.panic",
+        Opcode::Jump => "jump",
+        Opcode::Event => "event",
+        Opcode::L2ToL1Message => "to_l1",
+        Opcode::Decommit => "decommit",
+        Opcode::This => "context.this",
+        Opcode::Caller => "context.caller",
+        Opcode::CodeAddress => "context.code_address",
+        Opcode::ErgsLeft => "context.ergs_left",
+        Opcode::SP => "context.sp",
+        Opcode::ContextMeta => "context.meta",
+        Opcode::ContextU128 => "context.get_context_u128",
+        Opcode::SetContextU128 => "context.set_context_u128",
+        Opcode::IncrementTxNumber => "context.inc_tx_num",
+        Opcode::AuxMutating0 => "context.aux_mutating0",
+        Opcode::PrecompileCall => "precompile_call",
+        Opcode::HeapRead => "ld.1",
+        Opcode::HeapWrite => "st.1",
+        Opcode::AuxHeapRead => "ld.2",
+        Opcode::AuxHeapWrite => "st.2",
+        Opcode::PointerRead => "ld",
+        Opcode::PointerAdd => "ptr.add",
+        Opcode::PointerSub => "ptr.sub",
+        Opcode::PointerPack => "ptr.pack",
+        Opcode::PointerShrink => "ptr.shrink",
+        Opcode::StorageRead => "sload",
+        Opcode::StorageWrite => "sstore",
+        Opcode::TransientStorageRead => "tload",
+        Opcode::TransientStorageWrite => "tstore",
+    }
+}
+
+fn write_register(formatter: &mut fmt::Formatter<'_>, register: Register) -> fmt::Result {
+    write!(formatter, "r{}", register.index())
+}
+
+/// Writes the `reg + imm` part of stack and code page operands, omitting `r0` and zero immediates.
+fn write_register_and_immediate(
+    formatter: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    operand: RegisterAndImmediate,
+) -> fmt::Result {
+    let RegisterAndImmediate {
+        immediate,
+        register,
+    } = operand;
+    match (register.index(), immediate) {
+        (0, _) => write!(formatter, "{prefix}[{immediate}]"),
+        (_, 0) => write!(formatter, "{prefix}[r{}]", register.index()),
+        _ => write!(formatter, "{prefix}[r{} + {immediate}]", register.index()),
+    }
+}
+
+fn write_source(formatter: &mut fmt::Formatter<'_>, source: AnySource) -> fmt::Result {
+    match source {
+        AnySource::Register1(register) => write_register(formatter, register.0),
+        AnySource::Immediate1(immediate) => write!(formatter, "{}", immediate.0),
+        AnySource::AbsoluteStack(operand) => {
+            write_register_and_immediate(formatter, "stack", operand.0)
+        }
+        AnySource::RelativeStack(operand) => {
+            write_register_and_immediate(formatter, "stack-", operand.0)
+        }
+        AnySource::AdvanceStackPointer(operand) => {
+            write_register_and_immediate(formatter, "stack-=", operand.0)
+        }
+        AnySource::CodePage(operand) => write_register_and_immediate(formatter, "code", operand.0),
+    }
+}
+
+fn write_destination(
+    formatter: &mut fmt::Formatter<'_>,
+    destination: Option<AnyDestination>,
+) -> fmt::Result {
+    match destination {
+        None => formatter.write_str("_"),
+        Some(AnyDestination::Register1(register)) => write_register(formatter, register.0),
+        Some(AnyDestination::AbsoluteStack(operand)) => {
+            write_register_and_immediate(formatter, "stack", operand.0)
+        }
+        Some(AnyDestination::RelativeStack(operand)) => {
+            write_register_and_immediate(formatter, "stack-", operand.0)
+        }
+        Some(AnyDestination::AdvanceStackPointer(operand)) => {
+            write_register_and_immediate(formatter, "stack+=", operand.0)
+        }
+    }
+}
+
+pub(crate) fn decode_opcode(opcode: isa::Opcode) -> Option<Opcode> {
+    use isa::Opcode as Raw;
+
+    Some(match opcode {
+        Raw::Invalid(_) | Raw::UMA(UMAOpcode::StaticMemoryRead | UMAOpcode::StaticMemoryWrite) => {
+            return None;
+        }
+        Raw::Nop(_) => Opcode::Nop,
+        Raw::Add(_) => Opcode::Add,
+        Raw::Sub(_) => Opcode::Sub,
+        Raw::Mul(_) => Opcode::Mul,
+        Raw::Div(_) => Opcode::Div,
+        Raw::Jump(_) => Opcode::Jump,
+        Raw::NearCall(_) => Opcode::NearCall,
+        Raw::Binop(BinopOpcode::Xor) => Opcode::Xor,
+        Raw::Binop(BinopOpcode::And) => Opcode::And,
+        Raw::Binop(BinopOpcode::Or) => Opcode::Or,
+        Raw::Shift(ShiftOpcode::Shl) => Opcode::ShiftLeft,
+        Raw::Shift(ShiftOpcode::Shr) => Opcode::ShiftRight,
+        Raw::Shift(ShiftOpcode::Rol) => Opcode::RotateLeft,
+        Raw::Shift(ShiftOpcode::Ror) => Opcode::RotateRight,
+        Raw::Context(ContextOpcode::This) => Opcode::This,
+        Raw::Context(ContextOpcode::Caller) => Opcode::Caller,
+        Raw::Context(ContextOpcode::CodeAddress) => Opcode::CodeAddress,
+        Raw::Context(ContextOpcode::ErgsLeft) => Opcode::ErgsLeft,
+        Raw::Context(ContextOpcode::GetContextU128) => Opcode::ContextU128,
+        Raw::Context(ContextOpcode::SetContextU128) => Opcode::SetContextU128,
+        Raw::Context(ContextOpcode::Sp) => Opcode::SP,
+        Raw::Context(ContextOpcode::Meta) => Opcode::ContextMeta,
+        Raw::Context(ContextOpcode::IncrementTxNumber) => Opcode::IncrementTxNumber,
+        Raw::Context(ContextOpcode::AuxMutating0) => Opcode::AuxMutating0,
+        Raw::Ptr(PtrOpcode::Add) => Opcode::PointerAdd,
+        Raw::Ptr(PtrOpcode::Sub) => Opcode::PointerSub,
+        Raw::Ptr(PtrOpcode::Pack) => Opcode::PointerPack,
+        Raw::Ptr(PtrOpcode::Shrink) => Opcode::PointerShrink,
+        Raw::FarCall(FarCallOpcode::Normal) => Opcode::FarCall(CallingMode::Normal),
+        Raw::FarCall(FarCallOpcode::Delegate) => Opcode::FarCall(CallingMode::Delegate),
+        Raw::FarCall(FarCallOpcode::Mimic) => Opcode::FarCall(CallingMode::Mimic),
+        Raw::Ret(RetOpcode::Ok) => Opcode::Ret(ReturnType::Normal),
+        Raw::Ret(RetOpcode::Revert) => Opcode::Ret(ReturnType::Revert),
+        Raw::Ret(RetOpcode::Panic) => Opcode::Ret(ReturnType::Panic),
+        Raw::Log(LogOpcode::StorageRead) => Opcode::StorageRead,
+        Raw::Log(LogOpcode::StorageWrite) => Opcode::StorageWrite,
+        Raw::Log(LogOpcode::TransientStorageRead) => Opcode::TransientStorageRead,
+        Raw::Log(LogOpcode::TransientStorageWrite) => Opcode::TransientStorageWrite,
+        Raw::Log(LogOpcode::ToL1Message) => Opcode::L2ToL1Message,
+        Raw::Log(LogOpcode::Event) => Opcode::Event,
+        Raw::Log(LogOpcode::PrecompileCall) => Opcode::PrecompileCall,
+        Raw::Log(LogOpcode::Decommit) => Opcode::Decommit,
+        Raw::UMA(UMAOpcode::HeapRead) => Opcode::HeapRead,
+        Raw::UMA(UMAOpcode::HeapWrite) => Opcode::HeapWrite,
+        Raw::UMA(UMAOpcode::AuxHeapRead) => Opcode::AuxHeapRead,
+        Raw::UMA(UMAOpcode::AuxHeapWrite) => Opcode::AuxHeapWrite,
+        Raw::UMA(UMAOpcode::FatPointerRead) => Opcode::PointerRead,
+    })
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use super::*;
+    use crate::addressing_modes::{
+        AbsoluteStack, AdvanceStackPointer, CodePage, Immediate1, Register1, RelativeStack,
+    };
+
+    #[test]
+    fn decoding_invalid_instruction() {
+        let info = InstructionInfo::decode(0);
+        assert_eq!(info.opcode, None);
+        assert_eq!(info.flags, InstructionFlags::default());
+    }
+
+    #[test]
+    fn decoding_bytecode() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far");
+        let infos = InstructionInfo::decode_bytecode(bytecode);
+        assert_eq!(infos.len(), bytecode.len() / 8);
+
+        let far_call = infos
+            .iter()
+            .find(|info| info.opcode == Some(Opcode::FarCall(CallingMode::Normal)))
+            .expect("no far call");
+        assert!(matches!(far_call.src1, AnySource::Register1(_)));
+        assert!(!far_call.flags.is_static);
+        assert!(!far_call.flags.swap_operands);
+    }
+
+    fn info(opcode: Opcode, src1: AnySource, out: AnyDestination) -> InstructionInfo {
+        InstructionInfo {
+            opcode: Some(opcode),
+            predicate: Predicate::Always,
+            mode_requirements: ModeRequirements::none(),
+            gas_cost: 6,
+            src1,
+            src2: Register2(Register::new(2)),
+            out: Some(out),
+            out2: Register2(Register::new(4)),
+            imm1: 10,
+            imm2: 20,
+            flags: InstructionFlags::default(),
+        }
+    }
+
+    #[test]
+    fn displaying_instructions() {
+        let r1 = Register1(Register::new(1));
+        let stack = RegisterAndImmediate {
+            immediate: 3,
+            register: Register::new(5),
+        };
+
+        let mut sub = info(Opcode::Sub, r1.into(), AbsoluteStack(stack).into());
+        sub.predicate = Predicate::IfLT;
+        sub.flags.swap_operands = true;
+        sub.flags.set_flags = true;
+        assert_eq!(sub.to_string(), "sub.s.lt! r1, r2, stack[r5 + 3]");
+
+        let mul = info(Opcode::Mul, Immediate1(42).into(), r1.into());
+        assert_eq!(mul.to_string(), "mul 42, r2, r1, r4");
+
+        let code_page = CodePage(RegisterAndImmediate {
+            immediate: 7,
+            register: Register::new(0),
+        });
+        let jump = info(Opcode::Jump, code_page.into(), r1.into());
+        assert_eq!(jump.to_string(), "jump code[7], r1");
+
+        let near_call = info(Opcode::NearCall, r1.into(), r1.into());
+        assert_eq!(near_call.to_string(), "near_call r1, @10, @20");
+
+        let mut heap_read = info(Opcode::HeapRead, r1.into(), r1.into());
+        heap_read.flags.increment = true;
+        assert_eq!(heap_read.to_string(), "ld.1.inc r1, r1, r4");
+
+        let nop = info(
+            Opcode::Nop,
+            AdvanceStackPointer(stack).into(),
+            AdvanceStackPointer(RegisterAndImmediate {
+                immediate: 2,
+                register: Register::new(0),
+            })
+            .into(),
+        );
+        assert_eq!(nop.to_string(), "nop stack-=[r5 + 3], stack+=[2]");
+
+        let context = info(Opcode::This, r1.into(), RelativeStack(stack).into());
+        assert_eq!(context.to_string(), "context.this stack-[r5 + 3]");
+
+        assert_eq!(InstructionInfo::decode(0).to_string(), "invalid");
+    }
+}
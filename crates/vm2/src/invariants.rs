@@ -0,0 +1,181 @@
+//! Checks of VM state invariants, useful to catch state corruption early when developing instruction handlers.
+
+use std::{iter, ptr};
+
+use zksync_vm2_interface::Tracer;
+
+use crate::{
+    callframe::Callframe,
+    fat_pointer::FatPointer,
+    instruction_handlers::{invalid_instruction, spontaneous_panic},
+    VirtualMachine, World,
+};
+
+impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
+    /// Checks invariants of the VM state that must hold between instructions:
+    ///
+    /// - `r0` is zero and is not a pointer.
+    /// - The program counter points to an instruction of the executed program, or to a panic.
+    /// - Pointer registers and heaps of all frames refer to allocated heaps.
+    ///
+    /// The stack pointer is not checked since the stack has `1 << 16` slots (or wraps around with the `small_stack`
+    /// feature), so any stack pointer is in range.
+    /// Likewise, gas is unsigned and saturates on overflow, so it needs no checks.
+    /// With the `invariant_checks` feature, the VM calls this method before each instruction and
+    /// additionally checks that frame heap bounds never decrease.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any invariant is violated.
+    pub fn assert_invariants(&self) {
+        let state = &self.state;
+        assert!(state.registers[0].is_zero(), "r0 is non-zero");
+        assert_eq!(state.register_pointer_flags & 1, 0, "r0 is a pointer");
+
+        let frame = &state.current_frame;
+        let pc_in_program = u16::try_from(frame.get_raw_pc())
+            .ok()
+            .and_then(|pc| frame.program.instruction(pc))
+            .is_some_and(|instruction| ptr::eq(instruction, frame.pc));
+        assert!(
+            pc_in_program
+                || ptr::eq(frame.pc, spontaneous_panic())
+                || ptr::eq(frame.pc, invalid_instruction()),
+            "program counter points outside the program"
+        );
+
+        for (i, register) in state.registers.iter().enumerate() {
+            if state.register_pointer_flags & (1 << i) != 0 {
+                let heap = FatPointer::from(*register).memory_page;
+                assert!(
+                    state.heaps.contains(heap),
+                    "r{i} points to unallocated heap {heap:?}"
+                );
+            }
+        }
+        for frame in iter::once(frame).chain(&state.previous_frames) {
+            assert_frame_heaps(self, frame);
+        }
+    }
+}
+
+fn assert_frame_heaps<T, W>(vm: &VirtualMachine<T, W>, frame: &Callframe<T, W>) {
+    let heaps = [frame.heap, frame.aux_heap, frame.calldata_heap]
+        .into_iter()
+        .chain(frame.heaps_i_am_keeping_alive.iter().copied());
+    for heap in heaps {
+        assert!(
+            vm.state.heaps.contains(heap),
+            "frame of {:?} refers to unallocated heap {heap:?}",
+            frame.address
+        );
+    }
+}
+
+/// Checks invariants between instructions, including ones that involve the previous VM state.
+#[derive(Debug, Default)]
+pub(crate) struct InvariantChecker {
+    /// Heap and aux heap bounds of far frames, indexed by frame depth.
+    heap_bounds: Vec<(u32, u32)>,
+}
+
+impl InvariantChecker {
+    pub(crate) fn check<T: Tracer, W: World<T>>(&mut self, vm: &VirtualMachine<T, W>) {
+        vm.assert_invariants();
+
+        let depth = vm.state.previous_frames.len();
+        let frame = &vm.state.current_frame;
+        let bounds = (frame.heap_size, frame.aux_heap_size);
+        // Bounds of frames that have returned are no longer relevant.
+        self.heap_bounds.truncate(depth + 1);
+        if let Some(prev_bounds) = self.heap_bounds.get_mut(depth) {
+            assert!(
+                bounds.0 >= prev_bounds.0 && bounds.1 >= prev_bounds.1,
+                "heap bounds decreased from {prev_bounds:?} to {bounds:?}"
+            );
+            *prev_bounds = bounds;
+        } else {
+            // Bounds of the previous frames are unknown if checking starts in a nested frame.
+            self.heap_bounds.resize(depth, (0, 0));
+            self.heap_bounds.push(bounds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+    use zkevm_opcode_defs::ethereum_types::Address;
+    use zksync_vm2_interface::{HeapId, StateInterface};
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program, Settings,
+    };
+
+    fn test_vm() -> VirtualMachine<(), TestWorld<()>> {
+        let ret = Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+        );
+        let program = Program::from_raw(vec![ret], vec![]);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            1_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        )
+    }
+
+    #[test]
+    fn initial_state_satisfies_invariants() {
+        let vm = test_vm();
+        vm.assert_invariants();
+        InvariantChecker::default().check(&vm);
+    }
+
+    #[test]
+    #[should_panic(expected = "r0 is non-zero")]
+    fn detecting_nonzero_r0() {
+        let mut vm = test_vm();
+        vm.state.registers[0] = U256::one();
+        vm.assert_invariants();
+    }
+
+    #[test]
+    #[should_panic(expected = "points to unallocated heap")]
+    fn detecting_dangling_pointer() {
+        let mut vm = test_vm();
+        let pointer = FatPointer {
+            memory_page: HeapId::from_u32_unchecked(1_000),
+            offset: 0,
+            start: 0,
+            length: 32,
+        };
+        vm.set_register(2, pointer.into_u256(), true);
+        vm.assert_invariants();
+    }
+
+    #[test]
+    #[should_panic(expected = "heap bounds decreased")]
+    fn detecting_decreasing_heap_bounds() {
+        let mut vm = test_vm();
+        let mut checker = InvariantChecker::default();
+        vm.state.current_frame.heap_size = 64;
+        checker.check(&vm);
+        vm.state.current_frame.heap_size = 32;
+        checker.check(&vm);
+    }
+}
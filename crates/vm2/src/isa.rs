@@ -0,0 +1,22 @@
+//! Definitions of the EraVM instruction set used by the VM: instruction encoding, opcodes, flag indices,
+//! system contract addresses, system parameters and costs.
+//!
+//! The definitions are taken from `zkevm_opcode_defs`. Non-test code refers to them via this module rather than
+//! directly, so that updating the dependency (or supporting several versions of the instruction set) only requires
+//! changes here. Hashing primitives and Ethereum types re-exported by `zkevm_opcode_defs` are not a part of
+//! the instruction set and are used directly.
+
+#[cfg(feature = "precompiles")]
+pub(crate) use zkevm_opcode_defs::PrecompileCallABI;
+pub(crate) use zkevm_opcode_defs::{
+    decoding::{EncodingModeProduction, VmEncodingMode},
+    erase_fat_pointer_metadata, system_params, BinopOpcode, BlobSha256Format, Condition,
+    ContextOpcode, ContractCodeSha256Format, FarCallOpcode, ImmMemHandlerFlags, LogOpcode, Opcode,
+    Operand, PtrOpcode, RegOrImmFlags, RetOpcode, ShiftOpcode, UMAOpcode, VersionedHashLen32,
+    VmMetaParameters, ADDRESS_EVENT_WRITER, ADDRESS_MSG_VALUE, ECADD_PRECOMPILE_ADDRESS,
+    ECMUL_PRECOMPILE_ADDRESS, ECPAIRING_PRECOMPILE_ADDRESS, ERGS_PER_CODE_WORD_DECOMMITTMENT,
+    FAR_CALL_SHARD_FLAG_IDX, FAR_CALL_STATIC_FLAG_IDX, FIRST_MESSAGE_FLAG_IDX,
+    MODEXP_PRECOMPILE_ADDRESS, RET_TO_LABEL_BIT_IDX, SET_FLAGS_FLAG_IDX,
+    SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES, SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE,
+    UMA_INCREMENT_FLAG_IDX,
+};
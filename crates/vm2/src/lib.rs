@@ -1,6 +1,42 @@
 //! # High-Performance ZKsync Era VM
 //!
 //! This crate provides high-performance [`VirtualMachine`] for ZKsync Era.
+//!
+//! The most commonly used types and traits are re-exported in the [`prelude`].
+//!
+//! With the `symbolic` feature enabled, the `symbolic` module provides experimental symbolic execution of programs.
+//!
+//! With the `tracing` feature enabled, the VM emits debug-level `tracing` events for far calls and returns, panics,
+//! hooks and snapshot operations, with fields such as the contract address, gas and program counter. Without the feature,
+//! these events are not compiled.
+//!
+//! The [`hooks`] module provides typed representation of bootloader hooks
+//! the VM can be [suspended on](ExecutionEnd::SuspendedOnHook).
+//!
+//! The [`executor`] module provides a pool of worker threads running VMs that share a program cache and pooled memory,
+//! e.g. to serve many concurrent calls on an RPC node.
+//!
+//! The [`replay`] module allows to compare instruction-level traces of different VM builds, e.g. to validate
+//! refactorings of instruction handlers. The `compare_traces` example wraps it into a command-line tool comparing
+//! traces of two bytecodes.
+//!
+//! The `vm_interface` module provides an adapter exposing the VM to the ZKsync Era server via an API similar to its
+//! `VmInterface` trait: pushing transactions to the bootloader memory, executing them and batch-level snapshots.
+//!
+//! With the `era-integration` feature enabled, the `era` module provides conversions of VM outputs into
+//! `zksync_types` structures used by the ZKsync Era server.
+//!
+//! The `precompiles` feature (enabled by default) provides [legacy implementations](precompiles::LegacyPrecompiles)
+//! of precompiles other than keccak256. Without it, the crate doesn't depend on the crates implementing them, which is
+//! useful for tools that don't need to execute precompiles; calls to such precompiles stop the VM with
+//! [`ExecutionEnd::UnsupportedPrecompile`].
+//!
+//! The `small_stack` and `small_heap_pages` features reduce memory used by each VM, e.g. to run many VMs concurrently
+//! in tests. Unlike smaller heap pages, smaller stacks change VM behavior, so `small_stack` must not be used
+//! in production.
+//!
+//! With the `invariant_checks` feature enabled, the VM checks [state invariants](VirtualMachine::assert_invariants())
+//! before each executed instruction and panics if they are violated. This is slow and is meant for testing only.
 
 use std::hash::{DefaultHasher, Hash, Hasher};
 
@@ -8,47 +44,110 @@ use primitive_types::{H160, U256};
 pub use zksync_vm2_interface as interface;
 use zksync_vm2_interface::Tracer;
 
+#[cfg(not(feature = "single_instruction_test"))]
+pub use self::{
+    heap::HistoryStats,
+    hook_patch::{HookPatch, PatchError},
+    override_world::OverrideWorld,
+    program::{InvalidBytecode, ProgramTooLarge},
+    source_map::{ParseSourceMapError, SourceLocation, SourceMap},
+    state_delta::{HeapWrite, StateDelta},
+    suspended::{FrameSummary, SuspendedVm},
+    symbols::SymbolTable,
+};
 // Re-export missing modules if single instruction testing is enabled
 #[cfg(feature = "single_instruction_test")]
 pub(crate) use self::single_instruction_test::{heap, program, stack};
 pub use self::{
+    builder::{BuildError, VirtualMachineBuilder},
+    debugger::Debugger,
+    events::{merge_events, MergedEvent},
     fat_pointer::FatPointer,
+    gas_costs::GasCosts,
+    heap::{heap_diff, HeapSnapshot},
     instruction::{ExecutionEnd, Instruction},
+    instruction_info::{InstructionFlags, InstructionInfo},
     mode_requirements::ModeRequirements,
+    panic_info::PanicInfo,
     predication::Predicate,
     program::Program,
+    program_cache::ProgramCache,
+    refund_policy::RefundPolicy,
     vm::{Settings, VirtualMachine},
-    world_diff::{Snapshot, StorageChange, WorldDiff},
+    world_diff::{AccessList, Snapshot, StorageChange, WorldDiff},
 };
 use crate::precompiles::{LegacyPrecompiles, Precompiles};
 
 pub mod addressing_modes;
+pub mod allocator;
+pub mod analysis;
+pub mod batch;
 #[cfg(not(feature = "single_instruction_test"))]
 mod bitset;
+mod builder;
+#[cfg(not(feature = "single_instruction_test"))]
+mod byte_order;
 mod callframe;
+mod debugger;
 mod decode;
 mod decommit;
+#[cfg(feature = "era-integration")]
+pub mod era;
+mod events;
+pub mod exec;
+pub mod executor;
 mod fat_pointer;
+mod gas_costs;
 #[cfg(not(feature = "single_instruction_test"))]
 mod heap;
+#[cfg(not(feature = "single_instruction_test"))]
+mod hook_patch;
+pub mod hooks;
 mod instruction;
 mod instruction_handlers;
+mod instruction_info;
+#[cfg(not(feature = "single_instruction_test"))]
+mod invariants;
+mod isa;
+mod memory;
+pub mod metrics;
 mod mode_requirements;
+#[cfg(not(feature = "single_instruction_test"))]
+mod override_world;
+mod panic_info;
 pub mod precompiles;
 mod predication;
+pub mod prelude;
 #[cfg(not(feature = "single_instruction_test"))]
 mod program;
+mod program_cache;
+pub mod pubdata;
+mod refund_policy;
+pub mod replay;
 mod rollback;
 #[cfg(feature = "single_instruction_test")]
 pub mod single_instruction_test;
 #[cfg(not(feature = "single_instruction_test"))]
+mod source_map;
+#[cfg(not(feature = "single_instruction_test"))]
 mod stack;
 mod state;
+#[cfg(not(feature = "single_instruction_test"))]
+mod state_delta;
+#[cfg(not(feature = "single_instruction_test"))]
+mod suspended;
+#[cfg(feature = "symbolic")]
+pub mod symbolic;
+#[cfg(not(feature = "single_instruction_test"))]
+mod symbols;
 pub mod testonly;
 #[cfg(all(test, not(feature = "single_instruction_test")))]
 mod tests;
+pub mod tracers;
 mod tracing;
 mod vm;
+#[cfg(not(feature = "single_instruction_test"))]
+pub mod vm_interface;
 mod world_diff;
 
 /// Storage slot information returned from [`StorageInterface::read_storage()`].
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use crate::program::Program;
+
+/// Programs executed by the frames on the call stack of a VM. Used for memory accounting, so that a program executed
+/// by several frames (e.g., in case of recursive calls) is only counted once.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProgramsInUse {
+    /// Number of frames executing a program, keyed by [`Program::id()`].
+    frame_counts: HashMap<usize, usize>,
+    /// Total memory size of programs in `frame_counts`.
+    bytes: usize,
+}
+
+impl ProgramsInUse {
+    pub(crate) fn from_programs<'a, T: 'a, W: 'a>(
+        programs: impl IntoIterator<Item = &'a Program<T, W>>,
+    ) -> Self {
+        let mut this = Self::default();
+        for program in programs {
+            this.add(program);
+        }
+        this
+    }
+
+    pub(crate) fn add<T, W>(&mut self, program: &Program<T, W>) {
+        let count = self.frame_counts.entry(program.id()).or_insert(0);
+        if *count == 0 {
+            self.bytes += program.memory_size();
+        }
+        *count += 1;
+    }
+
+    pub(crate) fn remove<T, W>(&mut self, program: &Program<T, W>) {
+        let id = program.id();
+        let count = self
+            .frame_counts
+            .get_mut(&id)
+            .expect("removed program is not in use");
+        *count -= 1;
+        if *count == 0 {
+            self.frame_counts.remove(&id);
+            self.bytes -= program.memory_size();
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
@@ -0,0 +1,139 @@
+//! Metrics reported by VMs for monitoring long-running hosts.
+//!
+//! A [`Metrics`] implementation specified via [`VirtualMachineBuilder::metrics()`](crate::VirtualMachineBuilder::metrics())
+//! or [`VirtualMachine::set_metrics()`](crate::VirtualMachine::set_metrics()) receives callbacks mapping
+//! to Prometheus-style counters and gauges; e.g., it may forward them to the [`prometheus`] or [`vise`] crates.
+//! A single implementation can be shared by many VMs, including ones running on different threads.
+//! [`ProgramCache`](crate::ProgramCache) can report its hit rate to the same implementation.
+//!
+//! [`prometheus`]: https://docs.rs/prometheus
+//! [`vise`]: https://docs.rs/vise
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+/// Receiver of VM metrics. The VM may call its methods from any thread.
+///
+/// All methods have no-op default implementations, so implementations may only handle metrics they are interested in.
+pub trait Metrics: fmt::Debug + Send + Sync {
+    /// Counter: called when a `run*()` method of a VM returns with the number of instructions (including ones
+    /// skipped because of their predicate) executed by the call.
+    fn instructions_executed(&self, _count: u64) {}
+
+    /// Counter: called on each [`ProgramCache`](crate::ProgramCache) lookup with whether the program was cached.
+    fn program_cache_lookup(&self, _hit: bool) {}
+
+    /// Gauge: called when a `run*()` method of a VM returns with the number of bytes allocated for heaps of the VM.
+    fn heap_allocated(&self, _bytes: usize) {}
+
+    /// Counter: called on each [rollback](crate::VirtualMachine::rollback()) of a VM snapshot.
+    fn snapshot_rolled_back(&self) {}
+}
+
+/// Simple [`Metrics`] implementation storing metrics in atomic variables. Useful for tests and for hosts
+/// periodically exporting metrics in their own way.
+#[derive(Debug, Default)]
+pub struct AtomicMetrics {
+    instructions: AtomicU64,
+    program_cache_hits: AtomicU64,
+    program_cache_misses: AtomicU64,
+    heap_bytes: AtomicUsize,
+    rollbacks: AtomicU64,
+}
+
+impl AtomicMetrics {
+    /// Returns the total number of executed instructions.
+    pub fn instructions(&self) -> u64 {
+        self.instructions.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of program cache hits and misses.
+    pub fn program_cache_lookups(&self) -> (u64, u64) {
+        (
+            self.program_cache_hits.load(Ordering::Relaxed),
+            self.program_cache_misses.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the last reported number of heap bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.heap_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of rollbacks.
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks.load(Ordering::Relaxed)
+    }
+}
+
+impl Metrics for AtomicMetrics {
+    fn instructions_executed(&self, count: u64) {
+        self.instructions.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn program_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.program_cache_hits
+        } else {
+            &self.program_cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn heap_allocated(&self, bytes: usize) {
+        self.heap_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    fn snapshot_rolled_back(&self) {
+        self.rollbacks.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use std::sync::Arc;
+
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+    };
+
+    #[test]
+    fn reporting_vm_metrics() {
+        let ret = Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+        );
+        let program = Program::from_raw(vec![ret], vec![]);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+
+        let metrics = Arc::new(AtomicMetrics::default());
+        let mut vm = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .gas(1_000)
+            .metrics(metrics.clone())
+            .build()
+            .unwrap();
+        vm.make_snapshot();
+        assert_eq!(
+            vm.run(&mut world, &mut ()),
+            ExecutionEnd::ProgramFinished(vec![])
+        );
+        assert_eq!(metrics.instructions(), 1);
+        assert_eq!(metrics.heap_bytes(), vm.state.heaps.allocated_bytes());
+
+        vm.rollback();
+        assert_eq!(metrics.rollbacks(), 1);
+        assert_eq!(metrics.program_cache_lookups(), (0, 0));
+    }
+}
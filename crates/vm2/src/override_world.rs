@@ -0,0 +1,269 @@
+//! State overrides for simulations.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::sha3::{Digest, Keccak256};
+use zksync_vm2_interface::Tracer;
+
+use crate::{
+    instruction_handlers::address_into_u256,
+    isa::system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW, precompiles::Precompiles,
+    program::versioned_bytecode_hash, Program, StorageInterface, StorageSlot, World,
+};
+
+/// Lower 16 bits of the address of the system contract holding base token balances.
+const L2_BASE_TOKEN_ADDRESS_LOW: u16 = 0x800a;
+
+/// [`World`] layering overrides of contract code and storage on top of another world, similar to state overrides
+/// of the `eth_call` RPC method.
+///
+/// Code overrides are implemented by changing bytecode hashes stored by the deployer system contract, so they are
+/// visible to the VM like any other storage override. Since the programs of the underlying world are typed for that
+/// world, all programs (including non-overridden ones) are decoded from bytecodes returned by
+/// [`World::decommit_code()`] and cached by their hash. As a consequence, overriding code never invalidates cached
+/// programs: new code always has a new hash.
+#[derive(Debug)]
+pub struct OverrideWorld<T, W> {
+    inner: W,
+    storage_overrides: BTreeMap<(H160, U256), U256>,
+    cleared_storage: BTreeSet<H160>,
+    bytecodes: HashMap<U256, Vec<u8>>,
+    programs: HashMap<U256, Program<T, Self>>,
+}
+
+impl<T: Tracer, W: World<T>> OverrideWorld<T, W> {
+    /// Wraps the provided world without any overrides.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            storage_overrides: BTreeMap::new(),
+            cleared_storage: BTreeSet::new(),
+            bytecodes: HashMap::new(),
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying world.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the underlying world, discarding all overrides.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Overrides the value of a storage slot.
+    pub fn set_storage(&mut self, contract: H160, key: U256, value: U256) {
+        self.storage_overrides.insert((contract, key), value);
+    }
+
+    /// Clears the entire storage of a contract, so that only slots overridden using [`Self::set_storage()`] are
+    /// non-zero. This corresponds to the `state` field of `eth_call` state overrides.
+    pub fn clear_storage(&mut self, contract: H160) {
+        self.cleared_storage.insert(contract);
+        self.storage_overrides
+            .retain(|(overridden_contract, _), _| *overridden_contract != contract);
+    }
+
+    /// Overrides the code of a contract and returns the bytecode hash of the new code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytecode is not a valid EraVM bytecode, i.e. doesn't consist of an odd number of 32-byte words
+    /// fitting into `u16`.
+    pub fn set_code(&mut self, contract: H160, bytecode: Vec<u8>) -> U256 {
+        let hash = bytecode_hash(&bytecode);
+        let deployer_system_contract_address =
+            H160::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        self.set_storage(
+            deployer_system_contract_address,
+            address_into_u256(contract),
+            hash,
+        );
+        self.bytecodes.insert(hash, bytecode);
+        hash
+    }
+
+    /// Overrides the base token balance of an account.
+    pub fn set_balance(&mut self, account: H160, balance: U256) {
+        let base_token_address = H160::from_low_u64_be(L2_BASE_TOKEN_ADDRESS_LOW.into());
+        self.set_storage(base_token_address, balance_key(account), balance);
+    }
+
+    fn storage_override(&self, contract: H160, key: U256) -> Option<U256> {
+        let value = self.storage_overrides.get(&(contract, key)).copied();
+        if value.is_none() && self.cleared_storage.contains(&contract) {
+            Some(U256::zero())
+        } else {
+            value
+        }
+    }
+}
+
+/// Computes the versioned bytecode hash of a constructed EraVM contract.
+fn bytecode_hash(bytecode: &[u8]) -> U256 {
+    assert_eq!(
+        bytecode.len() % 32,
+        0,
+        "bytecode length must be divisible by 32"
+    );
+    let len_in_words = u16::try_from(bytecode.len() / 32).expect("bytecode is too long");
+    assert_eq!(
+        len_in_words % 2,
+        1,
+        "bytecode must have an odd number of words"
+    );
+    versioned_bytecode_hash(bytecode, len_in_words)
+}
+
+/// Computes the key of the base token balance of `account`, i.e. its key in the balance mapping at slot 0.
+fn balance_key(account: H160) -> U256 {
+    let mut hasher = Keccak256::new();
+    hasher.update([0_u8; 12]);
+    hasher.update(account.as_bytes());
+    hasher.update([0_u8; 32]);
+    U256::from_big_endian(&hasher.finalize())
+}
+
+impl<T: Tracer, W: World<T>> StorageInterface for OverrideWorld<T, W> {
+    fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
+        let slot = self.inner.read_storage(contract, key);
+        StorageSlot {
+            value: self.storage_override(contract, key).unwrap_or(slot.value),
+            is_write_initial: slot.is_write_initial,
+        }
+    }
+
+    fn read_storage_value(&mut self, contract: H160, key: U256) -> U256 {
+        self.storage_override(contract, key)
+            .unwrap_or_else(|| self.inner.read_storage_value(contract, key))
+    }
+
+    fn cost_of_writing_storage(&mut self, initial_slot: StorageSlot, new_value: U256) -> u32 {
+        self.inner.cost_of_writing_storage(initial_slot, new_value)
+    }
+
+    fn is_free_storage_slot(&self, contract: &H160, key: &U256) -> bool {
+        self.inner.is_free_storage_slot(contract, key)
+    }
+}
+
+impl<T: Tracer, W: World<T>> World<T> for OverrideWorld<T, W> {
+    fn decommit(&mut self, hash: U256) -> Program<T, Self> {
+        if let Some(program) = self.programs.get(&hash) {
+            return program.clone();
+        }
+        let program = Program::new(&self.decommit_code(hash), false);
+        self.programs.insert(hash, program.clone());
+        program
+    }
+
+    fn decommit_code(&mut self, hash: U256) -> Vec<u8> {
+        if let Some(bytecode) = self.bytecodes.get(&hash) {
+            bytecode.clone()
+        } else {
+            self.inner.decommit_code(hash)
+        }
+    }
+
+    fn precompiles(&self) -> &impl Precompiles {
+        self.inner.precompiles()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::testonly::{initial_decommit, TestWorld};
+
+    const BYTECODE: &[u8] = include_bytes!("tests/bytecodes/call_far");
+
+    #[test]
+    fn overriding_storage() {
+        let contract = Address::repeat_byte(1);
+        let mut inner = TestWorld::<()>::new(&[]);
+        inner.storage.insert((contract, 1.into()), 10.into());
+        inner.storage.insert((contract, 2.into()), 20.into());
+        let mut world = OverrideWorld::new(inner);
+
+        world.set_storage(contract, 2.into(), 21.into());
+        world.set_storage(contract, 3.into(), 31.into());
+        assert_eq!(world.read_storage_value(contract, 1.into()), 10.into());
+        assert_eq!(world.read_storage_value(contract, 2.into()), 21.into());
+        assert_eq!(world.read_storage(contract, 3.into()).value, 31.into());
+
+        world.clear_storage(contract);
+        world.set_storage(contract, 3.into(), 32.into());
+        assert_eq!(world.read_storage_value(contract, 1.into()), U256::zero());
+        assert_eq!(world.read_storage_value(contract, 2.into()), U256::zero());
+        assert_eq!(world.read_storage(contract, 3.into()).value, 32.into());
+        assert_eq!(world.inner().storage[&(contract, 1.into())], 10.into());
+    }
+
+    #[test]
+    fn overriding_code() {
+        let address = Address::repeat_byte(1);
+        let mut world = OverrideWorld::new(TestWorld::<()>::new(&[]));
+        let hash = world.set_code(address, BYTECODE.to_vec());
+
+        let mut hash_bytes = [0; 32];
+        hash.to_big_endian(&mut hash_bytes);
+        assert_eq!(hash_bytes[..4], [1, 0, 0, 1]);
+        assert_eq!(world.decommit_code(hash), BYTECODE);
+
+        let program = initial_decommit(&mut world, address);
+        let expected_code_page: Vec<_> = BYTECODE.chunks(32).map(U256::from_big_endian).collect();
+        assert_eq!(program.code_page().as_ref(), expected_code_page);
+        // The decoded program is cached.
+        let same_program = world.decommit(hash);
+        assert!(Arc::ptr_eq(program.code_page(), same_program.code_page()));
+    }
+
+    #[test]
+    fn decommitting_inner_code() {
+        let address = Address::repeat_byte(1);
+        let inner = TestWorld::<()>::new(&[(address, Program::new(BYTECODE, false))]);
+        let mut world = OverrideWorld::new(inner);
+
+        let program = initial_decommit(&mut world, address);
+        assert_eq!(program.code_page().len(), 1);
+
+        // Overriding code changes the decommitted program.
+        let other_address = Address::repeat_byte(2);
+        world.set_code(address, BYTECODE.repeat(3));
+        world.set_code(other_address, BYTECODE.to_vec());
+        let program = initial_decommit(&mut world, address);
+        assert_eq!(program.code_page().len(), 3);
+        let program = initial_decommit(&mut world, other_address);
+        assert_eq!(
+            program.code_page().as_ref(),
+            [U256::from_big_endian(BYTECODE)]
+        );
+    }
+
+    #[test]
+    fn overriding_balance() {
+        let account = Address::repeat_byte(1);
+        let mut world = OverrideWorld::new(TestWorld::<()>::new(&[]));
+        world.set_balance(account, 1_000.into());
+
+        let base_token_address = H160::from_low_u64_be(L2_BASE_TOKEN_ADDRESS_LOW.into());
+        let mut key_preimage = [0; 64];
+        key_preimage[12..32].copy_from_slice(account.as_bytes());
+        let expected_key = U256::from_big_endian(&Keccak256::digest(key_preimage));
+        assert_eq!(
+            world.read_storage_value(base_token_address, expected_key),
+            1_000.into()
+        );
+        assert_eq!(
+            world.read_storage_value(base_token_address, address_into_u256(account)),
+            U256::zero()
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! Information about frame panics.
+
+use primitive_types::H160;
+use zksync_vm2_interface::PanicReason;
+
+/// Information about a frame panic, as returned by [`VirtualMachine::last_panic()`](crate::VirtualMachine::last_panic()).
+///
+/// Allows finding out why and where execution has failed without single-stepping through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PanicInfo {
+    /// Reason of the panic.
+    pub reason: PanicReason,
+    /// Address of the contract whose code was executing the instruction that caused the panic. For a failed far call,
+    /// this is the caller rather than the called contract.
+    pub address: H160,
+    /// Program counter of the instruction that caused the panic, e.g. to be decoded with
+    /// [`InstructionInfo`](crate::InstructionInfo). `None` if the instruction is not located in the executed program,
+    /// e.g., if the program counter has jumped out of its bounds.
+    pub pc: Option<u16>,
+}
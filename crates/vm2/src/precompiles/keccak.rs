@@ -0,0 +1,41 @@
+//! Native implementation of the keccak256 round function precompile.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::sha3::{Digest, Keccak256};
+use zksync_vm2_interface::CycleStats;
+
+use super::{PrecompileMemoryReader, PrecompileOutput};
+
+/// Number of input bytes absorbed by a single keccak256 round (i.e., a call to the permutation function).
+const KECCAK_RATE_BYTES: u32 = 136;
+/// Number of rounds read from the heap and absorbed at once.
+const ROUNDS_PER_BATCH: usize = 16;
+
+/// Returns the number of keccak256 precompile cycles (i.e., rounds) needed to hash an input of the specified length.
+/// This is the value reported via [`CycleStats::Keccak256`] and can be used to estimate circuit usage.
+pub fn keccak256_cycles(input_len: u32) -> u32 {
+    // Padding always adds at least one byte.
+    input_len / KECCAK_RATE_BYTES + 1
+}
+
+/// Hashes the input memory. Unlike the legacy implementation, the input is read from the heap and absorbed in batches
+/// of several rounds using a single scratch buffer, rather than word by word.
+pub(super) fn keccak256(memory: &PrecompileMemoryReader<'_>) -> PrecompileOutput {
+    let mut buffer = [0_u8; KECCAK_RATE_BYTES as usize * ROUNDS_PER_BATCH];
+    let mut hasher = Keccak256::new();
+    let mut offset = memory.offset;
+    let mut remaining = memory.len as usize;
+    while remaining > 0 {
+        let chunk = &mut buffer[..remaining.min(KECCAK_RATE_BYTES as usize * ROUNDS_PER_BATCH)];
+        memory.heap.read_into(offset, chunk);
+        hasher.update(&*chunk);
+        #[allow(clippy::cast_possible_truncation)] // chunk length is bounded by the buffer size
+        let chunk_len = chunk.len() as u32;
+        offset = offset.wrapping_add(chunk_len);
+        remaining -= chunk.len();
+    }
+
+    let digest = U256::from_big_endian(&hasher.finalize());
+    PrecompileOutput::from(digest)
+        .with_cycle_stats(CycleStats::Keccak256(keccak256_cycles(memory.len)))
+}
@@ -1,28 +1,32 @@
+#[cfg(feature = "precompiles")]
 use primitive_types::{H160, U256};
+#[cfg(feature = "precompiles")]
 use zk_evm_abstractions::{
     aux::Timestamp,
     precompiles::{
         ecadd::ecadd_function, ecmul::ecmul_function, ecpairing::ecpairing_function,
-        ecrecover::ecrecover_function, keccak256::keccak256_rounds_function,
-        modexp::modexp_function, secp256r1_verify::secp256r1_verify_function,
-        sha256::sha256_rounds_function,
+        ecrecover::ecrecover_function, modexp::modexp_function,
+        secp256r1_verify::secp256r1_verify_function, sha256::sha256_rounds_function,
     },
     queries::{LogQuery, MemoryQuery},
     vm::Memory,
-    zkevm_opcode_defs::{
-        ECADD_PRECOMPILE_ADDRESS, ECMUL_PRECOMPILE_ADDRESS, ECPAIRING_PRECOMPILE_ADDRESS,
-        MODEXP_PRECOMPILE_ADDRESS,
-    },
-};
-use zkevm_opcode_defs::{
-    PrecompileCallABI, ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS,
-    KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS, SECP256R1_VERIFY_PRECOMPILE_ADDRESS,
-    SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
 };
+#[cfg(feature = "precompiles")]
 use zksync_vm2_interface::CycleStats;
 
-use super::{PrecompileMemoryReader, PrecompileOutput, Precompiles};
+use super::{
+    keccak::keccak256, PrecompileMemoryReader, PrecompileOutput, Precompiles,
+    ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS, KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+    SECP256R1_VERIFY_PRECOMPILE_ADDRESS, SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+};
+#[cfg(feature = "precompiles")]
+use crate::isa::PrecompileCallABI;
+use crate::isa::{
+    ECADD_PRECOMPILE_ADDRESS, ECMUL_PRECOMPILE_ADDRESS, ECPAIRING_PRECOMPILE_ADDRESS,
+    MODEXP_PRECOMPILE_ADDRESS,
+};
 
+#[cfg(feature = "precompiles")]
 fn create_query(input_offset: u32, input_len: u32, aux_data: u64) -> LogQuery {
     let abi = PrecompileCallABI {
         input_memory_offset: input_offset,
@@ -50,12 +54,14 @@ fn create_query(input_offset: u32, input_len: u32, aux_data: u64) -> LogQuery {
     }
 }
 
+#[cfg(feature = "precompiles")]
 #[derive(Debug)]
 struct LegacyIo<'a> {
     input: PrecompileMemoryReader<'a>,
     output: PrecompileOutput,
 }
 
+#[cfg(feature = "precompiles")]
 impl<'a> LegacyIo<'a> {
     fn new(input: PrecompileMemoryReader<'a>) -> Self {
         Self {
@@ -65,6 +71,7 @@ impl<'a> LegacyIo<'a> {
     }
 }
 
+#[cfg(feature = "precompiles")]
 impl Memory for LegacyIo<'_> {
     fn execute_partial_query(
         &mut self,
@@ -78,7 +85,7 @@ impl Memory for LegacyIo<'_> {
             self.output.len = self.output.len.max(start_word + 1);
         } else {
             // Access `Heap` directly for a speed-up
-            query.value = self.input.heap.read_u256(start_word * 32);
+            query.value = self.input.heap.read_u256(start_word.wrapping_mul(32));
             query.value_is_pointer = false;
         }
         query
@@ -97,68 +104,99 @@ impl Memory for LegacyIo<'_> {
     }
 }
 
-/// Precompiles implementation using legacy VM code.
+/// Precompiles implementation using legacy VM code. The keccak256 precompile is implemented natively since it's
+/// commonly called with large inputs; its output and cycle stats are the same as for the legacy implementation.
+///
+/// Without the `precompiles` feature, only keccak256 is supported; calls to other known precompiles
+/// return [`PrecompileOutput::unsupported()`].
 #[derive(Debug)]
 pub struct LegacyPrecompiles;
 
 impl Precompiles for LegacyPrecompiles {
-    #[allow(clippy::cast_possible_truncation)]
     fn call_precompile(
         &self,
         address_low: u16,
         memory: PrecompileMemoryReader<'_>,
         aux_input: u64,
     ) -> PrecompileOutput {
-        let query = create_query(memory.offset, memory.len, aux_input);
-        let mut io = LegacyIo::new(memory);
-        match address_low {
-            KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS => {
-                let cycles = keccak256_rounds_function::<_, false>(0, query, &mut io).0;
-                io.output
-                    .with_cycle_stats(CycleStats::Keccak256(cycles as u32))
-            }
-            SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS => {
-                let cycles = sha256_rounds_function::<_, false>(0, query, &mut io).0;
-                io.output
-                    .with_cycle_stats(CycleStats::Sha256(cycles as u32))
-            }
-            ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS => {
-                let cycles = ecrecover_function::<_, false>(0, query, &mut io).0;
-                io.output
-                    .with_cycle_stats(CycleStats::EcRecover(cycles as u32))
-            }
-            SECP256R1_VERIFY_PRECOMPILE_ADDRESS => {
-                let cycles = secp256r1_verify_function::<_, false>(0, query, &mut io).0;
-                io.output
-                    .with_cycle_stats(CycleStats::Secp256r1Verify(cycles as u32))
-            }
-            MODEXP_PRECOMPILE_ADDRESS => {
-                let cycles = modexp_function::<_, false>(0, query, &mut io).0;
-                io.output
-                    .with_cycle_stats(CycleStats::ModExp(cycles as u32))
-            }
-            ECADD_PRECOMPILE_ADDRESS => {
-                let cycles = ecadd_function::<_, false>(0, query, &mut io).0;
-                io.output.with_cycle_stats(CycleStats::EcAdd(cycles as u32))
-            }
-            ECMUL_PRECOMPILE_ADDRESS => {
-                let cycles = ecmul_function::<_, false>(0, query, &mut io).0;
-                io.output.with_cycle_stats(CycleStats::EcMul(cycles as u32))
-            }
-            ECPAIRING_PRECOMPILE_ADDRESS => {
-                let cycles = ecpairing_function::<_, false>(0, query, &mut io).0;
-                io.output
-                    .with_cycle_stats(CycleStats::EcPairing(cycles as u32))
-            }
-            _ => PrecompileOutput::default(),
+        if address_low == KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS {
+            return keccak256(&memory);
         }
+        call_legacy_precompile(address_low, memory, aux_input)
+    }
+}
+
+#[cfg(feature = "precompiles")]
+#[allow(clippy::cast_possible_truncation)]
+fn call_legacy_precompile(
+    address_low: u16,
+    memory: PrecompileMemoryReader<'_>,
+    aux_input: u64,
+) -> PrecompileOutput {
+    let query = create_query(memory.offset, memory.len, aux_input);
+    let mut io = LegacyIo::new(memory);
+    match address_low {
+        SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS => {
+            let cycles = sha256_rounds_function::<_, false>(0, query, &mut io).0;
+            io.output
+                .with_cycle_stats(CycleStats::Sha256(cycles as u32))
+        }
+        ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS => {
+            let cycles = ecrecover_function::<_, false>(0, query, &mut io).0;
+            io.output
+                .with_cycle_stats(CycleStats::EcRecover(cycles as u32))
+        }
+        SECP256R1_VERIFY_PRECOMPILE_ADDRESS => {
+            let cycles = secp256r1_verify_function::<_, false>(0, query, &mut io).0;
+            io.output
+                .with_cycle_stats(CycleStats::Secp256r1Verify(cycles as u32))
+        }
+        MODEXP_PRECOMPILE_ADDRESS => {
+            let cycles = modexp_function::<_, false>(0, query, &mut io).0;
+            io.output
+                .with_cycle_stats(CycleStats::ModExp(cycles as u32))
+        }
+        ECADD_PRECOMPILE_ADDRESS => {
+            let cycles = ecadd_function::<_, false>(0, query, &mut io).0;
+            io.output.with_cycle_stats(CycleStats::EcAdd(cycles as u32))
+        }
+        ECMUL_PRECOMPILE_ADDRESS => {
+            let cycles = ecmul_function::<_, false>(0, query, &mut io).0;
+            io.output.with_cycle_stats(CycleStats::EcMul(cycles as u32))
+        }
+        ECPAIRING_PRECOMPILE_ADDRESS => {
+            let cycles = ecpairing_function::<_, false>(0, query, &mut io).0;
+            io.output
+                .with_cycle_stats(CycleStats::EcPairing(cycles as u32))
+        }
+        _ => PrecompileOutput::default(),
+    }
+}
+
+#[cfg(not(feature = "precompiles"))]
+#[allow(clippy::needless_pass_by_value)] // the signature matches the implementation with the feature enabled
+fn call_legacy_precompile(
+    address_low: u16,
+    _memory: PrecompileMemoryReader<'_>,
+    _aux_input: u64,
+) -> PrecompileOutput {
+    match address_low {
+        SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS
+        | ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS
+        | SECP256R1_VERIFY_PRECOMPILE_ADDRESS
+        | MODEXP_PRECOMPILE_ADDRESS
+        | ECADD_PRECOMPILE_ADDRESS
+        | ECMUL_PRECOMPILE_ADDRESS
+        | ECPAIRING_PRECOMPILE_ADDRESS => PrecompileOutput::unsupported(),
+        _ => PrecompileOutput::default(),
     }
 }
 
 #[allow(clippy::cast_possible_truncation)] // OK for tests
-#[cfg(test)]
+#[cfg(all(test, feature = "precompiles"))]
 mod tests {
     use proptest::{array, collection, num, option, prelude::*};
+    use zk_evm_abstractions::precompiles::keccak256::keccak256_rounds_function;
     use zkevm_opcode_defs::{
         k256::ecdsa::{SigningKey as K256SigningKey, VerifyingKey as K256VerifyingKey},
         p256::ecdsa::SigningKey as P256SigningKey,
@@ -167,7 +205,7 @@ mod tests {
     use zksync_vm2_interface::HeapId;
 
     use super::*;
-    use crate::heap::Heaps;
+    use crate::{heap::Heaps, precompiles::keccak256_cycles};
 
     const MAX_LEN: usize = 2_048;
 
@@ -189,12 +227,12 @@ mod tests {
 
     fn test_keccak_precompile(input: &[u8], initial_offset: u32) -> Result<(), TestCaseError> {
         let input_len = input.len() as u32;
-        assert_eq!(input_len % 32, 0);
-
         let mut heaps = Heaps::new(&[]);
         for (i, u256_chunk) in input.chunks(32).enumerate() {
             let offset = i as u32 * 32 + initial_offset;
-            heaps.write_u256(HeapId::FIRST, offset, U256::from_big_endian(u256_chunk));
+            let mut word = [0_u8; 32];
+            word[..u256_chunk.len()].copy_from_slice(u256_chunk);
+            heaps.write_u256(HeapId::FIRST, offset, U256::from_big_endian(&word));
         }
 
         let memory = PrecompileMemoryReader::new(&heaps[HeapId::FIRST], initial_offset, input_len);
@@ -208,7 +246,19 @@ mod tests {
         let expected_hash = sha3::Keccak256::digest(input);
         let expected_hash = U256::from_big_endian(&expected_hash);
         prop_assert_eq!(output.buffer[0], expected_hash);
-        prop_assert!(matches!(output.cycle_stats, Some(CycleStats::Keccak256(_))));
+        prop_assert_eq!(
+            output.cycle_stats,
+            Some(CycleStats::Keccak256(keccak256_cycles(input_len)))
+        );
+
+        // Check that the output and cycle stats match the legacy implementation.
+        let memory = PrecompileMemoryReader::new(&heaps[HeapId::FIRST], initial_offset, input_len);
+        let query = create_query(initial_offset, input_len, 0);
+        let mut io = LegacyIo::new(memory);
+        let legacy_cycles = keccak256_rounds_function::<_, false>(0, query, &mut io).0;
+        prop_assert_eq!(io.output.len, 1);
+        prop_assert_eq!(io.output.buffer, output.buffer);
+        prop_assert_eq!(legacy_cycles as u32, keccak256_cycles(input_len));
         Ok(())
     }
 
@@ -455,6 +505,7 @@ mod tests {
     }
 
     proptest! {
+        #[cfg_attr(miri, ignore)]
         #[test]
         fn keccak_precompile_works(
             bytes in arbitrary_aligned_bytes(32),
@@ -463,6 +514,16 @@ mod tests {
             test_keccak_precompile(&bytes, initial_offset)?;
         }
 
+        #[cfg_attr(miri, ignore)]
+        #[test]
+        fn keccak_precompile_works_with_unaligned_input(
+            bytes in collection::vec(num::u8::ANY, 0..=MAX_LEN),
+            initial_offset in 0..u32::MAX / 2,
+        ) {
+            test_keccak_precompile(&bytes, initial_offset)?;
+        }
+
+        #[cfg_attr(miri, ignore)]
         #[test]
         fn sha256_precompile_works(
             bytes in arbitrary_aligned_bytes(64),
@@ -471,6 +532,7 @@ mod tests {
             test_sha256_precompile(&bytes, initial_offset_in_words)?;
         }
 
+        #[cfg_attr(miri, ignore)]
         #[test]
         fn ecrecover_precompile_works(
             signing_key in array::uniform32(num::u8::ANY)
@@ -481,6 +543,7 @@ mod tests {
             test_ecrecover_precompile(&signing_key, mutation, initial_offset_in_words)?;
         }
 
+        #[cfg_attr(miri, ignore)]
         #[test]
         fn secp256r1_precompile_works(
             signing_key in array::uniform32(num::u8::ANY)
@@ -1,15 +1,18 @@
 //! Precompiles support.
 
+use std::{fmt, sync::Arc};
+
 use primitive_types::U256;
-pub use zkevm_opcode_defs::system_params::{
-    ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS, KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
-    SECP256R1_VERIFY_PRECOMPILE_ADDRESS, SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
-};
 use zksync_vm2_interface::CycleStats;
 
-pub use self::legacy::LegacyPrecompiles;
+pub use self::{keccak::keccak256_cycles, legacy::LegacyPrecompiles};
 use crate::heap::Heap;
+pub use crate::isa::system_params::{
+    ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS, KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+    SECP256R1_VERIFY_PRECOMPILE_ADDRESS, SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+};
 
+mod keccak;
 mod legacy;
 
 /// Provides access to the input memory for a precompile call.
@@ -29,12 +32,21 @@ impl<'a> PrecompileMemoryReader<'a> {
     pub fn assume_offset_in_words(self) -> PrecompileMemoryReader<'a, true> {
         PrecompileMemoryReader {
             heap: self.heap,
-            offset: self.offset * 32,
-            len: self.len * 32,
+            offset: self.offset.wrapping_mul(32),
+            len: self.len.wrapping_mul(32),
         }
     }
 }
 
+impl<const IN_WORDS: bool> PrecompileMemoryReader<'_, IN_WORDS> {
+    /// Copies all remaining input bytes to a vector. This is more efficient than collecting bytes from the iterator.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.len as usize];
+        self.heap.read_into(self.offset, &mut bytes);
+        bytes
+    }
+}
+
 /// Iterates over input bytes.
 impl<const IN_WORDS: bool> Iterator for PrecompileMemoryReader<'_, IN_WORDS> {
     type Item = u8;
@@ -44,9 +56,9 @@ impl<const IN_WORDS: bool> Iterator for PrecompileMemoryReader<'_, IN_WORDS> {
             return None;
         }
 
-        // This assumes the offset never overflows
+        // The offset is provided by the caller, so it wraps around on overflow rather than panicking.
         let output = self.heap.read_byte(self.offset);
-        self.offset += 1;
+        self.offset = self.offset.wrapping_add(1);
         self.len -= 1;
         Some(output)
     }
@@ -68,9 +80,20 @@ pub struct PrecompileOutput {
     pub(crate) buffer: [U256; 3],
     pub(crate) len: u32,
     pub(crate) cycle_stats: Option<CycleStats>,
+    pub(crate) is_unsupported: bool,
 }
 
 impl PrecompileOutput {
+    /// Creates an output signaling that the called precompile is not supported, e.g., because this crate
+    /// is compiled without the `precompiles` feature. The VM stops with
+    /// [`ExecutionEnd::UnsupportedPrecompile`](crate::ExecutionEnd::UnsupportedPrecompile) on such outputs.
+    pub fn unsupported() -> Self {
+        Self {
+            is_unsupported: true,
+            ..Self::default()
+        }
+    }
+
     /// Assigns cycle stats for this output.
     #[must_use]
     pub fn with_cycle_stats(mut self, stats: CycleStats) -> Self {
@@ -85,6 +108,7 @@ impl From<U256> for PrecompileOutput {
             buffer: [value, U256::zero(), U256::zero()],
             len: 1,
             cycle_stats: None,
+            is_unsupported: false,
         }
     }
 }
@@ -100,6 +124,7 @@ macro_rules! impl_from_array_for_precompile_output {
                     buffer,
                     len: $n,
                     cycle_stats: None,
+                    is_unsupported: false,
                 }
             }
         }
@@ -120,3 +145,15 @@ pub trait Precompiles {
         aux_input: u64,
     ) -> PrecompileOutput;
 }
+
+/// [`Precompiles`] set for a VM, overriding ones returned by [`World::precompiles()`](crate::World::precompiles()).
+#[derive(Clone)]
+pub(crate) struct PrecompilesOverride(pub(crate) Arc<dyn Precompiles + Send + Sync>);
+
+impl fmt::Debug for PrecompilesOverride {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("PrecompilesOverride")
+            .finish_non_exhaustive()
+    }
+}
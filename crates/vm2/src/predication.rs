@@ -39,6 +39,10 @@ pub enum Predicate {
 impl Predicate {
     #[inline(always)]
     pub(crate) fn satisfied(self, flags: &Flags) -> bool {
+        // Most instructions are unconditional; for them, the predicate is checked without loading the flags.
+        if matches!(self, Self::Always) {
+            return true;
+        }
         let bits = self as u8;
         bits & flags.0 != 0 && (bits >> 4) & flags.0 == 0
     }
@@ -0,0 +1,25 @@
+//! Commonly used types and traits.
+//!
+//! Downstream crates can glob-import this module instead of picking items from different modules of this crate
+//! and [`zksync_vm2_interface`]:
+//!
+//! ```
+//! use zksync_vm2::prelude::*;
+//! ```
+//!
+//! Items are only added to the prelude, never removed.
+
+pub use zksync_vm2_interface::{
+    CallframeInterface, GlobalStateInterface, Opcode, OpcodeType, ShouldStop, StateInterface,
+    Tracer,
+};
+
+pub use crate::{
+    addressing_modes::{
+        AbsoluteStack, AdvanceStackPointer, AnyDestination, AnySource, Arguments, CodePage,
+        Immediate1, Immediate2, Register, Register1, Register2, RegisterAndImmediate,
+        RegisterOrImmediate, RelativeStack,
+    },
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, StorageInterface,
+    StorageSlot, VirtualMachine, World, WorldDiff,
+};
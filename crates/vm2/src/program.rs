@@ -1,22 +1,36 @@
-use std::{fmt, sync::Arc};
+use std::{
+    error,
+    fmt::{self, Write as _},
+    mem,
+    sync::Arc,
+};
 
 use primitive_types::U256;
+use zkevm_opcode_defs::sha2::{Digest, Sha256};
 use zksync_vm2_interface::Tracer;
 
 use crate::{
-    addressing_modes::Arguments, decode::decode, hash_for_debugging, instruction::ExecutionStatus,
-    Instruction, ModeRequirements, Predicate, VirtualMachine, World,
+    addressing_modes::{AnySource, Arguments},
+    analysis::{self, JumpTargets},
+    decode::decode,
+    hash_for_debugging,
+    instruction::{CompactInstruction, ExecutionStatus, Handler, HandlerTable},
+    GasCosts, Instruction, InstructionInfo, ModeRequirements, Predicate, SourceMap, SymbolTable,
+    VirtualMachine, World,
 };
 
 /// Compiled EraVM bytecode.
 ///
-/// Cloning this is cheap. It is a handle to memory similar to [`Arc`].
+/// Cloning this is cheap. It is a handle to memory similar to [`Arc`]. Like `Arc`, it is `Send` and `Sync`,
+/// so a program can be shared among VMs running on different threads.
 pub struct Program<T, W> {
-    // An internal representation that doesn't need two Arcs would be better
+    // An internal representation that doesn't need several Arcs would be better
     // but it would also require a lot of unsafe, so I made this wrapper to
     // enable changing the internals later.
     code_page: Arc<[U256]>,
-    instructions: Arc<[Instruction<T, W>]>,
+    instructions: Arc<[CompactInstruction]>,
+    handlers: Arc<[Handler<T, W>]>,
+    source_map: Option<Arc<SourceMap>>,
 }
 
 impl<T, W> Clone for Program<T, W> {
@@ -24,6 +38,8 @@ impl<T, W> Clone for Program<T, W> {
         Self {
             code_page: self.code_page.clone(),
             instructions: self.instructions.clone(),
+            handlers: self.handlers.clone(),
+            source_map: self.source_map.clone(),
         }
     }
 }
@@ -51,64 +67,367 @@ impl<T, W> fmt::Debug for Program<T, W> {
     }
 }
 
+/// Error returned by [`Program::try_new()`] and [`Program::try_from_words()`] if the bytecode has more instructions
+/// than allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramTooLarge {
+    /// Number of instructions in the bytecode.
+    pub instructions: usize,
+    /// Maximum allowed number of instructions.
+    pub limit: usize,
+}
+
+impl fmt::Display for ProgramTooLarge {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "program has {} instructions, while at most {} are allowed",
+            self.instructions, self.limit
+        )
+    }
+}
+
+impl error::Error for ProgramTooLarge {}
+
+/// Error returned by [`Program::new_checked()`] if the bytecode is not a valid EraVM bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidBytecode {
+    /// Bytecode length in bytes is not divisible by 32.
+    UnalignedLength(usize),
+    /// Bytecode has an even number of 32-byte words.
+    EvenWordCount(usize),
+    /// Bytecode has more 32-byte words than can be encoded in its hash.
+    TooManyWords(usize),
+    /// Bytecode doesn't match the provided versioned hash.
+    HashMismatch {
+        /// Provided hash.
+        expected: U256,
+        /// Hash of the bytecode.
+        actual: U256,
+    },
+}
+
+impl fmt::Display for InvalidBytecode {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnalignedLength(len) => {
+                write!(formatter, "bytecode length {len} is not divisible by 32")
+            }
+            Self::EvenWordCount(words) => {
+                write!(formatter, "bytecode has an even number of words ({words})")
+            }
+            Self::TooManyWords(words) => write!(
+                formatter,
+                "bytecode has {words} words, while at most {} are allowed",
+                u16::MAX
+            ),
+            Self::HashMismatch { expected, actual } => write!(
+                formatter,
+                "bytecode hash {actual:#x} doesn't match the expected hash {expected:#x}"
+            ),
+        }
+    }
+}
+
+impl error::Error for InvalidBytecode {}
+
+impl<T, W> Program<T, W> {
+    /// Maximum number of instructions addressable by the 16-bit program counter. Programs created with
+    /// [`Self::new()`] or [`Self::from_words()`] are truncated to this number of instructions, and the program counter
+    /// wraps around to the first instruction after executing the last one.
+    pub const MAX_INSTRUCTIONS: usize = MAX_INSTRUCTIONS;
+}
+
 impl<T: Tracer, W: World<T>> Program<T, W> {
     /// Creates a new program.
-    #[allow(clippy::missing_panics_doc)] // false positive
+    ///
+    /// Instructions beyond [`Self::MAX_INSTRUCTIONS`] are silently ignored; use [`Self::try_new()`] to reject
+    /// such bytecodes instead. The bytecode is not validated otherwise; see [`Self::new_checked()`].
     pub fn new(bytecode: &[u8], enable_hooks: bool) -> Self {
+        Self::new_with_gas_costs(bytecode, enable_hooks, &GasCosts::new())
+    }
+
+    /// Creates a new program charging static gas costs from `gas_costs` for the overridden opcodes.
+    /// Otherwise, this is the same as [`Self::new()`].
+    ///
+    /// Costs are applied when decoding, so they don't affect the performance of executing the program. A [`World`]
+    /// replaying protocol versions with different pricing should thus decode all programs it provides using this method.
+    #[allow(clippy::missing_panics_doc)] // false positive
+    pub fn new_with_gas_costs(bytecode: &[u8], enable_hooks: bool, gas_costs: &GasCosts) -> Self {
         let instructions = decode_program(
             &bytecode
                 .chunks_exact(8)
                 .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
                 .collect::<Vec<_>>(),
             enable_hooks,
+            gas_costs,
         );
         let code_page = bytecode
             .chunks_exact(32)
             .map(U256::from_big_endian)
             .collect::<Vec<_>>();
-        Self {
-            instructions: instructions.into(),
-            code_page: code_page.into(),
+        Self::from_instructions(instructions, code_page)
+    }
+
+    /// Creates a new program, checking that the bytecode has at most `max_instructions` 8-byte instructions
+    /// (a limit of `N` 32-byte words corresponds to `4 * N` instructions). The limit is capped at
+    /// [`Self::MAX_INSTRUCTIONS`], so this can be used to enforce lower limits, such as ones imposed
+    /// by a specific protocol version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytecode has more instructions than allowed.
+    pub fn try_new(
+        bytecode: &[u8],
+        enable_hooks: bool,
+        max_instructions: usize,
+    ) -> Result<Self, ProgramTooLarge> {
+        check_program_size(bytecode.len() / 8, max_instructions)?;
+        Ok(Self::new(bytecode, enable_hooks))
+    }
+
+    /// Creates a new program after checking that `bytecode` is a valid EraVM bytecode with the versioned `hash`:
+    /// its length is a multiple of 32 bytes, it has an odd number of words which fits into the hash, and its SHA-256
+    /// digest together with the length matches `hash`. The marker byte of `hash` (i.e., whether the contract
+    /// is being constructed) is not checked.
+    ///
+    /// This is intended for bytecodes received from untrusted sources. For trusted pipelines where bytecodes are known
+    /// to be valid (e.g., loaded from the VM storage), [`Self::new_unchecked()`] skips these checks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the checks fails.
+    pub fn new_checked(
+        bytecode: &[u8],
+        hash: U256,
+        enable_hooks: bool,
+    ) -> Result<Self, InvalidBytecode> {
+        if bytecode.len() % 32 != 0 {
+            return Err(InvalidBytecode::UnalignedLength(bytecode.len()));
+        }
+        let words = bytecode.len() / 32;
+        if words % 2 == 0 {
+            return Err(InvalidBytecode::EvenWordCount(words));
+        }
+        let len_in_words =
+            u16::try_from(words).map_err(|_| InvalidBytecode::TooManyWords(words))?;
+
+        let actual = versioned_bytecode_hash(bytecode, len_in_words);
+        let mut masked_hash = hash;
+        // Clear the marker byte, which is the second most significant byte.
+        masked_hash.0[3] &= !(0xff << 48);
+        if masked_hash != actual {
+            return Err(InvalidBytecode::HashMismatch {
+                expected: hash,
+                actual,
+            });
         }
+        Ok(Self::new(bytecode, enable_hooks))
+    }
+
+    /// Creates a new program without validating `bytecode`; the counterpart of [`Self::new_checked()`]
+    /// for bytecodes known to be valid. This is equivalent to [`Self::new()`], but makes the lack of checks explicit
+    /// at the call site.
+    pub fn new_unchecked(bytecode: &[u8], enable_hooks: bool) -> Self {
+        Self::new(bytecode, enable_hooks)
     }
 
     /// Creates a new program from `U256` words.
+    ///
+    /// Like [`Self::new()`], this silently ignores instructions beyond [`Self::MAX_INSTRUCTIONS`];
+    /// use [`Self::try_from_words()`] to reject such bytecodes instead.
     pub fn from_words(bytecode_words: Vec<U256>, enable_hooks: bool) -> Self {
+        Self::from_words_with_gas_costs(bytecode_words, enable_hooks, &GasCosts::new())
+    }
+
+    /// Creates a new program from `U256` words charging static gas costs from `gas_costs`.
+    /// See [`Self::new_with_gas_costs()`] for details.
+    pub fn from_words_with_gas_costs(
+        bytecode_words: Vec<U256>,
+        enable_hooks: bool,
+        gas_costs: &GasCosts,
+    ) -> Self {
         let instructions = decode_program(
             &bytecode_words
                 .iter()
                 .flat_map(|x| x.0.into_iter().rev())
                 .collect::<Vec<_>>(),
             enable_hooks,
+            gas_costs,
         );
-        Self {
-            instructions: instructions.into(),
-            code_page: bytecode_words.into(),
-        }
+        Self::from_instructions(instructions, bytecode_words)
+    }
+
+    /// Creates a new program from `U256` words, checking that the bytecode has at most `max_instructions` instructions.
+    /// See [`Self::try_new()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytecode has more instructions than allowed.
+    pub fn try_from_words(
+        bytecode_words: Vec<U256>,
+        enable_hooks: bool,
+        max_instructions: usize,
+    ) -> Result<Self, ProgramTooLarge> {
+        check_program_size(bytecode_words.len() * 4, max_instructions)?;
+        Ok(Self::from_words(bytecode_words, enable_hooks))
     }
 
     pub(crate) fn new_panicking() -> Self {
         Self::from_raw(vec![Instruction::from_spontaneous_panic()], vec![])
     }
 
+    /// Creates a program from already constructed instructions.
+    ///
+    /// Like for decoded programs, an invalid instruction is appended at the end, so that running past the last instruction
+    /// panics instead of reading out of bounds.
     #[doc(hidden)] // should only be used in low-level tests / benchmarks
-    pub fn from_raw(instructions: Vec<Instruction<T, W>>, code_page: Vec<U256>) -> Self {
+    pub fn from_raw(mut instructions: Vec<Instruction<T, W>>, code_page: Vec<U256>) -> Self {
+        instructions.push(Instruction::from_invalid());
+        Self::from_instructions(instructions, code_page)
+    }
+
+    fn from_instructions(instructions: Vec<Instruction<T, W>>, code_page: Vec<U256>) -> Self {
+        let mut handlers = HandlerTable::new();
+        let instructions: Vec<_> = instructions
+            .into_iter()
+            .map(|instruction| handlers.compact(instruction))
+            .collect();
         Self {
             instructions: instructions.into(),
+            handlers: handlers.into_handlers().into(),
             code_page: code_page.into(),
+            source_map: None,
         }
     }
 }
 
 impl<T, W> Program<T, W> {
-    pub(crate) fn instruction(&self, n: u16) -> Option<&Instruction<T, W>> {
+    pub(crate) fn instruction(&self, n: u16) -> Option<&CompactInstruction> {
         self.instructions.get::<usize>(n.into())
     }
 
-    /// Returns a reference to the code page of this program.
+    /// Returns the handler of `instruction`, which must either belong to this program or be one of the static
+    /// panic / invalid instructions.
+    #[inline(always)]
+    pub(crate) fn handler(&self, instruction: &CompactInstruction) -> Handler<T, W> {
+        self.handlers[usize::from(instruction.handler)]
+    }
+
+    /// Returns the encoded instruction at `pc`, taken from the code page.
+    pub(crate) fn raw_instruction(&self, pc: u16) -> Option<u64> {
+        let pc = usize::from(pc);
+        let word = self.code_page.get(pc / 4)?;
+        // Instructions are packed into words in the big-endian order, while `U256` limbs are little-endian.
+        Some(word.0[3 - pc % 4])
+    }
+
+    /// Returns a reference to the code page of this program, i.e., its bytecode split into big-endian `U256` words.
+    ///
+    /// The code page is readable by the program via [`CodePage`](crate::addressing_modes::CodePage) addressing;
+    /// reads beyond its end return zero.
     pub fn code_page(&self) -> &[U256] {
         &self.code_page
     }
+
+    /// Outputs a human-readable listing of this program, one instruction per line, using the [`InstructionInfo`]
+    /// syntax. Known addresses and hook offsets from `symbols` are added as comments to instructions using them,
+    /// as are source locations if a [source map](Self::with_source_map()) is attached.
+    ///
+    /// Instructions are decoded from the code page, so the output is only meaningful for programs created
+    /// from bytecode. Like the VM, this doesn't distinguish instructions from data in the bytecode; data words
+    /// are output as (usually invalid) instructions.
+    #[allow(clippy::missing_panics_doc)] // false positive; writing to a `String` cannot fail
+    pub fn pretty_print(&self, symbols: &SymbolTable) -> String {
+        let raw_instructions = self
+            .code_page
+            .iter()
+            .flat_map(|word| word.0.into_iter().rev())
+            .take(MAX_INSTRUCTIONS)
+            .zip(0_u16..=u16::MAX);
+
+        let mut output = String::new();
+        for (raw, i) in raw_instructions {
+            let info = InstructionInfo::decode(raw);
+            write!(output, "{i:>5}: {info}").unwrap();
+            let hint = self.symbol_hint(&info, symbols);
+            let location = self.source_map().and_then(|map| map.location(i));
+            match (hint, location) {
+                (Some(hint), Some(location)) => write!(output, "  ; {hint} ({location})").unwrap(),
+                (Some(hint), None) => write!(output, "  ; {hint}").unwrap(),
+                (None, Some(location)) => write!(output, "  ; {location}").unwrap(),
+                (None, None) => {}
+            }
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Returns static control flow targets of this program, e.g., to check whether a dynamic jump lands on a known
+    /// label. See [`analysis::jump_targets()`] for details.
+    ///
+    /// Jumps are not validated by the VM: any program counter is a valid jump destination, and jumping outside
+    /// the program panics. Like [`Self::pretty_print()`], this decodes instructions from the code page, so it's only
+    /// meaningful for programs created from bytecode.
+    pub fn jump_targets(&self) -> JumpTargets {
+        let instructions: Vec<_> = self
+            .code_page
+            .iter()
+            .flat_map(|word| word.0.into_iter().rev())
+            .take(MAX_INSTRUCTIONS)
+            .map(InstructionInfo::decode)
+            .collect();
+        analysis::jump_targets(&instructions)
+    }
+
+    fn symbol_hint<'a>(&self, info: &InstructionInfo, symbols: &'a SymbolTable) -> Option<&'a str> {
+        use zksync_vm2_interface::Opcode;
+
+        // Small values are predominantly used as plain numbers, so they aren't substituted.
+        let value_name = |value: U256| {
+            if value > U256::from(u8::MAX) {
+                symbols.value_name(value)
+            } else {
+                None
+            }
+        };
+
+        match (info.opcode?, info.src1) {
+            (Opcode::HeapWrite | Opcode::AuxHeapWrite, AnySource::Immediate1(offset)) => {
+                symbols.hook_name(offset.0.into())
+            }
+            (_, AnySource::Immediate1(value)) => value_name(value.0.into()),
+            (_, AnySource::CodePage(operand)) if operand.0.register.index() == 0 => {
+                value_name(*self.code_page.get(usize::from(operand.0.immediate))?)
+            }
+            _ => None,
+        }
+    }
+
+    /// Attaches a source map to this program. The source map is shared by all clones of the returned program.
+    #[must_use]
+    pub fn with_source_map(mut self, source_map: SourceMap) -> Self {
+        self.source_map = Some(Arc::new(source_map));
+        self
+    }
+
+    /// Returns the source map attached to this program, if any.
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_deref()
+    }
+
+    /// Returns an identifier of this program that is shared by all its clones.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.instructions).cast::<u8>() as usize
+    }
+
+    /// Returns the number of bytes occupied by the code page and decoded instructions of this program.
+    pub(crate) fn memory_size(&self) -> usize {
+        mem::size_of_val(&*self.code_page)
+            + mem::size_of_val(&*self.instructions)
+            + mem::size_of_val(&*self.handlers)
+    }
 }
 
 // This implementation compares pointers instead of programs.
@@ -123,6 +442,8 @@ impl<T, W> PartialEq for Program<T, W> {
     }
 }
 
+const MAX_INSTRUCTIONS: usize = 1 << 16;
+
 /// Wraparound instruction placed at the end of programs exceeding `1 << 16` instructions to simulate the 16-bit program counter overflowing.
 /// Does not invoke tracers because it is an implementation detail, not an actual instruction.
 fn jump_to_beginning<T, W>() -> Instruction<T, W> {
@@ -142,17 +463,213 @@ fn jump_to_beginning_handler<T, W>(
     ExecutionStatus::Running
 }
 
+/// Computes the versioned hash of a bytecode of a constructed contract. The bytecode length is not checked.
+pub(crate) fn versioned_bytecode_hash(bytecode: &[u8], len_in_words: u16) -> U256 {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&Sha256::digest(bytecode));
+    hash[0] = 1;
+    hash[1] = 0;
+    hash[2..4].copy_from_slice(&len_in_words.to_be_bytes());
+    U256::from_big_endian(&hash)
+}
+
+fn check_program_size(instructions: usize, max_instructions: usize) -> Result<(), ProgramTooLarge> {
+    let limit = max_instructions.min(MAX_INSTRUCTIONS);
+    if instructions > limit {
+        return Err(ProgramTooLarge {
+            instructions,
+            limit,
+        });
+    }
+    Ok(())
+}
+
 fn decode_program<T: Tracer, W: World<T>>(
     raw: &[u64],
     is_bootloader: bool,
+    gas_costs: &GasCosts,
 ) -> Vec<Instruction<T, W>> {
-    raw.iter()
-        .take(1 << 16)
-        .map(|i| decode(*i, is_bootloader))
-        .chain(std::iter::once(if raw.len() >= 1 << 16 {
-            jump_to_beginning()
-        } else {
-            Instruction::from_invalid()
-        }))
-        .collect()
+    let raw = &raw[..raw.len().min(MAX_INSTRUCTIONS)];
+    let mut instructions = Vec::with_capacity(raw.len() + 1);
+    decode_instructions(raw, is_bootloader, gas_costs, &mut instructions);
+    instructions.push(if raw.len() == MAX_INSTRUCTIONS {
+        jump_to_beginning()
+    } else {
+        Instruction::from_invalid()
+    });
+    instructions
+}
+
+#[cfg(not(feature = "rayon"))]
+fn decode_instructions<T: Tracer, W: World<T>>(
+    raw: &[u64],
+    is_bootloader: bool,
+    gas_costs: &GasCosts,
+    output: &mut Vec<Instruction<T, W>>,
+) {
+    output.extend(raw.iter().map(|&i| decode(i, is_bootloader, gas_costs)));
+}
+
+/// Minimum number of instructions for which decoding is parallelized; for smaller programs,
+/// the overhead of distributing work among threads outweighs the gains.
+#[cfg(feature = "rayon")]
+const PARALLEL_DECODING_THRESHOLD: usize = 1 << 12;
+
+#[cfg(feature = "rayon")]
+fn decode_instructions<T: Tracer, W: World<T>>(
+    raw: &[u64],
+    is_bootloader: bool,
+    gas_costs: &GasCosts,
+    output: &mut Vec<Instruction<T, W>>,
+) {
+    use rayon::prelude::*;
+
+    if raw.len() < PARALLEL_DECODING_THRESHOLD {
+        output.extend(raw.iter().map(|&i| decode(i, is_bootloader, gas_costs)));
+    } else {
+        // `collect_into_vec()` preserves the order of instructions.
+        raw.par_iter()
+            .map(|&i| decode(i, is_bootloader, gas_costs))
+            .collect_into_vec(output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        instruction_handlers::{invalid_instruction, spontaneous_panic},
+        testonly::TestWorld,
+    };
+
+    type TestProgram = Program<(), TestWorld<()>>;
+
+    #[test]
+    fn limiting_program_size() {
+        let bytecode = vec![0_u8; 32 * 10];
+        let program = TestProgram::try_new(&bytecode, false, 40).unwrap();
+        assert_eq!(program.code_page().len(), 10);
+
+        let err = TestProgram::try_new(&bytecode, false, 39).unwrap_err();
+        assert_eq!(
+            err,
+            ProgramTooLarge {
+                instructions: 40,
+                limit: 39
+            }
+        );
+        let err = TestProgram::try_from_words(vec![U256::zero(); 10], false, 36).unwrap_err();
+        assert_eq!(err.instructions, 40);
+    }
+
+    #[test]
+    fn limit_is_capped_by_program_counter_size() {
+        let words = vec![U256::zero(); TestProgram::MAX_INSTRUCTIONS / 4];
+        TestProgram::try_from_words(words.clone(), false, usize::MAX).unwrap();
+
+        let mut words = words;
+        words.push(U256::zero());
+        let err = TestProgram::try_from_words(words, false, usize::MAX).unwrap_err();
+        assert_eq!(err.limit, TestProgram::MAX_INSTRUCTIONS);
+        assert_eq!(
+            err.to_string(),
+            "program has 65540 instructions, while at most 65536 are allowed"
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_decoding_matches_sequential_decoding() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far");
+        let raw: Vec<_> = bytecode
+            .chunks_exact(8)
+            .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+            .cycle()
+            .take(MAX_INSTRUCTIONS + 5)
+            .collect();
+        let instructions = decode_program::<(), TestWorld<()>>(&raw, false);
+        assert_eq!(instructions.len(), MAX_INSTRUCTIONS + 1);
+
+        for (instruction, &raw) in instructions.iter().zip(&raw) {
+            let expected = decode::<(), TestWorld<()>>(raw, false);
+            assert_eq!(instruction.handler as usize, expected.handler as usize);
+            assert_eq!(format!("{instruction:?}"), format!("{expected:?}"));
+        }
+        let guard = jump_to_beginning::<(), TestWorld<()>>();
+        assert_eq!(
+            instructions[MAX_INSTRUCTIONS].handler as usize,
+            guard.handler as usize
+        );
+    }
+
+    #[test]
+    fn instructions_share_deduplicated_handlers() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far").repeat(8);
+        let program = TestProgram::new(&bytecode, false);
+        let raw: Vec<_> = bytecode
+            .chunks_exact(8)
+            .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(program.instructions.len(), raw.len() + 1);
+        // The reserved handlers and at most one handler per distinct instruction
+        assert!(program.handlers.len() <= 2 + 4);
+
+        for (instruction, &raw) in program.instructions.iter().zip(&raw) {
+            let expected = decode::<(), TestWorld<()>>(raw, false);
+            assert_eq!(
+                program.handler(instruction) as usize,
+                expected.handler as usize
+            );
+            assert_eq!(
+                format!("{:?}", instruction.arguments),
+                format!("{:?}", expected.arguments)
+            );
+        }
+
+        let reserved = [
+            (
+                spontaneous_panic(),
+                Instruction::<(), TestWorld<()>>::from_spontaneous_panic(),
+            ),
+            (invalid_instruction(), Instruction::from_invalid()),
+        ];
+        for (instruction, expected) in reserved {
+            assert_eq!(
+                program.handler(instruction) as usize,
+                expected.handler as usize
+            );
+        }
+    }
+
+    #[test]
+    fn checking_bytecode() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far");
+        let hash = versioned_bytecode_hash(bytecode, 1);
+        let program = TestProgram::new_checked(bytecode, hash, false).unwrap();
+        assert_eq!(program.code_page().len(), 1);
+        // The marker byte is ignored.
+        let mut constructing_hash = hash;
+        constructing_hash.0[3] |= 1 << 48;
+        TestProgram::new_checked(bytecode, constructing_hash, false).unwrap();
+
+        let wrong_hash = hash ^ U256::one();
+        assert_eq!(
+            TestProgram::new_checked(bytecode, wrong_hash, false).unwrap_err(),
+            InvalidBytecode::HashMismatch {
+                expected: wrong_hash,
+                actual: hash,
+            }
+        );
+        assert_eq!(
+            TestProgram::new_checked(&bytecode[..31], hash, false).unwrap_err(),
+            InvalidBytecode::UnalignedLength(31)
+        );
+        assert_eq!(
+            TestProgram::new_checked(&[0; 64], hash, false).unwrap_err(),
+            InvalidBytecode::EvenWordCount(2)
+        );
+        // The unchecked constructor accepts the same bytecode.
+        let program = TestProgram::new_unchecked(&[0; 64], false);
+        assert_eq!(program.code_page().len(), 2);
+    }
 }
@@ -0,0 +1,300 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use primitive_types::U256;
+
+use crate::{metrics::Metrics, Program};
+
+/// Magic bytes starting the [persisted](ProgramCache::save()) cache, including the format version.
+const FILE_MAGIC: &[u8; 8] = b"vm2pc\0\0\x01";
+
+/// Thread-safe cache of decoded [`Program`]s keyed by bytecode hash.
+///
+/// Decoding a large contract allocates and fills megabytes of instructions; since [`Program`]s are immutable
+/// and cheap to clone, a cache shared (e.g., via an [`Arc`](std::sync::Arc)) by [`World`](crate::World)s of
+/// concurrently running VMs allows to decode each bytecode only once. The cache has interior mutability,
+/// so it can be populated through a shared reference from within [`World::decommit()`](crate::World::decommit()).
+///
+/// A cache can be [saved](Self::save()) to a file and [loaded](Self::load()) on startup, so that a restarted process
+/// doesn't need to fetch the bytecodes of frequently used contracts again.
+#[derive(Debug)]
+pub struct ProgramCache<T, W> {
+    programs: RwLock<HashMap<U256, Program<T, W>>>,
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+impl<T, W> Default for ProgramCache<T, W> {
+    fn default() -> Self {
+        Self {
+            programs: RwLock::default(),
+            metrics: None,
+        }
+    }
+}
+
+impl<T, W> ProgramCache<T, W> {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty cache reporting [lookups](Metrics::program_cache_lookup()) by [`Self::get_or_insert_with()`]
+    /// to the provided metrics.
+    pub fn with_metrics(metrics: Arc<dyn Metrics>) -> Self {
+        Self {
+            programs: RwLock::default(),
+            metrics: Some(metrics),
+        }
+    }
+
+    /// Returns the cached program for the specified bytecode hash.
+    pub fn get(&self, hash: U256) -> Option<Program<T, W>> {
+        // The map is never left in an inconsistent state, so it's safe to ignore poisoning.
+        let programs = self.programs.read().unwrap_or_else(PoisonError::into_inner);
+        programs.get(&hash).cloned()
+    }
+
+    /// Returns the cached program for the specified bytecode hash, or caches the program returned by `decode`.
+    ///
+    /// `decode` is called without holding a lock, so it can be called concurrently for the same hash by several
+    /// threads. In this case, only the first decoded program is cached and returned to all callers.
+    pub fn get_or_insert_with(
+        &self,
+        hash: U256,
+        decode: impl FnOnce() -> Program<T, W>,
+    ) -> Program<T, W> {
+        let cached = self.get(hash);
+        if let Some(metrics) = &self.metrics {
+            metrics.program_cache_lookup(cached.is_some());
+        }
+        if let Some(program) = cached {
+            return program;
+        }
+        let program = decode();
+        let mut programs = self
+            .programs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        programs.entry(hash).or_insert(program).clone()
+    }
+
+    /// Returns the number of cached programs.
+    pub fn len(&self) -> usize {
+        self.programs
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Checks whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the code pages of all cached programs to `writer`, so that they can be [loaded](Self::load()) later.
+    ///
+    /// Decoded instructions contain function pointers, which cannot be persisted; thus, only code pages are saved,
+    /// and programs not created from bytecode (e.g., using [`Program::from_raw()`]) cannot be restored faithfully.
+    /// The writer is not buffered, so it's advisable to wrap files into [`BufWriter`](io::BufWriter).
+    ///
+    /// # Errors
+    ///
+    /// Propagates I/O errors.
+    pub fn save(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let programs = self.programs.read().unwrap_or_else(PoisonError::into_inner);
+        writer.write_all(FILE_MAGIC)?;
+        let mut buffer = [0_u8; 32];
+        for (hash, program) in programs.iter() {
+            hash.to_big_endian(&mut buffer);
+            writer.write_all(&buffer)?;
+            let len = u32::try_from(program.code_page().len()).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "code page is too large")
+            })?;
+            writer.write_all(&len.to_be_bytes())?;
+            for word in program.code_page().iter() {
+                word.to_big_endian(&mut buffer);
+                writer.write_all(&buffer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads programs [saved](Self::save()) to `reader`, decoding them using `decode` called with the bytecode hash
+    /// and the code page of each program (e.g., `|_, words| Program::from_words(words, false)`). Programs already
+    /// present in this cache are not replaced. Returns the number of loaded programs.
+    ///
+    /// The loaded data is trusted, i.e., code pages are not checked to match their hashes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a saved cache or is truncated, and propagates I/O errors.
+    pub fn load(
+        &self,
+        mut reader: impl io::Read,
+        mut decode: impl FnMut(U256, Vec<U256>) -> Program<T, W>,
+    ) -> io::Result<usize> {
+        let mut magic = [0_u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != *FILE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a saved program cache",
+            ));
+        }
+
+        let mut count = 0;
+        let mut decoded = vec![];
+        let mut buffer = [0_u8; 32];
+        loop {
+            // The end of data is only allowed at an entry boundary.
+            if reader.read(&mut buffer[..1])? == 0 {
+                break;
+            }
+            reader.read_exact(&mut buffer[1..])?;
+            let hash = U256::from_big_endian(&buffer);
+            let mut len = [0_u8; 4];
+            reader.read_exact(&mut len)?;
+            let len = u32::from_be_bytes(len);
+            // Don't trust the length for preallocation; it could be corrupted.
+            let mut code_page = Vec::with_capacity(len.min(1 << 16) as usize);
+            for _ in 0..len {
+                reader.read_exact(&mut buffer)?;
+                code_page.push(U256::from_big_endian(&buffer));
+            }
+            count += 1;
+            // Like in `get_or_insert_with()`, programs are decoded without holding a lock.
+            if self.get(hash).is_none() {
+                decoded.push((hash, code_page));
+            }
+        }
+
+        let decoded: Vec<_> = decoded
+            .into_iter()
+            .map(|(hash, code_page)| (hash, decode(hash, code_page)))
+            .collect();
+        let mut programs = self
+            .programs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (hash, program) in decoded {
+            programs.entry(hash).or_insert(program);
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::*;
+    use crate::{metrics::AtomicMetrics, testonly::TestWorld, Instruction};
+
+    type TestProgram = Program<(), TestWorld<()>>;
+
+    #[test]
+    fn caching_programs() {
+        let cache = ProgramCache::new();
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(1.into()), None);
+
+        let program = cache.get_or_insert_with(1.into(), || TestProgram::from_raw(vec![], vec![]));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(1.into()), Some(program.clone()));
+        let cached = cache.get_or_insert_with(1.into(), || unreachable!("program must be cached"));
+        assert_eq!(cached, program);
+
+        let other_program = cache.get_or_insert_with(2.into(), || {
+            TestProgram::from_raw(vec![Instruction::from_invalid()], vec![])
+        });
+        assert_eq!(cache.len(), 2);
+        assert_ne!(other_program, program);
+    }
+
+    #[test]
+    fn sharing_cache_among_threads() {
+        let cache = ProgramCache::new();
+        let decode_count = AtomicUsize::new(0);
+        let programs: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        cache.get_or_insert_with(U256::one(), || {
+                            decode_count.fetch_add(1, Ordering::Relaxed);
+                            TestProgram::from_raw(vec![], vec![U256::MAX])
+                        })
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        assert!(decode_count.into_inner() >= 1);
+        assert_eq!(cache.len(), 1);
+        // All threads must get the same program, even if it was decoded concurrently.
+        for program in &programs {
+            assert_eq!(*program, programs[0]);
+        }
+        assert_eq!(programs[0].code_page(), [U256::MAX]);
+    }
+
+    #[test]
+    fn saving_and_loading_cache() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far");
+        let cache = ProgramCache::new();
+        let program = cache.get_or_insert_with(1.into(), || TestProgram::new(bytecode, false));
+        cache.get_or_insert_with(2.into(), || TestProgram::from_raw(vec![], vec![U256::MAX]));
+        let mut saved = vec![];
+        cache.save(&mut saved).unwrap();
+
+        let loaded_cache = ProgramCache::new();
+        let existing =
+            loaded_cache.get_or_insert_with(2.into(), || TestProgram::from_raw(vec![], vec![]));
+        let count = loaded_cache
+            .load(saved.as_slice(), |_, words| {
+                TestProgram::from_words(words, false)
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(loaded_cache.len(), 2);
+        let loaded = loaded_cache.get(1.into()).unwrap();
+        assert_eq!(loaded.code_page(), program.code_page());
+        // Already cached programs are not replaced.
+        assert_eq!(loaded_cache.get(2.into()), Some(existing));
+    }
+
+    #[test]
+    fn loading_invalid_cache() {
+        let cache = ProgramCache::<(), TestWorld<()>>::new();
+        let decode = |_: U256, words: Vec<U256>| TestProgram::from_words(words, false);
+        let err = cache.load(&b"not a cache"[..], decode).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut truncated = FILE_MAGIC.to_vec();
+        truncated.extend_from_slice(&[0; 33]);
+        let err = cache.load(truncated.as_slice(), decode).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(cache.is_empty());
+
+        assert_eq!(cache.load(&FILE_MAGIC[..], decode).unwrap(), 0);
+    }
+
+    #[test]
+    fn reporting_cache_lookups() {
+        let metrics = Arc::new(AtomicMetrics::default());
+        let cache = ProgramCache::with_metrics(metrics.clone());
+        for _ in 0..3 {
+            cache.get_or_insert_with(1.into(), || TestProgram::from_raw(vec![], vec![]));
+        }
+        assert_eq!(metrics.program_cache_lookups(), (2, 1));
+    }
+}
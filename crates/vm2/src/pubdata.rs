@@ -0,0 +1,150 @@
+//! Pubdata cost model for storage changes.
+//!
+//! Storage changes are published in a compressed form. The slot is identified either by its full key
+//! (for initial writes) or by its much shorter enumeration index (for repeated writes). The new value is encoded
+//! relative to the value at the start of the block using the shortest of the supported [`ValueCompression`]s.
+//! [`World`](crate::World) implementations may use [`storage_write_pubdata()`] to price storage writes
+//! in [`StorageInterface::cost_of_writing_storage()`](crate::StorageInterface::cost_of_writing_storage()).
+
+use primitive_types::U256;
+
+/// Number of bytes identifying a slot on its initial write, i.e. the length of the hashed storage key.
+pub const INITIAL_WRITE_KEY_BYTES: u32 = 32;
+/// Number of bytes identifying a slot on a repeated write, i.e. the length of its enumeration index.
+pub const REPEATED_WRITE_KEY_BYTES: u32 = 4;
+
+/// Way a new storage value is encoded relative to the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueCompression {
+    /// The new value is published as is, in 32 bytes.
+    None,
+    /// The difference `after - before` (wrapping) is published.
+    Add,
+    /// The difference `before - after` (wrapping) is published.
+    Sub,
+    /// The new value is published with leading zero bytes stripped.
+    Transform,
+}
+
+impl ValueCompression {
+    fn id(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Add => 1,
+            Self::Sub => 2,
+            Self::Transform => 3,
+        }
+    }
+}
+
+/// Compressed encoding of a storage value change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressedValue {
+    /// Applied compression.
+    pub compression: ValueCompression,
+    /// Length of the compressed payload in bytes. Always less than 32 unless no compression is applied.
+    pub len: u8,
+}
+
+impl CompressedValue {
+    /// Chooses the shortest encoding of the change from `before` to `after`. On ties, [`ValueCompression::Add`]
+    /// is preferred to [`ValueCompression::Sub`], which is preferred to [`ValueCompression::Transform`].
+    pub fn new(before: U256, after: U256) -> Self {
+        let mut compression = ValueCompression::Add;
+        let mut len = byte_len(after.overflowing_sub(before).0);
+        for (candidate, payload) in [
+            (ValueCompression::Sub, before.overflowing_sub(after).0),
+            (ValueCompression::Transform, after),
+        ] {
+            let candidate_len = byte_len(payload);
+            if candidate_len < len {
+                compression = candidate;
+                len = candidate_len;
+            }
+        }
+
+        // The payload length must fit into 5 bits of the metadata byte.
+        if len < 32 {
+            Self { compression, len }
+        } else {
+            Self {
+                compression: ValueCompression::None,
+                len: 32,
+            }
+        }
+    }
+
+    /// Returns the metadata byte preceding the payload: the compression ID in the lower 3 bits
+    /// and the payload length in the upper 5 bits. For uncompressed values, the length is omitted.
+    pub fn metadata_byte(self) -> u8 {
+        match self.compression {
+            ValueCompression::None => ValueCompression::None.id(),
+            compression => (self.len << 3) | compression.id(),
+        }
+    }
+
+    /// Returns the number of published bytes for the value, including the metadata byte.
+    pub fn encoded_len(self) -> u32 {
+        1 + u32::from(self.len)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)] // `U256::bits()` is at most 256
+fn byte_len(value: U256) -> u8 {
+    value.bits().div_ceil(8) as u8
+}
+
+/// Returns the number of pubdata bytes published for a storage change, including the slot identifier.
+///
+/// `before` is the value of the slot at the start of the block, and `is_initial` specifies whether
+/// the slot was never written to before (cf. [`StorageSlot::is_write_initial`](crate::StorageSlot::is_write_initial)).
+pub fn storage_write_pubdata(is_initial: bool, before: U256, after: U256) -> u32 {
+    let key_bytes = if is_initial {
+        INITIAL_WRITE_KEY_BYTES
+    } else {
+        REPEATED_WRITE_KEY_BYTES
+    };
+    key_bytes + CompressedValue::new(before, after).encoded_len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_changes_are_compressed() {
+        let before = U256::from(1_000);
+        let value = CompressedValue::new(before, before + 1);
+        assert_eq!(value.compression, ValueCompression::Add);
+        assert_eq!(value.len, 1);
+        assert_eq!(value.metadata_byte(), (1 << 3) | 1);
+
+        let value = CompressedValue::new(before, before - 300);
+        assert_eq!(value.compression, ValueCompression::Sub);
+        assert_eq!(value.len, 2);
+
+        let value = CompressedValue::new(U256::one() << 200, 5.into());
+        assert_eq!(value.compression, ValueCompression::Transform);
+        assert_eq!(value.len, 1);
+
+        let value = CompressedValue::new(before, before);
+        assert_eq!(value.compression, ValueCompression::Add);
+        assert_eq!(value.encoded_len(), 1);
+    }
+
+    #[test]
+    fn large_changes_are_not_compressed() {
+        let value = CompressedValue::new(U256::zero(), U256::MAX >> 1);
+        assert_eq!(value.compression, ValueCompression::None);
+        assert_eq!(value.len, 32);
+        assert_eq!(value.metadata_byte(), 0);
+        assert_eq!(value.encoded_len(), 33);
+    }
+
+    #[test]
+    fn pubdata_depends_on_write_initialness() {
+        assert_eq!(storage_write_pubdata(true, 0.into(), 1.into()), 32 + 2);
+        assert_eq!(storage_write_pubdata(false, 0.into(), 1.into()), 4 + 2);
+    }
+}
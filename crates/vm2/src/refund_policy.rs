@@ -0,0 +1,97 @@
+//! Rules for crediting gas refunds.
+
+/// Rules for crediting refunds of warm storage accesses and repeated decommitments to the executing frames.
+///
+/// By default, a refund is added to the gas of the frame incurring it right away, which is how ZKsync Era works.
+/// With a [deferred](Self::deferred()) policy, refunds are accumulated per far call frame instead and are credited
+/// to the caller together with the leftover gas when the frame returns successfully; refunds of a frame that
+/// reverts or panics are dropped, as are refunds incurred in a near call that reverts or panics.
+/// Additionally, refunds credited on return can be [capped](Self::with_ceiling()) by a fraction of gas spent
+/// by the returning frame, which prevents frames from refunding more than they have paid for.
+///
+/// A policy can be provided when building a VM using
+/// [`VirtualMachineBuilder::refund_policy()`](crate::VirtualMachineBuilder::refund_policy()).
+/// This is useful to replay or experiment with protocol versions having different refund rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefundPolicy {
+    deferred: bool,
+    ceiling_quotient: Option<u32>,
+}
+
+impl RefundPolicy {
+    /// Credits refunds immediately, as done by ZKsync Era.
+    pub const fn immediate() -> Self {
+        Self {
+            deferred: false,
+            ceiling_quotient: None,
+        }
+    }
+
+    /// Credits refunds to the caller when the frame incurring them returns successfully. Refunds are not capped.
+    pub const fn deferred() -> Self {
+        Self {
+            deferred: true,
+            ceiling_quotient: None,
+        }
+    }
+
+    /// Caps refunds credited on return to `1 / quotient` of gas spent by the returning frame, i.e. the difference
+    /// between the gas the frame has started with (including stipends) and its leftover gas. Refunds of
+    /// callee frames credited to the frame on their return are included into its leftover gas, so they are
+    /// not paid twice. Has no effect on immediately credited refunds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quotient` is zero.
+    #[must_use]
+    pub fn with_ceiling(mut self, quotient: u32) -> Self {
+        assert!(quotient > 0, "refund ceiling quotient must be positive");
+        self.ceiling_quotient = Some(quotient);
+        self
+    }
+
+    /// Checks whether refunds are credited on return.
+    pub fn is_deferred(&self) -> bool {
+        self.deferred
+    }
+
+    /// Returns the quotient set by [`Self::with_ceiling()`], if any.
+    pub fn ceiling_quotient(&self) -> Option<u32> {
+        self.ceiling_quotient
+    }
+
+    /// Returns the part of `pending_refund` accumulated by a frame that is credited to the caller,
+    /// given the gas spent by the frame.
+    pub fn credited_refund(&self, pending_refund: u32, gas_spent: u32) -> u32 {
+        match self.ceiling_quotient {
+            Some(quotient) => pending_refund.min(gas_spent / quotient),
+            None => pending_refund,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crediting_refunds() {
+        let policy = RefundPolicy::deferred();
+        assert!(policy.is_deferred());
+        assert_eq!(policy.credited_refund(5_000, 100), 5_000);
+
+        let policy = policy.with_ceiling(5);
+        assert_eq!(policy.ceiling_quotient(), Some(5));
+        assert_eq!(policy.credited_refund(5_000, 100), 20);
+        assert_eq!(policy.credited_refund(5_000, 30_000), 5_000);
+        assert_eq!(policy.credited_refund(5_000, 4), 0);
+        assert_eq!(RefundPolicy::default(), RefundPolicy::immediate());
+        assert!(!RefundPolicy::immediate().is_deferred());
+    }
+
+    #[test]
+    #[should_panic(expected = "quotient must be positive")]
+    fn zero_ceiling_quotient() {
+        let _ = RefundPolicy::deferred().with_ceiling(0);
+    }
+}
@@ -0,0 +1,148 @@
+//! Comparison of instruction-level traces produced by different VM builds.
+//!
+//! When validating a refactoring of the VM (e.g., of instruction handlers), it's useful to run the same workload
+//! on the refactored VM and on a previous version of this crate, and to find the first executed instruction
+//! where their behavior differs. Each VM build is wrapped into a [`ReplayTarget`], which runs the workload
+//! and records it as [`StructLog`]s. A previous version of the crate can be added as a renamed dependency;
+//! its target then converts the logs of that version into the logs of this one.
+//!
+//! The `compare_traces` example (`cargo run --example compare_traces -- <LEFT> <RIGHT>`) runs two bytecode files
+//! and prints the first differing step.
+
+use std::fmt;
+
+use crate::tracers::StructLog;
+
+/// VM build able to run a workload and record executed instructions. Used via dynamic dispatch
+/// by [`compare_targets()`], so that targets can wrap different versions of this crate.
+pub trait ReplayTarget {
+    /// Human-readable name of the target used in reports, e.g. the crate version.
+    fn name(&self) -> &str;
+
+    /// Runs the workload, returning logs of all executed instructions.
+    fn run(&mut self) -> Vec<StructLog>;
+}
+
+/// First step at which two traces differ, as returned by [`first_divergence()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceDivergence {
+    /// 0-based index of the differing step.
+    pub step: usize,
+    /// Log of the step in the first trace, or `None` if the trace has ended.
+    pub left: Option<StructLog>,
+    /// Log of the step in the second trace, or `None` if the trace has ended.
+    pub right: Option<StructLog>,
+}
+
+/// Outputs the step index followed by JSON logs of the step in the first (`-`) and the second (`+`) trace.
+impl fmt::Display for TraceDivergence {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(formatter, "traces diverge at step {}", self.step)?;
+        for (prefix, log) in [("-", &self.left), ("+", &self.right)] {
+            match log {
+                Some(log) => writeln!(formatter, "{prefix} {}", log.to_json())?,
+                None => writeln!(formatter, "{prefix} (trace has ended)")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the first step at which the traces differ. Returns `None` if the traces are equal.
+pub fn first_divergence(left: &[StructLog], right: &[StructLog]) -> Option<TraceDivergence> {
+    let step = left
+        .iter()
+        .zip(right)
+        .position(|(left, right)| left != right)
+        .unwrap_or(left.len().min(right.len()));
+    if step == left.len() && step == right.len() {
+        return None;
+    }
+    Some(TraceDivergence {
+        step,
+        left: left.get(step).cloned(),
+        right: right.get(step).cloned(),
+    })
+}
+
+/// Runs both targets and compares their traces. Returns the divergence and the names of the targets
+/// (in the order they are provided), or `None` if the traces are equal.
+pub fn compare_targets(
+    left: &mut dyn ReplayTarget,
+    right: &mut dyn ReplayTarget,
+) -> Option<(TraceDivergence, [String; 2])> {
+    let divergence = first_divergence(&left.run(), &right.run())?;
+    Some((
+        divergence,
+        [left.name().to_owned(), right.name().to_owned()],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+    use zksync_vm2_interface::Opcode;
+
+    use super::*;
+
+    fn log(pc: u16, op: Opcode) -> StructLog {
+        StructLog {
+            pc: Some(pc),
+            op,
+            gas: 100,
+            gas_cost: 6,
+            depth: 1,
+            registers: [U256::zero(); 16],
+            heap_bound: 0,
+            aux_heap_bound: 0,
+            storage: None,
+        }
+    }
+
+    struct FixedTrace(&'static str, Vec<StructLog>);
+
+    impl ReplayTarget for FixedTrace {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn run(&mut self) -> Vec<StructLog> {
+            self.1.clone()
+        }
+    }
+
+    #[test]
+    fn finding_first_divergence() {
+        let trace = [
+            log(0, Opcode::Add),
+            log(1, Opcode::Jump),
+            log(5, Opcode::Nop),
+        ];
+        assert_eq!(first_divergence(&trace, &trace), None);
+
+        let mut changed = trace.clone();
+        changed[1].gas_cost = 7;
+        let divergence = first_divergence(&trace, &changed).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.left.as_ref(), Some(&trace[1]));
+        assert_eq!(divergence.right.as_ref(), Some(&changed[1]));
+
+        let divergence = first_divergence(&trace, &trace[..2]).unwrap();
+        assert_eq!(divergence.step, 2);
+        assert_eq!(divergence.right, None);
+        assert!(divergence.to_string().ends_with("+ (trace has ended)\n"));
+    }
+
+    #[test]
+    fn comparing_targets() {
+        let trace = vec![log(0, Opcode::Add), log(1, Opcode::Jump)];
+        let mut old = FixedTrace("old", trace.clone());
+        let mut new = FixedTrace("new", trace[..1].to_vec());
+        let (divergence, names) = compare_targets(&mut old, &mut new).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(names, ["old", "new"]);
+
+        let mut same = FixedTrace("same", trace);
+        assert_eq!(compare_targets(&mut old, &mut same), None);
+    }
+}
@@ -8,7 +8,7 @@ pub(crate) trait Rollback {
     fn delete_history(&mut self);
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct RollbackableMap<K: Ord, V> {
     map: BTreeMap<K, V>,
     old_entries: Vec<(K, Option<V>)>,
@@ -64,7 +64,7 @@ impl<K: Ord, V> AsRef<BTreeMap<K, V>> for RollbackableMap<K, V> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct RollbackableSet<K: Ord> {
     map: BTreeSet<K>,
     old_entries: Vec<K>,
@@ -105,7 +105,7 @@ impl<K: Ord> AsRef<BTreeSet<K>> for RollbackableSet<K> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct RollbackableLog<T> {
     entries: Vec<T>,
 }
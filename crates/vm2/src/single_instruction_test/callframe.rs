@@ -37,9 +37,14 @@ impl<'a, T: Tracer, W: World<T>> Arbitrary<'a> for Callframe<T, W> {
             context_u128: u.arbitrary()?,
             is_static: u.arbitrary()?,
             is_kernel: is_kernel(address),
+            shard_id: 0,
+            caller_shard_id: 0,
             stack: Box::new(Stack::new_arbitrary(u, calldata_heap, base_page)?),
             sp: u.arbitrary()?,
             gas: u.arbitrary()?,
+            // Refunds are always immediate in these tests, so the initial gas doesn't matter.
+            initial_gas: 0,
+            pending_refund: 0,
             near_calls: vec![],
             pc: program.instruction(0).unwrap(),
             program,
@@ -72,9 +77,13 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
             context_u128: 0,
             is_static: false,
             is_kernel: false,
+            shard_id: 0,
+            caller_shard_id: 0,
             stack: StackPool {}.get(),
             sp: 0,
             gas: 0,
+            initial_gas: 0,
+            pending_refund: 0,
             near_calls: vec![],
             pc: std::ptr::null(),
             program: Program::for_decommit(),
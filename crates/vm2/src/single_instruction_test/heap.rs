@@ -1,10 +1,11 @@
-use std::ops::Index;
+use std::{ops::Index, sync::Arc};
 
 use arbitrary::Arbitrary;
 use primitive_types::U256;
 use zksync_vm2_interface::HeapId;
 
 use super::mock_array::MockRead;
+use crate::allocator::Allocator;
 
 #[derive(Debug, Clone)]
 pub struct Heap {
@@ -23,6 +24,10 @@ impl Heap {
         unimplemented!()
     }
 
+    pub(crate) fn read_into(&self, _: u32, _: &mut [u8]) {
+        unimplemented!()
+    }
+
     pub(crate) fn read_u256(&self, start_address: u32) -> U256 {
         assert!(self.write.is_none());
         U256::from_little_endian(self.read.get(start_address))
@@ -43,6 +48,13 @@ impl Heap {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct HeapSnapshot(pub(crate) Heap);
+
+pub fn heap_diff(_: &HeapSnapshot, _: &HeapSnapshot) -> Vec<(u32, u8, u8)> {
+    unimplemented!()
+}
+
 impl<'a> Arbitrary<'a> for Heap {
     fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
         Ok(Self {
@@ -64,6 +76,10 @@ impl Heaps {
         unimplemented!("Should use arbitrary heap, not fresh heap in testing.")
     }
 
+    pub(crate) fn with_allocator(_: &[u8], _: Option<Arc<dyn Allocator>>) -> Self {
+        unimplemented!("Should use arbitrary heap, not fresh heap in testing.")
+    }
+
     pub(crate) fn allocate(&mut self) -> HeapId {
         self.heap_id
     }
@@ -76,6 +92,10 @@ impl Heaps {
         id
     }
 
+    pub(crate) fn contains(&self, _: HeapId) -> bool {
+        true
+    }
+
     pub(crate) fn deallocate(&mut self, _: HeapId) {}
 
     pub(crate) fn from_id(
@@ -103,6 +123,10 @@ impl Heaps {
     pub(crate) fn delete_history(&mut self) {
         unimplemented!()
     }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        0
+    }
 }
 
 impl Index<HeapId> for Heaps {
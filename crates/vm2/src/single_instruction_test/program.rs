@@ -5,16 +5,20 @@ use primitive_types::U256;
 use zksync_vm2_interface::Tracer;
 
 use super::mock_array::MockRead;
-use crate::{decode::decode, Instruction, World};
+use crate::{
+    decode::decode,
+    instruction::{CompactInstruction, Handler, HandlerTable},
+    GasCosts, Instruction, World,
+};
 
 #[derive(Debug)]
 pub struct Program<T, W> {
     pub raw_first_instruction: u64,
 
     // Need a two-instruction array so that incrementing the program counter is safe
-    first_instruction: MockRead<u16, Rc<[Instruction<T, W>; 2]>>,
-    #[allow(clippy::type_complexity)]
-    other_instruction: MockRead<u16, Rc<Option<[Instruction<T, W>; 2]>>>,
+    first_instruction: MockRead<u16, Rc<[CompactInstruction; 2]>>,
+    other_instruction: MockRead<u16, Rc<Option<[CompactInstruction; 2]>>>,
+    handlers: Rc<[Handler<T, W>]>,
 
     code_page: Arc<[U256]>,
 }
@@ -25,6 +29,7 @@ impl<T, W> Clone for Program<T, W> {
             raw_first_instruction: self.raw_first_instruction,
             first_instruction: self.first_instruction.clone(),
             other_instruction: self.other_instruction.clone(),
+            handlers: self.handlers.clone(),
             code_page: self.code_page.clone(),
         }
     }
@@ -33,24 +38,19 @@ impl<T, W> Clone for Program<T, W> {
 impl<'a, T: Tracer, W: World<T>> Arbitrary<'a> for Program<T, W> {
     fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
         let raw_first_instruction = u.arbitrary()?;
+        let has_other_instruction = u.arbitrary::<bool>()?;
 
-        Ok(Self {
+        Ok(Self::new(
             raw_first_instruction,
-            first_instruction: MockRead::new(Rc::new([
-                decode(raw_first_instruction, false),
-                Instruction::from_invalid(),
-            ])),
-            other_instruction: MockRead::new(Rc::new(
-                u.arbitrary::<bool>()?
-                    .then_some([Instruction::from_invalid(), Instruction::from_invalid()]),
-            )),
-            code_page: [u.arbitrary()?; 1].into(),
-        })
+            decode(raw_first_instruction, false, &GasCosts::new()),
+            has_other_instruction.then(Instruction::from_invalid),
+            [u.arbitrary()?; 1].into(),
+        ))
     }
 }
 
 impl<T, W> Program<T, W> {
-    pub fn instruction(&self, n: u16) -> Option<&Instruction<T, W>> {
+    pub fn instruction(&self, n: u16) -> Option<&CompactInstruction> {
         if n == 0 {
             Some(&self.first_instruction.get(n).as_ref()[0])
         } else {
@@ -62,40 +62,66 @@ impl<T, W> Program<T, W> {
         }
     }
 
+    pub(crate) fn handler(&self, instruction: &CompactInstruction) -> Handler<T, W> {
+        self.handlers[usize::from(instruction.handler)]
+    }
+
     pub fn code_page(&self) -> &Arc<[U256]> {
         &self.code_page
     }
+
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.code_page).cast::<u8>() as usize
+    }
+
+    pub(crate) fn memory_size(&self) -> usize {
+        self.code_page.len() * 32
+    }
 }
 
 impl<T: Tracer, W: World<T>> Program<T, W> {
-    pub fn for_decommit() -> Self {
+    fn new(
+        raw_first_instruction: u64,
+        first_instruction: Instruction<T, W>,
+        other_instruction: Option<Instruction<T, W>>,
+        code_page: Arc<[U256]>,
+    ) -> Self {
+        let mut handlers = HandlerTable::new();
+        let first_instruction = [
+            handlers.compact(first_instruction),
+            handlers.compact(Instruction::from_invalid()),
+        ];
+        let other_instruction = other_instruction.map(|instruction| {
+            [
+                handlers.compact(instruction),
+                handlers.compact(Instruction::from_invalid()),
+            ]
+        });
         Self {
-            raw_first_instruction: 0,
-            first_instruction: MockRead::new(Rc::new([
-                Instruction::from_invalid(),
-                Instruction::from_invalid(),
-            ])),
-            other_instruction: MockRead::new(Rc::new(Some([
-                Instruction::from_invalid(),
-                Instruction::from_invalid(),
-            ]))),
-            code_page: Arc::new([U256::zero(); 1]),
+            raw_first_instruction,
+            first_instruction: MockRead::new(Rc::new(first_instruction)),
+            other_instruction: MockRead::new(Rc::new(other_instruction)),
+            handlers: handlers.into_handlers().into(),
+            code_page,
         }
     }
 
+    pub fn for_decommit() -> Self {
+        Self::new(
+            0,
+            Instruction::from_invalid(),
+            Some(Instruction::from_invalid()),
+            Arc::new([U256::zero(); 1]),
+        )
+    }
+
     pub(crate) fn new_panicking() -> Self {
-        Self {
-            raw_first_instruction: 0xBAD,
-            first_instruction: MockRead::new(Rc::new([
-                Instruction::from_spontaneous_panic(),
-                Instruction::from_invalid(),
-            ])),
-            other_instruction: MockRead::new(Rc::new(Some([
-                Instruction::from_invalid(),
-                Instruction::from_invalid(),
-            ]))),
-            code_page: Arc::new([U256::zero(); 1]),
-        }
+        Self::new(
+            0xBAD,
+            Instruction::from_spontaneous_panic(),
+            Some(Instruction::from_invalid()),
+            Arc::new([U256::zero(); 1]),
+        )
     }
 }
 
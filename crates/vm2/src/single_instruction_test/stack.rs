@@ -1,9 +1,12 @@
+use std::sync::Arc;
+
 use primitive_types::U256;
 use zksync_vm2_interface::HeapId;
 
 use super::{
     mock_array::MockRead, validation::is_valid_tagged_value, vm::arbitrary_register_value,
 };
+use crate::allocator::Allocator;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Stack {
@@ -15,6 +18,17 @@ pub struct Stack {
 
 #[allow(clippy::unused_self)] // to align signatures with real implementation
 impl Stack {
+    pub(crate) fn new() -> Box<Self> {
+        // A single instruction shouldn't be able to touch a new stack
+        // but the stack is set to already written just in case.
+        Box::new(Self {
+            read: MockRead::new((U256::zero(), false)),
+            slot_written: Some(45678),
+            value_written: U256::zero(),
+            pointer_tag_written: false,
+        })
+    }
+
     pub(crate) fn new_arbitrary(
         u: &mut arbitrary::Unstructured,
         calldata_heap: HeapId,
@@ -84,23 +98,30 @@ impl Stack {
     }
 }
 
+pub(crate) type StackBox = Box<Stack>;
+
 #[derive(Default, Debug)]
 pub struct StackPool {}
 
 impl StackPool {
-    pub fn get(&mut self) -> Box<Stack> {
-        // A single instruction shouldn't be able to touch a new stack
-        // but the stack is set to already written just in case.
-        Box::new(Stack {
-            read: MockRead::new((U256::zero(), false)),
-            slot_written: Some(45678),
-            value_written: U256::zero(),
-            pointer_tag_written: false,
-        })
+    pub(crate) fn new(_: Option<Arc<dyn Allocator>>) -> Self {
+        Self {}
     }
 
-    pub fn recycle(&mut self, _: Box<Stack>) {}
+    pub fn get(&mut self) -> StackBox {
+        Stack::new()
+    }
+
+    pub fn recycle(&mut self, _: StackBox) {}
+
+    pub(crate) fn allocator(&self) -> Option<&Arc<dyn Allocator>> {
+        None
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        0
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct StackSnapshot;
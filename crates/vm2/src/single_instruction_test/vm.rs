@@ -4,14 +4,16 @@ use zksync_vm2_interface::{HeapId, Tracer};
 
 use super::{heap::Heaps, stack::StackPool};
 use crate::{
-    callframe::Callframe, fat_pointer::FatPointer, state::State, Settings, VirtualMachine, World,
-    WorldDiff,
+    callframe::{Callframe, FrameBufferPool},
+    fat_pointer::FatPointer,
+    state::State,
+    RefundPolicy, Settings, VirtualMachine, World, WorldDiff,
 };
 
 impl<T: Tracer, W> VirtualMachine<T, W> {
     pub fn run_single_instruction(&mut self, world: &mut W, tracer: &mut T) {
         unsafe {
-            ((*self.state.current_frame.pc).handler)(self, world, tracer);
+            self.current_handler()(self, world, tracer);
         }
     }
 
@@ -63,23 +65,36 @@ impl<'a, T: Tracer, W: World<T>> Arbitrary<'a> for VirtualMachine<T, W> {
 
         let heaps = Heaps::from_id(current_frame.heap, u)?;
 
+        // Exiting the final frame is different in vm2 on purpose,
+        // so always generate two frames to avoid that.
+        let previous_frames = vec![Callframe::dummy()];
+
         Ok(Self {
             state: State {
                 registers,
                 register_pointer_flags,
                 flags: u.arbitrary()?,
                 current_frame,
-                // Exiting the final frame is different in vm2 on purpose,
-                // so always generate two frames to avoid that.
-                previous_frames: vec![Callframe::dummy()],
+                previous_frames,
                 heaps,
                 transaction_number: u.arbitrary()?,
                 context_u128: u.arbitrary()?,
+                ergs_per_pubdata_byte: 0,
             },
             settings: u.arbitrary()?,
             world_diff: WorldDiff::default(),
             stack_pool: StackPool {},
+            frame_buffers: FrameBufferPool::default(),
             snapshot: None,
+            refund_policy: RefundPolicy::immediate(),
+            // `zk_evm` doesn't charge for pubdata, so charging must stay disabled to match it.
+            charge_for_pubdata: false,
+            memory_limit: None,
+            programs_in_use: None,
+            precompiles: None,
+            metrics: None,
+            pending_panic: None,
+            last_panic: None,
         })
     }
 }
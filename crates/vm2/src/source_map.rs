@@ -0,0 +1,192 @@
+//! Source maps linking program counters to source code locations.
+
+use std::{collections::BTreeMap, error, fmt, str::FromStr, sync::Arc};
+
+/// Location in the source code of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// Path to the source file as specified by the compiler.
+    pub file: Arc<str>,
+    /// 1-based line number.
+    pub line: u32,
+    /// 1-based column number, or 0 if unknown.
+    pub column: u32,
+}
+
+/// Formats the location as `file:line` or `file:line:column`.
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}:{}", self.file, self.line)?;
+        if self.column > 0 {
+            write!(formatter, ":{}", self.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error parsing a [`SourceMap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSourceMapError {
+    line_number: usize,
+    line: String,
+}
+
+impl fmt::Display for ParseSourceMapError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "invalid source map entry on line {}: {:?}",
+            self.line_number, self.line
+        )
+    }
+}
+
+impl error::Error for ParseSourceMapError {}
+
+/// Mapping from program counters of a [`Program`](crate::Program) to source code locations,
+/// e.g. obtained from the debug info emitted by `zksolc`.
+///
+/// Compilers usually only emit a location when it changes, so an instruction is mapped to the location of the closest
+/// preceding mapped instruction (including the instruction itself). A source map can be attached to a program
+/// via [`Program::with_source_map()`](crate::Program::with_source_map()) or provided to
+/// [`SourceMapTracer`](crate::tracers::SourceMapTracer).
+///
+/// # Text format
+///
+/// Source maps can be parsed from text with one `<pc> <file>:<line>[:<column>]` entry per line, where `pc` is a decimal
+/// program counter. Empty lines and lines starting with `#` are ignored.
+///
+/// ```
+/// # use zksync_vm2::SourceMap;
+/// let source_map: SourceMap = "0 contracts/Counter.sol:5:5\n3 contracts/Counter.sol:6".parse()?;
+/// assert_eq!(source_map.location(2).unwrap().to_string(), "contracts/Counter.sol:5:5");
+/// assert_eq!(source_map.location(10).unwrap().to_string(), "contracts/Counter.sol:6");
+/// # Ok::<_, zksync_vm2::ParseSourceMapError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    locations: BTreeMap<u16, SourceLocation>,
+}
+
+impl SourceMap {
+    /// Creates an empty source map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the instruction at `pc` (and following unmapped instructions) to the specified location.
+    pub fn insert(&mut self, pc: u16, location: SourceLocation) {
+        self.locations.insert(pc, location);
+    }
+
+    /// Returns the source location of the instruction at `pc`.
+    pub fn location(&self, pc: u16) -> Option<&SourceLocation> {
+        self.locations
+            .range(..=pc)
+            .next_back()
+            .map(|(_, location)| location)
+    }
+
+    /// Iterates over all explicitly mapped program counters, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &SourceLocation)> + '_ {
+        self.locations.iter().map(|(&pc, location)| (pc, location))
+    }
+}
+
+impl FromStr for SourceMap {
+    type Err = ParseSourceMapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut source_map = Self::new();
+        // Reuse file names so that locations in the same file share the allocation.
+        let mut files = BTreeMap::<&str, Arc<str>>::new();
+        for (i, line) in s.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (pc, file, line_number, column) =
+                parse_entry(trimmed).ok_or_else(|| ParseSourceMapError {
+                    line_number: i + 1,
+                    line: line.to_owned(),
+                })?;
+            let file = files.entry(file).or_insert_with(|| file.into()).clone();
+            source_map.insert(
+                pc,
+                SourceLocation {
+                    file,
+                    line: line_number,
+                    column,
+                },
+            );
+        }
+        Ok(source_map)
+    }
+}
+
+/// Parses a `<pc> <file>:<line>[:<column>]` entry. File paths may contain colons, so the entry is parsed
+/// from the end.
+fn parse_entry(entry: &str) -> Option<(u16, &str, u32, u32)> {
+    let (pc, location) = entry.split_once(char::is_whitespace)?;
+    let pc = pc.parse().ok()?;
+    let (rest, last) = location.trim_start().rsplit_once(':')?;
+    let last = last.parse().ok()?;
+    let (file, line, column) = match rest.rsplit_once(':') {
+        Some((file, line)) if !file.is_empty() && line.bytes().all(|b| b.is_ascii_digit()) => {
+            (file, line.parse().ok()?, last)
+        }
+        _ => (rest, last, 0),
+    };
+    if file.is_empty() {
+        return None;
+    }
+    Some((pc, file, line, column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_source_map() {
+        let source_map: SourceMap = "\
+            # Generated by a test\n\
+            0 Counter.sol:1\n\
+            \n\
+            2 C:/contracts/Counter.sol:10:7\n\
+            5 Counter.sol:3:1\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(source_map.iter().count(), 3);
+        assert_eq!(source_map.location(0).unwrap().to_string(), "Counter.sol:1");
+        assert_eq!(source_map.location(1).unwrap().to_string(), "Counter.sol:1");
+        let location = source_map.location(4).unwrap();
+        assert_eq!(&*location.file, "C:/contracts/Counter.sol");
+        assert_eq!((location.line, location.column), (10, 7));
+        assert_eq!(
+            source_map.location(u16::MAX).unwrap().to_string(),
+            "Counter.sol:3:1"
+        );
+    }
+
+    #[test]
+    fn instructions_before_first_entry_are_unmapped() {
+        let source_map: SourceMap = "3 Counter.sol:1".parse().unwrap();
+        assert_eq!(source_map.location(2), None);
+        assert!(source_map.location(3).is_some());
+    }
+
+    #[test]
+    fn parsing_invalid_source_map() {
+        for invalid in ["Counter.sol:1", "0 Counter.sol", "0 :5", "x Counter.sol:1"] {
+            let err = invalid.parse::<SourceMap>().unwrap_err();
+            assert_eq!(err.line_number, 1, "{invalid}");
+        }
+        let err = "0 a.sol:1\n1 a.sol:x\n".parse::<SourceMap>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"invalid source map entry on line 2: "1 a.sol:x""#
+        );
+    }
+}
@@ -1,22 +1,49 @@
 use std::{
     alloc::{alloc, alloc_zeroed, Layout},
     fmt,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    sync::Arc,
 };
 
 use primitive_types::U256;
 
-use crate::{bitset::Bitset, fat_pointer::FatPointer, hash_for_debugging};
+use crate::{
+    allocator::{Allocator, StackBuffer},
+    bitset::Bitset,
+    fat_pointer::FatPointer,
+    hash_for_debugging,
+};
+
+/// Number of stack slots. EraVM stacks have `1 << 16` slots; with the `small_stack` feature, stacks have `1 << 12` slots
+/// and stack addresses are taken modulo this number, which deviates from the spec.
+pub(crate) const STACK_SIZE: usize = if cfg!(feature = "small_stack") {
+    1 << 12
+} else {
+    1 << 16
+};
 
-#[derive(PartialEq)]
+/// Callframe stack.
+///
+/// Slots are grouped into areas; an area is *dirty* if it was written since the stack was created or reset.
+/// Slots and pointer flags in clean areas are always zero / unset, so that resetting a reused stack only needs to zero
+/// its dirty areas rather than the entire stack. Reads don't need to check whether an area is dirty.
 pub(crate) struct Stack {
     /// set of slots that may be interpreted as [`FatPointer`].
     pointer_flags: Bitset,
     dirty_areas: u64,
-    slots: [U256; 1 << 16],
+    slots: [U256; STACK_SIZE],
 }
 
 const NUMBER_OF_DIRTY_AREAS: usize = 64;
-const DIRTY_AREA_SIZE: usize = (1 << 16) / NUMBER_OF_DIRTY_AREAS;
+const DIRTY_AREA_SIZE: usize = STACK_SIZE / NUMBER_OF_DIRTY_AREAS;
+
+/// Maps a stack address to a slot; a no-op unless the `small_stack` feature is enabled.
+#[inline(always)]
+#[allow(clippy::cast_possible_truncation)] // `STACK_SIZE - 1` fits into `u16`
+fn slot_index(address: u16) -> u16 {
+    address & (STACK_SIZE - 1) as u16
+}
 
 impl Stack {
     #[allow(clippy::cast_ptr_alignment)] // aligned per `Stack` layout
@@ -24,45 +51,53 @@ impl Stack {
         unsafe { Box::from_raw(alloc_zeroed(Layout::new::<Self>()).cast()) }
     }
 
+    #[inline(always)]
+    fn mark_dirty(&mut self, slot: u16) {
+        self.dirty_areas |= 1 << (slot_index(slot) as usize / DIRTY_AREA_SIZE);
+    }
+
+    fn clear_area(&mut self, area: usize) {
+        let range = area * DIRTY_AREA_SIZE..(area + 1) * DIRTY_AREA_SIZE;
+        self.slots[range.clone()].fill(U256::zero());
+        self.pointer_flags.clear_range(range);
+    }
+
     #[inline(always)]
     pub(crate) fn get(&self, slot: u16) -> U256 {
-        self.slots[slot as usize]
+        self.slots[slot_index(slot) as usize]
     }
 
     #[inline(always)]
     pub(crate) fn set(&mut self, slot: u16, value: U256) {
-        let written_area = slot as usize / DIRTY_AREA_SIZE;
-        self.dirty_areas |= 1 << written_area;
-
-        self.slots[slot as usize] = value;
+        self.mark_dirty(slot);
+        self.slots[slot_index(slot) as usize] = value;
     }
 
+    /// Resets all slots to zero. Only dirty areas are zeroed since clean ones are zero already.
     fn zero(&mut self) {
-        for i in 0..NUMBER_OF_DIRTY_AREAS {
-            if self.dirty_areas & (1 << i) != 0 {
-                for slot in &mut self.slots[i * DIRTY_AREA_SIZE..(i + 1) * DIRTY_AREA_SIZE] {
-                    *slot = U256::zero();
-                }
+        for area in 0..NUMBER_OF_DIRTY_AREAS {
+            if self.dirty_areas & (1 << area) != 0 {
+                self.clear_area(area);
             }
         }
-
         self.dirty_areas = 0;
-        self.pointer_flags = Bitset::default();
     }
 
     #[inline(always)]
     pub(crate) fn get_pointer_flag(&self, slot: u16) -> bool {
-        self.pointer_flags.get(slot)
+        self.pointer_flags.get(slot_index(slot))
     }
 
     #[inline(always)]
     pub(crate) fn set_pointer_flag(&mut self, slot: u16) {
-        self.pointer_flags.set(slot);
+        self.mark_dirty(slot);
+        self.pointer_flags.set(slot_index(slot));
     }
 
     #[inline(always)]
     pub(crate) fn clear_pointer_flag(&mut self, slot: u16) {
-        self.pointer_flags.clear(slot);
+        self.mark_dirty(slot);
+        self.pointer_flags.clear(slot_index(slot));
     }
 
     pub(crate) fn snapshot(&self) -> StackSnapshot {
@@ -82,14 +117,22 @@ impl Stack {
             slots,
         } = snapshot;
 
+        // Zero areas dirtied after the snapshot; ones in the snapshot prefix are overwritten below anyway.
         self.zero();
-
         self.pointer_flags = pointer_flags;
         self.dirty_areas = dirty_areas;
         self.slots[..slots.len()].copy_from_slice(&slots);
     }
 }
 
+impl PartialEq for Stack {
+    // Dirty areas are not compared since they don't affect stack contents.
+    fn eq(&self, other: &Self) -> bool {
+        self.pointer_flags == other.pointer_flags && self.slots == other.slots
+    }
+}
+
+#[derive(Clone)]
 pub(crate) struct StackSnapshot {
     pointer_flags: Bitset,
     dirty_areas: u64,
@@ -106,22 +149,105 @@ impl Clone for Box<Stack> {
     }
 }
 
+/// Owned stack that is released to its [`Allocator`] (if any) when dropped.
+pub(crate) struct StackBox {
+    stack: ManuallyDrop<Box<Stack>>,
+    allocator: Option<Arc<dyn Allocator>>,
+}
+
+impl StackBox {
+    fn new(stack: Box<Stack>, allocator: Option<Arc<dyn Allocator>>) -> Self {
+        Self {
+            stack: ManuallyDrop::new(stack),
+            allocator,
+        }
+    }
+}
+
+impl Deref for StackBox {
+    type Target = Stack;
+
+    #[inline(always)]
+    fn deref(&self) -> &Stack {
+        &self.stack
+    }
+}
+
+impl DerefMut for StackBox {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Stack {
+        &mut self.stack
+    }
+}
+
+impl Drop for StackBox {
+    fn drop(&mut self) {
+        // `self.stack` is never accessed after this.
+        let stack = unsafe { ManuallyDrop::take(&mut self.stack) };
+        if let Some(allocator) = &self.allocator {
+            allocator.free_stack(StackBuffer(stack));
+        }
+    }
+}
+
+impl Clone for StackBox {
+    fn clone(&self) -> Self {
+        Self::new((*self.stack).clone(), self.allocator.clone())
+    }
+}
+
+impl PartialEq for StackBox {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl fmt::Debug for StackBox {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, formatter)
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct StackPool {
-    stacks: Vec<Box<Stack>>,
+    stacks: Vec<StackBox>,
+    allocator: Option<Arc<dyn Allocator>>,
 }
 
 impl StackPool {
-    pub(crate) fn get(&mut self) -> Box<Stack> {
-        self.stacks.pop().map_or_else(Stack::new, |mut s| {
-            s.zero();
-            s
-        })
+    pub(crate) fn new(allocator: Option<Arc<dyn Allocator>>) -> Self {
+        Self {
+            stacks: vec![],
+            allocator,
+        }
+    }
+
+    pub(crate) fn get(&mut self) -> StackBox {
+        if let Some(mut stack) = self.stacks.pop() {
+            stack.zero();
+            stack
+        } else if let Some(allocator) = &self.allocator {
+            let mut stack = allocator.allocate_stack().0;
+            stack.zero();
+            StackBox::new(stack, Some(allocator.clone()))
+        } else {
+            StackBox::new(Stack::new(), None)
+        }
     }
 
-    pub(crate) fn recycle(&mut self, stack: Box<Stack>) {
+    pub(crate) fn recycle(&mut self, stack: StackBox) {
         self.stacks.push(stack);
     }
+
+    /// Returns the number of stacks retained for reuse.
+    pub(crate) fn len(&self) -> usize {
+        self.stacks.len()
+    }
+
+    /// Returns the allocator used by this pool.
+    pub(crate) fn allocator(&self) -> Option<&Arc<dyn Allocator>> {
+        self.allocator.as_ref()
+    }
 }
 
 // region:Debug implementations
@@ -195,4 +321,81 @@ mod tests {
         let stack = Stack::new();
         let _ = stack.clone();
     }
+
+    #[test]
+    fn reset_stack_reads_as_zero() {
+        let mut stack = Stack::new();
+        stack.set(5, 42.into());
+        stack.set_pointer_flag(5);
+        stack.set(5_000, 23.into());
+        stack.zero();
+
+        assert_eq!(stack.get(5), U256::zero());
+        assert!(!stack.get_pointer_flag(5));
+        assert_eq!(stack.get(5_000), U256::zero());
+        assert!(*stack == *Stack::new());
+
+        // Writing to an area must not reveal stale values or pointer flags in it.
+        stack.set(6, 1.into());
+        assert_eq!(stack.get(5), U256::zero());
+        assert!(!stack.get_pointer_flag(5));
+        stack.zero();
+        stack.set_pointer_flag(7);
+        assert_eq!(stack.get(5), U256::zero());
+        assert!(!stack.get_pointer_flag(5));
+        assert!(stack.get_pointer_flag(7));
+        assert_eq!(stack.get(7), U256::zero());
+    }
+
+    #[test]
+    fn stack_addresses_map_to_slots() {
+        let mut stack = Stack::new();
+        stack.set(u16::MAX, 42.into());
+        stack.set_pointer_flag(u16::MAX);
+        // With the `small_stack` feature, the address is mapped to the last slot as well.
+        let last_slot = slot_index(u16::MAX);
+        assert_eq!(usize::from(last_slot), STACK_SIZE - 1);
+        assert_eq!(stack.get(last_slot), 42.into());
+        assert!(stack.get_pointer_flag(last_slot));
+        assert_eq!(stack.get(0), U256::zero());
+    }
+
+    #[test]
+    fn clearing_pointer_flag_in_clean_area() {
+        let mut stack = Stack::new();
+        stack.set_pointer_flag(3);
+        stack.zero();
+        stack.clear_pointer_flag(4);
+        assert!(!stack.get_pointer_flag(3));
+        assert!(!stack.get_pointer_flag(4));
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)] // slot indices fit into `u16`
+    fn rolling_back_stack_with_stale_areas() {
+        let mut stack = Stack::new();
+        stack.set(10, 1.into());
+        stack.set_pointer_flag(10);
+        stack.set(3 * DIRTY_AREA_SIZE as u16, 2.into());
+        stack.set_pointer_flag(3 * DIRTY_AREA_SIZE as u16);
+        stack.zero();
+
+        stack.set(3 * DIRTY_AREA_SIZE as u16 + 1, 3.into());
+        let snapshot = stack.snapshot();
+        assert!(snapshot.slots.iter().all(|&slot| slot <= 3.into()));
+        assert_eq!(snapshot.slots[10], U256::zero());
+        assert!(!snapshot.pointer_flags.get(10));
+
+        stack.set(10, 4.into());
+        stack.set_pointer_flag(10);
+        stack.set(7 * DIRTY_AREA_SIZE as u16, 5.into());
+        stack.rollback(snapshot);
+
+        assert_eq!(stack.get(10), U256::zero());
+        assert!(!stack.get_pointer_flag(10));
+        assert_eq!(stack.get(7 * DIRTY_AREA_SIZE as u16), U256::zero());
+        assert_eq!(stack.get(3 * DIRTY_AREA_SIZE as u16), U256::zero());
+        assert!(!stack.get_pointer_flag(3 * DIRTY_AREA_SIZE as u16));
+        assert_eq!(stack.get(3 * DIRTY_AREA_SIZE as u16 + 1), 3.into());
+    }
 }
@@ -1,14 +1,17 @@
+use std::sync::Arc;
+
 use primitive_types::{H160, U256};
 use zksync_vm2_interface::{HeapId, Tracer};
 
 use crate::{
     addressing_modes::Addressable,
+    allocator::Allocator,
     callframe::{Callframe, CallframeSnapshot},
     fat_pointer::FatPointer,
     heap::Heaps,
     predication::Flags,
     program::Program,
-    stack::Stack,
+    stack::StackBox,
     world_diff::Snapshot,
     World,
 };
@@ -26,9 +29,12 @@ pub(crate) struct State<T, W> {
     pub(crate) heaps: Heaps,
     pub(crate) transaction_number: u16,
     pub(crate) context_u128: u128,
+    /// Gas charged per byte of pubdata produced by storage writes and L2-to-L1 messages.
+    pub(crate) ergs_per_pubdata_byte: u32,
 }
 
 impl<T, W> State<T, W> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         address: H160,
         caller: H160,
@@ -36,7 +42,8 @@ impl<T, W> State<T, W> {
         gas: u32,
         program: Program<T, W>,
         world_before_this_frame: Snapshot,
-        stack: Box<Stack>,
+        stack: StackBox,
+        allocator: Option<Arc<dyn Allocator>>,
     ) -> Self {
         let mut registers: [U256; 16] = Default::default();
         registers[1] = FatPointer {
@@ -69,10 +76,11 @@ impl<T, W> State<T, W> {
             ),
             previous_frames: vec![],
 
-            heaps: Heaps::new(calldata),
+            heaps: Heaps::with_allocator(calldata, allocator),
 
             transaction_number: 0,
             context_u128: 0,
+            ergs_per_pubdata_byte: 0,
         }
     }
 
@@ -87,6 +95,11 @@ impl<T, W> State<T, W> {
         }
     }
 
+    /// Charges gas for `pubdata_bytes` bytes of pubdata at the current `ergs_per_pubdata_byte` rate.
+    pub(crate) fn use_gas_for_pubdata(&mut self, pubdata_bytes: u32) -> Result<(), ()> {
+        self.use_gas(pubdata_bytes.saturating_mul(self.ergs_per_pubdata_byte))
+    }
+
     pub(crate) fn set_context_u128(&mut self, value: u128) {
         self.context_u128 = value;
     }
@@ -97,14 +110,13 @@ impl<T, W> State<T, W> {
 }
 
 impl<T: Tracer, W: World<T>> State<T, W> {
-    /// Returns the total unspent gas in the VM, including stipends.
+    /// Returns the total unspent gas in the VM, including stipends. Saturates at `u32::MAX`
+    /// (which is only reachable with stipends if the VM was started with near-maximum gas).
     pub(crate) fn total_unspent_gas(&self) -> u32 {
-        self.current_frame.gas
-            + self
-                .previous_frames
-                .iter()
-                .map(Callframe::contained_gas)
-                .sum::<u32>()
+        self.previous_frames
+            .iter()
+            .map(Callframe::contained_gas)
+            .fold(self.current_frame.gas, u32::saturating_add)
     }
 
     pub(crate) fn snapshot(&self) -> StateSnapshot {
@@ -116,6 +128,7 @@ impl<T: Tracer, W: World<T>> State<T, W> {
             bootloader_heap_snapshot: self.heaps.snapshot(),
             transaction_number: self.transaction_number,
             context_u128: self.context_u128,
+            ergs_per_pubdata_byte: self.ergs_per_pubdata_byte,
         }
     }
 
@@ -128,6 +141,7 @@ impl<T: Tracer, W: World<T>> State<T, W> {
             bootloader_heap_snapshot,
             transaction_number,
             context_u128,
+            ergs_per_pubdata_byte,
         } = snapshot;
 
         for heap in self.current_frame.rollback(bootloader_frame) {
@@ -139,11 +153,24 @@ impl<T: Tracer, W: World<T>> State<T, W> {
         self.flags = flags;
         self.transaction_number = transaction_number;
         self.context_u128 = context_u128;
+        self.ergs_per_pubdata_byte = ergs_per_pubdata_byte;
     }
 
     pub(crate) fn delete_history(&mut self) {
         self.heaps.delete_history();
     }
+
+    /// Compacts the heap history given the snapshot that may be rolled back to. If there is no snapshot,
+    /// the history is not needed at all.
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub(crate) fn compact_history(&mut self, snapshot: Option<&StateSnapshot>) {
+        if let Some(snapshot) = snapshot {
+            self.heaps
+                .compact_history(snapshot.bootloader_heap_snapshot);
+        } else {
+            self.heaps.delete_history();
+        }
+    }
 }
 
 impl<T, W> Clone for State<T, W> {
@@ -157,6 +184,7 @@ impl<T, W> Clone for State<T, W> {
             heaps: self.heaps.clone(),
             transaction_number: self.transaction_number,
             context_u128: self.context_u128,
+            ergs_per_pubdata_byte: self.ergs_per_pubdata_byte,
         }
     }
 }
@@ -170,6 +198,7 @@ impl<T, W> PartialEq for State<T, W> {
             && self.flags == other.flags
             && self.transaction_number == other.transaction_number
             && self.context_u128 == other.context_u128
+            && self.ergs_per_pubdata_byte == other.ergs_per_pubdata_byte
             && self.current_frame == other.current_frame
             && self.previous_frames == other.previous_frames
             && self.heaps == other.heaps
@@ -218,7 +247,7 @@ impl<T, W> Addressable for State<T, W> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct StateSnapshot {
     registers: [U256; 16],
     register_pointer_flags: u16,
@@ -227,4 +256,5 @@ pub(crate) struct StateSnapshot {
     bootloader_heap_snapshot: (usize, usize),
     transaction_number: u16,
     context_u128: u128,
+    ergs_per_pubdata_byte: u32,
 }
@@ -0,0 +1,182 @@
+//! Changes of the VM state made by individual instructions.
+
+use std::collections::BTreeMap;
+
+use primitive_types::U256;
+use zksync_vm2_interface::{Flags, HeapId, StateInterface, Tracer};
+
+use crate::{ExecutionEnd, VirtualMachine, World};
+
+/// Word written to a heap by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapWrite {
+    /// Written heap.
+    pub heap: HeapId,
+    /// Offset of the first written byte.
+    pub offset: u32,
+    /// Written word; it's stored in the heap in the big-endian order.
+    pub value: U256,
+}
+
+/// Changes of the VM state made by a single instruction. Computed by [`VirtualMachine::step_with_delta()`].
+///
+/// Registers, flags and the stack pointer are compared before and after the instruction, so overwriting a value with
+/// an equal one is not reported. Heap writes, on the other hand, are reported as they are made.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDelta {
+    /// New values and pointer flags of changed registers, keyed by the register index.
+    pub registers: BTreeMap<u8, (U256, bool)>,
+    /// New execution flags if they have changed.
+    pub flags: Option<Flags>,
+    /// Heap writes in the order they were made, including writes made by precompiles.
+    pub heap_writes: Vec<HeapWrite>,
+    /// New stack pointer if it differs from the one before the instruction. For far calls and returns,
+    /// these are stack pointers of different frames.
+    pub stack_pointer: Option<u16>,
+    /// Gas spent by the instruction. Gas passed to a called frame is not included until the callee spends it.
+    pub gas_used: u32,
+}
+
+impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
+    /// Executes a single instruction and returns the changes it has made, together with the end of execution
+    /// if the instruction has ended it.
+    ///
+    /// Heap writes are only recorded during this call, so computing deltas doesn't slow down [`Self::run()`].
+    pub fn step_with_delta(
+        &mut self,
+        world: &mut W,
+        tracer: &mut T,
+    ) -> (Option<ExecutionEnd>, StateDelta) {
+        let (end, delta) = self.run_with_delta(world, tracer, &mut 1);
+        (
+            (end != ExecutionEnd::InstructionLimit).then_some(end),
+            delta,
+        )
+    }
+
+    /// Same as [`Self::run_with_instruction_limit()`], but also returns the changes made by executed instructions.
+    pub(crate) fn run_with_delta(
+        &mut self,
+        world: &mut W,
+        tracer: &mut T,
+        instruction_budget: &mut u64,
+    ) -> (ExecutionEnd, StateDelta) {
+        let registers = self.state.registers;
+        let register_pointer_flags = self.state.register_pointer_flags;
+        let flags = self.flags();
+        let stack_pointer = self.state.current_frame.sp;
+        let gas = self.state.total_unspent_gas();
+
+        self.state.heaps.start_recording_writes();
+        let end = self.run_with_instruction_limit(world, tracer, instruction_budget);
+        let heap_writes = self.state.heaps.stop_recording_writes();
+
+        let registers = (1_u8..16)
+            .filter_map(|i| {
+                let (value, is_pointer) = self.read_register(i);
+                let was_pointer = register_pointer_flags & (1 << i) != 0;
+                let changed = value != registers[usize::from(i)] || is_pointer != was_pointer;
+                changed.then_some((i, (value, is_pointer)))
+            })
+            .collect();
+        let new_flags = self.flags();
+        let new_stack_pointer = self.state.current_frame.sp;
+        let delta = StateDelta {
+            registers,
+            flags: (new_flags != flags).then_some(new_flags),
+            heap_writes,
+            stack_pointer: (new_stack_pointer != stack_pointer).then_some(new_stack_pointer),
+            gas_used: gas.saturating_sub(self.state.total_unspent_gas()),
+        };
+        (end, delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addressing_modes::{
+            AdvanceStackPointer, Arguments, Immediate1, Register, Register1, Register2,
+            RegisterAndImmediate,
+        },
+        testonly::vm_with_program,
+        Instruction, ModeRequirements, Predicate, Program,
+    };
+
+    #[test]
+    fn computing_state_deltas() {
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let (r0, r1) = (Register::new(0), Register::new(1));
+        let program = Program::from_raw(
+            vec![
+                Instruction::from_add(
+                    Immediate1(42).into(),
+                    Register2(r0),
+                    Register1(r1).into(),
+                    arguments(6),
+                    false,
+                    true,
+                ),
+                Instruction::from_heap_write(
+                    Register1(r1).into(),
+                    Register2(r1),
+                    None,
+                    arguments(7),
+                    false,
+                ),
+                Instruction::from_add(
+                    Register1(r0).into(),
+                    Register2(r0),
+                    AdvanceStackPointer(RegisterAndImmediate {
+                        immediate: 3,
+                        register: r0,
+                    })
+                    .into(),
+                    arguments(6),
+                    false,
+                    false,
+                ),
+                Instruction::from_ret(Register1(r0), None, arguments(5)),
+            ],
+            vec![],
+        );
+        let (mut vm, mut world) = vm_with_program(program, 1_000);
+
+        let (end, delta) = vm.step_with_delta(&mut world, &mut ());
+        assert_eq!(end, None);
+        assert_eq!(delta.registers, BTreeMap::from([(1, (42.into(), false))]));
+        let flags = Flags {
+            less_than: false,
+            equal: false,
+            greater: true,
+        };
+        assert_eq!(delta.flags, Some(flags));
+        assert_eq!(delta.stack_pointer, None);
+        assert_eq!(delta.gas_used, 6);
+
+        let (end, delta) = vm.step_with_delta(&mut world, &mut ());
+        assert_eq!(end, None);
+        assert!(delta.registers.is_empty());
+        assert_eq!(delta.flags, None);
+        let heap = vm.state.current_frame.heap;
+        assert_eq!(
+            delta.heap_writes,
+            [HeapWrite {
+                heap,
+                offset: 42,
+                value: 42.into()
+            }]
+        );
+        // Heap growth is paid in addition to the static cost.
+        assert!(delta.gas_used > 7, "{delta:?}");
+
+        let (end, delta) = vm.step_with_delta(&mut world, &mut ());
+        assert_eq!(end, None);
+        assert_eq!(delta.stack_pointer, Some(3));
+        assert!(delta.heap_writes.is_empty());
+
+        let (end, _) = vm.step_with_delta(&mut world, &mut ());
+        assert!(matches!(end, Some(ExecutionEnd::ProgramFinished(_))));
+    }
+}
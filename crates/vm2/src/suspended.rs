@@ -0,0 +1,178 @@
+//! Inspection of the instruction a suspended VM will execute next.
+
+use std::fmt;
+
+use primitive_types::H160;
+use zksync_vm2_interface::Tracer;
+
+use crate::{callframe::Callframe, InstructionInfo, SourceLocation, VirtualMachine, World};
+
+/// Summary of a far call frame of a [`SuspendedVm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSummary {
+    /// Address of the executed contract (i.e., the address used for storage accesses).
+    pub address: H160,
+    /// Address whose code is executed; differs from `address` for delegate calls.
+    pub code_address: H160,
+    /// Caller of the frame.
+    pub caller: H160,
+    /// Gas available to the frame, not including gas passed to near calls.
+    pub gas: u32,
+    /// Number of near calls currently active in the frame.
+    pub near_calls: usize,
+    /// Whether the frame is static.
+    pub is_static: bool,
+    /// Program counter of the next instruction executed in the frame, or `None` if the frame is about to panic.
+    /// For frames other than the current one, this is the instruction after the far call.
+    pub pc: Option<u16>,
+}
+
+impl FrameSummary {
+    fn new<T: Tracer, W: World<T>>(frame: &Callframe<T, W>) -> Self {
+        Self {
+            address: frame.address,
+            code_address: frame.code_address,
+            caller: frame.caller,
+            gas: frame.gas,
+            near_calls: frame.near_calls.len(),
+            is_static: frame.is_static,
+            pc: frame_pc(frame),
+        }
+    }
+}
+
+/// Returns the program counter of the next instruction of the frame if it's located in the frame program.
+fn frame_pc<T: Tracer, W: World<T>>(frame: &Callframe<T, W>) -> Option<u16> {
+    let pc = u16::try_from(frame.get_raw_pc()).ok()?;
+    frame.program.instruction(pc)?;
+    Some(pc)
+}
+
+/// Read-only view of a VM whose execution has stopped, e.g. on a [hook](crate::ExecutionEnd::SuspendedOnHook),
+/// an [instruction limit](crate::ExecutionEnd::InstructionLimit) or a tracer request. Obtained via
+/// [`VirtualMachine::suspended()`].
+///
+/// The view describes the instruction that will be executed when the VM is resumed, which allows hosts to log where
+/// execution has stopped. Its [`Display`](fmt::Display) implementation outputs a one-line description of the form
+/// `<address> @ <pc>: <instruction> (<source location>)`.
+#[derive(Debug)]
+pub struct SuspendedVm<'a, T, W> {
+    vm: &'a VirtualMachine<T, W>,
+}
+
+impl<'a, T: Tracer, W: World<T>> SuspendedVm<'a, T, W> {
+    /// Returns the program counter of the next instruction, or `None` if the VM is about to panic
+    /// (e.g., because the instruction is out of the program bounds).
+    pub fn pc(&self) -> Option<u16> {
+        frame_pc(&self.vm.state.current_frame)
+    }
+
+    /// Decodes the next instruction from the code page of the executed program.
+    ///
+    /// Like [`Program::pretty_print()`](crate::Program::pretty_print()), this is only meaningful for programs created
+    /// from bytecode. Returns `None` if [`Self::pc()`] is `None` or the instruction is not present in the code page.
+    pub fn instruction(&self) -> Option<InstructionInfo> {
+        let raw = self
+            .vm
+            .state
+            .current_frame
+            .program
+            .raw_instruction(self.pc()?)?;
+        Some(InstructionInfo::decode(raw))
+    }
+
+    /// Returns the source location of the next instruction. See [`VirtualMachine::current_source_location()`].
+    pub fn source_location(&self) -> Option<&'a SourceLocation> {
+        self.vm.current_source_location()
+    }
+
+    /// Returns the summary of the current far call frame.
+    pub fn current_frame(&self) -> FrameSummary {
+        FrameSummary::new(&self.vm.state.current_frame)
+    }
+
+    /// Iterates over summaries of all far call frames, starting from the current one and ending with the initial one.
+    pub fn frames(&self) -> impl Iterator<Item = FrameSummary> + 'a {
+        let state = &self.vm.state;
+        let previous_frames = state.previous_frames.iter().rev();
+        [&state.current_frame]
+            .into_iter()
+            .chain(previous_frames)
+            .map(FrameSummary::new)
+    }
+}
+
+impl<T: Tracer, W: World<T>> fmt::Display for SuspendedVm<'_, T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{:?} @ ", self.vm.state.current_frame.address)?;
+        let Some(pc) = self.pc() else {
+            return formatter.write_str("panic");
+        };
+        write!(formatter, "{pc}")?;
+        if let Some(instruction) = self.instruction() {
+            write!(formatter, ": {instruction}")?;
+        }
+        if let Some(location) = self.source_location() {
+            write!(formatter, " ({location})")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
+    /// Returns a view describing the instruction this VM will execute next and the call stack. Useful to log
+    /// where execution has stopped before deciding how to resume it.
+    pub fn suspended(&self) -> SuspendedVm<'_, T, W> {
+        SuspendedVm { vm: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Program, Settings,
+    };
+
+    #[test]
+    fn inspecting_suspended_vm() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far");
+        let program = Program::new(bytecode, false);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            u32::MAX,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        // The VM is suspended before executing any instructions.
+        let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut 0);
+        assert_eq!(end, ExecutionEnd::InstructionLimit);
+        let suspended = vm.suspended();
+        assert_eq!(suspended.pc(), Some(0));
+        let frames: Vec<_> = suspended.frames().collect();
+        assert_eq!(frames, [suspended.current_frame()]);
+        assert_eq!(frames[0].address, address);
+        assert_eq!(frames[0].near_calls, 0);
+
+        let expected = InstructionInfo::decode_bytecode(bytecode)[0];
+        let instruction = suspended.instruction().unwrap();
+        assert_eq!(instruction.opcode, expected.opcode);
+        assert_eq!(
+            suspended.to_string(),
+            format!("{address:?} @ 0: {expected}")
+        );
+    }
+}
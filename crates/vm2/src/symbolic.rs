@@ -0,0 +1,722 @@
+//! Experimental symbolic execution of programs.
+//!
+//! Symbolic execution runs a program with registers and heap cells holding [`Expr`]essions over unknown inputs
+//! instead of concrete values. If a predicate depends on an input, execution forks into two paths, each recording
+//! the corresponding path constraint; infeasible paths are pruned using a pluggable [`Solver`]. The main use case
+//! is proving that panics in system contracts are unreachable, see [`Exploration::unreachable_panics()`].
+//!
+//! Only a single frame is modeled, and only a subset of instructions is supported: arithmetic and bitwise operations
+//! on registers and immediates, jumps, returns, and heap accesses at concrete addresses. Paths reaching other
+//! instructions are reported as incomplete. Gas is not modeled, so out-of-gas panics are not considered.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use primitive_types::U256;
+use zksync_vm2_interface::{Opcode, ReturnType};
+
+use crate::{
+    addressing_modes::{AnyDestination, AnySource, Register},
+    InstructionInfo, Predicate,
+};
+
+/// Number of registers, including the zero register.
+const REGISTER_COUNT: usize = 16;
+
+/// Symbolic 256-bit value. Boolean values (e.g., comparison results) are represented as 0 or 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// Concrete value.
+    Const(U256),
+    /// Unknown value. Symbols 1 to 15 are the initial values of the corresponding registers; other symbols
+    /// are introduced during execution, e.g. for partially overwritten heap words.
+    Symbol(usize),
+    /// Wrapping addition.
+    Add(Rc<Expr>, Rc<Expr>),
+    /// Wrapping subtraction.
+    Sub(Rc<Expr>, Rc<Expr>),
+    /// Bitwise AND.
+    And(Rc<Expr>, Rc<Expr>),
+    /// Bitwise OR.
+    Or(Rc<Expr>, Rc<Expr>),
+    /// Bitwise XOR.
+    Xor(Rc<Expr>, Rc<Expr>),
+    /// Unsigned comparison `lhs < rhs`.
+    Lt(Rc<Expr>, Rc<Expr>),
+    /// Equality comparison.
+    Eq(Rc<Expr>, Rc<Expr>),
+}
+
+impl Expr {
+    /// Returns the value of this expression if it's concrete.
+    pub fn as_const(&self) -> Option<U256> {
+        match self {
+            Self::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn constant(value: impl Into<U256>) -> Rc<Self> {
+        Rc::new(Self::Const(value.into()))
+    }
+
+    fn fold(
+        lhs: &Rc<Self>,
+        rhs: &Rc<Self>,
+        op: impl FnOnce(U256, U256) -> U256,
+        build: impl FnOnce(Rc<Self>, Rc<Self>) -> Self,
+    ) -> Rc<Self> {
+        match (lhs.as_const(), rhs.as_const()) {
+            (Some(lhs), Some(rhs)) => Self::constant(op(lhs, rhs)),
+            _ => Rc::new(build(lhs.clone(), rhs.clone())),
+        }
+    }
+
+    fn add(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        Self::fold(lhs, rhs, |a, b| a.overflowing_add(b).0, Self::Add)
+    }
+
+    fn sub(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        if lhs == rhs {
+            return Self::constant(0);
+        }
+        Self::fold(lhs, rhs, |a, b| a.overflowing_sub(b).0, Self::Sub)
+    }
+
+    fn and(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        Self::fold(lhs, rhs, |a, b| a & b, Self::And)
+    }
+
+    fn or(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        Self::fold(lhs, rhs, |a, b| a | b, Self::Or)
+    }
+
+    fn xor(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        if lhs == rhs {
+            return Self::constant(0);
+        }
+        Self::fold(lhs, rhs, |a, b| a ^ b, Self::Xor)
+    }
+
+    fn lt(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        Self::fold(lhs, rhs, |a, b| u8::from(a < b).into(), Self::Lt)
+    }
+
+    fn eq(lhs: &Rc<Self>, rhs: &Rc<Self>) -> Rc<Self> {
+        if lhs == rhs {
+            return Self::constant(1);
+        }
+        Self::fold(lhs, rhs, |a, b| u8::from(a == b).into(), Self::Eq)
+    }
+
+    /// Boolean negation.
+    fn not(value: &Rc<Self>) -> Rc<Self> {
+        Self::eq(value, &Self::constant(0))
+    }
+}
+
+/// Result of a satisfiability check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatResult {
+    /// Constraints are satisfiable.
+    Sat,
+    /// Constraints are not satisfiable.
+    Unsat,
+    /// Solver cannot decide. The path is treated as feasible.
+    Unknown,
+}
+
+/// Decides whether path constraints are satisfiable. Can be implemented using an SMT solver, e.g. by translating
+/// [`Expr`]s to 256-bit bitvector terms.
+pub trait Solver {
+    /// Checks whether all `constraints` can be non-zero at the same time.
+    fn check(&mut self, constraints: &[Rc<Expr>]) -> SatResult;
+}
+
+/// [`Solver`] relying only on constant folding. Besides constant constraints, it detects constraints
+/// contradicting each other syntactically (i.e., a condition and its negation).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimpleSolver;
+
+impl Solver for SimpleSolver {
+    fn check(&mut self, constraints: &[Rc<Expr>]) -> SatResult {
+        let mut result = SatResult::Sat;
+        for constraint in constraints {
+            match constraint.as_const() {
+                Some(value) if value.is_zero() => return SatResult::Unsat,
+                Some(_) => {}
+                None => {
+                    if constraints.contains(&Expr::not(constraint)) {
+                        return SatResult::Unsat;
+                    }
+                    result = SatResult::Unknown;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Limits of symbolic execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolicConfig {
+    /// Maximum number of forks on a single path.
+    pub max_depth: usize,
+    /// Maximum number of instructions executed on a single path.
+    pub max_steps: usize,
+}
+
+impl Default for SymbolicConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_steps: 10_000,
+        }
+    }
+}
+
+/// Panic reached on an explored path.
+#[derive(Debug, Clone)]
+pub struct ReachablePanic {
+    /// Index of the panicking instruction. May be equal to the program length if execution runs past its end.
+    pub pc: usize,
+    /// Constraints under which the panic is reached.
+    pub constraints: Vec<Rc<Expr>>,
+}
+
+/// Reason why a path was not fully explored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IncompleteReason {
+    /// The path forks more than allowed by [`SymbolicConfig::max_depth`].
+    DepthLimit,
+    /// The path is longer than allowed by [`SymbolicConfig::max_steps`].
+    StepLimit,
+    /// The path reaches an instruction or an operand that isn't supported by symbolic execution.
+    UnsupportedInstruction,
+}
+
+/// Path that was not fully explored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncompletePath {
+    /// Index of the instruction at which exploration stopped.
+    pub pc: usize,
+    /// Why exploration stopped.
+    pub reason: IncompleteReason,
+}
+
+/// Outcome of [`explore()`].
+#[derive(Debug, Clone, Default)]
+pub struct Exploration {
+    /// Panics reached on explored paths.
+    pub panics: Vec<ReachablePanic>,
+    /// Paths that were not fully explored.
+    pub incomplete_paths: Vec<IncompletePath>,
+    /// Number of paths ending with a normal return or a revert.
+    pub completed_paths: usize,
+}
+
+impl Exploration {
+    /// Checks whether all feasible paths were explored.
+    pub fn is_complete(&self) -> bool {
+        self.incomplete_paths.is_empty()
+    }
+
+    /// Returns indices of panicking instructions (i.e., invalid instructions and panicking returns) that are not reached
+    /// on any path, or `None` if the exploration is incomplete. Because the [`Solver`] may not prune all infeasible
+    /// paths, some unreachable panics may be missing.
+    pub fn unreachable_panics(&self, instructions: &[InstructionInfo]) -> Option<Vec<usize>> {
+        if !self.is_complete() {
+            return None;
+        }
+        let unreachable = instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| matches!(info.opcode, None | Some(Opcode::Ret(ReturnType::Panic))))
+            .map(|(pc, _)| pc)
+            .filter(|&pc| self.panics.iter().all(|panic| panic.pc != pc))
+            .collect();
+        Some(unreachable)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Flags {
+    lt_of: Rc<Expr>,
+    eq: Rc<Expr>,
+    gt: Rc<Expr>,
+}
+
+impl Flags {
+    fn condition(&self, predicate: Predicate) -> Rc<Expr> {
+        match predicate {
+            Predicate::Always => Expr::constant(1),
+            Predicate::IfGT => self.gt.clone(),
+            Predicate::IfEQ => self.eq.clone(),
+            Predicate::IfLT => self.lt_of.clone(),
+            Predicate::IfGE => Expr::or(&self.gt, &self.eq),
+            Predicate::IfLE => Expr::or(&self.lt_of, &self.eq),
+            Predicate::IfNotEQ => Expr::not(&self.eq),
+            Predicate::IfGTOrLT => Expr::or(&self.gt, &self.lt_of),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Path {
+    pc: usize,
+    registers: [Rc<Expr>; REGISTER_COUNT],
+    flags: Flags,
+    /// 32-byte words written to the heap, keyed by their address.
+    heap: BTreeMap<u32, Rc<Expr>>,
+    constraints: Vec<Rc<Expr>>,
+    depth: usize,
+    steps: usize,
+    next_symbol: usize,
+}
+
+impl Path {
+    fn new() -> Self {
+        let zero = Expr::constant(0);
+        Self {
+            pc: 0,
+            registers: std::array::from_fn(|i| {
+                if i == 0 {
+                    zero.clone()
+                } else {
+                    Rc::new(Expr::Symbol(i))
+                }
+            }),
+            flags: Flags {
+                lt_of: zero.clone(),
+                eq: zero.clone(),
+                gt: zero,
+            },
+            heap: BTreeMap::new(),
+            constraints: vec![],
+            depth: 0,
+            steps: 0,
+            next_symbol: REGISTER_COUNT,
+        }
+    }
+
+    fn fresh_symbol(&mut self) -> Rc<Expr> {
+        self.next_symbol += 1;
+        Rc::new(Expr::Symbol(self.next_symbol - 1))
+    }
+
+    fn register(&self, register: Register) -> Rc<Expr> {
+        self.registers[usize::from(register.index())].clone()
+    }
+
+    fn read(&self, source: AnySource) -> Option<Rc<Expr>> {
+        match source {
+            AnySource::Register1(register) => Some(self.register(register.0)),
+            AnySource::Immediate1(value) => Some(Expr::constant(value.0)),
+            _ => None,
+        }
+    }
+
+    /// Returns `false` if the destination is not supported.
+    fn write(&mut self, destination: Option<AnyDestination>, value: Rc<Expr>) -> bool {
+        let Some(AnyDestination::Register1(register)) = destination else {
+            return false;
+        };
+        let index = usize::from(register.0.index());
+        // Writes to the zero register are ignored.
+        if index != 0 {
+            self.registers[index] = value;
+        }
+        true
+    }
+
+    fn read_heap(&mut self, address: u32) -> Rc<Expr> {
+        if let Some(value) = self.heap.get(&address) {
+            return value.clone();
+        }
+        let overlaps_written_word = self
+            .heap
+            .range(address.saturating_sub(31)..=address.saturating_add(31))
+            .next()
+            .is_some();
+        if overlaps_written_word {
+            self.fresh_symbol()
+        } else {
+            Expr::constant(0)
+        }
+    }
+
+    fn write_heap(&mut self, address: u32, value: Rc<Expr>) {
+        let overlapping: Vec<_> = self
+            .heap
+            .range(address.saturating_sub(31)..=address.saturating_add(31))
+            .map(|(&overlapping, _)| overlapping)
+            .filter(|&overlapping| overlapping != address)
+            .collect();
+        // Partially overwritten words are no longer known.
+        for overlapping in overlapping {
+            let symbol = self.fresh_symbol();
+            self.heap.insert(overlapping, symbol);
+        }
+        self.heap.insert(address, value);
+    }
+}
+
+/// Outcome of executing a single instruction.
+enum Step {
+    Continue,
+    Finish,
+    Panic,
+    Unsupported,
+}
+
+struct Explorer<'a, S> {
+    instructions: &'a [InstructionInfo],
+    config: SymbolicConfig,
+    solver: &'a mut S,
+    pending_paths: Vec<Path>,
+    exploration: Exploration,
+}
+
+impl<S: Solver> Explorer<'_, S> {
+    fn abandon(&mut self, path: &Path, reason: IncompleteReason) {
+        self.exploration.incomplete_paths.push(IncompletePath {
+            pc: path.pc,
+            reason,
+        });
+    }
+
+    fn run(&mut self, mut path: Path) {
+        loop {
+            let Some(info) = self.instructions.get(path.pc) else {
+                // Running past the end of the program is equivalent to executing an invalid instruction.
+                self.exploration.panics.push(ReachablePanic {
+                    pc: path.pc,
+                    constraints: path.constraints,
+                });
+                return;
+            };
+            if path.steps == self.config.max_steps {
+                return self.abandon(&path, IncompleteReason::StepLimit);
+            }
+            path.steps += 1;
+
+            let condition = path.flags.condition(info.predicate);
+            match condition.as_const() {
+                Some(value) if value.is_zero() => {
+                    path.pc += 1;
+                    continue;
+                }
+                Some(_) => {}
+                None => {
+                    if path.depth == self.config.max_depth {
+                        return self.abandon(&path, IncompleteReason::DepthLimit);
+                    }
+                    path.depth += 1;
+
+                    let mut skipped = path.clone();
+                    skipped.pc += 1;
+                    skipped.constraints.push(Expr::not(&condition));
+                    if self.solver.check(&skipped.constraints) != SatResult::Unsat {
+                        self.pending_paths.push(skipped);
+                    }
+                    path.constraints.push(condition);
+                    if self.solver.check(&path.constraints) == SatResult::Unsat {
+                        return;
+                    }
+                }
+            }
+
+            match Self::execute(&mut path, info) {
+                Step::Continue => {}
+                Step::Finish => {
+                    self.exploration.completed_paths += 1;
+                    return;
+                }
+                Step::Panic => {
+                    self.exploration.panics.push(ReachablePanic {
+                        pc: path.pc,
+                        constraints: path.constraints,
+                    });
+                    return;
+                }
+                Step::Unsupported => {
+                    return self.abandon(&path, IncompleteReason::UnsupportedInstruction);
+                }
+            }
+        }
+    }
+
+    fn execute(path: &mut Path, info: &InstructionInfo) -> Step {
+        let Some(opcode) = info.opcode else {
+            return Step::Panic;
+        };
+        match opcode {
+            Opcode::Nop => {}
+            Opcode::Add | Opcode::Sub | Opcode::And | Opcode::Or | Opcode::Xor => {
+                let Some(mut a) = path.read(info.src1) else {
+                    return Step::Unsupported;
+                };
+                let mut b = path.register(info.src2.0);
+                if info.flags.swap_operands {
+                    std::mem::swap(&mut a, &mut b);
+                }
+
+                let zero = Expr::constant(0);
+                let (result, lt_of) = match opcode {
+                    Opcode::Add => {
+                        let result = Expr::add(&a, &b);
+                        // Addition overflows iff the result is less than an operand.
+                        let overflow = Expr::lt(&result, &a);
+                        (result, overflow)
+                    }
+                    Opcode::Sub => (Expr::sub(&a, &b), Expr::lt(&a, &b)),
+                    Opcode::And => (Expr::and(&a, &b), zero.clone()),
+                    Opcode::Or => (Expr::or(&a, &b), zero.clone()),
+                    _ => (Expr::xor(&a, &b), zero.clone()),
+                };
+                if info.flags.set_flags {
+                    let eq = Expr::eq(&result, &zero);
+                    let gt = if matches!(opcode, Opcode::Add | Opcode::Sub) {
+                        Expr::not(&Expr::or(&lt_of, &eq))
+                    } else {
+                        zero
+                    };
+                    path.flags = Flags { lt_of, eq, gt };
+                }
+                if !path.write(info.out, result) {
+                    return Step::Unsupported;
+                }
+            }
+            Opcode::Jump => {
+                let target = path.read(info.src1).and_then(|target| target.as_const());
+                let Some(target) = target else {
+                    return Step::Unsupported;
+                };
+                if !path.write(info.out, Expr::constant(path.pc + 1)) {
+                    return Step::Unsupported;
+                }
+                #[allow(clippy::cast_possible_truncation)] // intentional
+                let target = target.low_u32() as u16;
+                path.pc = usize::from(target);
+                return Step::Continue;
+            }
+            Opcode::Ret(ReturnType::Panic) => return Step::Panic,
+            // Since only a single frame is modeled, returning ends execution.
+            Opcode::Ret(_) => return Step::Finish,
+            Opcode::HeapRead | Opcode::HeapWrite if !info.flags.increment => {
+                let address = path.read(info.src1).and_then(|address| address.as_const());
+                let Some(address) = address else {
+                    return Step::Unsupported;
+                };
+                let address = address.low_u32();
+                if opcode == Opcode::HeapRead {
+                    let value = path.read_heap(address);
+                    if !path.write(info.out, value) {
+                        return Step::Unsupported;
+                    }
+                } else {
+                    let value = path.register(info.src2.0);
+                    path.write_heap(address, value);
+                }
+            }
+            _ => return Step::Unsupported,
+        }
+        path.pc += 1;
+        Step::Continue
+    }
+}
+
+/// Symbolically executes a program starting from the first instruction, with registers initialized to unknown values,
+/// flags cleared and an empty heap.
+pub fn explore(
+    instructions: &[InstructionInfo],
+    config: SymbolicConfig,
+    solver: &mut impl Solver,
+) -> Exploration {
+    let mut explorer = Explorer {
+        instructions,
+        config,
+        solver,
+        pending_paths: vec![Path::new()],
+        exploration: Exploration::default(),
+    };
+    while let Some(path) = explorer.pending_paths.pop() {
+        explorer.run(path);
+    }
+    explorer.exploration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addressing_modes::{Immediate1, Register1, Register2},
+        InstructionFlags, ModeRequirements,
+    };
+
+    fn info(opcode: Opcode) -> InstructionInfo {
+        let r0 = Register::new(0);
+        InstructionInfo {
+            opcode: Some(opcode),
+            predicate: Predicate::Always,
+            mode_requirements: ModeRequirements::none(),
+            gas_cost: 6,
+            src1: Register1(r0).into(),
+            src2: Register2(r0),
+            out: Some(Register1(r0).into()),
+            out2: Register2(r0),
+            imm1: 0,
+            imm2: 0,
+            flags: InstructionFlags::default(),
+        }
+    }
+
+    fn binop(opcode: Opcode, src1: AnySource, src2: u8, out: u8) -> InstructionInfo {
+        InstructionInfo {
+            src1,
+            src2: Register2(Register::new(src2)),
+            out: Some(Register1(Register::new(out)).into()),
+            flags: InstructionFlags {
+                set_flags: true,
+                ..InstructionFlags::default()
+            },
+            ..info(opcode)
+        }
+    }
+
+    fn register(index: u8) -> AnySource {
+        Register1(Register::new(index)).into()
+    }
+
+    fn jump(target: u16, predicate: Predicate) -> InstructionInfo {
+        InstructionInfo {
+            src1: Immediate1(target).into(),
+            predicate,
+            ..info(Opcode::Jump)
+        }
+    }
+
+    fn ret(return_type: ReturnType, predicate: Predicate) -> InstructionInfo {
+        InstructionInfo {
+            predicate,
+            ..info(Opcode::Ret(return_type))
+        }
+    }
+
+    #[test]
+    fn panic_guarded_by_concrete_condition() {
+        let instructions = [
+            binop(Opcode::Sub, register(1), 1, 2),
+            ret(ReturnType::Panic, Predicate::IfNotEQ),
+            ret(ReturnType::Normal, Predicate::Always),
+        ];
+        let exploration = explore(&instructions, SymbolicConfig::default(), &mut SimpleSolver);
+
+        assert!(exploration.panics.is_empty());
+        assert_eq!(exploration.completed_paths, 1);
+        assert_eq!(exploration.unreachable_panics(&instructions), Some(vec![1]));
+    }
+
+    #[test]
+    fn forking_on_symbolic_condition() {
+        let instructions = [
+            binop(Opcode::Sub, Immediate1(5).into(), 1, 2),
+            jump(3, Predicate::IfEQ),
+            ret(ReturnType::Panic, Predicate::Always),
+            // Only reachable if the EQ flag is set
+            jump(5, Predicate::IfNotEQ),
+            ret(ReturnType::Normal, Predicate::Always),
+            ret(ReturnType::Panic, Predicate::Always),
+        ];
+        let exploration = explore(&instructions, SymbolicConfig::default(), &mut SimpleSolver);
+
+        assert_eq!(exploration.panics.len(), 1);
+        let panic = &exploration.panics[0];
+        assert_eq!(panic.pc, 2);
+        let eq = Expr::eq(
+            &Expr::sub(&Expr::constant(5), &Rc::new(Expr::Symbol(1))),
+            &Expr::constant(0),
+        );
+        assert_eq!(panic.constraints, [Expr::not(&eq)]);
+        assert_eq!(exploration.completed_paths, 1);
+        assert_eq!(exploration.unreachable_panics(&instructions), Some(vec![5]));
+    }
+
+    #[test]
+    fn heap_accesses() {
+        let heap_write = InstructionInfo {
+            src1: Immediate1(64).into(),
+            src2: Register2(Register::new(1)),
+            ..info(Opcode::HeapWrite)
+        };
+        let heap_read = |address, out| InstructionInfo {
+            src1: Immediate1(address).into(),
+            out: Some(Register1(Register::new(out)).into()),
+            ..info(Opcode::HeapRead)
+        };
+        let instructions = [
+            heap_write,
+            heap_read(64, 2),
+            binop(Opcode::Sub, register(2), 1, 0),
+            ret(ReturnType::Panic, Predicate::IfNotEQ),
+            // Unwritten heap is zeroed
+            heap_read(0, 3),
+            binop(Opcode::Or, register(3), 0, 0),
+            ret(ReturnType::Panic, Predicate::IfNotEQ),
+            // Word overlapping a written one is unknown
+            heap_read(48, 4),
+            binop(Opcode::Or, register(4), 0, 0),
+            ret(ReturnType::Panic, Predicate::IfNotEQ),
+            ret(ReturnType::Normal, Predicate::Always),
+        ];
+        let exploration = explore(&instructions, SymbolicConfig::default(), &mut SimpleSolver);
+
+        let panics: Vec<_> = exploration.panics.iter().map(|panic| panic.pc).collect();
+        assert_eq!(panics, [9]);
+        assert_eq!(
+            exploration.unreachable_panics(&instructions),
+            Some(vec![3, 6])
+        );
+    }
+
+    #[test]
+    fn exploration_limits() {
+        let config = SymbolicConfig {
+            max_depth: 1,
+            max_steps: 100,
+        };
+
+        let instructions = [jump(0, Predicate::Always)];
+        let exploration = explore(&instructions, config, &mut SimpleSolver);
+        assert_eq!(
+            exploration.incomplete_paths,
+            [IncompletePath {
+                pc: 0,
+                reason: IncompleteReason::StepLimit,
+            }]
+        );
+        assert_eq!(exploration.unreachable_panics(&instructions), None);
+
+        let instructions = [
+            binop(Opcode::Sub, register(1), 2, 0),
+            ret(ReturnType::Revert, Predicate::IfLT),
+            ret(ReturnType::Revert, Predicate::IfGT),
+            ret(ReturnType::Normal, Predicate::Always),
+        ];
+        let exploration = explore(&instructions, config, &mut SimpleSolver);
+        assert_eq!(exploration.completed_paths, 1);
+        let reasons: Vec<_> = exploration
+            .incomplete_paths
+            .iter()
+            .map(|path| path.reason)
+            .collect();
+        assert_eq!(reasons, [IncompleteReason::DepthLimit]);
+
+        let instructions = [info(Opcode::Decommit)];
+        let exploration = explore(&instructions, config, &mut SimpleSolver);
+        assert_eq!(
+            exploration.incomplete_paths[0].reason,
+            IncompleteReason::UnsupportedInstruction
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Symbol tables used to annotate pretty-printed programs.
+
+use std::collections::BTreeMap;
+
+use primitive_types::{H160, U256};
+
+use crate::decommit::u256_into_address;
+
+/// Names of ZKsync Era system contracts and precompiles, keyed by the lower 16 bits of their addresses.
+const SYSTEM_CONTRACTS: &[(u16, &str)] = &[
+    (0x01, "EcRecover"),
+    (0x02, "Sha256"),
+    (0x05, "ModExp"),
+    (0x06, "EcAdd"),
+    (0x07, "EcMul"),
+    (0x08, "EcPairing"),
+    (0x100, "P256Verify"),
+    (0x8001, "Bootloader"),
+    (0x8002, "AccountCodeStorage"),
+    (0x8003, "NonceHolder"),
+    (0x8004, "KnownCodesStorage"),
+    (0x8005, "ImmutableSimulator"),
+    (0x8006, "ContractDeployer"),
+    (0x8007, "ForceDeployer"),
+    (0x8008, "L1Messenger"),
+    (0x8009, "MsgValueSimulator"),
+    (0x800a, "L2BaseToken"),
+    (0x800b, "SystemContext"),
+    (0x800c, "BootloaderUtilities"),
+    (0x800d, "EventWriter"),
+    (0x800e, "Compressor"),
+    (0x800f, "ComplexUpgrader"),
+    (0x8010, "Keccak256"),
+    (0x8011, "PubdataChunkPublisher"),
+    (0x8012, "CodeOracle"),
+];
+
+/// Names for values that can be substituted when pretty-printing a [`Program`](crate::Program).
+///
+/// Contains two kinds of symbols: contract addresses, which are matched against immediates and code page constants
+/// used by instructions, and hook offsets, which are matched against heap offsets of immediate heap writes
+/// (as used by the bootloader to [suspend the VM](crate::ExecutionEnd::SuspendedOnHook)).
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    addresses: BTreeMap<H160, String>,
+    hooks: BTreeMap<u32, String>,
+}
+
+impl SymbolTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table with the names of ZKsync Era system contracts and precompiles.
+    pub fn system_contracts() -> Self {
+        let mut this = Self::new();
+        for &(address_low, name) in SYSTEM_CONTRACTS {
+            this.set_address(H160::from_low_u64_be(address_low.into()), name);
+        }
+        this
+    }
+
+    /// Adds a named address.
+    #[must_use]
+    pub fn with_address(mut self, address: H160, name: impl Into<String>) -> Self {
+        self.set_address(address, name);
+        self
+    }
+
+    /// Adds a named hook offset.
+    #[must_use]
+    pub fn with_hook(mut self, offset: u32, name: impl Into<String>) -> Self {
+        self.set_hook(offset, name);
+        self
+    }
+
+    /// Adds a named address, replacing the previous name if any.
+    pub fn set_address(&mut self, address: H160, name: impl Into<String>) {
+        self.addresses.insert(address, name.into());
+    }
+
+    /// Adds a named hook offset, replacing the previous name if any.
+    pub fn set_hook(&mut self, offset: u32, name: impl Into<String>) {
+        self.hooks.insert(offset, name.into());
+    }
+
+    /// Returns the name of the specified address.
+    pub fn address_name(&self, address: &H160) -> Option<&str> {
+        self.addresses.get(address).map(String::as_str)
+    }
+
+    /// Returns the name of the hook at the specified heap offset.
+    pub fn hook_name(&self, offset: u32) -> Option<&str> {
+        self.hooks.get(&offset).map(String::as_str)
+    }
+
+    /// Returns the name of an address represented by `value`, or `None` if `value` is not a known address.
+    pub(crate) fn value_name(&self, value: U256) -> Option<&str> {
+        if (value >> 160).is_zero() {
+            self.address_name(&u256_into_address(value))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testonly::TestWorld, Program};
+
+    #[test]
+    fn looking_up_symbols() {
+        let address = H160::repeat_byte(0x42);
+        let symbols = SymbolTable::system_contracts()
+            .with_address(address, "Token")
+            .with_hook(1024, "DebugLog");
+
+        assert_eq!(
+            symbols.address_name(&H160::from_low_u64_be(0x800b)),
+            Some("SystemContext")
+        );
+        assert_eq!(symbols.value_name(0x8006.into()), Some("ContractDeployer"));
+        assert_eq!(
+            symbols.value_name(U256::from_big_endian(address.as_bytes())),
+            Some("Token")
+        );
+        assert_eq!(
+            symbols.value_name((U256::one() << 160) | U256::from(0x8006)),
+            None
+        );
+        assert_eq!(symbols.hook_name(1024), Some("DebugLog"));
+        assert_eq!(symbols.hook_name(1056), None);
+    }
+
+    #[test]
+    fn pretty_printing_program() {
+        let bytecode = include_bytes!("tests/bytecodes/call_far");
+        let program = Program::<(), TestWorld<()>>::new(bytecode, false);
+        let listing = program.pretty_print(&SymbolTable::system_contracts());
+
+        let lines: Vec<_> = listing.lines().collect();
+        assert_eq!(lines.len(), bytecode.len() / 8);
+        assert!(lines[0].starts_with("    0: "), "{}", lines[0]);
+        assert!(
+            lines.iter().any(|line| line.contains(": far_call")),
+            "{listing}"
+        );
+    }
+}
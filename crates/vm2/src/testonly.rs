@@ -6,20 +6,74 @@ use std::{
 };
 
 use primitive_types::{H160, U256};
-use zkevm_opcode_defs::{
-    ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
-};
+use zkevm_opcode_defs::ethereum_types::Address;
 use zksync_vm2_interface::Tracer;
 
+pub use self::rng::TestRng;
+#[cfg(not(feature = "single_instruction_test"))]
+pub use self::{
+    exec_one::{exec_one, StateFixture},
+    msg_value::{msg_value_simulator_address, msg_value_simulator_program, MsgValueCall},
+    recording::{RecordingWorld, WorldAccess},
+};
+pub use crate::decommit::initial_decommit;
 use crate::{
-    instruction_handlers::address_into_u256, Program, StorageInterface, StorageSlot, World,
+    batch::BatchWorld, instruction_handlers::address_into_u256,
+    isa::system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW, Program, Settings, StorageInterface,
+    StorageSlot, VirtualMachine, World,
 };
 
+#[cfg(not(feature = "single_instruction_test"))]
+mod exec_one;
+#[cfg(not(feature = "single_instruction_test"))]
+mod msg_value;
+#[cfg(not(feature = "single_instruction_test"))]
+mod recording;
+mod rng;
+
+/// Address of the program deployed by [`world_with_program()`] and [`vm_with_program()`].
+pub const TEST_PROGRAM_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x12, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef,
+]);
+
+/// Creates a test world with `program` deployed at [`TEST_PROGRAM_ADDRESS`] and returns it together with
+/// the decommitted program, e.g. to create a VM with custom params.
+pub fn world_with_program<T: Tracer>(
+    program: Program<T, TestWorld<T>>,
+) -> (TestWorld<T>, Program<T, TestWorld<T>>) {
+    let mut world = TestWorld::new(&[(TEST_PROGRAM_ADDRESS, program)]);
+    let program = initial_decommit(&mut world, TEST_PROGRAM_ADDRESS);
+    (world, program)
+}
+
+/// Creates a VM executing `program` deployed at [`TEST_PROGRAM_ADDRESS`] with the specified `gas`, a zero caller,
+/// empty calldata and zeroed [`Settings`], and returns it together with the world the program is deployed in.
+pub fn vm_with_program<T: Tracer>(
+    program: Program<T, TestWorld<T>>,
+    gas: u32,
+) -> (VirtualMachine<T, TestWorld<T>>, TestWorld<T>) {
+    let (world, program) = world_with_program(program);
+    let vm = VirtualMachine::new(
+        TEST_PROGRAM_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        gas,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    (vm, world)
+}
+
 /// Test [`World`] implementation.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TestWorld<T> {
     pub(crate) address_to_hash: BTreeMap<U256, U256>,
     pub(crate) hash_to_contract: BTreeMap<U256, Program<T, Self>>,
+    pub(crate) storage: BTreeMap<(H160, U256), U256>,
 }
 
 impl<T: Tracer> TestWorld<T> {
@@ -39,8 +93,10 @@ impl<T: Tracer> TestWorld<T> {
 
             let mut code_info_bytes = [0; 32];
             code_info_bytes[24..].copy_from_slice(&hasher.finish().to_be_bytes());
+            // Valid EraVM bytecodes have an odd number of words, so the length is rounded up to satisfy this.
             let code_len = u16::try_from(code.code_page().len())
-                .expect("code length must not exceed u16::MAX");
+                .expect("code length must not exceed u16::MAX")
+                | 1;
             code_info_bytes[2..=3].copy_from_slice(&code_len.to_be_bytes());
             code_info_bytes[0] = 1;
             let hash = U256::from_big_endian(&code_info_bytes);
@@ -51,6 +107,7 @@ impl<T: Tracer> TestWorld<T> {
         Self {
             address_to_hash,
             hash_to_contract,
+            storage: BTreeMap::new(),
         }
     }
 }
@@ -77,6 +134,12 @@ impl<T: Tracer> World<T> for TestWorld<T> {
     }
 }
 
+impl<T: Tracer> BatchWorld<T> for TestWorld<T> {
+    fn apply_storage_change(&mut self, contract: H160, key: U256, value: U256) {
+        self.storage.insert((contract, key), value);
+    }
+}
+
 impl<T> StorageInterface for TestWorld<T> {
     fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
         let deployer_system_contract_address =
@@ -92,6 +155,11 @@ impl<T> StorageInterface for TestWorld<T> {
                 value,
                 is_write_initial: false,
             }
+        } else if let Some(&value) = self.storage.get(&(contract, key)) {
+            StorageSlot {
+                value,
+                is_write_initial: false,
+            }
         } else {
             StorageSlot::EMPTY
         }
@@ -105,22 +173,3 @@ impl<T> StorageInterface for TestWorld<T> {
         false
     }
 }
-
-/// May be used to load code when the VM first starts up.
-/// Doesn't check for any errors.
-/// Doesn't cost anything but also doesn't make the code free in future decommits.
-#[doc(hidden)] // should be used only in low-level testing / benches
-pub fn initial_decommit<T: Tracer, W: World<T>>(world: &mut W, address: H160) -> Program<T, W> {
-    let deployer_system_contract_address =
-        Address::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
-    let code_info =
-        world.read_storage_value(deployer_system_contract_address, address_into_u256(address));
-
-    let mut code_info_bytes = [0; 32];
-    code_info.to_big_endian(&mut code_info_bytes);
-
-    code_info_bytes[1] = 0;
-    let code_key: U256 = U256::from_big_endian(&code_info_bytes);
-
-    world.decommit(code_key)
-}
@@ -0,0 +1,85 @@
+//! Execution of a single instruction, for focused unit tests of instruction handlers.
+
+use std::ptr;
+
+use primitive_types::U256;
+use zksync_vm2_interface::{Flags, StateInterface};
+
+use super::{vm_with_program, TestWorld};
+use crate::{
+    instruction_handlers::spontaneous_panic, ExecutionEnd, Instruction, Program, StateDelta,
+};
+
+/// VM state an instruction is executed in by [`exec_one()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateFixture {
+    /// Register values. The value of `r0` is ignored since it's always zero.
+    pub registers: [U256; 16],
+    /// Bit mask of registers containing fat pointers.
+    pub register_pointer_flags: u16,
+    /// Execution flags.
+    pub flags: Flags,
+    /// Gas available to the executed frame.
+    pub gas: u32,
+    /// Code page of the executed program.
+    pub code_page: Vec<U256>,
+}
+
+impl Default for StateFixture {
+    fn default() -> Self {
+        Self {
+            registers: [U256::zero(); 16],
+            register_pointer_flags: 0,
+            flags: Flags {
+                less_than: false,
+                equal: false,
+                greater: false,
+            },
+            gas: 10_000,
+            code_page: vec![],
+        }
+    }
+}
+
+impl StateFixture {
+    /// Sets a register to a non-pointer `value`.
+    #[must_use]
+    pub fn with_register(mut self, register: u8, value: impl Into<U256>) -> Self {
+        self.registers[usize::from(register)] = value.into();
+        self.register_pointer_flags &= !(1 << register);
+        self
+    }
+
+    /// Sets execution flags.
+    #[must_use]
+    pub fn with_flags(mut self, flags: Flags) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
+/// Executes a single instruction against the `fixture` state and returns the changes it has made, together with
+/// the end of execution if the instruction has ended it. Allows testing instruction handlers without building
+/// and running whole programs.
+///
+/// The instruction is executed in the initial frame of a VM with empty calldata, so a panic ends execution.
+/// If the instruction panics after starting execution, the panic is executed as well, but the returned delta only
+/// covers the instruction itself.
+pub fn exec_one(
+    instruction: Instruction<(), TestWorld<()>>,
+    fixture: &StateFixture,
+) -> (Option<ExecutionEnd>, StateDelta) {
+    let program = Program::from_raw(vec![instruction], fixture.code_page.clone());
+    let (mut vm, mut world) = vm_with_program(program, fixture.gas);
+    for (register, &value) in (1..16).zip(&fixture.registers[1..]) {
+        let is_pointer = fixture.register_pointer_flags & (1 << register) != 0;
+        vm.set_register(register, value, is_pointer);
+    }
+    vm.set_flags(fixture.flags);
+
+    let (mut end, delta) = vm.step_with_delta(&mut world, &mut ());
+    if end.is_none() && ptr::eq(vm.state.current_frame.pc, spontaneous_panic()) {
+        end = vm.step_with_delta(&mut world, &mut ()).0;
+    }
+    (end, delta)
+}
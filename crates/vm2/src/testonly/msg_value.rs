@@ -0,0 +1,218 @@
+//! Simulation of value-bearing calls made via the `MsgValueSimulator` system contract.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::{ethereum_types::Address, ADDRESS_MSG_VALUE};
+use zksync_vm2_interface::{opcodes, Tracer};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    instruction_handlers::address_into_u256,
+    Instruction, ModeRequirements, Predicate, Program, World,
+};
+
+/// Returns the address of the `MsgValueSimulator` system contract.
+pub fn msg_value_simulator_address() -> Address {
+    Address::from_low_u64_be(ADDRESS_MSG_VALUE.into())
+}
+
+/// Value-bearing call routed through the `MsgValueSimulator` system contract.
+///
+/// Following the system contract conventions, the caller makes a system far call to the simulator, passing the value
+/// in `r3`, the called address in `r4` and extra flags in `r5` (bit 0 requests a system call to the called contract).
+/// The simulator is modeled by [`msg_value_simulator_program()`]; it sets `context.u128` to the value and forwards
+/// the calldata to the called contract. Balances are not modeled.
+#[derive(Debug, Clone)]
+pub struct MsgValueCall {
+    /// Address of the called contract.
+    pub to: Address,
+    /// Transferred value. The called contract can read it using the `context.get_context_u128` instruction.
+    pub value: u128,
+    /// Whether the simulator should call the contract using a system call. Only has an effect for kernel contracts.
+    pub is_system: bool,
+    /// Calldata passed to the called contract, written to the caller heap starting from offset 0.
+    pub calldata: Vec<U256>,
+    /// Gas passed to the simulator, not counting the additional gas mandated for simulator calls.
+    pub gas: u32,
+}
+
+impl MsgValueCall {
+    /// Creates a non-system call with empty calldata passing all available gas.
+    pub fn new(to: Address, value: u128) -> Self {
+        Self {
+            to,
+            value,
+            is_system: false,
+            calldata: vec![],
+            gas: u32::MAX,
+        }
+    }
+
+    /// Returns a program performing this call. The program returns or reverts with the output of the called contract;
+    /// if the called contract panics, it reverts with empty output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the calldata is too long to be written using immediate heap offsets.
+    pub fn caller_program<T: Tracer, W: World<T>>(&self) -> Program<T, W> {
+        const CALLDATA_START: u16 = 6;
+
+        let r1 = Register::new(1);
+        let calldata_len = u16::try_from(self.calldata.len() * 32).expect("calldata is too long");
+        let mut simulator_abi = U256::zero();
+        simulator_abi.0[1] = u64::from(calldata_len) << 32;
+        simulator_abi.0[3] = u64::from(self.gas) | SYSTEM_CALL_ABI_BIT;
+        let mut code_page = vec![
+            simulator_abi,
+            address_into_u256(msg_value_simulator_address()),
+            self.value.into(),
+            address_into_u256(self.to),
+            u8::from(self.is_system).into(),
+            forwarding_abi(),
+        ];
+        code_page.extend_from_slice(&self.calldata);
+
+        let mut instructions = vec![];
+        for i in 0..self.calldata.len() {
+            #[allow(clippy::cast_possible_truncation)] // checked above
+            let i = i as u16;
+            instructions.push(load_from_code_page(CALLDATA_START + i, r1));
+            instructions.push(Instruction::from_heap_write(
+                Immediate1(i * 32).into(),
+                Register2(r1),
+                None,
+                arguments(Predicate::Always, 7),
+                false,
+            ));
+        }
+        for (immediate, register) in [(1, 2), (2, 3), (3, 4), (4, 5), (0, 1)] {
+            instructions.push(load_from_code_page(immediate, Register::new(register)));
+        }
+        #[allow(clippy::cast_possible_truncation)] // the program is small
+        let revert_label = instructions.len() as u16 + 4;
+        instructions.push(Instruction::from_far_call::<opcodes::Normal>(
+            Register1(r1),
+            Register2(Register::new(2)),
+            Immediate1(revert_label),
+            false,
+            false,
+            arguments(Predicate::Always, 200),
+        ));
+        instructions.extend(forward_output(5));
+        Program::from_raw(instructions, code_page)
+    }
+}
+
+/// Bit of the far call ABI marking system calls.
+const SYSTEM_CALL_ABI_BIT: u64 = 1 << 56;
+
+/// ABI forwarding the fat pointer in the ABI register.
+fn forwarding_abi() -> U256 {
+    let mut abi = U256::zero();
+    abi.0[3] = 1 << 32;
+    abi
+}
+
+fn arguments(predicate: Predicate, gas: u32) -> Arguments {
+    Arguments::new(predicate, gas, ModeRequirements::none())
+}
+
+fn load_from_code_page<T: Tracer, W: World<T>>(immediate: u16, out: Register) -> Instruction<T, W> {
+    load_from_code_page_if(Predicate::Always, immediate, out)
+}
+
+fn load_from_code_page_if<T: Tracer, W: World<T>>(
+    predicate: Predicate,
+    immediate: u16,
+    out: Register,
+) -> Instruction<T, W> {
+    let r0 = Register::new(0);
+    Instruction::from_add(
+        CodePage(RegisterAndImmediate {
+            immediate,
+            register: r0,
+        })
+        .into(),
+        Register2(r0),
+        Register1(out).into(),
+        arguments(predicate, 6),
+        false,
+        false,
+    )
+}
+
+/// Returns on success and reverts on failure of the preceding far call, forwarding the returned fat pointer in `r1`.
+/// ABI forwarding the pointer must be stored in the code page at `forwarding_abi_index`.
+fn forward_output<T: Tracer, W: World<T>>(forwarding_abi_index: u16) -> [Instruction<T, W>; 6] {
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let pack_abi = || {
+        Instruction::from_pointer_pack(
+            Register1(r1).into(),
+            Register2(r2),
+            Register1(r1).into(),
+            arguments(Predicate::Always, 6),
+            false,
+        )
+    };
+    [
+        load_from_code_page(forwarding_abi_index, r2),
+        pack_abi(),
+        Instruction::from_ret(Register1(r1), None, arguments(Predicate::Always, 5)),
+        load_from_code_page(forwarding_abi_index, r2),
+        pack_abi(),
+        Instruction::from_revert(Register1(r1), None, arguments(Predicate::Always, 5)),
+    ]
+}
+
+/// Returns a program modeling the `MsgValueSimulator` system contract, to be deployed at
+/// [`msg_value_simulator_address()`]. See [`MsgValueCall`] for the calling conventions.
+pub fn msg_value_simulator_program<T: Tracer, W: World<T>>() -> Program<T, W> {
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r4 = Register::new(4);
+    let r5 = Register::new(5);
+    let r6 = Register::new(6);
+
+    let mut call_abi = U256::zero();
+    call_abi.0[3] = u64::from(u32::MAX) | (1 << 32);
+    let mut system_call_abi = call_abi;
+    system_call_abi.0[3] |= SYSTEM_CALL_ABI_BIT;
+    let code_page = vec![call_abi, system_call_abi, forwarding_abi()];
+
+    let mut instructions = vec![
+        Instruction::from_set_context_u128(
+            Register1(Register::new(3)),
+            Arguments::new(Predicate::Always, 5, ModeRequirements::new(true, false)),
+        ),
+        // Sets the EQ flag if the system call bit is not set.
+        Instruction::from_and(
+            Immediate1(1).into(),
+            Register2(r5),
+            Register1(r6).into(),
+            arguments(Predicate::Always, 6),
+            false,
+            true,
+        ),
+        load_from_code_page_if(Predicate::IfEQ, 0, r2),
+        load_from_code_page_if(Predicate::IfNotEQ, 1, r2),
+        Instruction::from_pointer_pack(
+            Register1(r1).into(),
+            Register2(r2),
+            Register1(r1).into(),
+            arguments(Predicate::Always, 6),
+            false,
+        ),
+        Instruction::from_far_call::<opcodes::Normal>(
+            Register1(r1),
+            Register2(r4),
+            Immediate1(9),
+            false,
+            false,
+            arguments(Predicate::Always, 200),
+        ),
+    ];
+    instructions.extend(forward_output(2));
+    Program::from_raw(instructions, code_page)
+}
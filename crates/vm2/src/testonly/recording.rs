@@ -0,0 +1,245 @@
+//! World recording storage reads and decommits.
+
+use std::{collections::HashMap, fmt};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::Tracer;
+
+use crate::{precompiles::Precompiles, Program, StorageInterface, StorageSlot, World};
+
+/// Access to the world recorded by [`RecordingWorld`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WorldAccess {
+    /// Storage slot read via [`StorageInterface::read_storage()`] or [`StorageInterface::read_storage_value()`].
+    StorageRead {
+        /// Contract owning the slot.
+        contract: H160,
+        /// Slot key.
+        key: U256,
+    },
+    /// Program decommitted via [`World::decommit()`].
+    Decommit(U256),
+    /// Bytecode decommitted via [`World::decommit_code()`].
+    DecommitCode(U256),
+}
+
+impl fmt::Display for WorldAccess {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StorageRead { contract, key } => write!(formatter, "read {contract:?}[{key:#x}]"),
+            Self::Decommit(hash) => write!(formatter, "decommit {hash:#x}"),
+            Self::DecommitCode(hash) => write!(formatter, "decommit_code {hash:#x}"),
+        }
+    }
+}
+
+/// [`World`] wrapper recording storage reads and decommits requested by the VM, so that tests can assert which
+/// accesses were made, e.g. to check caching behavior.
+///
+/// Like [`OverrideWorld`](crate::OverrideWorld), this world decodes programs from bytecodes returned by
+/// [`World::decommit_code()`] of the wrapped world, so the wrapped world must provide real bytecodes. Decoded
+/// programs are cached by their hash; the cache doesn't influence recorded accesses.
+#[derive(Debug)]
+pub struct RecordingWorld<T, W> {
+    inner: W,
+    accesses: Vec<WorldAccess>,
+    programs: HashMap<U256, Program<T, Self>>,
+}
+
+impl<T: Tracer, W: World<T>> RecordingWorld<T, W> {
+    /// Wraps the provided world.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            accesses: vec![],
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying world.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the underlying world.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Returns all accesses recorded so far, in the order they were made.
+    pub fn accesses(&self) -> &[WorldAccess] {
+        &self.accesses
+    }
+
+    /// Removes all recorded accesses, e.g. to only check accesses made by the following VM run.
+    pub fn clear_accesses(&mut self) {
+        self.accesses.clear();
+    }
+
+    /// Asserts that the recorded accesses are exactly `expected`, in the same order.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a line diff of expected and recorded accesses if they differ.
+    #[track_caller]
+    pub fn assert_accesses(&self, expected: &[WorldAccess]) {
+        assert!(
+            self.accesses == expected,
+            "recorded accesses differ from expected ('-' expected, '+' recorded):\n{}",
+            diff(expected, &self.accesses)
+        );
+    }
+
+    /// Asserts that the recorded accesses ignoring their order and repetitions are exactly `expected`.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing missing and unexpected accesses if they differ.
+    #[track_caller]
+    pub fn assert_access_set(&self, expected: impl IntoIterator<Item = WorldAccess>) {
+        let mut expected: Vec<_> = expected.into_iter().collect();
+        expected.sort_unstable();
+        expected.dedup();
+        let mut recorded = self.accesses.clone();
+        recorded.sort_unstable();
+        recorded.dedup();
+        assert!(
+            recorded == expected,
+            "recorded access set differs from expected ('-' missing, '+' unexpected):\n{}",
+            diff(&expected, &recorded)
+        );
+    }
+}
+
+/// Outputs a line diff of two access sequences based on their longest common subsequence.
+fn diff(expected: &[WorldAccess], recorded: &[WorldAccess]) -> String {
+    // `lcs[i][j]` is the length of the longest common subsequence of `expected[i..]` and `recorded[j..]`.
+    let mut lcs = vec![vec![0_usize; recorded.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..recorded.len()).rev() {
+            lcs[i][j] = if expected[i] == recorded[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < recorded.len() {
+        let line = if i < expected.len() && j < recorded.len() && expected[i] == recorded[j] {
+            i += 1;
+            j += 1;
+            format!("  {}\n", expected[i - 1])
+        } else if i < expected.len() && (j == recorded.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            i += 1;
+            format!("- {}\n", expected[i - 1])
+        } else {
+            j += 1;
+            format!("+ {}\n", recorded[j - 1])
+        };
+        output.push_str(&line);
+    }
+    output
+}
+
+impl<T: Tracer, W: World<T>> StorageInterface for RecordingWorld<T, W> {
+    fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
+        self.accesses
+            .push(WorldAccess::StorageRead { contract, key });
+        self.inner.read_storage(contract, key)
+    }
+
+    fn read_storage_value(&mut self, contract: H160, key: U256) -> U256 {
+        self.accesses
+            .push(WorldAccess::StorageRead { contract, key });
+        self.inner.read_storage_value(contract, key)
+    }
+
+    fn cost_of_writing_storage(&mut self, initial_slot: StorageSlot, new_value: U256) -> u32 {
+        self.inner.cost_of_writing_storage(initial_slot, new_value)
+    }
+
+    fn is_free_storage_slot(&self, contract: &H160, key: &U256) -> bool {
+        self.inner.is_free_storage_slot(contract, key)
+    }
+}
+
+impl<T: Tracer, W: World<T>> World<T> for RecordingWorld<T, W> {
+    fn decommit(&mut self, hash: U256) -> Program<T, Self> {
+        self.accesses.push(WorldAccess::Decommit(hash));
+        if let Some(program) = self.programs.get(&hash) {
+            return program.clone();
+        }
+        let program = Program::new(&self.inner.decommit_code(hash), false);
+        self.programs.insert(hash, program.clone());
+        program
+    }
+
+    fn decommit_code(&mut self, hash: U256) -> Vec<u8> {
+        self.accesses.push(WorldAccess::DecommitCode(hash));
+        self.inner.decommit_code(hash)
+    }
+
+    fn precompiles(&self) -> &impl Precompiles {
+        self.inner.precompiles()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zkevm_opcode_defs::{
+        ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
+    };
+
+    use super::*;
+    use crate::{
+        instruction_handlers::address_into_u256,
+        testonly::{initial_decommit, TestWorld},
+    };
+
+    const BYTECODE: &[u8] = include_bytes!("../tests/bytecodes/call_far");
+
+    fn recording_world() -> (RecordingWorld<(), TestWorld<()>>, Address) {
+        let address = Address::repeat_byte(1);
+        let inner = TestWorld::new(&[(address, Program::new(BYTECODE, false))]);
+        (RecordingWorld::new(inner), address)
+    }
+
+    #[test]
+    fn recording_accesses() {
+        let (mut world, address) = recording_world();
+        let program = initial_decommit(&mut world, address);
+        assert_eq!(program.code_page().len() * 32, BYTECODE.len());
+
+        let deployer = Address::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        let hash = world.inner().address_to_hash[&address_into_u256(address)];
+        let read = WorldAccess::StorageRead {
+            contract: deployer,
+            key: address_into_u256(address),
+        };
+        world.assert_accesses(&[read, WorldAccess::Decommit(hash)]);
+
+        world.decommit(hash);
+        world.assert_access_set([WorldAccess::Decommit(hash), read]);
+        world.clear_accesses();
+        assert!(world.accesses().is_empty());
+    }
+
+    #[test]
+    fn diffing_accesses() {
+        let [a, b, c] = [1, 2, 3].map(|hash| WorldAccess::Decommit(hash.into()));
+        assert_eq!(
+            diff(&[a, b, c], &[a, c, c]),
+            "  decommit 0x1\n- decommit 0x2\n  decommit 0x3\n+ decommit 0x3\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "- decommit 0x2")]
+    fn asserting_missing_access() {
+        let (world, _) = recording_world();
+        world.assert_access_set([WorldAccess::Decommit(2.into())]);
+    }
+}
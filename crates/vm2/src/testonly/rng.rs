@@ -0,0 +1,171 @@
+//! Deterministic pseudo-random number generation for tests.
+
+use primitive_types::{H160, U256};
+
+const SEED_ENV_VAR: &str = "VM2_TEST_SEED";
+const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Deterministic pseudo-random number generator based on `SplitMix64`.
+///
+/// Unlike [`DefaultHasher`](std::collections::hash_map::DefaultHasher) or thread-local RNGs, the produced
+/// sequence depends only on the seed, so it's identical across platforms and Rust versions. This makes it suitable
+/// for tracers and fuzz harnesses that need randomness but must stay reproducible for shrinking and replay.
+/// The generator is passed around explicitly rather than stored globally, so that independent consumers
+/// don't affect each other's sequences; use [`Self::fork()`] to derive independent generators.
+///
+/// This generator is **not** cryptographically secure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestRng {
+    seed: u64,
+    state: u64,
+}
+
+impl TestRng {
+    /// Creates a generator with the specified seed.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Creates a generator with the seed taken from the `VM2_TEST_SEED` env variable (decimal or `0x`-prefixed hex),
+    /// or a fixed default seed if the variable is not set. The seed can be obtained via [`Self::seed()`] to replay
+    /// a failing test.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the env variable is set, but is not a valid seed.
+    pub fn from_env() -> Self {
+        let Ok(raw_seed) = std::env::var(SEED_ENV_VAR) else {
+            return Self::new(DEFAULT_SEED);
+        };
+        let seed = if let Some(hex) = raw_seed.strip_prefix("0x") {
+            u64::from_str_radix(hex, 16)
+        } else {
+            raw_seed.parse()
+        };
+        let seed =
+            seed.unwrap_or_else(|err| panic!("invalid `{SEED_ENV_VAR}` {raw_seed:?}: {err}"));
+        Self::new(seed)
+    }
+
+    /// Returns the seed this generator was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Derives an independent generator from this one, advancing this generator by a single step.
+    #[must_use]
+    pub fn fork(&mut self) -> Self {
+        Self::new(self.next_u64())
+    }
+
+    /// Returns the next pseudo-random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the next pseudo-random `u32`.
+    #[allow(clippy::cast_possible_truncation)] // no truncation happens after the shift
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Returns a pseudo-random value in `0..bound`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bound` is zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "bound must be positive");
+        // Rejection sampling to avoid modulo bias.
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+
+    /// Returns `true` with the probability of `numerator / denominator`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denominator` is zero.
+    pub fn ratio(&mut self, numerator: u64, denominator: u64) -> bool {
+        self.below(denominator) < numerator
+    }
+
+    /// Fills the provided buffer with pseudo-random bytes.
+    pub fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    /// Returns a pseudo-random `U256`.
+    pub fn next_u256(&mut self) -> U256 {
+        U256([
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64(),
+        ])
+    }
+
+    /// Returns a pseudo-random address.
+    pub fn next_address(&mut self) -> H160 {
+        let mut address = H160::zero();
+        self.fill_bytes(address.as_bytes_mut());
+        address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_is_stable() {
+        let mut rng = TestRng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+        assert_eq!(rng.seed(), 0);
+    }
+
+    #[test]
+    fn replaying_from_seed() {
+        let mut rng = TestRng::new(123);
+        let mut forked = rng.fork();
+        let values: Vec<_> = (0..10).map(|_| rng.below(7)).collect();
+        let forked_values: Vec<_> = (0..10).map(|_| forked.next_u64()).collect();
+
+        let mut replayed = TestRng::new(123);
+        let mut replayed_fork = replayed.fork();
+        assert_eq!(replayed_fork.seed(), forked.seed());
+        assert_eq!(
+            values,
+            (0..10).map(|_| replayed.below(7)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            forked_values,
+            (0..10)
+                .map(|_| replayed_fork.next_u64())
+                .collect::<Vec<_>>()
+        );
+        assert!(values.iter().all(|&value| value < 7));
+    }
+
+    #[test]
+    fn filling_bytes() {
+        let mut bytes = [0; 20];
+        TestRng::new(0).fill_bytes(&mut bytes);
+        assert_eq!(bytes[..8], 0xe220_a839_7b1d_cdaf_u64.to_le_bytes());
+        assert_eq!(bytes[16..], 0x06c4_5d18_8009_454f_u64.to_le_bytes()[..4]);
+        assert_eq!(TestRng::new(0).next_address().as_bytes(), bytes);
+    }
+}
@@ -0,0 +1,99 @@
+//! Tests for arithmetic at boundary values, which must behave the same in debug and release builds.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+
+use crate::{
+    addressing_modes::{Arguments, CodePage, Register, Register1, Register2, RegisterAndImmediate},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+fn run(
+    instructions: Vec<Instruction<(), TestWorld<()>>>,
+    code_page: Vec<U256>,
+    gas: u32,
+) -> ExecutionEnd {
+    let program = Program::from_raw(instructions, code_page);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        gas,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.run(&mut world, &mut ())
+}
+
+fn arguments(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// Reads the heap at `address` and increments the pointer.
+fn run_heap_read(address: u32, gas: u32) -> ExecutionEnd {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let instructions = vec![
+        Instruction::from_add(
+            CodePage(RegisterAndImmediate {
+                immediate: 0,
+                register: r0,
+            })
+            .into(),
+            Register2(r0),
+            Register1(r1).into(),
+            arguments(6),
+            false,
+            false,
+        ),
+        Instruction::from_heap_read(
+            Register1(r1).into(),
+            Register1(Register::new(2)),
+            Some(Register2(Register::new(3))),
+            arguments(7),
+        ),
+        Instruction::from_ret(Register1(r0), None, arguments(5)),
+    ];
+    run(instructions, vec![address.into()], gas)
+}
+
+#[test]
+fn heap_access_beyond_last_address_panics() {
+    for address in [u32::MAX - 31, u32::MAX] {
+        assert_eq!(
+            run_heap_read(address, 100_000),
+            ExecutionEnd::Panicked,
+            "{address}"
+        );
+    }
+}
+
+#[test]
+fn heap_access_at_last_address() {
+    let address = u32::MAX - 32;
+    // Growing the heap to `u32::MAX` bytes costs almost all gas.
+    assert_eq!(run_heap_read(address, 100_000), ExecutionEnd::Panicked);
+    assert_eq!(
+        run_heap_read(address, u32::MAX),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+}
+
+#[test]
+fn running_with_gas_near_zero() {
+    let ret = || Instruction::from_ret(Register1(Register::new(0)), None, arguments(5));
+    assert_eq!(
+        run(vec![ret()], vec![], 5),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(run(vec![ret()], vec![], 4), ExecutionEnd::Panicked);
+    assert_eq!(run(vec![ret()], vec![], 0), ExecutionEnd::Panicked);
+}
@@ -0,0 +1,301 @@
+//! Unit tests of arithmetic and bitwise instruction handlers executed via [`exec_one()`].
+
+use std::collections::BTreeMap;
+
+use primitive_types::U256;
+use zksync_vm2_interface::Flags;
+
+use crate::{
+    addressing_modes::{
+        AnyDestination, AnySource, Arguments, Immediate1, Register, Register1, Register2,
+    },
+    testonly::{exec_one, StateFixture, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, StateDelta,
+};
+
+type TestInstruction = Instruction<(), TestWorld<()>>;
+
+const GAS: u32 = 6;
+const NO_FLAGS: Flags = Flags {
+    less_than: false,
+    equal: false,
+    greater: false,
+};
+const LT: Flags = Flags {
+    less_than: true,
+    ..NO_FLAGS
+};
+const EQ: Flags = Flags {
+    equal: true,
+    ..NO_FLAGS
+};
+const LT_EQ: Flags = Flags {
+    less_than: true,
+    ..EQ
+};
+const GT: Flags = Flags {
+    greater: true,
+    ..NO_FLAGS
+};
+
+fn r(index: u8) -> Register {
+    Register::new(index)
+}
+
+fn args() -> Arguments {
+    Arguments::new(Predicate::Always, GAS, ModeRequirements::none())
+}
+
+/// Signature of constructors of operations with a single output.
+type BinopConstructor =
+    fn(AnySource, Register2, AnyDestination, Arguments, bool, bool) -> TestInstruction;
+/// Signature of constructors of operations with two outputs.
+type TwoOutputBinopConstructor =
+    fn(AnySource, Register2, AnyDestination, Register2, Arguments, bool, bool) -> TestInstruction;
+/// Value of output registers before executing an instruction, chosen to differ from all outputs in tests.
+const INITIAL_OUTPUT: u32 = 0xdead;
+
+/// Executes `r3 = r1 <op> r2` and checks that only `r3` has changed.
+fn exec_binop(
+    constructor: BinopConstructor,
+    a: impl Into<U256>,
+    b: impl Into<U256>,
+    swap: bool,
+    set_flags: bool,
+) -> (U256, Flags) {
+    let instruction = constructor(
+        Register1(r(1)).into(),
+        Register2(r(2)),
+        Register1(r(3)).into(),
+        args(),
+        swap,
+        set_flags,
+    );
+    let fixture = StateFixture::default()
+        .with_register(1, a)
+        .with_register(2, b)
+        .with_register(3, INITIAL_OUTPUT);
+    let (end, delta) = exec_one(instruction, &fixture);
+    assert_successful(end, &delta);
+    assert_eq!(delta.registers.keys().copied().collect::<Vec<_>>(), [3]);
+    (delta.registers[&3].0, delta.flags.unwrap_or(fixture.flags))
+}
+
+fn assert_successful(end: Option<ExecutionEnd>, delta: &StateDelta) {
+    assert_eq!(end, None);
+    assert_eq!(delta.gas_used, GAS);
+    assert!(delta.registers.values().all(|&(_, is_pointer)| !is_pointer));
+}
+
+#[test]
+fn add() {
+    let add = Instruction::from_add as BinopConstructor;
+    assert_eq!(exec_binop(add, 2, 3, false, true), (5.into(), GT));
+    assert_eq!(
+        exec_binop(add, U256::MAX, 1, false, true),
+        (U256::zero(), LT_EQ)
+    );
+    assert_eq!(exec_binop(add, U256::MAX, 2, false, true), (1.into(), LT));
+    assert_eq!(exec_binop(add, 0, 0, true, true), (U256::zero(), EQ));
+}
+
+#[test]
+fn add_immediate() {
+    let instruction = Instruction::from_add(
+        Immediate1(42).into(),
+        Register2(r(1)),
+        Register1(r(2)).into(),
+        args(),
+        false,
+        false,
+    );
+    let (end, delta) = exec_one(instruction, &StateFixture::default().with_register(1, 8));
+    assert_successful(end, &delta);
+    assert_eq!(delta.registers, BTreeMap::from([(2, (50.into(), false))]));
+}
+
+#[test]
+fn sub() {
+    let sub = Instruction::from_sub as BinopConstructor;
+    assert_eq!(exec_binop(sub, 5, 3, false, true), (2.into(), GT));
+    assert_eq!(exec_binop(sub, 3, 3, false, true), (U256::zero(), EQ));
+    assert_eq!(exec_binop(sub, 3, 5, false, true), (U256::MAX - 1, LT));
+    // Swapping operands computes `r2 - r1`.
+    assert_eq!(exec_binop(sub, 3, 5, true, true), (2.into(), GT));
+}
+
+#[test]
+fn flags_are_only_changed_with_set_flags() {
+    let sub = Instruction::from_sub as BinopConstructor;
+    let initial_flags = [NO_FLAGS, LT, EQ, GT];
+    for flags in initial_flags {
+        let instruction = sub(
+            Register1(r(1)).into(),
+            Register2(r(2)),
+            Register1(r(3)).into(),
+            args(),
+            false,
+            false,
+        );
+        let fixture = StateFixture::default()
+            .with_register(1, 3)
+            .with_register(2, 5)
+            .with_flags(flags);
+        let (end, delta) = exec_one(instruction, &fixture);
+        assert_successful(end, &delta);
+        assert_eq!(delta.flags, None);
+    }
+}
+
+#[test]
+fn bitwise_operations() {
+    let (a, b) = (0b1100, 0b1010);
+    let and = Instruction::from_and as BinopConstructor;
+    assert_eq!(
+        exec_binop(and, a, b, false, true),
+        (0b1000.into(), NO_FLAGS)
+    );
+    assert_eq!(exec_binop(and, a, 0b0011, false, true), (U256::zero(), EQ));
+    let or = Instruction::from_or as BinopConstructor;
+    assert_eq!(exec_binop(or, a, b, false, true), (0b1110.into(), NO_FLAGS));
+    assert_eq!(exec_binop(or, 0, 0, false, true), (U256::zero(), EQ));
+    let xor = Instruction::from_xor as BinopConstructor;
+    assert_eq!(
+        exec_binop(xor, a, b, false, true),
+        (0b0110.into(), NO_FLAGS)
+    );
+    assert_eq!(exec_binop(xor, a, a, false, true), (U256::zero(), EQ));
+}
+
+#[test]
+fn shifts() {
+    let shl = Instruction::from_shift_left as BinopConstructor;
+    assert_eq!(
+        exec_binop(shl, 1, 255, false, true),
+        (U256::one() << 255, NO_FLAGS)
+    );
+    assert_eq!(exec_binop(shl, 2, 255, false, true), (U256::zero(), EQ));
+    // Only the lowest byte of the shift is used.
+    assert_eq!(exec_binop(shl, 1, 257, false, true), (2.into(), NO_FLAGS));
+    // Swapping operands shifts `r2` by `r1`.
+    assert_eq!(exec_binop(shl, 3, 1, true, true), (8.into(), NO_FLAGS));
+
+    let shr = Instruction::from_shift_right as BinopConstructor;
+    assert_eq!(
+        exec_binop(shr, U256::one() << 255, 255, false, true),
+        (U256::one(), NO_FLAGS)
+    );
+    assert_eq!(exec_binop(shr, 1, 1, false, true), (U256::zero(), EQ));
+    assert_eq!(exec_binop(shr, 4, 258, false, true), (1.into(), NO_FLAGS));
+}
+
+#[test]
+fn rotations() {
+    let rol = Instruction::from_rotate_left as BinopConstructor;
+    assert_eq!(
+        exec_binop(rol, U256::one() << 255, 1, false, true),
+        (U256::one(), NO_FLAGS)
+    );
+    assert_eq!(exec_binop(rol, 0, 5, false, true), (U256::zero(), EQ));
+    assert_eq!(
+        exec_binop(rol, 3, 255 + 256, false, true),
+        ((U256::one() << 255) | U256::one(), NO_FLAGS)
+    );
+
+    let ror = Instruction::from_rotate_right as BinopConstructor;
+    assert_eq!(
+        exec_binop(ror, 1, 1, false, true),
+        (U256::one() << 255, NO_FLAGS)
+    );
+    assert_eq!(exec_binop(ror, 6, 1, false, true), (3.into(), NO_FLAGS));
+}
+
+/// Executes `r3, r4 = r1 <op> r2` for an operation with two outputs.
+fn exec_two_output_binop(
+    constructor: TwoOutputBinopConstructor,
+    a: impl Into<U256>,
+    b: impl Into<U256>,
+) -> ([U256; 2], Flags) {
+    let instruction = constructor(
+        Register1(r(1)).into(),
+        Register2(r(2)),
+        Register1(r(3)).into(),
+        Register2(r(4)),
+        args(),
+        false,
+        true,
+    );
+    let fixture = StateFixture::default()
+        .with_register(1, a)
+        .with_register(2, b)
+        .with_register(3, INITIAL_OUTPUT)
+        .with_register(4, INITIAL_OUTPUT);
+    let (end, delta) = exec_one(instruction, &fixture);
+    assert_successful(end, &delta);
+    assert_eq!(delta.registers.keys().copied().collect::<Vec<_>>(), [3, 4]);
+    (
+        [delta.registers[&3].0, delta.registers[&4].0],
+        delta.flags.unwrap_or(fixture.flags),
+    )
+}
+
+#[test]
+fn mul() {
+    let mul = Instruction::from_mul;
+    assert_eq!(
+        exec_two_output_binop(mul, 6, 7),
+        ([42.into(), U256::zero()], GT)
+    );
+    assert_eq!(
+        exec_two_output_binop(mul, 0, 7),
+        ([U256::zero(), U256::zero()], EQ)
+    );
+    assert_eq!(
+        exec_two_output_binop(mul, U256::one() << 255, 4),
+        ([U256::zero(), 2.into()], LT_EQ)
+    );
+    assert_eq!(
+        exec_two_output_binop(mul, U256::MAX, 2),
+        ([U256::MAX - 1, 1.into()], LT)
+    );
+}
+
+#[test]
+fn div() {
+    let div = Instruction::from_div;
+    assert_eq!(
+        exec_two_output_binop(div, 7, 2),
+        ([3.into(), 1.into()], NO_FLAGS)
+    );
+    assert_eq!(
+        exec_two_output_binop(div, 8, 2),
+        ([4.into(), U256::zero()], GT)
+    );
+    assert_eq!(
+        exec_two_output_binop(div, 1, 2),
+        ([U256::zero(), 1.into()], EQ)
+    );
+    assert_eq!(
+        exec_two_output_binop(div, 7, 0),
+        ([U256::zero(), U256::zero()], LT)
+    );
+}
+
+#[test]
+fn binop_without_enough_gas_panics() {
+    let instruction = Instruction::from_add(
+        Register1(r(1)).into(),
+        Register2(r(2)),
+        Register1(r(3)).into(),
+        args(),
+        false,
+        true,
+    );
+    let fixture = StateFixture {
+        gas: GAS - 1,
+        ..StateFixture::default().with_register(1, 1)
+    };
+    let (end, delta) = exec_one(instruction, &fixture);
+    assert_eq!(end, Some(ExecutionEnd::Panicked));
+    assert!(delta.registers.is_empty());
+}
@@ -0,0 +1,76 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{HeapId, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Register, Register1},
+    testonly::{initial_decommit, TestWorld},
+    Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+fn create_vm() -> VirtualMachine<(), TestWorld<()>> {
+    let ret = Instruction::from_ret(
+        Register1(Register::new(0)),
+        None,
+        Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+    );
+    let program = Program::from_raw(vec![ret], vec![]);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn writing_to_bootloader_heap() {
+    let mut vm = create_vm();
+    vm.write_to_bootloader_heap(64, &[U256::one(), U256::MAX]);
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 64), U256::one());
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 96), U256::MAX);
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 128), U256::zero());
+}
+
+#[test]
+fn bootloader_heap_writes_are_rolled_back() {
+    let mut vm = create_vm();
+    vm.write_to_bootloader_heap(0, &[U256::one()]);
+    vm.make_snapshot();
+    vm.write_to_bootloader_heap(0, &[2.into(), 3.into()]);
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 32), 3.into());
+
+    vm.rollback();
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 0), U256::one());
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 32), U256::zero());
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit into 32-bit addresses")]
+fn overflowing_bootloader_heap_write() {
+    let mut vm = create_vm();
+    vm.write_to_bootloader_heap(u32::MAX - 16, &[U256::one()]);
+}
+
+#[test]
+fn compacting_bootloader_heap_history() {
+    let mut vm = create_vm();
+    vm.write_to_bootloader_heap(0, &[U256::one(); 4]);
+    // Without a snapshot, the history isn't needed.
+    vm.compact_history();
+    assert_eq!(vm.history_stats().heap_entries, 0);
+
+    vm.make_snapshot();
+    for i in 0..100_u64 {
+        vm.write_to_bootloader_heap(0, &[i.into(), i.into()]);
+    }
+    assert_eq!(vm.history_stats().heap_entries, 200);
+    vm.compact_history();
+    assert_eq!(vm.history_stats().heap_entries, 2);
+
+    vm.rollback();
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 0), U256::one());
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 32), U256::one());
+}
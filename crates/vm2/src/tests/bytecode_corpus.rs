@@ -0,0 +1,238 @@
+//! Smoke tests for a corpus of real contract bytecodes. These tests are only compiled with the `bytecode_corpus`
+//! feature; see `bytecodes/corpus/README.md` for how to obtain the corpus.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::{
+    ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
+};
+
+use crate::{
+    addressing_modes::{Arguments, Register, Register1},
+    instruction_handlers::address_into_u256,
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, StorageInterface,
+    StorageSlot, VirtualMachine, World,
+};
+
+const CORPUS_ENV_VAR: &str = "VM2_BYTECODE_CORPUS";
+const GAS: u32 = 10_000_000;
+/// Instruction budget for a single entry point, so that unexpectedly long executions don't stall the tests.
+const INSTRUCTION_BUDGET: u64 = 1_000_000;
+/// Bytecode hash returned for accounts without code. Any call to such an account (including system contracts,
+/// which aren't part of the corpus) immediately returns.
+const STUB_HASH: [u8; 32] = {
+    let mut hash = [0; 32];
+    hash[0] = 1;
+    hash[3] = 1;
+    hash[31] = 0xff;
+    hash
+};
+
+#[derive(Debug)]
+struct CorpusEntry {
+    name: String,
+    bytecode: Vec<u8>,
+    calls: Vec<Vec<u8>>,
+}
+
+fn corpus_dir() -> PathBuf {
+    std::env::var_os(CORPUS_ENV_VAR).map_or_else(
+        || Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/bytecodes/corpus"),
+        PathBuf::from,
+    )
+}
+
+fn parse_hex(name: &str, hex: &str) -> Vec<u8> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    assert_eq!(hex.len() % 2, 0, "{name}: odd number of hex digits");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .unwrap_or_else(|err| panic!("{name}: invalid hex: {err}"))
+        })
+        .collect()
+}
+
+/// Loads `<name>.hex` bytecode files together with optional `<name>.calls` files listing hex-encoded calldata
+/// for the entry points to execute, one per line.
+fn load_corpus() -> Vec<CorpusEntry> {
+    let dir = corpus_dir();
+    let entries = fs::read_dir(&dir).unwrap_or_else(|err| {
+        panic!("cannot read bytecode corpus at {dir:?} ({err}); see `bytecodes/corpus/README.md`")
+    });
+
+    let mut corpus: Vec<_> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "hex"))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let bytecode = parse_hex(&name, &fs::read_to_string(&path).unwrap());
+            let calls = fs::read_to_string(path.with_extension("calls"))
+                .map(|calls| {
+                    calls
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(|line| parse_hex(&name, line))
+                        .collect()
+                })
+                .unwrap_or_default();
+            CorpusEntry {
+                name,
+                bytecode,
+                calls,
+            }
+        })
+        .collect();
+    assert!(!corpus.is_empty(), "bytecode corpus at {dir:?} is empty");
+    corpus.sort_by(|a, b| a.name.cmp(&b.name));
+    corpus
+}
+
+/// Mock world containing a single contract. All storage slots are empty and all other accounts are stubs.
+#[derive(Debug)]
+struct CorpusWorld {
+    address: H160,
+    hash: U256,
+    programs: HashMap<U256, Program<(), Self>>,
+    bytecode: Vec<u8>,
+}
+
+impl CorpusWorld {
+    fn new(address: H160, bytecode: &[u8]) -> Self {
+        let len_in_words = u16::try_from(bytecode.len() / 32).expect("bytecode is too long");
+        let mut hash = [0; 32];
+        hash[0] = 1;
+        hash[2..4].copy_from_slice(&len_in_words.to_be_bytes());
+        let hash = U256::from_big_endian(&hash);
+
+        let ret = Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+        );
+        let programs = HashMap::from([
+            (hash, Program::new(bytecode, false)),
+            (
+                U256::from_big_endian(&STUB_HASH),
+                Program::from_raw(vec![ret], vec![]),
+            ),
+        ]);
+        Self {
+            address,
+            hash,
+            programs,
+            bytecode: bytecode.to_vec(),
+        }
+    }
+}
+
+impl StorageInterface for CorpusWorld {
+    fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
+        let deployer_system_contract_address =
+            Address::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        if contract == deployer_system_contract_address && key == address_into_u256(self.address) {
+            StorageSlot {
+                value: self.hash,
+                is_write_initial: false,
+            }
+        } else {
+            StorageSlot::EMPTY
+        }
+    }
+
+    fn cost_of_writing_storage(&mut self, _initial_slot: StorageSlot, _new_value: U256) -> u32 {
+        50
+    }
+
+    fn is_free_storage_slot(&self, _contract: &H160, _key: &U256) -> bool {
+        false
+    }
+}
+
+impl World<()> for CorpusWorld {
+    fn decommit(&mut self, hash: U256) -> Program<(), Self> {
+        self.programs
+            .get(&hash)
+            .unwrap_or_else(|| &self.programs[&U256::from_big_endian(&STUB_HASH)])
+            .clone()
+    }
+
+    fn decommit_code(&mut self, hash: U256) -> Vec<u8> {
+        if hash == self.hash {
+            self.bytecode.clone()
+        } else {
+            vec![0; 32]
+        }
+    }
+}
+
+#[test]
+fn decoding_corpus_bytecodes() {
+    for entry in load_corpus() {
+        assert_eq!(
+            entry.bytecode.len() % 32,
+            0,
+            "{}: bytecode length is not divisible by 32",
+            entry.name
+        );
+        let program = Program::<(), CorpusWorld>::new(&entry.bytecode, false);
+        assert_eq!(program.code_page().len(), entry.bytecode.len() / 32);
+        for raw in entry.bytecode.chunks_exact(8) {
+            let raw = u64::from_be_bytes(raw.try_into().unwrap());
+            // Decoding must not panic regardless of whether `raw` is an instruction or data.
+            crate::decode::decode::<(), CorpusWorld>(raw, false);
+            crate::decode::decode::<(), CorpusWorld>(raw, true);
+        }
+    }
+}
+
+#[test]
+fn executing_corpus_entry_points() {
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    for entry in load_corpus() {
+        let mut world = CorpusWorld::new(address, &entry.bytecode);
+        let calls = [Vec::new()].into_iter().chain(entry.calls);
+        for calldata in calls {
+            let program = world.decommit(world.hash);
+            let mut vm = VirtualMachine::new(
+                address,
+                program,
+                Address::zero(),
+                &calldata,
+                GAS,
+                Settings {
+                    default_aa_code_hash: STUB_HASH,
+                    evm_interpreter_code_hash: STUB_HASH,
+                    hook_address: 0,
+                },
+            );
+
+            let mut budget = INSTRUCTION_BUDGET;
+            let end = loop {
+                match vm.run_with_instruction_limit(&mut world, &mut (), &mut budget) {
+                    ExecutionEnd::SuspendedOnHook(_) => continue,
+                    end => break end,
+                }
+            };
+            assert!(
+                matches!(
+                    end,
+                    ExecutionEnd::ProgramFinished(_)
+                        | ExecutionEnd::Reverted(_)
+                        | ExecutionEnd::Panicked
+                        | ExecutionEnd::InstructionLimit
+                ),
+                "{}: unexpected end {end:?}",
+                entry.name
+            );
+        }
+    }
+}
@@ -0,0 +1,26 @@
+#!/usr/bin/env bash
+# Downloads the bytecode of a deployed contract to the corpus directory.
+# Usage: ./fetch.sh <rpc_url> <name> <address>
+
+set -euo pipefail
+
+if [[ $# -ne 3 ]]; then
+  echo "Usage: $0 <rpc_url> <name> <address>" >&2
+  exit 1
+fi
+
+RPC_URL=$1
+NAME=$2
+ADDRESS=$3
+DIR=$(dirname "$0")
+
+REQUEST="{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"eth_getCode\",\"params\":[\"$ADDRESS\",\"latest\"]}"
+CODE=$(curl -sSf -H 'Content-Type: application/json' -d "$REQUEST" "$RPC_URL" |
+  sed -n 's/.*"result":"\(0x[0-9a-fA-F]*\)".*/\1/p')
+
+if [[ -z "$CODE" || "$CODE" == "0x" ]]; then
+  echo "No code at $ADDRESS" >&2
+  exit 1
+fi
+echo "$CODE" > "$DIR/$NAME.hex"
+echo "Saved $(( (${#CODE} - 2) / 2 )) bytes to $DIR/$NAME.hex"
@@ -0,0 +1,51 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::StateInterface;
+
+use crate::{
+    addressing_modes::{Arguments, Register, Register1},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, FatPointer, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+#[test]
+fn setting_calldata() {
+    let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+    let program = Program::from_raw(
+        vec![
+            // Read the first calldata word into r2.
+            Instruction::from_pointer_read(
+                Register1(Register::new(1)),
+                Register1(Register::new(2)),
+                None,
+                arguments(7),
+            ),
+            Instruction::from_ret(Register1(Register::new(0)), None, arguments(5)),
+        ],
+        vec![],
+    );
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .calldata([0xff; 64])
+        .build()
+        .unwrap();
+
+    let calldata: Vec<u8> = (1..=16).collect();
+    vm.set_calldata(&calldata);
+    let (pointer, is_pointer) = vm.read_register(1);
+    assert!(is_pointer);
+    assert_eq!(FatPointer::from(pointer).read_from(&vm), calldata);
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    // The word is padded with zeros since it extends beyond the calldata end.
+    let mut expected_word = [0_u8; 32];
+    expected_word[..16].copy_from_slice(&calldata);
+    assert_eq!(vm.read_register(2).0, U256::from_big_endian(&expected_word));
+}
@@ -0,0 +1,172 @@
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    opcodes::{self, TypeLevelCallingMode},
+    CallframeInterface, GlobalStateInterface, Opcode, OpcodeType, ReturnType, StateInterface,
+    Tracer,
+};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    instruction_handlers::address_into_u256,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const MAIN_CALLER: Address = Address::repeat_byte(0x01);
+const CALLED_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
+]);
+const MIMICKED_CALLER: Address = Address::repeat_byte(0x42);
+
+/// Addresses observed in the called contract: `(this, caller, code_address)`.
+type Addresses = (H160, H160, H160);
+
+/// Records addresses in the called frame just before it returns.
+#[derive(Debug, Default)]
+struct AddressRecorder {
+    from_interface: Option<Addresses>,
+    from_context_opcodes: Option<Addresses>,
+}
+
+impl Tracer for AddressRecorder {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if OP::VALUE != Opcode::Ret(ReturnType::Normal) || state.number_of_callframes() != 2 {
+            return;
+        }
+
+        let from_interface = {
+            let frame = state.current_frame();
+            (frame.address(), frame.caller(), frame.code_address())
+        };
+        self.from_interface = Some(from_interface);
+        let read_address = |register| {
+            let (value, _) = state.read_register(register);
+            let mut bytes = [0; 32];
+            value.to_big_endian(&mut bytes);
+            H160::from_slice(&bytes[12..])
+        };
+        self.from_context_opcodes = Some((read_address(1), read_address(2), read_address(3)));
+    }
+}
+
+fn args(gas_cost: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas_cost, ModeRequirements::none())
+}
+
+fn load_from_code_page(
+    immediate: u16,
+    out: Register,
+) -> Instruction<AddressRecorder, TestWorld<AddressRecorder>> {
+    Instruction::from_add(
+        CodePage(RegisterAndImmediate {
+            immediate,
+            register: Register::new(0),
+        })
+        .into(),
+        Register2(Register::new(0)),
+        Register1(out).into(),
+        args(6),
+        false,
+        false,
+    )
+}
+
+fn record_called_frame_addresses<M: TypeLevelCallingMode>() -> AddressRecorder {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r3 = Register::new(3);
+
+    let mut abi = U256::zero();
+    abi.0[3] = 10_000;
+    let main_program = Program::from_raw(
+        vec![
+            load_from_code_page(0, r1),
+            load_from_code_page(1, r2),
+            // Mimic calls use r15 as the caller.
+            load_from_code_page(2, Register::new(15)),
+            Instruction::from_far_call::<M>(
+                Register1(r1),
+                Register2(r2),
+                Immediate1(5),
+                false,
+                false,
+                args(200),
+            ),
+            Instruction::from_ret(Register1(r0), None, args(5)),
+            // 5: Exception handler
+            Instruction::from_revert(Register1(r0), None, args(5)),
+        ],
+        vec![
+            abi,
+            address_into_u256(CALLED_ADDRESS),
+            address_into_u256(MIMICKED_CALLER),
+        ],
+    );
+    let called_program = Program::from_raw(
+        vec![
+            Instruction::from_this(Register1(r1), args(5)),
+            Instruction::from_caller(Register1(r2), args(5)),
+            Instruction::from_code_address(Register1(r3), args(5)),
+            Instruction::from_ret(Register1(r0), None, args(5)),
+        ],
+        vec![],
+    );
+
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let main_program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        main_program,
+        MAIN_CALLER,
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let mut tracer = AddressRecorder::default();
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(tracer.from_interface, tracer.from_context_opcodes);
+    tracer
+}
+
+#[test]
+fn normal_call_addresses() {
+    let tracer = record_called_frame_addresses::<opcodes::Normal>();
+    assert_eq!(
+        tracer.from_interface,
+        Some((CALLED_ADDRESS, MAIN_ADDRESS, CALLED_ADDRESS))
+    );
+}
+
+#[test]
+fn delegate_call_addresses() {
+    // Delegate calls keep the address and the caller of the calling frame.
+    let tracer = record_called_frame_addresses::<opcodes::Delegate>();
+    assert_eq!(
+        tracer.from_interface,
+        Some((MAIN_ADDRESS, MAIN_CALLER, CALLED_ADDRESS))
+    );
+}
+
+#[test]
+fn mimic_call_addresses() {
+    let tracer = record_called_frame_addresses::<opcodes::Mimic>();
+    assert_eq!(
+        tracer.from_interface,
+        Some((CALLED_ADDRESS, MIMICKED_CALLER, CALLED_ADDRESS))
+    );
+}
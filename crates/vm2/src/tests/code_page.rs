@@ -0,0 +1,86 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+#[test]
+fn code_page_reads() {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r3 = Register::new(3);
+
+    let read_code_page = |register, immediate, out| {
+        Instruction::from_add(
+            CodePage(RegisterAndImmediate {
+                immediate,
+                register,
+            })
+            .into(),
+            Register2(r0),
+            Register1(out).into(),
+            Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+            false,
+            false,
+        )
+    };
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                Immediate1(1).into(),
+                Register2(r0),
+                Register1(r1).into(),
+                Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            read_code_page(r0, 0, r2),
+            // Register + immediate addressing
+            read_code_page(r1, 0, r1),
+            // Out of range read
+            read_code_page(r0, 2, r3),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![U256::MAX, 42.into()],
+    );
+
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    assert_eq!(program.code_page(), [U256::MAX, 42.into()]);
+
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        1000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.state.registers[3] = 1.into();
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+
+    assert_eq!(vm.read_register(1), (42.into(), false));
+    assert_eq!(vm.read_register(2), (U256::MAX, false));
+    assert_eq!(vm.read_register(3), (U256::zero(), false));
+    assert_eq!(vm.current_frame().read_contract_code(1), 42.into());
+    assert_eq!(vm.current_frame().read_contract_code(2), U256::zero());
+}
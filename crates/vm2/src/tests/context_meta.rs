@@ -0,0 +1,94 @@
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::{
+    ethereum_types::Address, system_params::NEW_FRAME_MEMORY_STIPEND, VmMetaParameters,
+};
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+/// Grows the heap, then reads VM metadata into r1.
+fn read_meta(address: H160, pubdata: i32) -> (U256, u32, u32) {
+    let r0 = Register::new(0);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_heap_write(
+                Immediate1(5_000).into(),
+                Register2(r0),
+                None,
+                Arguments::new(Predicate::Always, 7, ModeRequirements::none()),
+                false,
+            ),
+            Instruction::from_context_meta(
+                Register1(Register::new(1)),
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        100_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.set_pubdata(pubdata);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+
+    let (meta, is_pointer) = vm.read_register(1);
+    assert!(!is_pointer);
+    let heap_bound = vm.current_frame().heap_bound();
+    let aux_heap_bound = vm.current_frame().aux_heap_bound();
+    (meta, heap_bound, aux_heap_bound)
+}
+
+#[test]
+fn context_meta_in_user_contract() {
+    let (meta, heap_size, aux_heap_size) = read_meta(Address::repeat_byte(0x23), 42);
+    assert_eq!(heap_size, NEW_FRAME_MEMORY_STIPEND.max(5_032));
+    let expected = VmMetaParameters {
+        heap_size,
+        aux_heap_size,
+        this_shard_id: 0,
+        caller_shard_id: 0,
+        code_shard_id: 0,
+        // Pubdata is only exposed to kernel contracts
+        aux_field_0: 0,
+    };
+    assert_eq!(meta, expected.to_u256());
+}
+
+#[test]
+fn context_meta_in_kernel_contract() {
+    let (meta, heap_size, aux_heap_size) = read_meta(Address::from_low_u64_be(0x8002), -3);
+    let expected = VmMetaParameters {
+        heap_size,
+        aux_heap_size,
+        this_shard_id: 0,
+        caller_shard_id: 0,
+        code_shard_id: 0,
+        #[allow(clippy::cast_sign_loss)] // negative pubdata is wrapped
+        aux_field_0: -3_i32 as u32,
+    };
+    assert_eq!(meta, expected.to_u256());
+}
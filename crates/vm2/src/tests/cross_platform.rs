@@ -0,0 +1,187 @@
+//! Tests checking that VM state doesn't depend on the endianness or pointer width of the target.
+//!
+//! Expected values are spelled out explicitly instead of being derived from native integer layouts, so these tests
+//! are meaningful on big-endian and 32-bit targets. Such targets can be tested without the corresponding hardware
+//! using Miri, e.g. `cargo +nightly miri-test --target s390x-unknown-linux-gnu -- cross_platform`.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{HeapId, StateInterface};
+
+use crate::{
+    addressing_modes::{AnyDestination, AnySource, Arguments, Register, Register1, Register2},
+    allocator::HEAP_PAGE_SIZE,
+    decommit::u256_into_address,
+    instruction_handlers::address_into_u256,
+    testonly::{exec_one, initial_decommit, StateFixture, TestWorld},
+    FatPointer, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+/// Big-endian representation of [`word()`]: bytes `1..=32`.
+#[allow(clippy::cast_possible_truncation)] // `i < 32`
+const WORD_BYTES: [u8; 32] = {
+    let mut bytes = [0; 32];
+    let mut i = 0;
+    while i < 32 {
+        bytes[i] = i as u8 + 1;
+        i += 1;
+    }
+    bytes
+};
+
+/// Word with distinct bytes, given by its limbs from the least significant one.
+fn word() -> U256 {
+    U256([
+        0x191a_1b1c_1d1e_1f20,
+        0x1112_1314_1516_1718,
+        0x090a_0b0c_0d0e_0f10,
+        0x0102_0304_0506_0708,
+    ])
+}
+
+fn vm(calldata: Vec<u8>) -> VirtualMachine<(), TestWorld<()>> {
+    let ret = Instruction::from_ret(
+        Register1(Register::new(0)),
+        None,
+        Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+    );
+    let program = Program::from_raw(vec![ret], vec![]);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .calldata(calldata)
+        .build()
+        .unwrap()
+}
+
+#[track_caller]
+fn assert_word_at(vm: &VirtualMachine<(), TestWorld<()>>, offset: u32) {
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, offset), word());
+    let bytes: Vec<_> = (0..32)
+        .map(|i| vm.read_heap_byte(HeapId::FIRST, offset + i))
+        .collect();
+    assert_eq!(bytes, WORD_BYTES);
+    if let Some(end) = offset.checked_add(32) {
+        assert_eq!(vm.read_heap_range(HeapId::FIRST, offset..end), WORD_BYTES);
+    }
+}
+
+#[test]
+fn heap_words_are_big_endian() {
+    let mut vm = vm(vec![]);
+    vm.write_heap_u256(HeapId::FIRST, 100, word());
+    assert_word_at(&vm, 100);
+    // An unaligned read shifts the word by a byte; the missing byte is zero.
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 101), word() << 8);
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 99), word() >> 8);
+}
+
+#[test]
+fn heap_words_crossing_page_boundary() {
+    let mut vm = vm(vec![]);
+    let offset = u32::try_from(HEAP_PAGE_SIZE).unwrap() - 13;
+    vm.write_heap_u256(HeapId::FIRST, offset, word());
+    assert_word_at(&vm, offset);
+}
+
+#[test]
+fn heap_words_at_the_end_of_address_space() {
+    let mut vm = vm(vec![]);
+    let offset = u32::MAX - 31;
+    vm.write_heap_u256(HeapId::FIRST, offset, word());
+    assert_word_at(&vm, offset);
+    assert_eq!(vm.read_heap_byte(HeapId::FIRST, u32::MAX), 32);
+}
+
+#[test]
+fn calldata_is_copied_in_byte_order() {
+    let mut calldata = WORD_BYTES.to_vec();
+    calldata.push(0xff);
+    let vm = vm(calldata);
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST_CALLDATA, 0), word());
+    assert_eq!(vm.read_heap_byte(HeapId::FIRST_CALLDATA, 32), 0xff);
+
+    let (pointer, is_pointer) = vm.read_register(1);
+    assert!(is_pointer);
+    let calldata_heap = u64::from(HeapId::FIRST_CALLDATA.as_u32());
+    assert_eq!(pointer, U256([calldata_heap << 32, 33 << 32, 0, 0]));
+    let pointer = FatPointer::from(pointer);
+    assert_eq!(
+        (
+            pointer.offset,
+            pointer.memory_page,
+            pointer.start,
+            pointer.length
+        ),
+        (0, HeapId::FIRST_CALLDATA, 0, 33)
+    );
+}
+
+#[test]
+fn fat_pointer_layout() {
+    let pointer = FatPointer::from(word());
+    assert_eq!(pointer.offset, 0x1d1e_1f20);
+    assert_eq!(pointer.memory_page.as_u32(), 0x191a_1b1c);
+    assert_eq!(pointer.start, 0x1516_1718);
+    assert_eq!(pointer.length, 0x1112_1314);
+    assert_eq!(pointer.into_u256(), word().low_u128().into());
+}
+
+/// Executes a pointer instruction on a pointer with non-zero upper 128 bits.
+fn exec_pointer_op(
+    constructor: fn(
+        AnySource,
+        Register2,
+        AnyDestination,
+        Arguments,
+        bool,
+    ) -> Instruction<(), TestWorld<()>>,
+    operand: u32,
+) -> U256 {
+    let (r1, r2, r3) = (Register::new(1), Register::new(2), Register::new(3));
+    let instruction = constructor(
+        Register1(r1).into(),
+        Register2(r2),
+        Register1(r3).into(),
+        Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+        false,
+    );
+    let fixture = StateFixture {
+        register_pointer_flags: 1 << 1,
+        ..StateFixture::default()
+            .with_register(1, word())
+            .with_register(2, operand)
+    };
+    let (end, delta) = exec_one(instruction, &fixture);
+    assert_eq!(end, None);
+    let (value, is_pointer) = delta.registers[&3];
+    assert!(is_pointer);
+    value
+}
+
+#[test]
+fn pointer_arithmetic_keeps_upper_bits() {
+    let [low, high, upper_low, upper_high] = word().0;
+
+    let added = exec_pointer_op(Instruction::from_pointer_add, 5);
+    assert_eq!(added, U256([low + 5, high, upper_low, upper_high]));
+    let subtracted = exec_pointer_op(Instruction::from_pointer_sub, 0x20);
+    assert_eq!(subtracted, U256([low - 0x20, high, upper_low, upper_high]));
+    // Shrinking decreases the length stored in the upper half of the second limb.
+    let shrunk = exec_pointer_op(Instruction::from_pointer_shrink, 0x14);
+    assert_eq!(
+        shrunk,
+        U256([low, high - (0x14 << 32), upper_low, upper_high])
+    );
+}
+
+#[test]
+fn address_conversions() {
+    let address = Address::from_slice(&WORD_BYTES[12..]);
+    let low_160_bits = (U256::one() << 160) - 1;
+    assert_eq!(address_into_u256(address), word() & low_160_bits);
+    assert_eq!(u256_into_address(word()), address);
+}
@@ -0,0 +1,113 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::{ethereum_types::Address, system_params::NEW_KERNEL_FRAME_MEMORY_STIPEND};
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    instruction_handlers::address_into_u256,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, FatPointer, Instruction, ModeRequirements, Predicate, Program, Settings,
+    VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CODE_ADDRESS: Address = Address::repeat_byte(0x42);
+const EXTRA_COST: u16 = 1_000;
+const INITIAL_GAS: u32 = 100_000;
+
+fn code_program() -> Program<(), TestWorld<()>> {
+    Program::from_raw(vec![Instruction::from_invalid()], vec![U256::from(0x_c0de)])
+}
+
+#[test]
+fn decommit_opcode() {
+    // The world assigns hashes based on the contract index and code, so the hash is the same for both worlds.
+    let code_hash = TestWorld::new(&[(CODE_ADDRESS, code_program())]).address_to_hash
+        [&address_into_u256(CODE_ADDRESS)];
+
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+    let decommit = |hash, output| {
+        Instruction::from_decommit(
+            Register1(hash),
+            Register2(r2),
+            Register1(output),
+            arguments(10),
+        )
+    };
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                arguments(6),
+                false,
+                false,
+            ),
+            Instruction::from_add(
+                Immediate1(EXTRA_COST).into(),
+                Register2(r0),
+                Register1(r2).into(),
+                arguments(6),
+                false,
+                false,
+            ),
+            decommit(r1, Register::new(3)),
+            // The second decommitment of the same code refunds the extra cost.
+            decommit(r1, Register::new(4)),
+            // Invalid hashes are charged, but not decommitted.
+            decommit(r0, Register::new(5)),
+            Instruction::from_ret(Register1(r0), None, arguments(5)),
+        ],
+        vec![code_hash],
+    );
+
+    let mut world = TestWorld::new(&[(CODE_ADDRESS, code_program()), (MAIN_ADDRESS, main_program)]);
+    let main_program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        main_program,
+        Address::zero(),
+        &[],
+        INITIAL_GAS,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    let static_costs = 6 + 6 + 3 * 10 + 5;
+    assert_eq!(
+        vm.current_frame().gas(),
+        INITIAL_GAS - static_costs - 2 * u32::from(EXTRA_COST)
+    );
+
+    for register in [3, 4] {
+        let (value, is_pointer) = vm.read_register(register);
+        assert!(is_pointer);
+        let pointer = FatPointer::from(value);
+        assert_eq!(pointer.offset, 0);
+        assert_eq!(pointer.start, 0);
+        assert_eq!(pointer.length, NEW_KERNEL_FRAME_MEMORY_STIPEND);
+        assert_eq!(vm.read_heap_u256(pointer.memory_page, 0), 0x_c0de.into());
+    }
+    assert_ne!(
+        FatPointer::from(vm.read_register(3).0).memory_page,
+        FatPointer::from(vm.read_register(4).0).memory_page
+    );
+    assert_eq!(vm.read_register(5), (U256::zero(), false));
+}
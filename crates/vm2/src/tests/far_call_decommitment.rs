@@ -13,7 +13,8 @@ use crate::{
 };
 
 const GAS_TO_PASS: u32 = 10_000;
-const LARGE_BYTECODE_LEN: usize = 10_000;
+// Reduced under Miri since the bytecode is decommitted (i.e., copied) several times.
+const LARGE_BYTECODE_LEN: usize = if cfg!(miri) { 1_000 } else { 10_000 };
 const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
 const CALLED_ADDRESS: Address = H160([
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
@@ -189,3 +190,33 @@ fn test_with_initial_out_of_gas_error() {
         "{remaining_gas}"
     );
 }
+
+#[test]
+fn warmed_up_bytecode_is_not_charged() {
+    let mut world = create_test_world();
+    let main_program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let initial_gas = 1_000_000;
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        main_program,
+        Address::zero(),
+        &[],
+        initial_gas,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let called_bytecode_hash = world.address_to_hash[&CALLED_ADDRESS.to_low_u64_be().into()];
+    vm.warm_up_bytecodes([called_bytecode_hash]);
+
+    let result = vm.run(&mut world, &mut ());
+    let remaining_gas = vm.current_frame().gas();
+    assert_eq!(result, ExecutionEnd::SuspendedOnHook(0));
+    let expected_decommit_cost = u32::try_from(LARGE_BYTECODE_LEN).unwrap() * 4;
+    assert!(
+        initial_gas - remaining_gas < expected_decommit_cost,
+        "{remaining_gas}"
+    );
+}
@@ -0,0 +1,143 @@
+//! Tests of the gas passed to far calls: the 63/64 rule and paying for calldata memory growth before applying it.
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::{ethereum_types::Address, system_params::NEW_FRAME_MEMORY_STIPEND};
+use zksync_vm2_interface::{opcodes, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
+]);
+const FAR_CALL_COST: u32 = 200;
+const GAS: u32 = 100_000;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// Creates a far call ABI making a new heap pointer with the specified `start` and `length` for calldata.
+fn far_call_abi(gas_to_pass: u32, start: u32, length: u32) -> U256 {
+    U256([
+        0,
+        (u64::from(length) << 32) | u64::from(start),
+        0,
+        gas_to_pass.into(),
+    ])
+}
+
+/// Executes a far call with the specified ABI in a frame with `gas` and returns the gas of the callee
+/// and the caller after the call.
+fn gas_after_far_call(gas: u32, abi: U256) -> (u32, u32) {
+    let r0 = Register1(Register::new(0));
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_far_call::<opcodes::Normal>(
+                Register1(Register::new(1)),
+                Register2(Register::new(2)),
+                Immediate1(1),
+                false,
+                false,
+                args(FAR_CALL_COST),
+            ),
+            // 1: exception handler
+            Instruction::from_ret(r0, None, args(5)),
+        ],
+        vec![],
+    );
+    let called_program = Program::from_raw(vec![Instruction::from_ret(r0, None, args(5))], vec![]);
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::builder()
+        .address(MAIN_ADDRESS)
+        .program(program)
+        .gas(gas)
+        .build()
+        .unwrap();
+    vm.set_register(1, abi, false);
+    vm.set_register(2, CALLED_ADDRESS.to_low_u64_be().into(), false);
+
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut 1);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(vm.number_of_callframes(), 2);
+    (vm.callframe_gas(0), vm.callframe_gas(1))
+}
+
+/// Returns the cost of decommitting the called program, which is paid before passing gas to it.
+fn decommit_cost() -> u32 {
+    let (callee_gas, caller_gas) = gas_after_far_call(GAS, far_call_abi(0, 0, 0));
+    assert_eq!(callee_gas, 0);
+    GAS - FAR_CALL_COST - caller_gas
+}
+
+#[test]
+fn at_most_63_64_of_gas_is_passed() {
+    let decommit_cost = decommit_cost();
+    // Check all remainders modulo 64 of the gas left after paying for the call.
+    for gas_left in 64_000..64_064 {
+        let gas = gas_left + FAR_CALL_COST + decommit_cost;
+        let (callee_gas, caller_gas) = gas_after_far_call(gas, far_call_abi(u32::MAX, 0, 0));
+        let expected = gas_left / 64 * 63;
+        assert_eq!(callee_gas, expected, "{gas_left}");
+        assert_eq!(caller_gas, gas_left - expected, "{gas_left}");
+    }
+
+    let gas = 64_063 + FAR_CALL_COST + decommit_cost;
+    let (callee_gas, caller_gas) = gas_after_far_call(gas, far_call_abi(u32::MAX, 0, 0));
+    assert_eq!((callee_gas, caller_gas), (63_000, 1_063));
+}
+
+#[test]
+fn passing_gas_at_the_boundary() {
+    let gas_left = 64_000;
+    let gas = gas_left + FAR_CALL_COST + decommit_cost();
+    let maximum = 63_000;
+    for (requested, passed) in [
+        (0, 0),
+        (1, 1),
+        (maximum - 1, maximum - 1),
+        (maximum, maximum),
+        (maximum + 1, maximum),
+        (gas_left, maximum),
+    ] {
+        let (callee_gas, caller_gas) = gas_after_far_call(gas, far_call_abi(requested, 0, 0));
+        assert_eq!(callee_gas, passed, "{requested}");
+        assert_eq!(caller_gas, gas_left - passed, "{requested}");
+    }
+}
+
+#[test]
+fn calldata_memory_growth_is_paid_before_passing_gas() {
+    let growth = 1_000;
+    let gas_left = 64_000;
+    let gas = gas_left + FAR_CALL_COST + decommit_cost();
+
+    // Calldata within the memory stipend is free.
+    let abi = far_call_abi(u32::MAX, 0, NEW_FRAME_MEMORY_STIPEND);
+    assert_eq!(gas_after_far_call(gas, abi), (63_000, 1_000));
+
+    let abi = far_call_abi(u32::MAX, 0, NEW_FRAME_MEMORY_STIPEND + growth);
+    let (callee_gas, caller_gas) = gas_after_far_call(gas, abi);
+    let expected = (gas_left - growth) / 64 * 63;
+    assert_eq!(callee_gas, expected);
+    assert_eq!(caller_gas, gas_left - growth - expected);
+
+    // The pointer start counts towards the heap bound as well.
+    let abi = far_call_abi(u32::MAX, growth, NEW_FRAME_MEMORY_STIPEND);
+    assert_eq!(gas_after_far_call(gas, abi), (callee_gas, caller_gas));
+}
+
+#[test]
+fn unaffordable_calldata_burns_all_gas() {
+    let gas = GAS + FAR_CALL_COST;
+    let abi = far_call_abi(u32::MAX, 0, NEW_FRAME_MEMORY_STIPEND + GAS + 1);
+    assert_eq!(gas_after_far_call(gas, abi), (0, 0));
+}
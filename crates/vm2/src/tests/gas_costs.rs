@@ -0,0 +1,57 @@
+use zksync_vm2_interface::{CallingMode, Opcode, ReturnType};
+
+use crate::{testonly::TestWorld, GasCosts, InstructionInfo, Program};
+
+type TestProgram = Program<(), TestWorld<()>>;
+
+const BYTECODE: &[u8] = include_bytes!("bytecodes/call_far");
+
+fn static_costs(program: &TestProgram) -> Vec<u32> {
+    (0..=u16::MAX)
+        .map_while(|pc| program.instruction(pc))
+        .map(|instruction| instruction.arguments.get_static_gas_cost())
+        .collect()
+}
+
+#[test]
+fn default_gas_costs() {
+    let expected = static_costs(&TestProgram::new(BYTECODE, false));
+    let infos = InstructionInfo::decode_bytecode(BYTECODE);
+    for (info, &cost) in infos.iter().zip(&expected) {
+        assert_eq!(cost, info.gas_cost, "{info:?}");
+    }
+
+    let program = TestProgram::new_with_gas_costs(BYTECODE, false, &GasCosts::new());
+    assert_eq!(static_costs(&program), expected);
+}
+
+#[test]
+fn overridden_gas_costs() {
+    let costs = GasCosts::new()
+        .with_cost(Opcode::FarCall(CallingMode::Normal), 100)
+        .with_cost(Opcode::Ret(ReturnType::Normal), 0);
+    let program = TestProgram::new_with_gas_costs(BYTECODE, false, &costs);
+
+    let infos = InstructionInfo::decode_bytecode(BYTECODE);
+    assert!(infos
+        .iter()
+        .any(|info| info.opcode == Some(Opcode::FarCall(CallingMode::Normal))));
+    for (info, cost) in infos.iter().zip(static_costs(&program)) {
+        let expected = match info.opcode {
+            Some(Opcode::FarCall(CallingMode::Normal)) => 100,
+            Some(Opcode::Ret(ReturnType::Normal)) => 0,
+            _ => info.gas_cost,
+        };
+        assert_eq!(cost, expected, "{info:?}");
+    }
+
+    let words = TestProgram::new(BYTECODE, false).code_page().to_vec();
+    let program_from_words = TestProgram::from_words_with_gas_costs(words, false, &costs);
+    assert_eq!(static_costs(&program_from_words), static_costs(&program));
+}
+
+#[test]
+#[should_panic(expected = "Gas cost doesn't fit into 8 bits")]
+fn unencodable_gas_cost() {
+    let _ = GasCosts::new().with_cost(Opcode::Add, 1_000);
+}
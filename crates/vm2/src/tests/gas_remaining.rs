@@ -0,0 +1,54 @@
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1, Register2},
+    testonly::vm_with_program,
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program,
+};
+
+const GAS: u32 = 1_000;
+
+#[test]
+fn gas_remaining_includes_gas_of_all_frames() {
+    let args = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let program = Program::from_raw(
+        vec![
+            // 0: r1 = 100
+            Instruction::from_add(
+                Immediate1(100).into(),
+                Register2(r0),
+                Register1(r1).into(),
+                args(6),
+                false,
+                false,
+            ),
+            // 1: call the function at 3, passing 100 gas to it
+            Instruction::from_near_call(Register1(r1), Immediate1(3), Immediate2(0), args(25)),
+            Instruction::from_ret(Register1(r0), None, args(5)),
+            // 3: function body
+            Instruction::from_ret(Register1(r0), None, args(5)),
+        ],
+        vec![],
+    );
+    let (mut vm, mut world) = vm_with_program(program, GAS);
+    assert_eq!(vm.gas_remaining(), GAS);
+    let mark = vm.gas_mark();
+
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut 2);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(vm.number_of_callframes(), 2);
+    assert_eq!(vm.callframe_gas(0), 100);
+    assert_eq!(vm.current_frame().gas(), 100);
+    assert_eq!(vm.callframe_gas(1), GAS - 31 - 100);
+    assert_eq!(vm.gas_remaining(), GAS - 31);
+    assert_eq!(vm.gas_used_since(&mark), 31);
+    // Gas used in the current frame also counts towards the total.
+    let mark = vm.gas_mark();
+    vm.current_frame().set_gas(50);
+    assert_eq!(vm.gas_used_since(&mark), 50);
+    // Increasing gas, e.g. after a refund, doesn't make the used gas negative.
+    vm.current_frame().set_gas(200);
+    assert_eq!(vm.gas_used_since(&mark), 0);
+}
@@ -0,0 +1,80 @@
+use zkevm_opcode_defs::{ethereum_types::Address, system_params::NEW_FRAME_MEMORY_STIPEND};
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+const WRITE_OFFSET: u16 = 10_000;
+
+#[test]
+fn heap_bound_grows_on_access() {
+    let r0 = Register::new(0);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_near_call(
+                Register1(r0),
+                Immediate1(2),
+                Immediate2(0xFFFF),
+                Arguments::new(Predicate::Always, 25, ModeRequirements::none()),
+            ),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+            // 2: Near call function
+            Instruction::from_heap_write(
+                Immediate1(WRITE_OFFSET).into(),
+                Register2(r0),
+                None,
+                Arguments::new(Predicate::Always, 7, ModeRequirements::none()),
+                false,
+            ),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        100_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    assert_eq!(vm.current_frame().heap_bound(), NEW_FRAME_MEMORY_STIPEND);
+    assert_eq!(
+        vm.current_frame().aux_heap_bound(),
+        NEW_FRAME_MEMORY_STIPEND
+    );
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+
+    // The bound set in the near call persists after returning from it.
+    let new_bound = u32::from(WRITE_OFFSET) + 32;
+    assert_eq!(vm.current_frame().heap_bound(), new_bound);
+    assert_eq!(
+        vm.current_frame().aux_heap_bound(),
+        NEW_FRAME_MEMORY_STIPEND
+    );
+    let expected_gas = 100_000 - 25 - 7 - 5 - 5 - (new_bound - NEW_FRAME_MEMORY_STIPEND);
+    assert_eq!(vm.current_frame().gas(), expected_gas);
+}
@@ -0,0 +1,48 @@
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1},
+    testonly::vm_with_program,
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program,
+};
+
+const GAS: u32 = 10_000;
+
+#[test]
+fn instruction_limit_stops_infinite_loop() {
+    let infinite_loop = Instruction::from_jump(
+        Immediate1(0).into(),
+        Register1(Register::new(0)),
+        Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+    );
+    let (mut vm, mut world) = vm_with_program(Program::from_raw(vec![infinite_loop], vec![]), GAS);
+
+    let mut budget = 10;
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut budget);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(budget, 0);
+    assert_eq!(vm.current_frame().gas(), GAS - 10 * 6);
+
+    // The VM can be resumed with a new budget.
+    let mut budget = 5;
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut budget);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(vm.current_frame().gas(), GAS - 15 * 6);
+    assert_eq!(vm.current_frame().program_counter(), Some(0));
+    assert_eq!(end.into_result(), Err(ExecutionEnd::InstructionLimit));
+}
+
+#[test]
+fn unused_instruction_budget_is_returned() {
+    let ret = Instruction::from_ret(
+        Register1(Register::new(0)),
+        None,
+        Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+    );
+    let (mut vm, mut world) = vm_with_program(Program::from_raw(vec![ret], vec![]), GAS);
+
+    let mut budget = 10;
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut budget);
+    assert_eq!(end.into_result(), Ok(vec![]));
+    assert_eq!(budget, 9);
+}
@@ -0,0 +1,87 @@
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    allocator::HEAP_PAGE_SIZE,
+    testonly::{vm_with_program, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+/// Offset not at a heap page boundary, so that a single page is allocated by a write.
+const WRITE_OFFSET: u16 = 10_000;
+
+fn create_vm() -> (VirtualMachine<(), TestWorld<()>>, TestWorld<()>) {
+    let r0 = Register::new(0);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_heap_write(
+                Immediate1(WRITE_OFFSET).into(),
+                Register2(r0),
+                None,
+                Arguments::new(Predicate::Always, 7, ModeRequirements::none()),
+                false,
+            ),
+            Instruction::from_ret(
+                Register1(r0),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+    vm_with_program(program, 100_000)
+}
+
+#[test]
+fn memory_usage_grows_with_heap() {
+    let (mut vm, mut world) = create_vm();
+    let initial_usage = vm.memory_usage();
+    assert!(initial_usage > 0);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    // The write allocates a single heap page.
+    assert_eq!(vm.memory_usage(), initial_usage + HEAP_PAGE_SIZE);
+}
+
+#[test]
+fn exceeding_memory_limit() {
+    let (mut vm, mut world) = create_vm();
+    let limit = vm.memory_usage() + 1_000;
+    vm.set_memory_limit(Some(limit));
+    assert_eq!(vm.memory_limit(), Some(limit));
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::MemoryLimitExceeded
+    );
+    assert_eq!(vm.current_frame().program_counter(), Some(1));
+    // The VM is stopped before executing the next instruction.
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::MemoryLimitExceeded
+    );
+
+    // Execution can be resumed after raising the limit.
+    vm.set_memory_limit(None);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+}
+
+#[test]
+fn programs_are_only_tracked_with_limit() {
+    let (mut vm, _) = create_vm();
+    assert!(vm.programs_in_use.is_none());
+    let initial_usage = vm.memory_usage();
+
+    vm.set_memory_limit(Some(usize::MAX));
+    assert!(vm.programs_in_use.is_some());
+    assert_eq!(vm.memory_usage(), initial_usage);
+
+    vm.set_memory_limit(None);
+    assert!(vm.programs_in_use.is_none());
+    assert_eq!(vm.memory_usage(), initial_usage);
+}
@@ -1,6 +1,38 @@
 //! Low-level VM tests.
 
+mod arithmetic_boundaries;
+mod binop_handlers;
+mod bootloader_heap;
 mod bytecode_behaviour;
+#[cfg(feature = "bytecode_corpus")]
+mod bytecode_corpus;
+mod calldata;
+mod callframe_addresses;
+mod code_page;
+mod context_meta;
+mod cross_platform;
+mod decommit_opcode;
 mod far_call_decommitment;
+mod far_call_gas;
+mod gas_costs;
+mod gas_remaining;
+mod heap_bounds;
+mod instruction_limit;
+mod memory_limit;
+mod msg_value_call;
+mod near_call_gas;
 mod panic;
+mod panic_reasons;
+mod precompile_abi;
+mod precompile_override;
+mod predicates;
+mod program_counter;
+mod pubdata_charging;
+mod refunds;
+mod revert_data;
+mod set_flags;
+mod spec;
+mod stack_pointer;
+mod thread_safety;
 mod trace_failing_far_call;
+mod zero_register;
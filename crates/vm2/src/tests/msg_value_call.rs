@@ -0,0 +1,118 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2, SSTORE_COST},
+    testonly::{
+        initial_decommit, msg_value_simulator_address, msg_value_simulator_program, MsgValueCall,
+        TestWorld,
+    },
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+/// Program storing the received value, call type and the first calldata word to storage slots 0, 1 and 2 respectively.
+fn recording_program(revert: bool) -> Program<(), TestWorld<()>> {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r3 = Register::new(3);
+    let r4 = Register::new(4);
+    let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+    let set_key = |key| {
+        Instruction::from_add(
+            Immediate1(key).into(),
+            Register2(r0),
+            Register1(r4).into(),
+            arguments(6),
+            false,
+            false,
+        )
+    };
+    let end = if revert {
+        Instruction::from_revert(Register1(r0), None, arguments(5))
+    } else {
+        Instruction::from_ret(Register1(r0), None, arguments(5))
+    };
+    Program::from_raw(
+        vec![
+            Instruction::from_context_u128(Register1(r3), arguments(5)),
+            Instruction::from_storage_write(Register1(r0), Register2(r3), arguments(SSTORE_COST)),
+            set_key(1),
+            Instruction::from_storage_write(Register1(r4), Register2(r2), arguments(SSTORE_COST)),
+            Instruction::from_pointer_read(Register1(r1), Register1(r3), None, arguments(7)),
+            set_key(2),
+            Instruction::from_storage_write(Register1(r4), Register2(r3), arguments(SSTORE_COST)),
+            end,
+        ],
+        vec![],
+    )
+}
+
+fn run_call(call: &MsgValueCall, revert: bool) -> VirtualMachine<(), TestWorld<()>> {
+    let caller = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[
+        (caller, call.caller_program()),
+        (msg_value_simulator_address(), msg_value_simulator_program()),
+        (call.to, recording_program(revert)),
+    ]);
+    let program = initial_decommit(&mut world, caller);
+    let mut vm = VirtualMachine::builder()
+        .address(caller)
+        .program(program)
+        .gas(10_000_000)
+        .build()
+        .unwrap();
+
+    let expected_end = if revert {
+        ExecutionEnd::Reverted(vec![])
+    } else {
+        ExecutionEnd::ProgramFinished(vec![])
+    };
+    assert_eq!(vm.run(&mut world, &mut ()), expected_end);
+    vm
+}
+
+fn stored_value(vm: &VirtualMachine<(), TestWorld<()>>, contract: Address, key: u64) -> U256 {
+    vm.world_diff
+        .get_storage_state()
+        .get(&(contract, key.into()))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[test]
+fn calling_with_value() {
+    let callee = Address::from_low_u64_be(0x_1234_5678);
+    let mut call = MsgValueCall::new(callee, 42);
+    call.calldata = vec![0xdead.into()];
+    let vm = run_call(&call, false);
+
+    assert_eq!(stored_value(&vm, callee, 0), 42.into());
+    assert_eq!(stored_value(&vm, callee, 1), U256::zero());
+    assert_eq!(stored_value(&vm, callee, 2), 0xdead.into());
+}
+
+#[test]
+fn making_system_call_with_value() {
+    // System calls are only possible to kernel contracts.
+    let callee = Address::from_low_u64_be(0x9000);
+    let mut call = MsgValueCall::new(callee, u128::MAX);
+    call.is_system = true;
+    let vm = run_call(&call, false);
+
+    assert_eq!(stored_value(&vm, callee, 0), u128::MAX.into());
+    // Only the system call bit of the call type is set.
+    assert_eq!(stored_value(&vm, callee, 1), 2.into());
+}
+
+#[test]
+fn reverting_call_with_value() {
+    let callee = Address::from_low_u64_be(0x_1234_5678);
+    let vm = run_call(&MsgValueCall::new(callee, 42), true);
+
+    let callee_changes = vm
+        .world_diff
+        .get_storage_changes()
+        .filter(|((contract, _), _)| *contract == callee);
+    assert_eq!(callee_changes.count(), 0);
+}
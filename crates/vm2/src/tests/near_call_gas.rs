@@ -0,0 +1,130 @@
+//! Conformance tests of passing gas to near calls and returning leftover gas from them.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::StateInterface;
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+const GAS: u32 = 1_000;
+const NEAR_CALL_COST: u32 = 25;
+/// Gas available to the caller when executing the near call.
+const AVAILABLE_GAS: u32 = GAS - NEAR_CALL_COST;
+
+type TestVm = VirtualMachine<(), TestWorld<()>>;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// Creates a VM near-calling the function at 2 with the gas specified in `r1`. The function body
+/// is `callee` followed by an exception handler at 3.
+fn vm(
+    gas_to_pass: impl Into<U256>,
+    callee: Instruction<(), TestWorld<()>>,
+) -> (TestVm, TestWorld<()>) {
+    let r0 = Register1(Register::new(0));
+    let r1 = Register1(Register::new(1));
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_near_call(r1, Immediate1(2), Immediate2(3), args(NEAR_CALL_COST)),
+            Instruction::from_ret(r0, None, args(5)),
+            // 2: function body
+            callee,
+            // 3: exception handler
+            Instruction::from_ret(r0, None, args(5)),
+        ],
+        vec![],
+    );
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .gas(GAS)
+        .build()
+        .unwrap();
+    vm.set_register(1, gas_to_pass.into(), false);
+    (vm, world)
+}
+
+/// Executes the near call and returns the gas of the callee and the caller.
+fn passed_gas(gas_to_pass: impl Into<U256>) -> (u32, u32) {
+    let ret = Instruction::from_ret(Register1(Register::new(0)), None, args(5));
+    let (mut vm, mut world) = vm(gas_to_pass, ret);
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut 1);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(vm.number_of_callframes(), 2);
+    assert_eq!(vm.gas_remaining(), AVAILABLE_GAS);
+    (vm.callframe_gas(0), vm.callframe_gas(1))
+}
+
+#[test]
+fn passing_zero_gas_passes_all_gas() {
+    assert_eq!(passed_gas(0), (AVAILABLE_GAS, 0));
+}
+
+#[test]
+fn passing_some_gas() {
+    assert_eq!(passed_gas(100), (100, AVAILABLE_GAS - 100));
+    assert_eq!(passed_gas(AVAILABLE_GAS - 1), (AVAILABLE_GAS - 1, 1));
+    assert_eq!(passed_gas(AVAILABLE_GAS), (AVAILABLE_GAS, 0));
+}
+
+#[test]
+fn passing_more_gas_than_available_passes_all_gas() {
+    assert_eq!(passed_gas(AVAILABLE_GAS + 1), (AVAILABLE_GAS, 0));
+    assert_eq!(passed_gas(u32::MAX), (AVAILABLE_GAS, 0));
+}
+
+#[test]
+fn only_low_32_bits_of_passed_gas_are_used() {
+    let high_bits = U256::one() << 32;
+    assert_eq!(passed_gas(high_bits), (AVAILABLE_GAS, 0));
+    assert_eq!(
+        passed_gas(high_bits + U256::from(100)),
+        (100, AVAILABLE_GAS - 100)
+    );
+    assert_eq!(passed_gas(U256::MAX), (AVAILABLE_GAS, 0));
+}
+
+/// Runs the VM with the `callee` function and returns the gas left in the caller after the near call returns.
+fn gas_after_return(gas_to_pass: u32, callee: Instruction<(), TestWorld<()>>) -> u32 {
+    let (mut vm, mut world) = vm(gas_to_pass, callee);
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut 2);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(vm.number_of_callframes(), 1);
+    vm.gas_remaining()
+}
+
+#[test]
+fn leftover_gas_is_returned_to_caller() {
+    let r0 = Register1(Register::new(0));
+    for gas_to_pass in [0, 100, AVAILABLE_GAS + 1] {
+        let ret = Instruction::from_ret(r0, None, args(5));
+        assert_eq!(gas_after_return(gas_to_pass, ret), AVAILABLE_GAS - 5);
+        let revert = Instruction::from_revert(r0, None, args(5));
+        assert_eq!(gas_after_return(gas_to_pass, revert), AVAILABLE_GAS - 5);
+        let panic = Instruction::from_panic(None, args(5));
+        assert_eq!(gas_after_return(gas_to_pass, panic), AVAILABLE_GAS - 5);
+    }
+}
+
+#[test]
+fn gas_burned_by_callee_is_not_returned() {
+    // The invalid instruction burns all gas passed to the callee, but not the gas kept by the caller.
+    assert_eq!(gas_after_return(0, Instruction::from_invalid()), 0);
+    assert_eq!(
+        gas_after_return(100, Instruction::from_invalid()),
+        AVAILABLE_GAS - 100
+    );
+
+    // Running out of gas in the callee also burns only the passed gas.
+    let expensive = Instruction::from_ret(Register1(Register::new(0)), None, args(200));
+    assert_eq!(gas_after_return(100, expensive), AVAILABLE_GAS - 100);
+}
@@ -8,6 +8,7 @@ use crate::{
 };
 
 proptest! {
+    #[cfg_attr(miri, ignore)]
     #[test]
     fn panic_to_varying_label(label: u16) {
         let mut instructions = vec![
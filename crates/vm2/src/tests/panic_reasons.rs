@@ -0,0 +1,306 @@
+//! Tests of classifying frame panics, as reported by [`VirtualMachine::last_panic()`] and [`Tracer::on_panic()`].
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{opcodes, PanicReason, StateInterface, Tracer};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Immediate2, Register, Register1, Register2,
+        RegisterAndImmediate, SSTORE_COST,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, PanicInfo, Predicate, Program, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
+]);
+const GAS: u32 = 10_000;
+
+#[derive(Debug, Default)]
+struct PanicRecorder(Vec<PanicReason>);
+
+impl Tracer for PanicRecorder {
+    fn on_panic(&mut self, reason: PanicReason) {
+        self.0.push(reason);
+    }
+}
+
+type TestInstruction = Instruction<PanicRecorder, TestWorld<PanicRecorder>>;
+type TestProgram = Program<PanicRecorder, TestWorld<PanicRecorder>>;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+fn r(index: u8) -> Register {
+    Register::new(index)
+}
+
+fn add_immediate(value: u16, out: u8, arguments: Arguments) -> TestInstruction {
+    Instruction::from_add(
+        Immediate1(value).into(),
+        Register2(r(0)),
+        Register1(r(out)).into(),
+        arguments,
+        false,
+        false,
+    )
+}
+
+fn load_from_code_page(immediate: u16, out: u8) -> TestInstruction {
+    Instruction::from_add(
+        CodePage(RegisterAndImmediate {
+            immediate,
+            register: r(0),
+        })
+        .into(),
+        Register2(r(0)),
+        Register1(r(out)).into(),
+        args(6),
+        false,
+        false,
+    )
+}
+
+fn panic() -> TestInstruction {
+    Instruction::from_panic(None, args(5))
+}
+
+/// Runs the first of `programs` with the specified (non-pointer) register values and returns the execution end,
+/// reasons reported to the tracer and the last panic.
+fn run(
+    programs: Vec<(H160, TestProgram)>,
+    registers: &[(u8, U256)],
+) -> (ExecutionEnd, Vec<PanicReason>, Option<PanicInfo>) {
+    let address = programs[0].0;
+    let mut world = TestWorld::new(&programs);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .gas(GAS)
+        .build()
+        .unwrap();
+    for &(register, value) in registers {
+        vm.set_register(register, value, false);
+    }
+    let mut tracer = PanicRecorder::default();
+    let end = vm.run(&mut world, &mut tracer);
+    (end, tracer.0, vm.last_panic())
+}
+
+fn run_main(instructions: Vec<TestInstruction>, code_page: Vec<U256>) -> Option<PanicInfo> {
+    let program = Program::from_raw(instructions, code_page);
+    let (end, reasons, last_panic) = run(vec![(MAIN_ADDRESS, program)], &[]);
+    assert_eq!(end, ExecutionEnd::Panicked);
+    assert_eq!(reasons.len(), 1);
+    assert_eq!(Some(reasons[0]), last_panic.map(|panic| panic.reason));
+    last_panic
+}
+
+#[test]
+fn no_panic_is_recorded_on_success() {
+    let ret = Instruction::from_ret(Register1(r(0)), None, args(5));
+    let program = Program::from_raw(vec![ret], vec![]);
+    let (end, reasons, last_panic) = run(vec![(MAIN_ADDRESS, program)], &[]);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert!(reasons.is_empty());
+    assert_eq!(last_panic, None);
+}
+
+#[test]
+fn explicit_panic() {
+    let last_panic = run_main(vec![add_immediate(1, 1, args(6)), panic()], vec![]);
+    let expected = PanicInfo {
+        reason: PanicReason::Explicit,
+        address: MAIN_ADDRESS,
+        pc: Some(1),
+    };
+    assert_eq!(last_panic, Some(expected));
+}
+
+#[test]
+fn panics_are_classified() {
+    // Address beyond the heap bounds, and the ABI forwarding a fat pointer.
+    let code_page = || vec![U256::one() << 32, U256([0, 0, 0, 1 << 32])];
+    let cases = [
+        (
+            "static gas",
+            // Costs of both additions together exceed `GAS`.
+            vec![
+                add_immediate(1, 1, args(SSTORE_COST)),
+                add_immediate(2, 1, args(SSTORE_COST)),
+            ],
+            PanicReason::OutOfGas,
+            Some(1),
+        ),
+        (
+            "heap growth",
+            vec![Instruction::from_heap_read(
+                Immediate1(60_000).into(),
+                Register1(r(1)),
+                None,
+                args(7),
+            )],
+            PanicReason::OutOfGas,
+            Some(0),
+        ),
+        (
+            "heap out of bounds",
+            vec![
+                load_from_code_page(0, 1),
+                Instruction::from_heap_read(Register1(r(1)).into(), Register1(r(2)), None, args(7)),
+            ],
+            PanicReason::HeapOutOfBounds,
+            Some(1),
+        ),
+        (
+            "pointer arithmetic on a non-pointer",
+            vec![Instruction::from_pointer_add(
+                Register1(r(1)).into(),
+                Register2(r(0)),
+                Register1(r(1)).into(),
+                args(6),
+                false,
+            )],
+            PanicReason::PointerMisuse,
+            Some(0),
+        ),
+        (
+            "returning a non-pointer",
+            vec![
+                load_from_code_page(1, 1),
+                Instruction::from_ret(Register1(r(1)), None, args(5)),
+            ],
+            PanicReason::PointerMisuse,
+            Some(1),
+        ),
+        (
+            "invalid instruction",
+            vec![Instruction::from_invalid()],
+            PanicReason::InvalidInstruction,
+            Some(0),
+        ),
+        (
+            "jump out of bounds",
+            vec![Instruction::from_jump(
+                Immediate1(100).into(),
+                Register1(r(0)),
+                args(6),
+            )],
+            PanicReason::InvalidInstruction,
+            None,
+        ),
+        (
+            "kernel-only instruction",
+            vec![add_immediate(
+                1,
+                1,
+                Arguments::new(Predicate::Always, 6, ModeRequirements::new(true, false)),
+            )],
+            PanicReason::PrivilegeViolation,
+            Some(0),
+        ),
+    ];
+
+    for (name, instructions, reason, pc) in cases {
+        let expected = PanicInfo {
+            reason,
+            address: MAIN_ADDRESS,
+            pc,
+        };
+        assert_eq!(
+            run_main(instructions, code_page()),
+            Some(expected),
+            "{name}"
+        );
+    }
+}
+
+/// An invalid instruction in a near call is handled by the exception handler, which panics explicitly.
+#[test]
+fn panics_in_near_calls_are_reported() {
+    let instructions = vec![
+        Instruction::from_near_call(Register1(r(1)), Immediate1(2), Immediate2(3), args(25)),
+        Instruction::from_ret(Register1(r(0)), None, args(5)),
+        // 2: function body
+        Instruction::from_invalid(),
+        // 3: exception handler
+        panic(),
+    ];
+    let program = Program::from_raw(instructions, vec![]);
+    let (end, reasons, last_panic) = run(vec![(MAIN_ADDRESS, program)], &[(1, 100.into())]);
+    assert_eq!(end, ExecutionEnd::Panicked);
+    assert_eq!(
+        reasons,
+        [PanicReason::InvalidInstruction, PanicReason::Explicit]
+    );
+    let expected = PanicInfo {
+        reason: PanicReason::Explicit,
+        address: MAIN_ADDRESS,
+        pc: Some(3),
+    };
+    assert_eq!(last_panic, Some(expected));
+}
+
+/// Far calls [`CALLED_ADDRESS`] with the ABI in `r1` and returns normally regardless of the outcome.
+fn run_far_call(
+    called_instructions: Vec<TestInstruction>,
+    abi: U256,
+) -> (Vec<PanicReason>, Option<PanicInfo>) {
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_far_call::<opcodes::Normal>(
+                Register1(r(1)),
+                Register2(r(2)),
+                Immediate1(1),
+                false,
+                false,
+                args(200),
+            ),
+            // 1: next instruction and exception handler
+            Instruction::from_ret(Register1(r(0)), None, args(5)),
+        ],
+        vec![],
+    );
+    let called_program = Program::from_raw(called_instructions, vec![]);
+    let programs = vec![
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ];
+    let registers = [(1, abi), (2, CALLED_ADDRESS.to_low_u64_be().into())];
+    let (end, reasons, last_panic) = run(programs, &registers);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    (reasons, last_panic)
+}
+
+#[test]
+fn handled_panic_in_called_contract() {
+    let far_call_abi = U256([0, 0, 0, 1_000]);
+    let (reasons, last_panic) =
+        run_far_call(vec![add_immediate(1, 1, args(6)), panic()], far_call_abi);
+    assert_eq!(reasons, [PanicReason::Explicit]);
+    let expected = PanicInfo {
+        reason: PanicReason::Explicit,
+        address: CALLED_ADDRESS,
+        pc: Some(1),
+    };
+    assert_eq!(last_panic, Some(expected));
+}
+
+#[test]
+fn failed_far_call_is_attributed_to_caller() {
+    // Forwarding a value that is not a pointer as calldata
+    let far_call_abi = U256([0, 0, 0, (1 << 32) | 1_000]);
+    let (reasons, last_panic) = run_far_call(vec![panic()], far_call_abi);
+    assert_eq!(reasons, [PanicReason::PointerMisuse]);
+    let expected = PanicInfo {
+        reason: PanicReason::PointerMisuse,
+        address: MAIN_ADDRESS,
+        pc: Some(0),
+    };
+    assert_eq!(last_panic, Some(expected));
+}
@@ -0,0 +1,71 @@
+//! Precompile call ABIs are provided by the calling contract, so malformed ABIs must not panic the host.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+
+use crate::{
+    addressing_modes::{Arguments, CodePage, Register, Register1, Register2, RegisterAndImmediate},
+    precompiles::KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+fn call_precompile(abi: U256) -> ExecutionEnd {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                arguments(6),
+                false,
+                false,
+            ),
+            Instruction::from_precompile_call(
+                Register1(r1),
+                Register2(r0),
+                Register1(r0),
+                arguments(6),
+            ),
+            Instruction::from_ret(Register1(r0), None, arguments(5)),
+        ],
+        vec![abi],
+    );
+
+    let address = Address::from_low_u64_be(KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS.into());
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .gas(100_000)
+        .build()
+        .unwrap();
+    vm.run(&mut world, &mut ())
+}
+
+#[test]
+fn precompile_call_with_unallocated_heap_panics() {
+    for page_shift in [0, 32] {
+        let mut abi = U256::zero();
+        abi.0[0] = 32 << 32;
+        abi.0[1] = 1 << 32;
+        abi.0[2] = 1_000 << page_shift;
+        assert_eq!(call_precompile(abi), ExecutionEnd::Panicked, "{page_shift}");
+    }
+}
+
+#[test]
+fn precompile_call_with_overflowing_offsets() {
+    let mut abi = U256::zero();
+    abi.0[0] = u64::from(u32::MAX) | (32 << 32);
+    abi.0[1] = u64::from(u32::MAX) | (1 << 32);
+    assert_eq!(call_precompile(abi), ExecutionEnd::ProgramFinished(vec![]));
+}
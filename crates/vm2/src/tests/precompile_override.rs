@@ -0,0 +1,150 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{HeapId, StateInterface};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    precompiles::{
+        PrecompileMemoryReader, PrecompileOutput, Precompiles,
+        KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+/// Precompiles returning the sum of input bytes.
+#[derive(Debug, Default)]
+struct SummingPrecompiles {
+    calls: AtomicU32,
+}
+
+impl Precompiles for SummingPrecompiles {
+    fn call_precompile(
+        &self,
+        address_low: u16,
+        memory: PrecompileMemoryReader<'_>,
+        _aux_input: u64,
+    ) -> PrecompileOutput {
+        assert_eq!(address_low, KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let bytes = memory.to_vec();
+        assert_eq!(bytes.len(), 64);
+        let sum: u32 = bytes.iter().copied().map(u32::from).sum();
+        U256::from(sum).into()
+    }
+}
+
+/// Precompiles that don't support any calls.
+#[derive(Debug)]
+struct UnsupportedPrecompiles;
+
+impl Precompiles for UnsupportedPrecompiles {
+    fn call_precompile(
+        &self,
+        _address_low: u16,
+        _memory: PrecompileMemoryReader<'_>,
+        _aux_input: u64,
+    ) -> PrecompileOutput {
+        PrecompileOutput::unsupported()
+    }
+}
+
+/// Creates a VM writing 0x0102 to the heap and calling a precompile on the first 64 heap bytes,
+/// with the output written to the heap at offset 64.
+fn create_vm(
+    precompiles: Arc<dyn Precompiles + Send + Sync>,
+) -> (VirtualMachine<(), TestWorld<()>>, TestWorld<()>) {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+
+    // Input offset 0, input length 64 bytes, output offset 2 words, output length 1 word.
+    let mut abi = U256::zero();
+    abi.0[0] = 64 << 32;
+    abi.0[1] = 2 | (1 << 32);
+    let program = Program::from_raw(
+        vec![
+            // Write 0x0102 to the heap at offset 0.
+            Instruction::from_add(
+                Immediate1(0x0102).into(),
+                Register2(r0),
+                Register1(r1).into(),
+                arguments(6),
+                false,
+                false,
+            ),
+            Instruction::from_heap_write(
+                Immediate1(0).into(),
+                Register2(r1),
+                None,
+                arguments(7),
+                false,
+            ),
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                arguments(6),
+                false,
+                false,
+            ),
+            Instruction::from_precompile_call(
+                Register1(r1),
+                Register2(r0),
+                Register1(r0),
+                arguments(6),
+            ),
+            Instruction::from_ret(Register1(r0), None, arguments(5)),
+        ],
+        vec![abi],
+    );
+
+    let address = Address::from_low_u64_be(KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS.into());
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .gas(100_000)
+        .precompiles(precompiles)
+        .build()
+        .unwrap();
+    (vm, world)
+}
+
+#[test]
+fn overriding_world_precompiles() {
+    let precompiles = Arc::new(SummingPrecompiles::default());
+    let (mut vm, mut world) = create_vm(precompiles.clone());
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(precompiles.calls.load(Ordering::Relaxed), 1);
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 64), 3.into());
+
+    // Cloned VMs share precompiles.
+    let vm = vm.clone();
+    assert!(vm.precompiles.is_some());
+}
+
+#[test]
+fn unsupported_precompile_call_stops_vm() {
+    let (mut vm, mut world) = create_vm(Arc::new(UnsupportedPrecompiles));
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::UnsupportedPrecompile(KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS)
+    );
+    assert_eq!(vm.read_heap_u256(HeapId::FIRST, 64), U256::zero());
+}
@@ -0,0 +1,125 @@
+//! Exhaustive checks of instruction predicates against all combinations of execution flags.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::{ethereum_types::Address, Condition};
+use zksync_vm2_interface::{
+    CallframeInterface, Flags, GlobalStateInterface, Opcode, OpcodeType, StateInterface, Tracer,
+};
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    decode::decode_predicate,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+const GAS: u32 = 10_000;
+const GAS_COST: u32 = 6;
+
+const CONDITIONS: [Condition; 8] = [
+    Condition::Always,
+    Condition::Gt,
+    Condition::Lt,
+    Condition::Eq,
+    Condition::Ge,
+    Condition::Le,
+    Condition::Ne,
+    Condition::GtOrLt,
+];
+
+/// Reference semantics of conditions, as specified for EraVM (and implemented by the legacy VM).
+fn condition_is_satisfied(condition: Condition, flags: Flags) -> bool {
+    let Flags {
+        less_than,
+        equal,
+        greater,
+    } = flags;
+    match condition {
+        Condition::Always => true,
+        Condition::Gt => greater,
+        Condition::Lt => less_than,
+        Condition::Eq => equal,
+        Condition::Ge => greater || equal,
+        Condition::Le => less_than || equal,
+        Condition::Ne => !equal,
+        Condition::GtOrLt => greater || less_than,
+    }
+}
+
+fn all_flags() -> impl Iterator<Item = Flags> {
+    (0_u8..8).map(|bits| Flags {
+        less_than: bits & 1 != 0,
+        equal: bits & 2 != 0,
+        greater: bits & 4 != 0,
+    })
+}
+
+#[derive(Debug, Default)]
+struct OpcodeRecorder(Vec<Opcode>);
+
+impl Tracer for OpcodeRecorder {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, _state: &mut S) {
+        self.0.push(OP::VALUE);
+    }
+}
+
+/// Executes a single `add 1, r0, r1` instruction with the specified predicate and flags. Returns whether
+/// the instruction was executed (i.e., has written to `r1`) and the opcode reported to the tracer.
+fn execute_predicated_instruction(predicate: Predicate, flags: Flags) -> (bool, Opcode) {
+    let instruction = Instruction::from_add(
+        Immediate1(1).into(),
+        Register2(Register::new(0)),
+        Register1(Register::new(1)).into(),
+        Arguments::new(predicate, GAS_COST, ModeRequirements::none()),
+        false,
+        false,
+    );
+    let program = Program::from_raw(vec![instruction], vec![]);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        GAS,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.set_register(1, U256::zero(), false);
+    vm.set_flags(flags);
+
+    let mut tracer = OpcodeRecorder::default();
+    let mut budget = 1;
+    let end = vm.run_with_instruction_limit(&mut world, &mut tracer, &mut budget);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+
+    // Skipped instructions are still charged and advance the program counter; they also don't affect flags.
+    assert_eq!(vm.current_frame().gas(), GAS - GAS_COST);
+    assert_eq!(vm.current_frame().program_counter(), Some(1));
+    assert_eq!(StateInterface::flags(&vm), flags);
+
+    let (r1, _) = vm.read_register(1);
+    let [opcode] = tracer.0[..] else {
+        panic!("unexpected traced opcodes: {:?}", tracer.0);
+    };
+    (r1 == U256::one(), opcode)
+}
+
+#[test]
+fn predicates_match_reference_conditions() {
+    for condition in CONDITIONS {
+        let predicate = decode_predicate(condition);
+        for flags in all_flags() {
+            let expected = condition_is_satisfied(condition, flags);
+            let (executed, opcode) = execute_predicated_instruction(predicate, flags);
+            assert_eq!(executed, expected, "{predicate:?} with {flags:?}");
+            let expected_opcode = if expected { Opcode::Add } else { Opcode::Nop };
+            assert_eq!(opcode, expected_opcode, "{predicate:?} with {flags:?}");
+        }
+    }
+}
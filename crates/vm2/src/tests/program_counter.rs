@@ -0,0 +1,156 @@
+//! Tests for all the ways the program counter can move. These double as checks of the raw instruction pointer
+//! used by the VM (e.g., when run under Miri).
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    CallframeInterface, GlobalStateInterface, OpcodeType, StateInterface, Tracer,
+};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Immediate2, Register, Register1, Register2,
+        RegisterAndImmediate,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+#[derive(Debug, Default)]
+struct ProgramCounterRecorder(Vec<Option<u16>>);
+
+impl Tracer for ProgramCounterRecorder {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        self.0.push(state.current_frame().program_counter());
+    }
+}
+
+fn args() -> Arguments {
+    Arguments::new(Predicate::Always, 6, ModeRequirements::none())
+}
+
+type RecordingWorld = TestWorld<ProgramCounterRecorder>;
+
+fn run(
+    instructions: Vec<Instruction<ProgramCounterRecorder, RecordingWorld>>,
+    code_page: Vec<U256>,
+) -> (
+    VirtualMachine<ProgramCounterRecorder, RecordingWorld>,
+    ExecutionEnd,
+    Vec<Option<u16>>,
+) {
+    let program = Program::from_raw(instructions, code_page);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        1000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let mut tracer = ProgramCounterRecorder::default();
+    let end = vm.run(&mut world, &mut tracer);
+    (vm, end, tracer.0)
+}
+
+#[test]
+fn running_past_program_end_panics() {
+    let r0 = Register::new(0);
+    let add = Instruction::from_add(
+        Immediate1(1).into(),
+        Register2(r0),
+        Register1(r0).into(),
+        args(),
+        false,
+        false,
+    );
+    let (mut vm, end, pcs) = run(vec![add], vec![]);
+    assert_eq!(end, ExecutionEnd::Panicked);
+    // The invalid instruction at the end of the program burns all gas.
+    assert_eq!(pcs, [Some(0), Some(1)]);
+    assert_eq!(vm.current_frame().gas(), 0);
+}
+
+#[test]
+fn jump_out_of_bounds_panics() {
+    let r0 = Register::new(0);
+    let jump = Instruction::from_jump(Immediate1(100).into(), Register1(r0), args());
+    let (mut vm, end, pcs) = run(vec![jump], vec![]);
+    assert_eq!(end, ExecutionEnd::Panicked);
+    assert_eq!(pcs, [Some(0), None]);
+    assert_eq!(vm.current_frame().gas(), 0);
+}
+
+#[test]
+fn spontaneous_panic_in_near_call_jumps_to_exception_handler() {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r3 = Register::new(3);
+    let instructions = vec![
+        // 0..=1: Prepare near call gas and an invalid heap address
+        Instruction::from_add(
+            Immediate1(100).into(),
+            Register2(r0),
+            Register1(r3).into(),
+            args(),
+            false,
+            false,
+        ),
+        Instruction::from_add(
+            CodePage(RegisterAndImmediate {
+                immediate: 0,
+                register: r0,
+            })
+            .into(),
+            Register2(r0),
+            Register1(r1).into(),
+            args(),
+            false,
+            false,
+        ),
+        Instruction::from_near_call(Register1(r3), Immediate1(4), Immediate2(5), args()),
+        // 3: Not reached
+        Instruction::from_ret(Register1(r0), None, args()),
+        // 4: Near call function
+        Instruction::from_heap_read(Register1(r1).into(), Register1(r2), None, args()),
+        // 5: Exception handler
+        Instruction::from_ret(Register1(r0), None, args()),
+    ];
+    let (_, end, pcs) = run(instructions, vec![U256::MAX]);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    // The panic caused by the heap read is executed outside the program.
+    assert_eq!(pcs, [Some(0), Some(1), Some(2), Some(4), None, Some(5)]);
+}
+
+#[test]
+fn near_call_returns_to_next_instruction() {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let instructions = vec![
+        Instruction::from_near_call(Register1(r0), Immediate1(3), Immediate2(3), args()),
+        Instruction::from_add(
+            Immediate1(42).into(),
+            Register2(r0),
+            Register1(r1).into(),
+            args(),
+            false,
+            false,
+        ),
+        Instruction::from_ret(Register1(r0), None, args()),
+        // 3: Near call function
+        Instruction::from_ret(Register1(r0), None, args()),
+    ];
+    let (vm, end, pcs) = run(instructions, vec![]);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert_eq!(pcs, [Some(0), Some(3), Some(1), Some(2)]);
+    assert_eq!(vm.read_register(1), (42.into(), false));
+}
@@ -0,0 +1,158 @@
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::StateInterface;
+
+use crate::{
+    addressing_modes::{Arguments, Register, Register1, Register2, L1_MESSAGE_COST, SSTORE_COST},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+/// Pubdata cost of every storage write in [`TestWorld`].
+const STORAGE_WRITE_PUBDATA: u32 = 50;
+/// Pubdata produced by a single L2-to-L1 message.
+const L2_TO_L1_LOG_PUBDATA: u32 = 88;
+
+type TestVm = VirtualMachine<(), TestWorld<()>>;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// Creates a VM running a program that sets `ergs_per_pubdata_byte` and then executes `charged_instruction`,
+/// recording the gas left before and after it in r3 and r4.
+fn vm_with_program(
+    address: Address,
+    charge_for_pubdata: bool,
+    ergs_per_pubdata_byte: u32,
+    charged_instruction: Instruction<(), TestWorld<()>>,
+) -> (TestVm, TestWorld<()>) {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_set_ergs_per_pubdata_byte(
+                Register1(r1),
+                Arguments::new(Predicate::Always, 5, ModeRequirements::new(true, false)),
+            ),
+            Instruction::from_ergs_left(Register1(Register::new(3)), args(0)),
+            charged_instruction,
+            Instruction::from_ergs_left(Register1(Register::new(4)), args(0)),
+            Instruction::from_ret(Register1(r0), None, args(5)),
+        ],
+        vec![],
+    );
+
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .gas(1_000_000)
+        .charge_for_pubdata(charge_for_pubdata)
+        .build()
+        .unwrap();
+    // Not all values fit into an immediate, so r1 is initialized directly. It's also used as the key / value
+    // of the charged instruction.
+    vm.set_register(1, ergs_per_pubdata_byte.into(), false);
+    (vm, world)
+}
+
+fn kernel_address() -> Address {
+    Address::from_low_u64_be(0x8002)
+}
+
+fn storage_write() -> Instruction<(), TestWorld<()>> {
+    let r1 = Register::new(1);
+    Instruction::from_storage_write(Register1(r1), Register2(r1), args(SSTORE_COST))
+}
+
+fn charged_gas(vm: &TestVm) -> u32 {
+    (vm.read_register(3).0 - vm.read_register(4).0).as_u32()
+}
+
+#[test]
+fn storage_writes_are_charged_for_pubdata() {
+    let (mut vm, mut world) = vm_with_program(kernel_address(), true, 10, storage_write());
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(vm.ergs_per_pubdata_byte(), 10);
+    assert_eq!(vm.world_diff().pubdata(), 50);
+    assert_eq!(charged_gas(&vm), SSTORE_COST + 10 * STORAGE_WRITE_PUBDATA);
+}
+
+#[test]
+fn pubdata_is_not_charged_by_default() {
+    let (mut vm, mut world) = vm_with_program(kernel_address(), false, 10, storage_write());
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(vm.ergs_per_pubdata_byte(), 10);
+    assert_eq!(vm.world_diff().pubdata(), 50);
+    assert_eq!(charged_gas(&vm), SSTORE_COST);
+}
+
+#[test]
+fn pubdata_is_free_at_zero_rate() {
+    let (mut vm, mut world) = vm_with_program(kernel_address(), true, 0, storage_write());
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(vm.ergs_per_pubdata_byte(), 0);
+    assert_eq!(charged_gas(&vm), SSTORE_COST);
+}
+
+#[test]
+fn l2_to_l1_messages_are_charged_for_pubdata() {
+    let r1 = Register::new(1);
+    let message = Instruction::from_l2_to_l1_message(
+        Register1(r1),
+        Register2(r1),
+        false,
+        args(L1_MESSAGE_COST),
+    );
+    let (mut vm, mut world) = vm_with_program(kernel_address(), true, 10, message);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(vm.world_diff().l2_to_l1_logs().len(), 1);
+    assert_eq!(
+        charged_gas(&vm),
+        L1_MESSAGE_COST + 10 * L2_TO_L1_LOG_PUBDATA
+    );
+}
+
+#[test]
+fn running_out_of_gas_for_pubdata_panics() {
+    let (mut vm, mut world) = vm_with_program(kernel_address(), true, u32::MAX, storage_write());
+    assert_eq!(vm.run(&mut world, &mut ()), ExecutionEnd::Panicked);
+    assert_eq!(vm.world_diff().get_storage_changes().count(), 0);
+}
+
+#[test]
+fn setting_ergs_per_pubdata_byte_requires_kernel_mode() {
+    let (mut vm, mut world) =
+        vm_with_program(Address::repeat_byte(0x23), true, 10, storage_write());
+    assert_eq!(vm.run(&mut world, &mut ()), ExecutionEnd::Panicked);
+    assert_eq!(vm.ergs_per_pubdata_byte(), 0);
+}
+
+#[test]
+fn ergs_per_pubdata_byte_is_restored_on_rollback() {
+    let (mut vm, mut world) = vm_with_program(kernel_address(), true, 10, storage_write());
+    vm.set_ergs_per_pubdata_byte(3);
+    vm.make_snapshot();
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(vm.ergs_per_pubdata_byte(), 10);
+
+    vm.rollback();
+    assert_eq!(vm.ergs_per_pubdata_byte(), 3);
+    assert_eq!(vm.world_diff().pubdata(), 0);
+}
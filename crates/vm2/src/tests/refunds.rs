@@ -0,0 +1,128 @@
+//! Tests crediting storage refunds according to [`RefundPolicy`].
+
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::StateInterface;
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1, SLOAD_COST},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, RefundPolicy, VirtualMachine,
+};
+
+const GAS: u32 = 100_000;
+/// Gas spent by [`storage_reads()`] before refunds.
+const SPENT_ON_READS: u32 = 2 * SLOAD_COST + 5;
+
+type TestInstruction = Instruction<(), TestWorld<()>>;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// Reads the same slot twice, so that the second read is refunded.
+fn storage_reads() -> Vec<TestInstruction> {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    vec![
+        Instruction::from_storage_read(Register1(r0), Register1(r1), args(SLOAD_COST)),
+        Instruction::from_storage_read(Register1(r0), Register1(r1), args(SLOAD_COST)),
+    ]
+}
+
+/// Runs `instructions` in the initial frame and returns the execution end, the leftover gas
+/// and the total refund recorded by the world diff.
+fn run(instructions: Vec<TestInstruction>, policy: RefundPolicy) -> (ExecutionEnd, u32, u32) {
+    let program = Program::from_raw(instructions, vec![]);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::builder()
+        .address(address)
+        .program(program)
+        .gas(GAS)
+        .refund_policy(policy)
+        .build()
+        .unwrap();
+    let end = vm.run(&mut world, &mut ());
+    let refund = vm.world_diff().storage_refunds().iter().sum();
+    (end, vm.gas_remaining(), refund)
+}
+
+fn reads_and_return(is_revert: bool) -> Vec<TestInstruction> {
+    let r0 = Register1(Register::new(0));
+    let mut instructions = storage_reads();
+    instructions.push(if is_revert {
+        Instruction::from_revert(r0, None, args(5))
+    } else {
+        Instruction::from_ret(r0, None, args(5))
+    });
+    instructions
+}
+
+#[test]
+fn refunds_are_credited_immediately_by_default() {
+    let (end, gas, refund) = run(reads_and_return(false), RefundPolicy::default());
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert!(refund > 0 && refund < SLOAD_COST);
+    assert_eq!(gas, GAS - SPENT_ON_READS + refund);
+
+    // Reverting the frame doesn't take back immediate refunds.
+    let (end, reverted_gas, _) = run(reads_and_return(true), RefundPolicy::immediate());
+    assert_eq!(end, ExecutionEnd::Reverted(vec![]));
+    assert_eq!(reverted_gas, gas);
+}
+
+#[test]
+fn deferred_refunds_are_credited_on_return() {
+    let (_, immediate_gas, refund) = run(reads_and_return(false), RefundPolicy::immediate());
+    let (end, gas, _) = run(reads_and_return(false), RefundPolicy::deferred());
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert_eq!(gas, immediate_gas);
+
+    let (end, gas, _) = run(reads_and_return(true), RefundPolicy::deferred());
+    assert_eq!(end, ExecutionEnd::Reverted(vec![]));
+    assert_eq!(gas, GAS - SPENT_ON_READS);
+
+    // The second read refunds most of its cost, so the ceiling is lower than the refund.
+    let policy = RefundPolicy::deferred().with_ceiling(1_000);
+    let (_, gas, _) = run(reads_and_return(false), policy);
+    let ceiling = SPENT_ON_READS / 1_000;
+    assert!(ceiling < refund);
+    assert_eq!(gas, GAS - SPENT_ON_READS + ceiling);
+
+    let policy = RefundPolicy::deferred().with_ceiling(1);
+    let (_, gas, _) = run(reads_and_return(false), policy);
+    assert_eq!(gas, immediate_gas);
+}
+
+/// Calls a function reading storage and reverting.
+fn reads_in_reverted_near_call() -> Vec<TestInstruction> {
+    let r0 = Register1(Register::new(0));
+    let mut instructions = vec![
+        // 0: call the function at 2 passing all gas, with the exception handler at 5
+        Instruction::from_near_call(r0, Immediate1(2), Immediate2(5), args(25)),
+        Instruction::from_ret(r0, None, args(5)),
+    ];
+    // 2: function body
+    instructions.extend(storage_reads());
+    instructions.extend([
+        Instruction::from_revert(r0, None, args(5)),
+        // 5: exception handler
+        Instruction::from_ret(r0, None, args(5)),
+    ]);
+    instructions
+}
+
+#[test]
+fn deferred_refunds_of_failed_near_calls_are_dropped() {
+    let spent = 25 + SPENT_ON_READS + 5;
+    let (end, gas, _) = run(reads_in_reverted_near_call(), RefundPolicy::deferred());
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert_eq!(gas, GAS - spent);
+
+    // Immediate refunds are kept by the caller even though the near call has reverted.
+    let (_, _, refund) = run(reads_and_return(false), RefundPolicy::immediate());
+    let (end, immediate_gas, _) = run(reads_in_reverted_near_call(), RefundPolicy::immediate());
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert_eq!(immediate_gas, gas + refund);
+}
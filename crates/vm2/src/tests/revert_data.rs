@@ -0,0 +1,188 @@
+//! Integration tests of propagating revert data through nested far calls to [`ExecutionEnd::Reverted`].
+//!
+//! The innermost contract mimics Solidity's `require(condition, message)`: it writes an `Error(string)`-encoded
+//! message to its aux heap and reverts with a pointer to it. All calling contracts forward the returned fat pointer
+//! when their callee reverts, like `revert(returndata)` in Solidity. The pointer is rewrapped by `ptr.pack`,
+//! which sets the ABI forwarding flag in its upper 128 bits.
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::opcodes;
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+type TestInstruction = Instruction<(), TestWorld<()>>;
+type TestProgram = Program<(), TestWorld<()>>;
+
+/// Selector of `Error(string)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const MESSAGE: &[u8] = b"ERC20: transfer amount exceeds balance";
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+fn r(index: u8) -> Register {
+    Register::new(index)
+}
+
+fn load_from_code_page(immediate: u16, out: u8) -> TestInstruction {
+    Instruction::from_add(
+        CodePage(RegisterAndImmediate {
+            immediate,
+            register: r(0),
+        })
+        .into(),
+        Register2(r(0)),
+        Register1(r(out)).into(),
+        args(6),
+        false,
+        false,
+    )
+}
+
+fn address(index: u8) -> H160 {
+    H160::from_low_u64_be(0xeeee_ee00 + u64::from(index))
+}
+
+fn big_endian_word(value: usize) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    U256::from(value).to_big_endian(&mut bytes);
+    bytes
+}
+
+/// Revert data produced by `require(false, MESSAGE)`.
+fn error_data() -> Vec<u8> {
+    let padded_len = MESSAGE.len().div_ceil(32) * 32;
+    let mut data = ERROR_SELECTOR.to_vec();
+    data.extend_from_slice(&big_endian_word(32));
+    data.extend_from_slice(&big_endian_word(MESSAGE.len()));
+    data.extend_from_slice(MESSAGE);
+    data.resize(4 + 64 + padded_len, 0);
+    data
+}
+
+/// Program writing [`error_data()`] to the aux heap and reverting with it.
+fn failing_require() -> TestProgram {
+    let data = error_data();
+    let data_len = u32::try_from(data.len()).unwrap();
+    // Split data into 32-byte words written at offsets 0, 32, ... The last word is padded with zeros.
+    let mut code_page: Vec<_> = data
+        .chunks(32)
+        .map(|chunk| {
+            let mut word = [0; 32];
+            word[..chunk.len()].copy_from_slice(chunk);
+            U256::from_big_endian(&word)
+        })
+        .collect();
+    let word_count = u16::try_from(code_page.len()).unwrap();
+    // ABI of a new pointer to the aux heap
+    code_page.push(U256([0, u64::from(data_len) << 32, 0, 2 << 32]));
+
+    let mut instructions = vec![];
+    for i in 0..word_count {
+        instructions.push(load_from_code_page(i, 1));
+        instructions.push(Instruction::from_aux_heap_store(
+            Immediate1(i * 32).into(),
+            Register2(r(1)),
+            None,
+            args(7),
+        ));
+    }
+    instructions.push(load_from_code_page(word_count, 1));
+    instructions.push(Instruction::from_revert(Register1(r(1)), None, args(5)));
+    Program::from_raw(instructions, code_page)
+}
+
+/// Program calling `callee` and forwarding its revert data. If `skip_bytes` is non-zero, that many bytes
+/// are removed from the start of revert data before forwarding it.
+fn forwarding_proxy(callee: H160, skip_bytes: u16) -> TestProgram {
+    let far_call_abi = U256([0, 0, 0, u32::MAX.into()]);
+    let forwarding_abi = U256([0, 0, 0, 1 << 32]);
+    let code_page = vec![far_call_abi, callee.to_low_u64_be().into(), forwarding_abi];
+    let instructions = vec![
+        load_from_code_page(0, 1),
+        load_from_code_page(1, 2),
+        Instruction::from_far_call::<opcodes::Normal>(
+            Register1(r(1)),
+            Register2(r(2)),
+            Immediate1(4),
+            false,
+            false,
+            args(200),
+        ),
+        Instruction::from_ret(Register1(r(0)), None, args(5)),
+        // 4: exception handler; `r1` is the pointer to revert data.
+        Instruction::from_add(
+            Immediate1(skip_bytes).into(),
+            Register2(r(0)),
+            Register1(r(4)).into(),
+            args(6),
+            false,
+            false,
+        ),
+        Instruction::from_pointer_add(
+            Register1(r(1)).into(),
+            Register2(r(4)),
+            Register1(r(1)).into(),
+            args(6),
+            false,
+        ),
+        load_from_code_page(2, 3),
+        Instruction::from_pointer_pack(
+            Register1(r(1)).into(),
+            Register2(r(3)),
+            Register1(r(1)).into(),
+            args(6),
+            false,
+        ),
+        Instruction::from_revert(Register1(r(1)), None, args(5)),
+    ];
+    Program::from_raw(instructions, code_page)
+}
+
+/// Runs a chain of `depth` proxies ending with [`failing_require()`]. `skip_bytes` are provided for each proxy,
+/// starting from the outermost one.
+fn run_call_chain(skip_bytes: &[u16]) -> ExecutionEnd {
+    let depth = u8::try_from(skip_bytes.len()).unwrap();
+    let mut programs = vec![(address(depth), failing_require())];
+    for (i, &skip_bytes) in (0..depth).zip(skip_bytes) {
+        programs.push((address(i), forwarding_proxy(address(i + 1), skip_bytes)));
+    }
+    let mut world = TestWorld::new(&programs);
+    let program = initial_decommit(&mut world, address(0));
+    let mut vm = VirtualMachine::builder()
+        .address(address(0))
+        .program(program)
+        .gas(10_000_000)
+        .build()
+        .unwrap();
+    vm.run(&mut world, &mut ())
+}
+
+#[test]
+fn revert_data_of_require() {
+    let end = run_call_chain(&[]);
+    assert_eq!(end, ExecutionEnd::Reverted(error_data()));
+}
+
+#[test]
+fn revert_data_is_forwarded_from_deep_frames() {
+    for depth in [1, 2, 5] {
+        let end = run_call_chain(&vec![0; depth]);
+        assert_eq!(end, ExecutionEnd::Reverted(error_data()), "{depth}");
+    }
+}
+
+#[test]
+fn narrowed_revert_data_is_forwarded() {
+    // The middle proxy strips the selector, and the outer one the message offset.
+    let end = run_call_chain(&[32, 4, 0]);
+    let expected = error_data()[36..].to_vec();
+    assert_eq!(end, ExecutionEnd::Reverted(expected));
+}
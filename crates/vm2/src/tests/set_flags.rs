@@ -0,0 +1,188 @@
+//! Checks that only instructions with the `set_flags` bit update execution flags.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    Flags, GlobalStateInterface, OpcodeType, ShouldStop, StateInterface, Tracer,
+};
+
+use crate::{
+    addressing_modes::{AnySource, Arguments, Immediate1, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+const NO_FLAGS: Flags = Flags {
+    less_than: false,
+    equal: false,
+    greater: false,
+};
+const LT: Flags = Flags {
+    less_than: true,
+    ..NO_FLAGS
+};
+const EQ: Flags = Flags {
+    equal: true,
+    ..NO_FLAGS
+};
+
+#[derive(Debug, Default)]
+struct FlagsRecorder(Vec<Flags>);
+
+impl Tracer for FlagsRecorder {
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        self.0.push(state.flags());
+        ShouldStop::Continue
+    }
+}
+
+fn r(index: u8) -> Register {
+    Register::new(index)
+}
+
+fn args(predicate: Predicate) -> Arguments {
+    Arguments::new(predicate, 6, ModeRequirements::none())
+}
+
+/// Loads an immediate into the specified register without touching flags.
+fn load(
+    value: u16,
+    out: u8,
+    predicate: Predicate,
+) -> Instruction<FlagsRecorder, TestWorld<FlagsRecorder>> {
+    Instruction::from_add(
+        Immediate1(value).into(),
+        Register2(r(0)),
+        Register1(r(out)).into(),
+        args(predicate),
+        false,
+        false,
+    )
+}
+
+#[test]
+fn flags_are_only_set_by_tagged_instructions() {
+    let src = |index| AnySource::from(Register1(r(index)));
+    let instructions = vec![
+        load(3, 1, Predicate::Always),
+        load(5, 2, Predicate::Always),
+        // 3 - 5 underflows.
+        Instruction::from_sub(
+            src(1),
+            Register2(r(2)),
+            Register1(r(3)).into(),
+            args(Predicate::Always),
+            false,
+            true,
+        ),
+        // None of the following instructions set flags, although they would produce different flags.
+        Instruction::from_add(
+            src(1),
+            Register2(r(2)),
+            Register1(r(4)).into(),
+            args(Predicate::Always),
+            false,
+            false,
+        ),
+        Instruction::from_mul(
+            src(1),
+            Register2(r(2)),
+            Register1(r(5)).into(),
+            Register2(r(6)),
+            args(Predicate::Always),
+            false,
+            false,
+        ),
+        Instruction::from_div(
+            src(2),
+            Register2(r(1)),
+            Register1(r(7)).into(),
+            Register2(r(8)),
+            args(Predicate::Always),
+            false,
+            false,
+        ),
+        Instruction::from_xor(
+            src(1),
+            Register2(r(1)),
+            Register1(r(9)).into(),
+            args(Predicate::Always),
+            false,
+            false,
+        ),
+        Instruction::from_shift_left(
+            src(1),
+            Register2(r(2)),
+            Register1(r(9)).into(),
+            args(Predicate::Always),
+            false,
+            false,
+        ),
+        // Executed since the "less than" flag is still set.
+        load(1, 10, Predicate::IfLT),
+        // 3 - 3 == 0; swapping operands doesn't affect setting flags.
+        Instruction::from_sub(
+            src(1),
+            Register2(r(1)),
+            Register1(r(3)).into(),
+            args(Predicate::Always),
+            true,
+            true,
+        ),
+        Instruction::from_or(
+            src(1),
+            Register2(r(2)),
+            Register1(r(9)).into(),
+            args(Predicate::Always),
+            false,
+            false,
+        ),
+        Instruction::from_sub(
+            src(2),
+            Register2(r(1)),
+            Register1(r(9)).into(),
+            args(Predicate::Always),
+            true,
+            false,
+        ),
+        // Executed since the "equal" flag is still set.
+        load(1, 11, Predicate::IfEQ),
+        // Skipped since the "less than" flag is reset.
+        load(1, 12, Predicate::IfLT),
+    ];
+    let instruction_count = instructions.len();
+    let program = Program::from_raw(instructions, vec![]);
+
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        10_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    for register in 10..=12 {
+        vm.set_register(register, U256::zero(), false);
+    }
+
+    let mut tracer = FlagsRecorder::default();
+    let mut budget = u64::try_from(instruction_count).unwrap();
+    let end = vm.run_with_instruction_limit(&mut world, &mut tracer, &mut budget);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+
+    let expected_flags = [[NO_FLAGS; 2].as_slice(), &[LT; 7], &[EQ; 5]].concat();
+    assert_eq!(tracer.0, expected_flags);
+    assert_eq!(vm.read_register(10), (U256::one(), false));
+    assert_eq!(vm.read_register(11), (U256::one(), false));
+    assert_eq!(vm.read_register(12), (U256::zero(), false));
+}
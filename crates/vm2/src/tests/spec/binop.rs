@@ -0,0 +1,170 @@
+//! Arithmetic and bitwise binops: `add`, `sub`, `and`, `or`, `xor`.
+
+use primitive_types::U256;
+
+use super::{check_cases, flags, int, output, ptr, Case, ALL_FLAGS, EQ, GT, LT, NO_FLAGS};
+use crate::Instruction;
+
+const GAS_COST: u32 = 6;
+
+#[test]
+fn add() {
+    check_cases(
+        "add",
+        GAS_COST,
+        |ops| {
+            Instruction::from_add(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new("small", int(2), int(3), output(int(5), GT)),
+            Case::new("zero", int(0), int(0), output(int(0), EQ)),
+            Case::new(
+                "overflow to zero",
+                int(U256::MAX),
+                int(1),
+                output(int(0), flags(true, true, false)),
+            ),
+            Case::new("overflow", int(U256::MAX), int(2), output(int(1), LT)),
+            Case::new("pointer flag is erased", ptr(5), int(1), output(int(6), GT)),
+            Case::new("flags are preserved", int(2), int(3), output(int(5), GT))
+                .without_setting_flags(),
+        ],
+    );
+}
+
+#[test]
+fn sub() {
+    check_cases(
+        "sub",
+        GAS_COST,
+        |ops| {
+            Instruction::from_sub(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new("small", int(5), int(3), output(int(2), GT)),
+            Case::new("equal operands", int(3), int(3), output(int(0), EQ)),
+            Case::new("underflow", int(3), int(5), output(int(U256::MAX - 1), LT)),
+            Case::new("swapped", int(3), int(5), output(int(2), GT)).swapped(),
+            Case::new(
+                "flags are preserved",
+                int(3),
+                int(5),
+                output(int(U256::MAX - 1), LT),
+            )
+            .without_setting_flags(),
+        ],
+    );
+}
+
+#[test]
+fn and() {
+    check_cases(
+        "and",
+        GAS_COST,
+        |ops| {
+            Instruction::from_and(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new(
+                "non-zero",
+                int(0b1100),
+                int(0b1010),
+                output(int(0b1000), NO_FLAGS),
+            ),
+            Case::new("zero", int(0b1100), int(0b0011), output(int(0), EQ)),
+            Case::new(
+                "max",
+                int(U256::MAX),
+                int(U256::MAX),
+                output(int(U256::MAX), NO_FLAGS),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn or() {
+    check_cases(
+        "or",
+        GAS_COST,
+        |ops| {
+            Instruction::from_or(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new(
+                "non-zero",
+                int(0b1100),
+                int(0b1010),
+                output(int(0b1110), NO_FLAGS),
+            ),
+            Case::new("zero", int(0), int(0), output(int(0), EQ)),
+            Case::new(
+                "flags are preserved",
+                int(0),
+                int(0),
+                output(int(0), ALL_FLAGS),
+            )
+            .without_setting_flags(),
+        ],
+    );
+}
+
+#[test]
+fn xor() {
+    check_cases(
+        "xor",
+        GAS_COST,
+        |ops| {
+            Instruction::from_xor(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new(
+                "non-zero",
+                int(0b1100),
+                int(0b1010),
+                output(int(0b0110), NO_FLAGS),
+            ),
+            Case::new(
+                "same operands",
+                int(U256::MAX),
+                int(U256::MAX),
+                output(int(0), EQ),
+            ),
+        ],
+    );
+}
@@ -0,0 +1,269 @@
+//! Far returns: `ret`, `revert` and `panic` from a called contract, as well as instructions failing in it.
+//! Checks the state of the caller right after the return: registers, flags, the program counter and
+//! the context register. All failures are compared against an explicit `panic`, and reverts against panics,
+//! since callers (e.g., wallets decoding revert reasons) rely on telling them apart by the returned data.
+//!
+//! After any far return, all registers except `r1` are zeroed and lose their pointer flags; `r1` holds
+//! a fat pointer to the returned data, which is the empty pointer (all zeros) on panic. The "less than" flag
+//! is set on panic; all other flags are cleared.
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{opcodes, CallframeInterface, Flags, StateInterface};
+
+use super::{ptr, Operand, LT, NO_FLAGS};
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, FatPointer, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+};
+
+type TestInstruction = Instruction<(), TestWorld<()>>;
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
+]);
+/// Program counter of the caller after a successful return.
+const NEXT_PC: u16 = 1;
+/// Program counter of the caller's exception handler.
+const EXCEPTION_HANDLER: u16 = 2;
+/// Length of data returned by the called contract.
+const RETURN_DATA_LEN: u32 = 32;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+fn r(index: u8) -> Register {
+    Register::new(index)
+}
+
+/// ABI of a new pointer to the called contract's heap, or of forwarding a fat pointer.
+fn return_abi(forward: bool) -> U256 {
+    U256([
+        0,
+        u64::from(RETURN_DATA_LEN) << 32,
+        0,
+        u64::from(forward) << 32,
+    ])
+}
+
+/// Caller state after the far return.
+#[derive(Debug, PartialEq)]
+struct CallerState {
+    returned: Operand,
+    /// Registers `r2..=r15`.
+    other_registers: Vec<(U256, bool)>,
+    flags: Flags,
+    pc: Option<u16>,
+    context_u128: u128,
+}
+
+/// Performs a far call to a contract that dirties registers and flags, and then executes `end`.
+fn caller_state_after_return(end: TestInstruction) -> CallerState {
+    let add_immediate = |value, out| {
+        Instruction::from_add(
+            Immediate1(value).into(),
+            Register2(r(0)),
+            Register1(r(out)).into(),
+            args(6),
+            false,
+            false,
+        )
+    };
+    let called_instructions = vec![
+        // Load the return ABI from the code page. `r2` is for the ABI forwarding the (non-pointer) value.
+        Instruction::from_add(
+            CodePage(RegisterAndImmediate {
+                immediate: 0,
+                register: r(0),
+            })
+            .into(),
+            Register2(r(0)),
+            Register1(r(1)).into(),
+            args(6),
+            false,
+            false,
+        ),
+        Instruction::from_add(
+            CodePage(RegisterAndImmediate {
+                immediate: 1,
+                register: r(0),
+            })
+            .into(),
+            Register2(r(0)),
+            Register1(r(2)).into(),
+            args(6),
+            false,
+            false,
+        ),
+        add_immediate(3, 3),
+        add_immediate(15, 15),
+        // Sets the "equal" flag.
+        Instruction::from_sub(
+            Register1(r(0)).into(),
+            Register2(r(0)),
+            Register1(r(4)).into(),
+            args(6),
+            false,
+            true,
+        ),
+        end,
+    ];
+    let instruction_count = u64::try_from(called_instructions.len()).unwrap() + 1;
+    let called_program = Program::from_raw(
+        called_instructions,
+        vec![return_abi(false), return_abi(true)],
+    );
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_far_call::<opcodes::Normal>(
+                Register1(r(1)),
+                Register2(r(2)),
+                Immediate1(EXCEPTION_HANDLER),
+                false,
+                false,
+                args(200),
+            ),
+            // 1: next instruction
+            Instruction::from_ret(Register1(r(0)), None, args(5)),
+            // 2: exception handler
+            Instruction::from_revert(Register1(r(0)), None, args(5)),
+        ],
+        vec![],
+    );
+
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm: VirtualMachine<(), TestWorld<()>> = VirtualMachine::builder()
+        .address(MAIN_ADDRESS)
+        .program(program)
+        .gas(100_000)
+        .build()
+        .unwrap();
+    let mut far_call_abi = U256::zero();
+    far_call_abi.0[3] = 10_000;
+    vm.set_register(1, far_call_abi, false);
+    vm.set_register(2, CALLED_ADDRESS.to_low_u64_be().into(), false);
+
+    let mut budget = instruction_count;
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut budget);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+    assert_eq!(vm.number_of_callframes(), 1);
+
+    let (value, is_pointer) = vm.read_register(1);
+    CallerState {
+        returned: Operand { value, is_pointer },
+        other_registers: (2..16).map(|i| vm.read_register(i)).collect(),
+        flags: StateInterface::flags(&vm),
+        pc: vm.current_frame().program_counter(),
+        context_u128: vm.context_u128_register(),
+    }
+}
+
+fn ret() -> TestInstruction {
+    Instruction::from_ret(Register1(r(1)), None, args(5))
+}
+
+fn revert() -> TestInstruction {
+    Instruction::from_revert(Register1(r(1)), None, args(5))
+}
+
+fn panic() -> TestInstruction {
+    Instruction::from_panic(None, args(5))
+}
+
+#[track_caller]
+fn assert_cleared_registers(state: &CallerState) {
+    assert_eq!(state.other_registers, [(U256::zero(), false); 14]);
+    assert_eq!(state.context_u128, 0);
+}
+
+#[track_caller]
+fn assert_returned_data(state: &CallerState) {
+    assert!(state.returned.is_pointer);
+    let pointer = FatPointer::from(state.returned.value);
+    assert_eq!(
+        (pointer.offset, pointer.start, pointer.length),
+        (0, 0, RETURN_DATA_LEN)
+    );
+}
+
+#[test]
+fn normal_return() {
+    let state = caller_state_after_return(ret());
+    assert_returned_data(&state);
+    assert_eq!(state.flags, NO_FLAGS);
+    assert_eq!(state.pc, Some(NEXT_PC));
+    assert_cleared_registers(&state);
+}
+
+#[test]
+fn revert_returns_data_to_exception_handler() {
+    let state = caller_state_after_return(revert());
+    assert_returned_data(&state);
+    assert_eq!(state.flags, NO_FLAGS);
+    assert_eq!(state.pc, Some(EXCEPTION_HANDLER));
+    assert_cleared_registers(&state);
+
+    // A revert differs from a normal return only by the program counter.
+    let ret_state = caller_state_after_return(ret());
+    assert_eq!(
+        CallerState {
+            pc: Some(EXCEPTION_HANDLER),
+            ..ret_state
+        },
+        state
+    );
+}
+
+#[test]
+fn panic_returns_empty_pointer() {
+    let state = caller_state_after_return(panic());
+    assert_eq!(state.returned, ptr(U256::zero()));
+    assert_eq!(state.flags, LT);
+    assert_eq!(state.pc, Some(EXCEPTION_HANDLER));
+    assert_cleared_registers(&state);
+
+    // A panic differs from a revert only by the returned pointer and flags.
+    let revert_state = caller_state_after_return(revert());
+    assert_ne!(revert_state.returned, state.returned);
+    assert_eq!(
+        CallerState {
+            returned: state.returned,
+            flags: LT,
+            ..revert_state
+        },
+        state
+    );
+}
+
+#[test]
+fn failures_are_equivalent_to_panic() {
+    let expected = caller_state_after_return(panic());
+    let failures = [
+        // Forwarding a value that is not a pointer
+        (
+            "invalid returndata",
+            Instruction::from_ret(Register1(r(2)), None, args(5)),
+        ),
+        (
+            "invalid revert data",
+            Instruction::from_revert(Register1(r(2)), None, args(5)),
+        ),
+        ("out of gas", Instruction::from_invalid()),
+        (
+            "panic to label",
+            Instruction::from_panic(Some(Immediate1(0)), args(5)),
+        ),
+    ];
+    for (name, end) in failures {
+        assert_eq!(caller_state_after_return(end), expected, "{name}");
+    }
+}
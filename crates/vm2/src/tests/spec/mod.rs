@@ -0,0 +1,248 @@
+//! Table-driven conformance tests for individual opcodes, grouped by opcode family.
+//!
+//! Each case executes a single instruction reading its inputs from `r1` / `r2` and writing its outputs to `r3` / `r4`,
+//! and then checks output values together with their pointer flags, execution flags and consumed gas
+//! (i.e., that the instruction doesn't charge anything on top of its static cost), or that the instruction panicked.
+//!
+//! Covered families: arithmetic and bitwise binops ([`binop`]), shifts and rotations ([`shift`]),
+//! multiplication and division ([`mul_div`]) and fat pointer operations ([`pointer`]); operand swapping is checked
+//! for all of them in [`swap`]. Far returns ([`far_return`]) are checked with a separate harness since they need
+//! a caller frame. Other families (context, heap access, storage, events and calls) depend on more VM state than
+//! registers and flags and are currently covered by dedicated tests elsewhere; they should get their own files here
+//! as the harness is extended.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{CallframeInterface, Flags, StateInterface};
+
+use crate::{
+    addressing_modes::{AnyDestination, AnySource, Arguments, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+mod binop;
+mod far_return;
+mod mul_div;
+mod pointer;
+mod shift;
+mod swap;
+
+const GAS: u32 = 10_000;
+
+type TestInstruction = Instruction<(), TestWorld<()>>;
+
+/// Flags with all bits cleared.
+const NO_FLAGS: Flags = flags(false, false, false);
+/// Only the "less than" / overflow flag set.
+const LT: Flags = flags(true, false, false);
+/// Only the "equal" flag set.
+const EQ: Flags = flags(false, true, false);
+/// Only the "greater than" flag set.
+const GT: Flags = flags(false, false, true);
+/// All flags set; useful as input flags to check that they are overwritten or preserved.
+const ALL_FLAGS: Flags = flags(true, true, true);
+
+const fn flags(less_than: bool, equal: bool, greater: bool) -> Flags {
+    Flags {
+        less_than,
+        equal,
+        greater,
+    }
+}
+
+/// Register value together with its pointer flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Operand {
+    value: U256,
+    is_pointer: bool,
+}
+
+/// Creates a non-pointer operand.
+fn int(value: impl Into<U256>) -> Operand {
+    Operand {
+        value: value.into(),
+        is_pointer: false,
+    }
+}
+
+/// Creates a pointer operand.
+fn ptr(value: impl Into<U256>) -> Operand {
+    Operand {
+        value: value.into(),
+        is_pointer: true,
+    }
+}
+
+/// Expected outcome of executing an instruction.
+#[derive(Debug)]
+enum Expected {
+    /// The instruction completes, writing the specified outputs and flags.
+    Output {
+        out: Operand,
+        /// Second output for opcodes that have one (e.g., `mul` and `div`); `None` means that `r4` is unchanged.
+        out2: Option<U256>,
+        flags: Flags,
+    },
+    /// The instruction panics.
+    Panic,
+}
+
+/// Single conformance case.
+#[derive(Debug)]
+struct Case {
+    name: &'static str,
+    in1: Operand,
+    in2: Operand,
+    swap: bool,
+    set_flags: bool,
+    flags_in: Flags,
+    expected: Expected,
+}
+
+impl Case {
+    /// Creates a case with non-swapped operands, flags set by the instruction and [`ALL_FLAGS`] as input flags.
+    fn new(name: &'static str, in1: Operand, in2: Operand, expected: Expected) -> Self {
+        Self {
+            name,
+            in1,
+            in2,
+            swap: false,
+            set_flags: true,
+            flags_in: ALL_FLAGS,
+            expected,
+        }
+    }
+
+    fn swapped(mut self) -> Self {
+        self.swap = true;
+        self
+    }
+
+    fn without_setting_flags(mut self) -> Self {
+        self.set_flags = false;
+        self
+    }
+}
+
+/// Shortcut for [`Expected::Output`] without the second output.
+fn output(out: Operand, flags: Flags) -> Expected {
+    Expected::Output {
+        out,
+        out2: None,
+        flags,
+    }
+}
+
+/// Shortcut for [`Expected::Output`] with both outputs.
+fn outputs(out: impl Into<U256>, out2: impl Into<U256>, flags: Flags) -> Expected {
+    Expected::Output {
+        out: int(out),
+        out2: Some(out2.into()),
+        flags,
+    }
+}
+
+/// Operands of the instruction under test.
+#[derive(Debug)]
+struct Operands {
+    src1: AnySource,
+    src2: Register2,
+    out: AnyDestination,
+    out2: Register2,
+    arguments: Arguments,
+    swap: bool,
+    set_flags: bool,
+}
+
+/// Value written to `r4` before execution, so that writes to it can be detected.
+const OUT2_SENTINEL: u64 = 0xdead_beef;
+
+/// Outcome of executing a single instruction.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Completed {
+        out: Operand,
+        out2: (U256, bool),
+        flags: Flags,
+        gas_left: u32,
+    },
+    Panicked,
+}
+
+fn operands(gas_cost: u32, swap: bool, set_flags: bool) -> Operands {
+    Operands {
+        src1: Register1(Register::new(1)).into(),
+        src2: Register2(Register::new(2)),
+        out: Register1(Register::new(3)).into(),
+        out2: Register2(Register::new(4)),
+        arguments: Arguments::new(Predicate::Always, gas_cost, ModeRequirements::none()),
+        swap,
+        set_flags,
+    }
+}
+
+/// Runs all `cases` for the instruction created by `build` with the static cost `gas_cost`.
+fn check_cases(
+    opcode: &str,
+    gas_cost: u32,
+    build: impl Fn(Operands) -> TestInstruction,
+    cases: &[Case],
+) {
+    for case in cases {
+        let instruction = build(operands(gas_cost, case.swap, case.set_flags));
+        let context = format!("{opcode}: {}", case.name);
+        let outcome = execute(instruction, case.in1, case.in2, case.flags_in);
+        let expected = match case.expected {
+            Expected::Output { out, out2, flags } => Outcome::Completed {
+                out,
+                out2: (out2.unwrap_or(OUT2_SENTINEL.into()), false),
+                flags: if case.set_flags { flags } else { case.flags_in },
+                gas_left: GAS - gas_cost,
+            },
+            Expected::Panic => Outcome::Panicked,
+        };
+        assert_eq!(outcome, expected, "{context}");
+    }
+}
+
+fn execute(instruction: TestInstruction, in1: Operand, in2: Operand, flags_in: Flags) -> Outcome {
+    let program = Program::from_raw(vec![instruction], vec![]);
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        GAS,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.set_register(1, in1.value, in1.is_pointer);
+    vm.set_register(2, in2.value, in2.is_pointer);
+    vm.set_register(3, U256::zero(), false);
+    vm.set_register(4, OUT2_SENTINEL.into(), false);
+    vm.set_flags(flags_in);
+
+    let mut budget = 1;
+    let end = vm.run_with_instruction_limit(&mut world, &mut (), &mut budget);
+    assert_eq!(end, ExecutionEnd::InstructionLimit);
+
+    // A panicking instruction jumps to the panic handler instead of the next instruction.
+    if vm.current_frame().program_counter() != Some(1) {
+        assert_eq!(vm.run(&mut world, &mut ()), ExecutionEnd::Panicked);
+        return Outcome::Panicked;
+    }
+    let (value, is_pointer) = vm.read_register(3);
+    Outcome::Completed {
+        out: Operand { value, is_pointer },
+        out2: vm.read_register(4),
+        flags: StateInterface::flags(&vm),
+        gas_left: vm.current_frame().gas(),
+    }
+}
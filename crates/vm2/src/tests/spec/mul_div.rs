@@ -0,0 +1,76 @@
+//! Multiplication and division: `mul`, `div`. Both write a second output: `mul` writes the high 256 bits
+//! of the product, and `div` writes the remainder.
+
+use primitive_types::U256;
+
+use super::{check_cases, flags, int, outputs, Case, EQ, GT, LT, NO_FLAGS};
+use crate::Instruction;
+
+const GAS_COST: u32 = 6;
+
+#[test]
+fn mul() {
+    check_cases(
+        "mul",
+        GAS_COST,
+        |ops| {
+            Instruction::from_mul(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.out2,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new("small", int(6), int(7), outputs(42, 0, GT)),
+            Case::new("zero", int(6), int(0), outputs(0, 0, EQ)),
+            Case::new(
+                "overflow",
+                int(U256::MAX),
+                int(2),
+                outputs(U256::MAX - 1, 1, LT),
+            ),
+            Case::new(
+                "overflow with zero low half",
+                int(U256::one() << 255),
+                int(2),
+                outputs(0, 1, flags(true, true, false)),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn div() {
+    check_cases(
+        "div",
+        GAS_COST,
+        |ops| {
+            Instruction::from_div(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.out2,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new("exact", int(42), int(7), outputs(6, 0, GT)),
+            Case::new("with remainder", int(43), int(7), outputs(6, 1, NO_FLAGS)),
+            Case::new("zero quotient", int(3), int(7), outputs(0, 3, EQ)),
+            Case::new(
+                "zero quotient and remainder",
+                int(0),
+                int(7),
+                outputs(0, 0, flags(false, true, true)),
+            ),
+            Case::new("division by zero", int(42), int(0), outputs(0, 0, LT)),
+            Case::new("swapped", int(7), int(42), outputs(6, 0, GT)).swapped(),
+        ],
+    );
+}
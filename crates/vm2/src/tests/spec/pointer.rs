@@ -0,0 +1,134 @@
+//! Fat pointer operations: `ptr.add`, `ptr.sub`, `ptr.pack`, `ptr.shrink`. The first operand must be a pointer;
+//! otherwise, the instruction panics. (The second operand must not be a pointer, but this is only checked
+//! in kernel mode; the cases here are executed in user mode.) These instructions never change flags.
+
+use primitive_types::U256;
+
+use super::{check_cases, int, output, ptr, Case, Expected, ALL_FLAGS};
+use crate::Instruction;
+
+const GAS_COST: u32 = 6;
+
+/// Encodes a fat pointer as a `U256` value.
+fn fat_pointer(offset: u32, page: u32, start: u32, length: u32) -> U256 {
+    U256([
+        u64::from(offset) | (u64::from(page) << 32),
+        u64::from(start) | (u64::from(length) << 32),
+        0,
+        0,
+    ])
+}
+
+#[test]
+fn pointer_add() {
+    check_cases(
+        "ptr.add",
+        GAS_COST,
+        |ops| Instruction::from_pointer_add(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap),
+        &[
+            Case::new(
+                "small",
+                ptr(fat_pointer(10, 2, 0, 100)),
+                int(5),
+                output(ptr(fat_pointer(15, 2, 0, 100)), ALL_FLAGS),
+            ),
+            Case::new(
+                "offset overflow",
+                ptr(fat_pointer(u32::MAX, 2, 0, 100)),
+                int(1),
+                Expected::Panic,
+            ),
+            Case::new(
+                "too large addend",
+                ptr(fat_pointer(0, 2, 0, 100)),
+                int(U256::from(u32::MAX) + 1),
+                Expected::Panic,
+            ),
+            Case::new(
+                "non-pointer input",
+                int(fat_pointer(10, 2, 0, 100)),
+                int(5),
+                Expected::Panic,
+            ),
+            Case::new(
+                "swapped",
+                int(5),
+                ptr(fat_pointer(10, 2, 0, 100)),
+                output(ptr(fat_pointer(15, 2, 0, 100)), ALL_FLAGS),
+            )
+            .swapped(),
+        ],
+    );
+}
+
+#[test]
+fn pointer_sub() {
+    check_cases(
+        "ptr.sub",
+        GAS_COST,
+        |ops| Instruction::from_pointer_sub(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap),
+        &[
+            Case::new(
+                "small",
+                ptr(fat_pointer(10, 2, 0, 100)),
+                int(5),
+                output(ptr(fat_pointer(5, 2, 0, 100)), ALL_FLAGS),
+            ),
+            Case::new(
+                "offset underflow",
+                ptr(fat_pointer(4, 2, 0, 100)),
+                int(5),
+                Expected::Panic,
+            ),
+        ],
+    );
+}
+
+#[test]
+fn pointer_pack() {
+    let high_bits = U256::from(0xdead) << 128;
+    check_cases(
+        "ptr.pack",
+        GAS_COST,
+        |ops| Instruction::from_pointer_pack(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap),
+        &[
+            Case::new(
+                "small",
+                ptr(fat_pointer(10, 2, 0, 100)),
+                int(high_bits),
+                output(ptr(fat_pointer(10, 2, 0, 100) | high_bits), ALL_FLAGS),
+            ),
+            Case::new(
+                "non-zero low bits",
+                ptr(fat_pointer(10, 2, 0, 100)),
+                int(high_bits | U256::one()),
+                Expected::Panic,
+            ),
+        ],
+    );
+}
+
+#[test]
+fn pointer_shrink() {
+    check_cases(
+        "ptr.shrink",
+        GAS_COST,
+        |ops| {
+            Instruction::from_pointer_shrink(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap)
+        },
+        &[
+            Case::new(
+                "small",
+                ptr(fat_pointer(10, 2, 0, 100)),
+                int(30),
+                output(ptr(fat_pointer(10, 2, 0, 70)), ALL_FLAGS),
+            ),
+            Case::new(
+                "length underflow",
+                ptr(fat_pointer(10, 2, 0, 100)),
+                int(101),
+                Expected::Panic,
+            ),
+        ],
+    );
+}
@@ -0,0 +1,135 @@
+//! Shifts and rotations: `shl`, `shr`, `rol`, `ror`. The shift amount is taken modulo 256 from the low 32 bits
+//! of the second operand.
+
+use primitive_types::U256;
+
+use super::{check_cases, int, output, Case, EQ, NO_FLAGS};
+use crate::Instruction;
+
+const GAS_COST: u32 = 6;
+
+#[test]
+fn shift_left() {
+    check_cases(
+        "shl",
+        GAS_COST,
+        |ops| {
+            Instruction::from_shift_left(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new("small", int(1), int(4), output(int(16), NO_FLAGS)),
+            Case::new(
+                "amount modulo 256",
+                int(1),
+                int(260),
+                output(int(16), NO_FLAGS),
+            ),
+            Case::new("full word", int(1), int(256), output(int(1), NO_FLAGS)),
+            Case::new(
+                "amount from low 32 bits",
+                int(1),
+                int((1_u64 << 32) + 4),
+                output(int(16), NO_FLAGS),
+            ),
+            Case::new(
+                "shifted out",
+                int(U256::one() << 255),
+                int(1),
+                output(int(0), EQ),
+            ),
+            Case::new("swapped", int(4), int(1), output(int(16), NO_FLAGS)).swapped(),
+        ],
+    );
+}
+
+#[test]
+fn shift_right() {
+    check_cases(
+        "shr",
+        GAS_COST,
+        |ops| {
+            Instruction::from_shift_right(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new("small", int(16), int(4), output(int(1), NO_FLAGS)),
+            Case::new(
+                "high bit",
+                int(U256::MAX),
+                int(255),
+                output(int(1), NO_FLAGS),
+            ),
+            Case::new("shifted out", int(1), int(1), output(int(0), EQ)),
+        ],
+    );
+}
+
+#[test]
+fn rotate_left() {
+    check_cases(
+        "rol",
+        GAS_COST,
+        |ops| {
+            Instruction::from_rotate_left(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new(
+                "wrapping",
+                int(U256::one() << 255),
+                int(1),
+                output(int(1), NO_FLAGS),
+            ),
+            Case::new("no rotation", int(5), int(0), output(int(5), NO_FLAGS)),
+            Case::new("full word", int(5), int(256), output(int(5), NO_FLAGS)),
+            Case::new("zero", int(0), int(17), output(int(0), EQ)),
+        ],
+    );
+}
+
+#[test]
+fn rotate_right() {
+    check_cases(
+        "ror",
+        GAS_COST,
+        |ops| {
+            Instruction::from_rotate_right(
+                ops.src1,
+                ops.src2,
+                ops.out,
+                ops.arguments,
+                ops.swap,
+                ops.set_flags,
+            )
+        },
+        &[
+            Case::new(
+                "wrapping",
+                int(1),
+                int(1),
+                output(int(U256::one() << 255), NO_FLAGS),
+            ),
+            Case::new("no rotation", int(5), int(0), output(int(5), NO_FLAGS)),
+            Case::new("small", int(0b100), int(2), output(int(1), NO_FLAGS)),
+        ],
+    );
+}
@@ -0,0 +1,180 @@
+//! Swapping operands: for every binop, executing `op a, b` with swapped operands must be equivalent to executing
+//! `op b, a` without swapping, including second outputs and flags. This matters most for non-commutative
+//! opcodes (`sub`, `div`, shifts and rotations, pointer operations).
+
+use primitive_types::U256;
+
+use super::{
+    check_cases, execute, int, operands, output, outputs, ptr, Case, Operand, Operands,
+    TestInstruction, ALL_FLAGS, GT, LT, NO_FLAGS,
+};
+use crate::Instruction;
+
+const GAS_COST: u32 = 6;
+
+type Builder = fn(Operands) -> TestInstruction;
+
+fn values() -> [U256; 8] {
+    [
+        U256::zero(),
+        U256::one(),
+        7.into(),
+        255.into(),
+        256.into(),
+        U256::one() << 128,
+        U256::MAX - 1,
+        U256::MAX,
+    ]
+}
+
+fn binops() -> [(&'static str, Builder); 11] {
+    macro_rules! binop {
+        ($name:ident) => {
+            |ops: Operands| {
+                Instruction::$name(
+                    ops.src1,
+                    ops.src2,
+                    ops.out,
+                    ops.arguments,
+                    ops.swap,
+                    ops.set_flags,
+                )
+            }
+        };
+        ($name:ident, out2) => {
+            |ops: Operands| {
+                Instruction::$name(
+                    ops.src1,
+                    ops.src2,
+                    ops.out,
+                    ops.out2,
+                    ops.arguments,
+                    ops.swap,
+                    ops.set_flags,
+                )
+            }
+        };
+    }
+
+    [
+        ("add", binop!(from_add)),
+        ("sub", binop!(from_sub)),
+        ("and", binop!(from_and)),
+        ("or", binop!(from_or)),
+        ("xor", binop!(from_xor)),
+        ("shl", binop!(from_shift_left)),
+        ("shr", binop!(from_shift_right)),
+        ("rol", binop!(from_rotate_left)),
+        ("ror", binop!(from_rotate_right)),
+        ("mul", binop!(from_mul, out2)),
+        ("div", binop!(from_div, out2)),
+    ]
+}
+
+fn pointer_ops() -> [(&'static str, Builder); 4] {
+    [
+        ("ptr.add", |ops: Operands| {
+            Instruction::from_pointer_add(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap)
+        }),
+        ("ptr.sub", |ops: Operands| {
+            Instruction::from_pointer_sub(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap)
+        }),
+        ("ptr.pack", |ops: Operands| {
+            Instruction::from_pointer_pack(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap)
+        }),
+        ("ptr.shrink", |ops: Operands| {
+            Instruction::from_pointer_shrink(ops.src1, ops.src2, ops.out, ops.arguments, ops.swap)
+        }),
+    ]
+}
+
+fn assert_mirrored(opcode: &str, build: Builder, first: Operand, second: Operand) {
+    for set_flags in [false, true] {
+        let swapped = execute(
+            build(operands(GAS_COST, true, set_flags)),
+            second,
+            first,
+            ALL_FLAGS,
+        );
+        let direct = execute(
+            build(operands(GAS_COST, false, set_flags)),
+            first,
+            second,
+            ALL_FLAGS,
+        );
+        assert_eq!(
+            swapped, direct,
+            "{opcode} {first:?}, {second:?} (set_flags = {set_flags})"
+        );
+    }
+}
+
+#[test]
+fn swapping_binop_operands() {
+    for (opcode, build) in binops() {
+        for a in values() {
+            for b in values() {
+                assert_mirrored(opcode, build, int(a), int(b));
+            }
+        }
+    }
+}
+
+#[test]
+fn swapping_pointer_op_operands() {
+    let pointers = [
+        U256::zero(),
+        U256([0x0000_0002_0000_0010, 0x0000_0064_0000_0000, 0, 0]),
+        U256([u64::MAX, u64::MAX, 0, 0]),
+    ];
+    for (opcode, build) in pointer_ops() {
+        for pointer in pointers {
+            for b in values() {
+                assert_mirrored(opcode, build, ptr(pointer), int(b));
+                // The pointer flag requirements must be mirrored as well.
+                assert_mirrored(opcode, build, int(pointer), ptr(b));
+            }
+        }
+    }
+}
+
+#[test]
+fn swapped_non_commutative_binops() {
+    let [(_, sub), (_, shl), (_, div)] = [binops()[1], binops()[5], binops()[10]];
+    check_cases(
+        "sub",
+        GAS_COST,
+        sub,
+        &[
+            Case::new("swapped", int(3), int(5), output(int(2), GT)).swapped(),
+            Case::new(
+                "swapped underflow",
+                int(5),
+                int(3),
+                output(int(U256::MAX - 1), LT),
+            )
+            .swapped(),
+        ],
+    );
+    check_cases(
+        "shl",
+        GAS_COST,
+        shl,
+        &[Case::new("swapped", int(4), int(1), output(int(16), NO_FLAGS)).swapped()],
+    );
+    check_cases(
+        "div",
+        GAS_COST,
+        div,
+        &[
+            Case::new("swapped", int(7), int(43), outputs(6, 1, NO_FLAGS)).swapped(),
+            Case::new(
+                "swapped division by zero",
+                int(0),
+                int(43),
+                outputs(0, 0, LT),
+            )
+            .swapped(),
+        ],
+    );
+}
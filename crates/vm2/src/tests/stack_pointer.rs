@@ -0,0 +1,108 @@
+use proptest::prelude::*;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    addressing_modes::{
+        AdvanceStackPointer, Arguments, Immediate1, Register, Register1, Register2,
+        RegisterAndImmediate,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+fn advance_by(offset: u16) -> AdvanceStackPointer {
+    AdvanceStackPointer(RegisterAndImmediate {
+        immediate: offset,
+        register: Register::new(0),
+    })
+}
+
+fn run_program(
+    mut instructions: Vec<Instruction<(), TestWorld<()>>>,
+) -> VirtualMachine<(), TestWorld<()>> {
+    instructions.push(Instruction::from_ret(
+        Register1(Register::new(0)),
+        None,
+        Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+    ));
+    let program = Program::from_raw(instructions, vec![]);
+
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        1000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    vm
+}
+
+fn nop(pop: u16, push: u16) -> Instruction<(), TestWorld<()>> {
+    Instruction::from_nop(
+        advance_by(pop),
+        advance_by(push),
+        Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+    )
+}
+
+#[test]
+fn nop_stack_adjustment_wraps_around() {
+    let mut vm = run_program(vec![nop(1, 0), nop(0, 3)]);
+    assert_eq!(vm.current_frame().stack_pointer(), 2);
+
+    let mut vm = run_program(vec![nop(0, u16::MAX), nop(0, 2)]);
+    assert_eq!(vm.current_frame().stack_pointer(), 1);
+}
+
+#[test]
+fn push_and_pop_across_stack_end() {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let mut vm = run_program(vec![
+        nop(1, 0),
+        // Pushes to the last stack slot, moving the stack pointer to 0.
+        Instruction::from_add(
+            Immediate1(42).into(),
+            Register2(r0),
+            advance_by(1).into(),
+            Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+            false,
+            false,
+        ),
+        // Pops it back.
+        Instruction::from_add(
+            advance_by(1).into(),
+            Register2(r0),
+            Register1(r1).into(),
+            Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+            false,
+            false,
+        ),
+    ]);
+    assert_eq!(vm.current_frame().stack_pointer(), u16::MAX);
+    assert_eq!(vm.read_register(1), (42.into(), false));
+}
+
+proptest! {
+    #[cfg_attr(miri, ignore)]
+    #[test]
+    fn nop_stack_adjustment_is_modular(pop: u16, push: u16) {
+        let mut vm = run_program(vec![nop(pop, push), nop(pop, push)]);
+        let expected = push.wrapping_sub(pop).wrapping_mul(2);
+        prop_assert_eq!(vm.current_frame().stack_pointer(), expected);
+    }
+}
@@ -0,0 +1,67 @@
+use std::thread;
+
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::StateInterface;
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+    WorldDiff,
+};
+
+fn assert_send_and_sync<T: Send + Sync>() {}
+
+#[test]
+fn vm_types_are_send_and_sync() {
+    assert_send_and_sync::<VirtualMachine<(), TestWorld<()>>>();
+    assert_send_and_sync::<Program<(), TestWorld<()>>>();
+    assert_send_and_sync::<WorldDiff>();
+    assert_send_and_sync::<TestWorld<()>>();
+}
+
+#[test]
+fn vm_can_be_executed_on_another_thread() {
+    let program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                Immediate1(42).into(),
+                Register2(Register::new(0)),
+                Register1(Register::new(1)).into(),
+                Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            ),
+            Instruction::from_ret(
+                Register1(Register::new(0)),
+                None,
+                Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+            ),
+        ],
+        vec![],
+    );
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        1000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+
+    let vm = thread::spawn(move || {
+        let end = vm.run(&mut world, &mut ());
+        assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+        vm
+    })
+    .join()
+    .unwrap();
+    assert_eq!(vm.read_register(1).0, 42.into());
+}
@@ -0,0 +1,121 @@
+//! Checks that register 0 is a constant zero for all instructions writing to registers.
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+
+use crate::{
+    addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+    interface::{GlobalStateInterface, OpcodeType, ShouldStop, StateInterface, Tracer},
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+#[derive(Default)]
+struct ZeroRegisterChecker {
+    instruction_count: usize,
+}
+
+impl Tracer for ZeroRegisterChecker {
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        assert_eq!(
+            state.read_register(0),
+            (U256::zero(), false),
+            "r0 modified by {:?}",
+            OP::VALUE
+        );
+        self.instruction_count += 1;
+        ShouldStop::Continue
+    }
+}
+
+fn args() -> Arguments {
+    Arguments::new(Predicate::Always, 6, ModeRequirements::none())
+}
+
+#[test]
+fn writes_to_zero_register_are_discarded() {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+
+    let instructions = vec![
+        // Register1 destination
+        Instruction::from_add(
+            Immediate1(42).into(),
+            Register2(r0),
+            Register1(r0).into(),
+            args(),
+            false,
+            false,
+        ),
+        Instruction::from_add(
+            Immediate1(5).into(),
+            Register2(r0),
+            Register1(r2).into(),
+            args(),
+            false,
+            false,
+        ),
+        // Register1 and Register2 destinations
+        Instruction::from_div(
+            Immediate1(7).into(),
+            Register2(r2),
+            Register1(r0).into(),
+            Register2(r0),
+            args(),
+            false,
+            false,
+        ),
+        // Fat pointer destination; r1 contains the calldata pointer.
+        Instruction::from_pointer_add(
+            Register1(r1).into(),
+            Register2(r0),
+            Register1(r0).into(),
+            args(),
+            false,
+        ),
+        // Context destinations
+        Instruction::from_this(Register1(r0), args()),
+        Instruction::from_ergs_left(Register1(r0), args()),
+        Instruction::from_context_meta(Register1(r0), args()),
+        // Heap read with an incremented address
+        Instruction::from_heap_read(
+            Immediate1(0).into(),
+            Register1(r0),
+            Some(Register2(r0)),
+            args(),
+        ),
+        Instruction::from_ret(Register1(r0), None, args()),
+    ];
+    let instruction_count = instructions.len();
+    let program = Program::from_raw(instructions, vec![]);
+
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        10_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let mut tracer = ZeroRegisterChecker::default();
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::ProgramFinished(vec![])
+    );
+    assert_eq!(tracer.instruction_count, instruction_count);
+
+    vm.set_register(0, 1.into(), true);
+    assert_eq!(vm.read_register(0), (U256::zero(), false));
+}
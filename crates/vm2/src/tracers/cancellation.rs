@@ -0,0 +1,171 @@
+//! Cooperative cancellation of VM execution.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use zksync_vm2_interface::{GlobalStateInterface, OpcodeType, ShouldStop, Tracer};
+
+/// Token allowing to cancel VM execution from another thread. Cloning the token is cheap; all clones
+/// refer to the same cancellation flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a new token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of all executions observing this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks whether cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracer stopping VM execution once its [`CancellationToken`] is cancelled.
+///
+/// Once the VM observes cancellation, it returns [`ExecutionEnd::StoppedByTracer`](crate::ExecutionEnd::StoppedByTracer)
+/// after finishing the current instruction. The VM state stays consistent, so execution can be resumed
+/// (e.g., after resetting the tracer) or the VM can be discarded. Combine with other tracers using tuples.
+#[derive(Debug)]
+pub struct CancellationTracer {
+    token: CancellationToken,
+    check_interval: u32,
+    instructions_until_check: u32,
+}
+
+impl CancellationTracer {
+    /// Creates a tracer checking the provided token after each instruction.
+    pub fn new(token: CancellationToken) -> Self {
+        Self::with_check_interval(token, 1)
+    }
+
+    /// Creates a tracer checking the provided token once per `check_interval` instructions. This reduces the overhead
+    /// of checking the token at the cost of stopping execution slightly later.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `check_interval` is zero.
+    pub fn with_check_interval(token: CancellationToken, check_interval: u32) -> Self {
+        assert!(check_interval > 0, "check interval must be positive");
+        Self {
+            token,
+            check_interval,
+            instructions_until_check: check_interval,
+        }
+    }
+
+    /// Returns the token observed by this tracer.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Tracer for CancellationTracer {
+    #[inline(always)]
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        _state: &mut S,
+    ) -> ShouldStop {
+        self.instructions_until_check -= 1;
+        if self.instructions_until_check > 0 {
+            return ShouldStop::Continue;
+        }
+
+        self.instructions_until_check = self.check_interval;
+        if self.token.is_cancelled() {
+            ShouldStop::Stop
+        } else {
+            ShouldStop::Continue
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+    };
+
+    /// Cancels the token after the specified number of instructions.
+    struct Canceller {
+        token: CancellationToken,
+        instructions_left: usize,
+    }
+
+    impl Tracer for Canceller {
+        fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, _: &mut S) {
+            if self.instructions_left == 0 {
+                self.token.cancel();
+            } else {
+                self.instructions_left -= 1;
+            }
+        }
+    }
+
+    fn run_infinite_loop(cancel_after: usize, check_interval: u32) -> (ExecutionEnd, u32) {
+        // An infinite loop jumping to itself
+        let program = Program::from_raw(
+            vec![Instruction::from_jump(
+                Immediate1(0).into(),
+                Register1(Register::new(0)),
+                Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+            )],
+            vec![],
+        );
+
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            u32::MAX,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        let token = CancellationToken::new();
+        let mut tracer = (
+            Canceller {
+                token: token.clone(),
+                instructions_left: cancel_after,
+            },
+            CancellationTracer::with_check_interval(token, check_interval),
+        );
+        let end = vm.run(&mut world, &mut tracer);
+        let gas_spent = u32::MAX - vm.state.current_frame.gas;
+        (end, gas_spent / 6)
+    }
+
+    #[test]
+    fn cancelling_execution() {
+        let (end, executed_instructions) = run_infinite_loop(10, 1);
+        assert_eq!(end, ExecutionEnd::StoppedByTracer);
+        assert_eq!(executed_instructions, 11);
+    }
+
+    #[test]
+    fn cancelling_execution_with_check_interval() {
+        let (end, executed_instructions) = run_infinite_loop(10, 8);
+        assert_eq!(end, ExecutionEnd::StoppedByTracer);
+        assert_eq!(executed_instructions, 16);
+    }
+}
@@ -0,0 +1,296 @@
+//! Tracer exporting calls and hooks in the Chrome trace event format.
+
+use std::{fmt::Write as _, time::Instant};
+
+use zksync_vm2_interface::{
+    CallframeInterface, GlobalStateInterface, Opcode, OpcodeType, ShouldStop, StateInterface,
+    Tracer,
+};
+
+/// Time source for timestamps of [`ChromeTraceTracer`] events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Clock {
+    /// Wall-clock time since the tracer was created.
+    WallTime(Instant),
+    /// Number of instructions executed so far.
+    Instructions,
+}
+
+/// Span that has started but not yet finished.
+#[derive(Debug)]
+struct OpenSpan {
+    name: String,
+    category: &'static str,
+    start: f64,
+    /// Gas of the frame that started the span before the starting instruction.
+    gas_before: u32,
+}
+
+/// Finished span, serialized as a complete (`"ph":"X"`) event.
+#[derive(Debug)]
+struct Span {
+    name: String,
+    category: &'static str,
+    start: f64,
+    duration: f64,
+    /// Gas spent by the frame that started the span; `None` for hooks.
+    gas_used: Option<u32>,
+}
+
+/// Tracer recording a span for each far call, near call and hook, which can be exported into the
+/// [Chrome trace event format] and inspected with Perfetto or `chrome://tracing`.
+///
+/// Call spans last from the call instruction until the frame is popped (by a return or a panic); their `gas_used`
+/// argument is the gas spent by the calling frame, which includes the gas consumed by the callee. The initial frame
+/// is recorded as the `root` span. Since the VM doesn't notify tracers about hooks, the embedder should call
+/// [`Self::record_hook()`] after the VM is [suspended on a hook](crate::ExecutionEnd::SuspendedOnHook); the hook span
+/// lasts until execution is resumed, i.e. it measures the time spent handling the hook outside the VM.
+///
+/// By default, timestamps are wall-clock microseconds since the tracer was created. With
+/// [`Self::with_instruction_clock()`], the number of executed instructions is used instead, which makes traces
+/// deterministic.
+///
+/// [Chrome trace event format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+#[derive(Debug)]
+pub struct ChromeTraceTracer {
+    clock: Clock,
+    instructions: u64,
+    /// Gas of the current frame before the last instruction.
+    gas_before: u32,
+    /// Number of frames before the last instruction.
+    depth_before: usize,
+    open_spans: Vec<OpenSpan>,
+    open_hook: Option<OpenSpan>,
+    spans: Vec<Span>,
+}
+
+impl Default for ChromeTraceTracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChromeTraceTracer {
+    /// Creates a tracer using wall-clock timestamps.
+    pub fn new() -> Self {
+        Self::with_clock(Clock::WallTime(Instant::now()))
+    }
+
+    /// Creates a tracer using the number of executed instructions as timestamps.
+    pub fn with_instruction_clock() -> Self {
+        Self::with_clock(Clock::Instructions)
+    }
+
+    fn with_clock(clock: Clock) -> Self {
+        Self {
+            clock,
+            instructions: 0,
+            gas_before: 0,
+            depth_before: 0,
+            open_spans: vec![],
+            open_hook: None,
+            spans: vec![],
+        }
+    }
+
+    /// Timestamp in microseconds (or in instructions for the instruction clock).
+    #[allow(clippy::cast_precision_loss)] // OK for timestamps
+    fn now(&self) -> f64 {
+        match self.clock {
+            Clock::WallTime(start) => start.elapsed().as_secs_f64() * 1e6,
+            Clock::Instructions => self.instructions as f64,
+        }
+    }
+
+    /// Records a hook the VM was suspended on. The hook span ends when VM execution is resumed.
+    pub fn record_hook(&mut self, hook: u32) {
+        self.finish_hook();
+        self.open_hook = Some(OpenSpan {
+            name: format!("hook {hook}"),
+            category: "hook",
+            start: self.now(),
+            gas_before: 0,
+        });
+    }
+
+    fn finish_hook(&mut self) {
+        if let Some(hook) = self.open_hook.take() {
+            let now = self.now();
+            self.spans.push(Span {
+                name: hook.name,
+                category: hook.category,
+                start: hook.start,
+                duration: now - hook.start,
+                gas_used: None,
+            });
+        }
+    }
+
+    fn finish_span(&mut self, gas_after: u32) {
+        if let Some(span) = self.open_spans.pop() {
+            let now = self.now();
+            self.spans.push(Span {
+                name: span.name,
+                category: span.category,
+                start: span.start,
+                duration: now - span.start,
+                gas_used: Some(span.gas_before.saturating_sub(gas_after)),
+            });
+        }
+    }
+
+    /// Serializes recorded spans into a JSON object of the form `{"traceEvents":[...]}`. Spans that haven't finished
+    /// yet (e.g., because execution was suspended) are output as ending at the current time.
+    #[allow(clippy::missing_panics_doc)] // false positive; writing to a `String` cannot fail
+    pub fn to_json(&self) -> String {
+        let now = self.now();
+        let unfinished = self
+            .open_spans
+            .iter()
+            .rev()
+            .chain(&self.open_hook)
+            .map(|span| Span {
+                name: span.name.clone(),
+                category: span.category,
+                start: span.start,
+                duration: now - span.start,
+                gas_used: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut json = String::from("{\"traceEvents\":[");
+        for (i, span) in self.spans.iter().chain(&unfinished).enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":1",
+                span.name, span.category, span.start, span.duration
+            )
+            .unwrap();
+            if let Some(gas_used) = span.gas_used {
+                write!(json, ",\"args\":{{\"gas_used\":{gas_used}}}").unwrap();
+            }
+            json.push('}');
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl Tracer for ChromeTraceTracer {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        self.finish_hook();
+        self.depth_before = state.number_of_callframes();
+        self.gas_before = state.current_frame().gas();
+        if self.open_spans.is_empty() {
+            self.open_spans.push(OpenSpan {
+                name: "root".to_owned(),
+                category: "far_call",
+                start: self.now(),
+                gas_before: self.gas_before,
+            });
+        }
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        self.instructions += 1;
+
+        let depth = state.number_of_callframes();
+        let gas = state.current_frame().gas();
+        // Returning from the initial frame finishes execution without popping the frame.
+        let depth = if matches!(OP::VALUE, Opcode::Ret(_)) && self.depth_before == 1 {
+            0
+        } else {
+            depth
+        };
+        while self.open_spans.len() > depth {
+            self.finish_span(gas);
+        }
+        if self.open_spans.len() < depth && depth > 1 {
+            let (name, category) = match OP::VALUE {
+                Opcode::FarCall(_) => {
+                    let address = state.current_frame().address();
+                    (format!("far_call {address:?}"), "far_call")
+                }
+                Opcode::NearCall => {
+                    let pc = state.current_frame().program_counter().unwrap_or_default();
+                    (format!("near_call @{pc}"), "near_call")
+                }
+                op => (format!("{op:?}"), "far_call"),
+            };
+            self.open_spans.push(OpenSpan {
+                name,
+                category,
+                start: self.now(),
+                gas_before: self.gas_before,
+            });
+        }
+        ShouldStop::Continue
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Immediate2, Register, Register1},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+    };
+
+    #[test]
+    fn recording_near_call_spans() {
+        let args = || Arguments::new(Predicate::Always, 5, ModeRequirements::none());
+        let r0 = Register1(Register::new(0));
+        let program = Program::from_raw(
+            vec![
+                // 0: call the function at 2
+                Instruction::from_near_call(r0, Immediate1(2), Immediate2(0), args()),
+                Instruction::from_ret(r0, None, args()),
+                // 2: function body
+                Instruction::from_ret(r0, None, args()),
+            ],
+            vec![],
+        );
+
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        let mut tracer = ChromeTraceTracer::with_instruction_clock();
+        let end = vm.run(&mut world, &mut tracer);
+        assert!(matches!(end, ExecutionEnd::ProgramFinished(_)), "{end:?}");
+        tracer.record_hook(3);
+
+        // Each instruction costs 5 gas; the near call spans `ret` in the function body.
+        let json = tracer.to_json();
+        let expected = [
+            r#"{"name":"near_call @2","cat":"near_call","ph":"X","ts":1,"dur":1,"pid":1,"tid":1,"args":{"gas_used":10}}"#,
+            r#"{"name":"root","cat":"far_call","ph":"X","ts":0,"dur":3,"pid":1,"tid":1,"args":{"gas_used":15}}"#,
+            r#"{"name":"hook 3","cat":"hook","ph":"X","ts":3,"dur":0,"pid":1,"tid":1}"#,
+        ];
+        assert_eq!(
+            json,
+            format!("{{\"traceEvents\":[{}]}}", expected.join(","))
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Aggregation of prover cycle statistics.
+
+use std::collections::BTreeMap;
+
+use zksync_vm2_interface::{CycleStats, Tracer};
+
+use crate::{
+    isa::{
+        ECADD_PRECOMPILE_ADDRESS, ECMUL_PRECOMPILE_ADDRESS, ECPAIRING_PRECOMPILE_ADDRESS,
+        MODEXP_PRECOMPILE_ADDRESS,
+    },
+    precompiles::{
+        ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS, KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+        SECP256R1_VERIFY_PRECOMPILE_ADDRESS, SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS,
+    },
+};
+
+/// Invocation and cycle counts for a single precompile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrecompileStats {
+    /// Number of precompile calls.
+    pub calls: u64,
+    /// Total number of cycles (e.g., hash rounds for `keccak256` and `sha256`) taken by the calls.
+    pub cycles: u64,
+}
+
+/// Tracer aggregating [`CycleStats`] reported by the VM, e.g. for circuit capacity accounting.
+///
+/// Precompile statistics are keyed by the low 16 bits of the precompile address, such as
+/// [`KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS`].
+#[derive(Debug, Clone, Default)]
+pub struct CycleStatsTracer {
+    precompiles: BTreeMap<u16, PrecompileStats>,
+    decommit_cycles: u64,
+    storage_reads: u64,
+    storage_writes: u64,
+}
+
+impl CycleStatsTracer {
+    /// Creates a tracer with all statistics set to zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns statistics for the precompile with the specified address.
+    pub fn precompile_stats(&self, address_low: u16) -> PrecompileStats {
+        self.precompiles
+            .get(&address_low)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Iterates over statistics for all called precompiles, ordered by the precompile address.
+    pub fn precompiles(&self) -> impl Iterator<Item = (u16, PrecompileStats)> + '_ {
+        self.precompiles
+            .iter()
+            .map(|(&address, &stats)| (address, stats))
+    }
+
+    /// Returns the total number of cycles taken by decommitments.
+    pub fn decommit_cycles(&self) -> u64 {
+        self.decommit_cycles
+    }
+
+    /// Returns the number of storage reads.
+    pub fn storage_reads(&self) -> u64 {
+        self.storage_reads
+    }
+
+    /// Returns the number of storage writes.
+    pub fn storage_writes(&self) -> u64 {
+        self.storage_writes
+    }
+
+    fn record_precompile_call(&mut self, address_low: u16, cycles: u32) {
+        let stats = self.precompiles.entry(address_low).or_default();
+        stats.calls += 1;
+        stats.cycles += u64::from(cycles);
+    }
+}
+
+impl Tracer for CycleStatsTracer {
+    fn on_extra_prover_cycles(&mut self, stats: CycleStats) {
+        let (address_low, cycles) = match stats {
+            CycleStats::Keccak256(cycles) => (KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::Sha256(cycles) => (SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::EcRecover(cycles) => (ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::Secp256r1Verify(cycles) => (SECP256R1_VERIFY_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::ModExp(cycles) => (MODEXP_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::EcAdd(cycles) => (ECADD_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::EcMul(cycles) => (ECMUL_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::EcPairing(cycles) => (ECPAIRING_PRECOMPILE_ADDRESS, cycles),
+            CycleStats::Decommit(cycles) => {
+                self.decommit_cycles += u64::from(cycles);
+                return;
+            }
+            CycleStats::StorageRead => {
+                self.storage_reads += 1;
+                return;
+            }
+            CycleStats::StorageWrite => {
+                self.storage_writes += 1;
+                return;
+            }
+        };
+        self.record_precompile_call(address_low, cycles);
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use primitive_types::U256;
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{
+            Arguments, CodePage, Register, Register1, Register2, RegisterAndImmediate,
+        },
+        precompiles::keccak256_cycles,
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, VirtualMachine,
+    };
+
+    #[test]
+    fn aggregating_precompile_calls() {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let precompile_call = Instruction::from_precompile_call(
+            Register1(r1),
+            Register2(r0),
+            Register1(r0),
+            arguments(6),
+        );
+
+        // Input offset 0, input length 500 bytes, output offset 0, output length 1 word.
+        let mut abi = U256::zero();
+        abi.0[0] = 500 << 32;
+        abi.0[1] = 1 << 32;
+        let program = Program::from_raw(
+            vec![
+                Instruction::from_add(
+                    CodePage(RegisterAndImmediate {
+                        immediate: 0,
+                        register: r0,
+                    })
+                    .into(),
+                    Register2(r0),
+                    Register1(r1).into(),
+                    arguments(6),
+                    false,
+                    false,
+                ),
+                precompile_call.clone(),
+                precompile_call,
+                Instruction::from_ret(Register1(r0), None, arguments(5)),
+            ],
+            vec![abi],
+        );
+
+        let address = Address::from_low_u64_be(KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS.into());
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::builder()
+            .address(address)
+            .program(program)
+            .gas(100_000)
+            .build()
+            .unwrap();
+        let mut tracer = CycleStatsTracer::new();
+        assert_eq!(
+            vm.run(&mut world, &mut tracer),
+            ExecutionEnd::ProgramFinished(vec![])
+        );
+
+        let stats = tracer.precompile_stats(KECCAK256_ROUND_FUNCTION_PRECOMPILE_ADDRESS);
+        assert_eq!(
+            stats,
+            PrecompileStats {
+                calls: 2,
+                cycles: 2 * u64::from(keccak256_cycles(500)),
+            }
+        );
+        assert_eq!(
+            tracer.precompile_stats(SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS),
+            PrecompileStats::default()
+        );
+        assert_eq!(tracer.precompiles().count(), 1);
+    }
+
+    #[test]
+    fn aggregating_other_stats() {
+        let mut tracer = CycleStatsTracer::new();
+        tracer.on_extra_prover_cycles(CycleStats::Decommit(3));
+        tracer.on_extra_prover_cycles(CycleStats::Decommit(5));
+        tracer.on_extra_prover_cycles(CycleStats::StorageRead);
+        tracer.on_extra_prover_cycles(CycleStats::StorageWrite);
+        tracer.on_extra_prover_cycles(CycleStats::StorageWrite);
+        tracer.on_extra_prover_cycles(CycleStats::EcRecover(1));
+
+        assert_eq!(tracer.decommit_cycles(), 8);
+        assert_eq!(tracer.storage_reads(), 1);
+        assert_eq!(tracer.storage_writes(), 2);
+        let precompiles: Vec<_> = tracer.precompiles().collect();
+        assert_eq!(
+            precompiles,
+            [(
+                ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS,
+                PrecompileStats {
+                    calls: 1,
+                    cycles: 1
+                }
+            )]
+        );
+    }
+}
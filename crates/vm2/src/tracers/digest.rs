@@ -0,0 +1,221 @@
+//! Tracer computing a canonical digest of execution for golden-file tests.
+
+use std::{fmt::Write as _, fs, path::Path};
+
+use primitive_types::U256;
+use zkevm_opcode_defs::sha3::{Digest, Keccak256};
+use zksync_vm2_interface::{
+    CallframeInterface, GlobalStateInterface, OpcodeType, ShouldStop, StateInterface, Tracer,
+};
+
+/// Environment variable making [`DigestTracer::assert_golden_file()`] overwrite golden files instead of checking them.
+pub const UPDATE_GOLDEN_ENV_VAR: &str = "VM2_UPDATE_GOLDEN";
+
+/// Tracer computing a hash chain over executed instructions.
+///
+/// For each instruction, the digest is updated to `keccak256(digest || step)`, where `step` encodes the program counter
+/// and the opcode before the instruction is executed, and the flags and changed register values after it is executed.
+/// Skipped instructions are recorded as `Nop`s. Since the digest only depends on the observable VM state, comparing it
+/// with a checked-in golden value allows to verify that refactoring instruction handlers doesn't change VM behavior.
+#[derive(Debug, Default)]
+pub struct DigestTracer {
+    digest: [u8; 32],
+    steps: usize,
+    /// Register values after the previous step, or `None` before the first step.
+    registers: Option<[U256; 16]>,
+    /// Encoding of the currently executed step.
+    step: Vec<u8>,
+}
+
+impl DigestTracer {
+    /// Creates a tracer with an all-zero digest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current digest.
+    pub fn digest(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    /// Returns the current digest as 64 hex digits.
+    #[allow(clippy::missing_panics_doc)] // writing to a string cannot fail
+    pub fn digest_hex(&self) -> String {
+        let mut hex = String::with_capacity(64);
+        for byte in self.digest {
+            write!(hex, "{byte:02x}").unwrap();
+        }
+        hex
+    }
+
+    /// Returns the number of instructions recorded so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Checks that the digest matches the one stored in the golden file at `path`. If the [`UPDATE_GOLDEN_ENV_VAR`]
+    /// environment variable is set, the golden file is overwritten instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the digest doesn't match, or if the golden file cannot be read or written.
+    pub fn assert_golden_file(&self, path: impl AsRef<Path>) {
+        let update = std::env::var_os(UPDATE_GOLDEN_ENV_VAR).is_some();
+        self.check_golden_file(path.as_ref(), update);
+    }
+
+    fn check_golden_file(&self, path: &Path, update: bool) {
+        let actual = self.digest_hex();
+        if update {
+            fs::write(path, format!("{actual}\n")).unwrap_or_else(|err| {
+                panic!("cannot write golden file `{}`: {err}", path.display())
+            });
+            return;
+        }
+
+        let expected = fs::read_to_string(path).unwrap_or_else(|err| {
+            panic!(
+                "cannot read golden file `{}`: {err}; rerun with {UPDATE_GOLDEN_ENV_VAR}=1 to create it",
+                path.display()
+            )
+        });
+        assert_eq!(
+            expected.trim(),
+            actual,
+            "execution digest differs from golden file `{}`; if the change is intended, rerun with {UPDATE_GOLDEN_ENV_VAR}=1",
+            path.display()
+        );
+    }
+
+    fn read_registers(state: &impl StateInterface) -> [U256; 16] {
+        let mut registers = [U256::zero(); 16];
+        for (i, register) in (0_u8..).zip(&mut registers) {
+            *register = state.read_register(i).0;
+        }
+        registers
+    }
+}
+
+impl Tracer for DigestTracer {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if self.registers.is_none() {
+            self.registers = Some(Self::read_registers(state));
+        }
+
+        let pc = state.current_frame().program_counter().unwrap_or(u16::MAX);
+        let op = format!("{:?}", OP::VALUE);
+        self.step.clear();
+        self.step.extend_from_slice(&pc.to_be_bytes());
+        #[allow(clippy::cast_possible_truncation)] // opcode names are short
+        let op_len = op.len() as u8;
+        self.step.push(op_len);
+        self.step.extend_from_slice(op.as_bytes());
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        let flags = state.flags();
+        self.step.push(
+            u8::from(flags.less_than)
+                | (u8::from(flags.equal) << 1)
+                | (u8::from(flags.greater) << 2),
+        );
+
+        let registers = Self::read_registers(state);
+        let previous = self.registers.replace(registers).unwrap_or_default();
+        for (i, (value, previous)) in (0_u8..).zip(registers.iter().zip(&previous)) {
+            if value != previous {
+                let mut bytes = [0_u8; 32];
+                value.to_big_endian(&mut bytes);
+                self.step.push(i);
+                self.step.extend_from_slice(&bytes);
+            }
+        }
+
+        let mut hasher = Keccak256::new();
+        hasher.update(self.digest);
+        hasher.update(&self.step);
+        self.digest.copy_from_slice(&hasher.finalize());
+        self.steps += 1;
+        ShouldStop::Continue
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+        testonly::vm_with_program,
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program,
+    };
+
+    fn run(value: u16) -> DigestTracer {
+        let r0 = Register::new(0);
+        let program = Program::from_raw(
+            vec![
+                Instruction::from_add(
+                    Immediate1(value).into(),
+                    Register2(r0),
+                    Register1(Register::new(1)).into(),
+                    Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+                    false,
+                    true,
+                ),
+                Instruction::from_ret(
+                    Register1(r0),
+                    None,
+                    Arguments::new(Predicate::IfEQ, 5, ModeRequirements::none()),
+                ),
+                Instruction::from_ret(
+                    Register1(r0),
+                    None,
+                    Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+                ),
+            ],
+            vec![],
+        );
+
+        let (mut vm, mut world) = vm_with_program(program, 1000);
+
+        let mut tracer = DigestTracer::new();
+        let end = vm.run(&mut world, &mut tracer);
+        assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+        tracer
+    }
+
+    #[test]
+    fn digest_is_deterministic() {
+        let tracer = run(42);
+        assert_eq!(tracer.steps(), 3);
+        assert_ne!(tracer.digest(), [0; 32]);
+        assert_eq!(run(42).digest(), tracer.digest());
+
+        assert_ne!(run(43).digest(), tracer.digest());
+        // Setting the EQ flag changes both the flags and the executed path.
+        let tracer_with_zero = run(0);
+        assert_eq!(tracer_with_zero.steps(), 2);
+        assert_ne!(tracer_with_zero.digest(), tracer.digest());
+    }
+
+    #[test]
+    fn checking_golden_file() {
+        let tracer = run(42);
+        let path = std::env::temp_dir().join(format!(
+            "zksync_vm2-digest-{}-checking_golden_file",
+            std::process::id()
+        ));
+        tracer.check_golden_file(&path, true);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", tracer.digest_hex())
+        );
+        tracer.check_golden_file(&path, false);
+
+        let result = std::panic::catch_unwind(|| run(43).check_golden_file(&path, false));
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,21 @@
+//! Reusable [`Tracer`](crate::interface::Tracer) implementations.
+
+#[cfg(not(feature = "single_instruction_test"))]
+pub use self::source_map::{SourceMapTracer, SourceStep};
+pub use self::{
+    cancellation::{CancellationToken, CancellationTracer},
+    chrome_trace::ChromeTraceTracer,
+    cycle_stats::{CycleStatsTracer, PrecompileStats},
+    digest::{DigestTracer, UPDATE_GOLDEN_ENV_VAR},
+    struct_log::{StructLog, StructLogTracer},
+    taint::{TaintTracer, TaintedCall, TaintedStorageWrite},
+};
+
+mod cancellation;
+mod chrome_trace;
+mod cycle_stats;
+mod digest;
+#[cfg(not(feature = "single_instruction_test"))]
+mod source_map;
+mod struct_log;
+mod taint;
@@ -0,0 +1,146 @@
+//! Tracer annotating executed instructions with source code locations.
+
+use std::collections::HashMap;
+
+use primitive_types::H160;
+use zksync_vm2_interface::{CallframeInterface, GlobalStateInterface, Opcode, OpcodeType, Tracer};
+
+use crate::{SourceLocation, SourceMap};
+
+/// Single executed instruction recorded by [`SourceMapTracer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceStep {
+    /// Address of the executed code (i.e., the code address of the current frame, which differs
+    /// from the frame address for delegate calls).
+    pub code_address: H160,
+    /// Program counter of the instruction, or `None` if the instruction is a panic not located in the program.
+    pub pc: Option<u16>,
+    /// Executed opcode. Instructions skipped because of their predicate are recorded as [`Opcode::Nop`].
+    pub op: Opcode,
+    /// Source location of the instruction, or `None` if the executed contract has no source map
+    /// or the map doesn't cover the instruction.
+    pub location: Option<SourceLocation>,
+}
+
+/// Tracer recording source locations of executed instructions using source maps of the executed contracts.
+///
+/// Tracers cannot access executed programs, so source maps are provided per contract code address rather than taken
+/// from [`Program::source_map()`](crate::Program::source_map()). For the latter, use
+/// [`VirtualMachine::current_source_location()`](crate::VirtualMachine::current_source_location()).
+#[derive(Debug, Default)]
+pub struct SourceMapTracer {
+    source_maps: HashMap<H160, SourceMap>,
+    steps: Vec<SourceStep>,
+}
+
+impl SourceMapTracer {
+    /// Creates a tracer without any source maps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a source map for the contract with the specified code address.
+    #[must_use]
+    pub fn with_source_map(mut self, code_address: H160, source_map: SourceMap) -> Self {
+        self.source_maps.insert(code_address, source_map);
+        self
+    }
+
+    /// Returns steps recorded so far.
+    pub fn steps(&self) -> &[SourceStep] {
+        &self.steps
+    }
+
+    /// Consumes this tracer returning the recorded steps.
+    pub fn into_steps(self) -> Vec<SourceStep> {
+        self.steps
+    }
+}
+
+impl Tracer for SourceMapTracer {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        let frame = state.current_frame();
+        let code_address = frame.code_address();
+        let pc = frame.program_counter();
+        let location = pc.and_then(|pc| self.source_maps.get(&code_address)?.location(pc));
+        self.steps.push(SourceStep {
+            code_address,
+            pc,
+            op: OP::VALUE,
+            location: location.cloned(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+    };
+
+    #[test]
+    fn annotating_steps_with_source_locations() {
+        let add = || {
+            Instruction::from_add(
+                Immediate1(1).into(),
+                Register2(Register::new(1)),
+                Register1(Register::new(1)).into(),
+                Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+                false,
+                false,
+            )
+        };
+        let source_map: SourceMap = "0 Counter.sol:3:9\n2 Counter.sol:4".parse().unwrap();
+
+        let program = Program::from_raw(vec![add(), add(), add()], vec![]);
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address).with_source_map(source_map.clone());
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+        assert_eq!(
+            vm.current_source_location().unwrap().to_string(),
+            "Counter.sol:3:9"
+        );
+
+        let mut tracer = SourceMapTracer::new().with_source_map(address, source_map);
+        let mut budget = 3;
+        let end = vm.run_with_instruction_limit(&mut world, &mut tracer, &mut budget);
+        assert_eq!(end, ExecutionEnd::InstructionLimit);
+        // The appended invalid instruction is mapped to the last location.
+        assert_eq!(
+            vm.current_source_location().unwrap().to_string(),
+            "Counter.sol:4"
+        );
+
+        let locations: Vec<_> = tracer
+            .steps()
+            .iter()
+            .map(|step| (step.pc, step.location.as_ref().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                (Some(0), "Counter.sol:3:9".to_owned()),
+                (Some(1), "Counter.sol:3:9".to_owned()),
+                (Some(2), "Counter.sol:4".to_owned()),
+            ]
+        );
+        assert!(tracer.steps().iter().all(|step| step.op == Opcode::Add));
+    }
+}
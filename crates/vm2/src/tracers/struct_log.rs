@@ -0,0 +1,254 @@
+//! Tracer producing per-instruction logs in a format close to Geth's `debug_traceTransaction`.
+
+use std::{collections::BTreeMap, fmt};
+
+use primitive_types::U256;
+use zksync_vm2_interface::{
+    CallframeInterface, GlobalStateInterface, Opcode, OpcodeType, ShouldStop, StateInterface,
+    Tracer,
+};
+
+/// Single executed instruction recorded by [`StructLogTracer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLog {
+    /// Program counter of the instruction, or `None` if the instruction is a panic not located in the program.
+    pub pc: Option<u16>,
+    /// Executed opcode. Instructions skipped because of their predicate are recorded as [`Opcode::Nop`].
+    pub op: Opcode,
+    /// Gas available in the current frame before the instruction was executed.
+    pub gas: u32,
+    /// Gas spent by the instruction. For calls, this includes the gas passed to the callee.
+    /// Zero for instructions that leave the current frame.
+    pub gas_cost: u32,
+    /// Number of frames (including near calls) at the time of execution; the initial frame has depth 1.
+    pub depth: usize,
+    /// Register values before execution. Serialized as the `stack` field since EraVM is a register machine.
+    pub registers: [U256; 16],
+    /// Number of paid bytes of the current frame's heap after execution. Serialized as `memSize`.
+    pub heap_bound: u32,
+    /// Number of paid bytes of the current frame's auxiliary heap after execution.
+    pub aux_heap_bound: u32,
+    /// Storage slots of the current contract written so far. Only recorded for storage access instructions.
+    pub storage: Option<BTreeMap<U256, U256>>,
+}
+
+impl StructLog {
+    /// Serializes this log into a JSON object.
+    pub fn to_json(&self) -> String {
+        JsonStructLog(self).to_string()
+    }
+}
+
+struct JsonStructLog<'a>(&'a StructLog);
+
+impl fmt::Display for JsonStructLog<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let log = self.0;
+        formatter.write_str("{\"pc\":")?;
+        match log.pc {
+            Some(pc) => write!(formatter, "{pc}")?,
+            None => formatter.write_str("null")?,
+        }
+        write!(
+            formatter,
+            ",\"op\":\"{:?}\",\"gas\":{},\"gasCost\":{},\"depth\":{},\"stack\":[",
+            log.op, log.gas, log.gas_cost, log.depth
+        )?;
+        for (i, register) in log.registers.iter().enumerate() {
+            if i > 0 {
+                formatter.write_str(",")?;
+            }
+            write!(formatter, "\"{register:#x}\"")?;
+        }
+        write!(
+            formatter,
+            "],\"memSize\":{},\"auxMemSize\":{}",
+            log.heap_bound, log.aux_heap_bound
+        )?;
+        if let Some(storage) = &log.storage {
+            formatter.write_str(",\"storage\":{")?;
+            for (i, (key, value)) in storage.iter().enumerate() {
+                if i > 0 {
+                    formatter.write_str(",")?;
+                }
+                formatter.write_str("\"")?;
+                write_padded_hex(formatter, *key)?;
+                formatter.write_str("\":\"")?;
+                write_padded_hex(formatter, *value)?;
+                formatter.write_str("\"")?;
+            }
+            formatter.write_str("}")?;
+        }
+        formatter.write_str("}")
+    }
+}
+
+/// Writes a word as 64 hex digits without a prefix, which is how Geth serializes storage slots.
+fn write_padded_hex(formatter: &mut fmt::Formatter<'_>, value: U256) -> fmt::Result {
+    let mut bytes = [0_u8; 32];
+    value.to_big_endian(&mut bytes);
+    for byte in bytes {
+        write!(formatter, "{byte:02x}")?;
+    }
+    Ok(())
+}
+
+/// Tracer recording a [`StructLog`] for every executed instruction.
+///
+/// The output of [`Self::to_json()`] follows the `structLogs` schema of Geth's `debug_traceTransaction`
+/// so that existing trace analysis tools can consume it. Heap contents are not recorded; only heap bounds are.
+#[derive(Debug, Default)]
+pub struct StructLogTracer {
+    logs: Vec<StructLog>,
+}
+
+impl StructLogTracer {
+    /// Returns all logs recorded so far.
+    pub fn logs(&self) -> &[StructLog] {
+        &self.logs
+    }
+
+    /// Takes all logs recorded so far, leaving the tracer empty.
+    pub fn take_logs(&mut self) -> Vec<StructLog> {
+        std::mem::take(&mut self.logs)
+    }
+
+    /// Serializes recorded logs into a JSON object of the form `{"structLogs":[...]}`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"structLogs\":[");
+        for (i, log) in self.logs.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(&log.to_json());
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl Tracer for StructLogTracer {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        let mut registers = [U256::zero(); 16];
+        for (i, register) in (0_u8..).zip(&mut registers) {
+            *register = state.read_register(i).0;
+        }
+        let depth = state.number_of_callframes();
+        let frame = state.current_frame();
+        self.logs.push(StructLog {
+            pc: frame.program_counter(),
+            op: OP::VALUE,
+            gas: frame.gas(),
+            gas_cost: 0,
+            depth,
+            registers,
+            heap_bound: frame.heap_bound(),
+            aux_heap_bound: frame.aux_heap_bound(),
+            storage: None,
+        });
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        let Some(log) = self.logs.last_mut() else {
+            return ShouldStop::Continue;
+        };
+
+        let depth = state.number_of_callframes();
+        if let Some(index) = depth.checked_sub(log.depth) {
+            // The frame the instruction was executed in; calls push new frames on top of it.
+            let frame = state.callframe(index);
+            log.gas_cost = log.gas.saturating_sub(frame.gas());
+            log.heap_bound = frame.heap_bound();
+            log.aux_heap_bound = frame.aux_heap_bound();
+        }
+
+        if matches!(OP::VALUE, Opcode::StorageRead | Opcode::StorageWrite) {
+            let address = state.current_frame().address();
+            let storage = state
+                .get_storage_state()
+                .filter(|((contract, _), _)| *contract == address)
+                .map(|((_, key), value)| (key, value))
+                .collect();
+            log.storage = Some(storage);
+        }
+        ShouldStop::Continue
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+    use zksync_vm2_interface::ReturnType;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+    };
+
+    #[test]
+    fn struct_logs_are_recorded() {
+        let r0 = Register::new(0);
+        let program = Program::from_raw(
+            vec![
+                Instruction::from_add(
+                    Immediate1(42).into(),
+                    Register2(r0),
+                    Register1(Register::new(1)).into(),
+                    Arguments::new(Predicate::Always, 6, ModeRequirements::none()),
+                    false,
+                    false,
+                ),
+                Instruction::from_ret(
+                    Register1(r0),
+                    None,
+                    Arguments::new(Predicate::Always, 5, ModeRequirements::none()),
+                ),
+            ],
+            vec![],
+        );
+
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            1000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        let mut tracer = StructLogTracer::default();
+        let end = vm.run(&mut world, &mut tracer);
+        assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+
+        let logs = tracer.logs();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].pc, Some(0));
+        assert_eq!(logs[0].op, Opcode::Add);
+        assert_eq!(logs[0].gas, 1000);
+        assert_eq!(logs[0].gas_cost, 6);
+        assert_eq!(logs[0].depth, 1);
+        assert_eq!(logs[1].pc, Some(1));
+        assert_eq!(logs[1].op, Opcode::Ret(ReturnType::Normal));
+        assert_eq!(logs[1].registers[1], 42.into());
+
+        let json = tracer.to_json();
+        assert!(
+            json.starts_with("{\"structLogs\":[{\"pc\":0,\"op\":\"Add\",\"gas\":1000,\"gasCost\":6,\"depth\":1,\"stack\":[\"0x0\""),
+            "{json}"
+        );
+        assert!(json.contains("\"stack\":[\"0x0\",\"0x2a\""), "{json}");
+        assert!(json.ends_with("}]}"), "{json}");
+    }
+}
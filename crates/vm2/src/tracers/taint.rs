@@ -0,0 +1,467 @@
+//! Tracer tracking values derived from calldata.
+
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::{
+    CallframeInterface, GlobalStateInterface, HeapId, Opcode, OpcodeType, ReturnType, Tracer,
+};
+
+use crate::{
+    addressing_modes::{AnyDestination, AnySource, Register, RegisterAndImmediate},
+    decommit::u256_into_address,
+    FatPointer, InstructionInfo,
+};
+
+/// Storage write with a calldata-controlled key or value recorded by [`TaintTracer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaintedStorageWrite {
+    /// Address of the contract writing to its storage.
+    pub address: H160,
+    /// Program counter of the write instruction.
+    pub pc: u16,
+    /// Written storage key.
+    pub key: U256,
+    /// Whether the key is derived from calldata.
+    pub is_key_tainted: bool,
+    /// Whether the written value is derived from calldata.
+    pub is_value_tainted: bool,
+}
+
+/// Far call with a calldata-controlled destination recorded by [`TaintTracer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaintedCall {
+    /// Address of the calling contract.
+    pub caller: H160,
+    /// Program counter of the far call instruction.
+    pub pc: u16,
+    /// Called address.
+    pub target: H160,
+}
+
+/// Tracer tracking values derived from the calldata of the initial frame.
+///
+/// Initially, only the calldata pointer (`r1`) is tainted. Taint propagates through arithmetic and pointer operations,
+/// registers, stack and heaps (with byte granularity); values loaded from heaps via tainted addresses are tainted as well.
+/// Taint does not propagate through storage or flags, so control dependencies are not tracked.
+///
+/// The tracer needs to know operands of executed instructions. For programs created with [`Program::new()`](crate::Program::new()),
+/// instructions are decoded from the code page. For other programs (e.g., ones created from raw instructions),
+/// decoded instructions must be provided using [`Self::with_program()`].
+#[derive(Debug, Default)]
+pub struct TaintTracer {
+    programs: HashMap<H160, Vec<InstructionInfo>>,
+    is_started: bool,
+    registers: [bool; 16],
+    /// Tainted stack slots keyed by the heap ID of the owning frame (which is unique for each far call)
+    /// and the slot index.
+    stack: HashSet<(u32, u16)>,
+    /// Tainted heap bytes keyed by the heap ID and the offset.
+    heaps: HashSet<(u32, u32)>,
+    storage_writes: Vec<TaintedStorageWrite>,
+    calls: Vec<TaintedCall>,
+}
+
+impl TaintTracer {
+    /// Creates a new tracer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provides decoded instructions of the program with the specified code address.
+    #[must_use]
+    pub fn with_program(mut self, code_address: H160, instructions: Vec<InstructionInfo>) -> Self {
+        self.programs.insert(code_address, instructions);
+        self
+    }
+
+    /// Returns storage writes with tainted keys or values recorded so far.
+    pub fn storage_writes(&self) -> &[TaintedStorageWrite] {
+        &self.storage_writes
+    }
+
+    /// Returns far calls to tainted addresses recorded so far.
+    pub fn calls(&self) -> &[TaintedCall] {
+        &self.calls
+    }
+
+    fn current_instruction(
+        &self,
+        frame: &impl CallframeInterface,
+    ) -> Option<(u16, InstructionInfo)> {
+        let pc = frame.program_counter()?;
+        let info = if let Some(instructions) = self.programs.get(&frame.code_address()) {
+            *instructions.get(usize::from(pc))?
+        } else {
+            // The code page of programs created from bytecode is the bytecode itself, with 4 instructions per word.
+            let word = frame.read_contract_code(pc / 4);
+            InstructionInfo::decode(word.0[3 - usize::from(pc % 4)])
+        };
+        Some((pc, info))
+    }
+
+    fn register(&self, register: Register) -> bool {
+        self.registers[usize::from(register.index())]
+    }
+
+    fn set_register(&mut self, register: Register, taint: bool) {
+        let index = usize::from(register.index());
+        // The zero register cannot be written to.
+        if index != 0 {
+            self.registers[index] = taint;
+        }
+    }
+
+    fn is_heap_tainted(&self, heap: HeapId, offset: u32) -> bool {
+        (0..32).any(|i| {
+            self.heaps
+                .contains(&(heap.as_u32(), offset.wrapping_add(i)))
+        })
+    }
+
+    fn set_heap_taint(&mut self, heap: HeapId, offset: u32, taint: bool) {
+        for i in 0..32 {
+            let byte = (heap.as_u32(), offset.wrapping_add(i));
+            if taint {
+                self.heaps.insert(byte);
+            } else {
+                self.heaps.remove(&byte);
+            }
+        }
+    }
+}
+
+/// Current frame data necessary to resolve instruction operands.
+struct Operands<'a, S> {
+    state: &'a S,
+    stack_id: u32,
+    stack_pointer: u16,
+}
+
+impl<S: GlobalStateInterface> Operands<'_, S> {
+    #[allow(clippy::cast_possible_truncation)] // intentional
+    fn stack_address(&self, operand: RegisterAndImmediate) -> u16 {
+        let register = self.state.read_register(operand.register.index()).0;
+        (register.low_u32() as u16).wrapping_add(operand.immediate)
+    }
+
+    fn value(&self, source: AnySource) -> Option<U256> {
+        match source {
+            AnySource::Register1(register) => Some(self.state.read_register(register.0.index()).0),
+            AnySource::Immediate1(value) => Some(value.0.into()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the source is tainted, moving the stack pointer if necessary.
+    fn source_taint(&mut self, tracer: &TaintTracer, source: AnySource) -> bool {
+        let stack_address = match source {
+            AnySource::Register1(register) => return tracer.register(register.0),
+            AnySource::Immediate1(_) | AnySource::CodePage(_) => return false,
+            AnySource::AbsoluteStack(operand) => self.stack_address(operand.0),
+            AnySource::RelativeStack(operand) => self
+                .stack_pointer
+                .wrapping_sub(self.stack_address(operand.0)),
+            AnySource::AdvanceStackPointer(operand) => {
+                self.stack_pointer = self
+                    .stack_pointer
+                    .wrapping_sub(self.stack_address(operand.0));
+                self.stack_pointer
+            }
+        };
+        tracer.stack.contains(&(self.stack_id, stack_address))
+    }
+
+    fn set_destination_taint(
+        &mut self,
+        tracer: &mut TaintTracer,
+        destination: Option<AnyDestination>,
+        taint: bool,
+    ) {
+        let stack_address = match destination {
+            None => return,
+            Some(AnyDestination::Register1(register)) => {
+                tracer.set_register(register.0, taint);
+                return;
+            }
+            Some(AnyDestination::AbsoluteStack(operand)) => self.stack_address(operand.0),
+            Some(AnyDestination::RelativeStack(operand)) => self
+                .stack_pointer
+                .wrapping_sub(self.stack_address(operand.0)),
+            Some(AnyDestination::AdvanceStackPointer(operand)) => {
+                let address = self.stack_pointer;
+                self.stack_pointer = address.wrapping_add(self.stack_address(operand.0));
+                address
+            }
+        };
+        if taint {
+            tracer.stack.insert((self.stack_id, stack_address));
+        } else {
+            tracer.stack.remove(&(self.stack_id, stack_address));
+        }
+    }
+}
+
+impl Tracer for TaintTracer {
+    #[allow(clippy::too_many_lines)]
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if !self.is_started {
+            self.is_started = true;
+            self.registers[1] = true;
+        }
+        // Skipped instructions are reported as `Nop`s; real `Nop`s only move the stack pointer.
+        if OP::VALUE == Opcode::Nop {
+            return;
+        }
+
+        let (pc, info, address, heap, aux_heap, is_near_call, stack_pointer) = {
+            let frame = state.current_frame();
+            let Some((pc, info)) = self.current_instruction(&frame) else {
+                return;
+            };
+            (
+                pc,
+                info,
+                frame.address(),
+                frame.heap(),
+                frame.aux_heap(),
+                frame.is_near_call(),
+                frame.stack_pointer(),
+            )
+        };
+        if info.opcode != Some(OP::VALUE) {
+            return;
+        }
+
+        let mut operands = Operands {
+            state: &*state,
+            stack_id: heap.as_u32(),
+            stack_pointer,
+        };
+        match OP::VALUE {
+            Opcode::Add
+            | Opcode::Sub
+            | Opcode::And
+            | Opcode::Or
+            | Opcode::Xor
+            | Opcode::ShiftLeft
+            | Opcode::ShiftRight
+            | Opcode::RotateLeft
+            | Opcode::RotateRight
+            | Opcode::Mul
+            | Opcode::Div
+            | Opcode::PointerAdd
+            | Opcode::PointerSub
+            | Opcode::PointerPack
+            | Opcode::PointerShrink => {
+                let taint = operands.source_taint(self, info.src1) || self.register(info.src2.0);
+                operands.set_destination_taint(self, info.out, taint);
+                if matches!(OP::VALUE, Opcode::Mul | Opcode::Div) {
+                    self.set_register(info.out2.0, taint);
+                }
+            }
+            Opcode::HeapRead | Opcode::AuxHeapRead | Opcode::PointerRead => {
+                let Some(pointer) = operands.value(info.src1) else {
+                    return;
+                };
+                let pointer_taint = operands.source_taint(self, info.src1);
+                let (heap, offset) = match OP::VALUE {
+                    Opcode::HeapRead => (heap, pointer.low_u32()),
+                    Opcode::AuxHeapRead => (aux_heap, pointer.low_u32()),
+                    _ => {
+                        let pointer = FatPointer::from(pointer);
+                        (
+                            pointer.memory_page,
+                            pointer.start.wrapping_add(pointer.offset),
+                        )
+                    }
+                };
+                let taint = pointer_taint || self.is_heap_tainted(heap, offset);
+                operands.set_destination_taint(self, info.out, taint);
+                if info.flags.increment {
+                    self.set_register(info.out2.0, pointer_taint);
+                }
+            }
+            Opcode::HeapWrite | Opcode::AuxHeapWrite => {
+                let Some(pointer) = operands.value(info.src1) else {
+                    return;
+                };
+                let pointer_taint = operands.source_taint(self, info.src1);
+                let heap = if OP::VALUE == Opcode::HeapWrite {
+                    heap
+                } else {
+                    aux_heap
+                };
+                self.set_heap_taint(heap, pointer.low_u32(), self.register(info.src2.0));
+                if info.flags.increment {
+                    operands.set_destination_taint(self, info.out, pointer_taint);
+                }
+            }
+            Opcode::StorageWrite => {
+                let key = operands.value(info.src1).unwrap_or_default();
+                let is_key_tainted = operands.source_taint(self, info.src1);
+                let is_value_tainted = self.register(info.src2.0);
+                if is_key_tainted || is_value_tainted {
+                    self.storage_writes.push(TaintedStorageWrite {
+                        address,
+                        pc,
+                        key,
+                        is_key_tainted,
+                        is_value_tainted,
+                    });
+                }
+            }
+            Opcode::FarCall(_) => {
+                let abi_taint = operands.source_taint(self, info.src1);
+                if self.register(info.src2.0) {
+                    let target = operands.state.read_register(info.src2.0.index()).0;
+                    self.calls.push(TaintedCall {
+                        caller: address,
+                        pc,
+                        target: u256_into_address(target),
+                    });
+                }
+                // The callee receives calldata in r1 and call flags in r2. r3 to r12 are only kept for system calls,
+                // but the tracer conservatively keeps their taint for all calls.
+                self.registers[1] = abi_taint;
+                self.registers[2] = false;
+                self.registers[13..].fill(false);
+            }
+            Opcode::Ret(return_type) if !is_near_call => {
+                // The caller receives the return value pointer in r1; other registers are cleared.
+                let taint =
+                    return_type != ReturnType::Panic && operands.source_taint(self, info.src1);
+                self.registers = [false; 16];
+                self.registers[1] = taint;
+            }
+            Opcode::Jump
+            | Opcode::This
+            | Opcode::Caller
+            | Opcode::CodeAddress
+            | Opcode::ErgsLeft
+            | Opcode::SP
+            | Opcode::ContextMeta
+            | Opcode::ContextU128
+            | Opcode::Decommit
+            | Opcode::PrecompileCall
+            | Opcode::StorageRead
+            | Opcode::TransientStorageRead => {
+                operands.set_destination_taint(self, info.out, false);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register1, Register2},
+        testonly::{initial_decommit, TestWorld},
+        ExecutionEnd, Instruction, InstructionFlags, ModeRequirements, Predicate, Program,
+        Settings, VirtualMachine,
+    };
+
+    fn info(opcode: Opcode, src1: AnySource, src2: u8, out: u8) -> InstructionInfo {
+        InstructionInfo {
+            opcode: Some(opcode),
+            predicate: Predicate::Always,
+            mode_requirements: ModeRequirements::none(),
+            gas_cost: 0,
+            src1,
+            src2: Register2(Register::new(src2)),
+            out: Some(Register1(Register::new(out)).into()),
+            out2: Register2(Register::new(0)),
+            imm1: 0,
+            imm2: 0,
+            flags: InstructionFlags::default(),
+        }
+    }
+
+    fn register(index: u8) -> Register1 {
+        Register1(Register::new(index))
+    }
+
+    #[test]
+    fn tracking_calldata_taint() {
+        let r0 = Register::new(0);
+        let args = || Arguments::new(Predicate::Always, 6, ModeRequirements::none());
+        let instructions = vec![
+            // r2 = r1 (tainted)
+            Instruction::from_add(
+                register(1).into(),
+                Register2(r0),
+                register(2).into(),
+                args(),
+                false,
+                false,
+            ),
+            // r3 = 7 (not tainted)
+            Instruction::from_add(
+                Immediate1(7).into(),
+                Register2(r0),
+                register(3).into(),
+                args(),
+                false,
+                false,
+            ),
+            Instruction::from_storage_write(register(3), Register2(Register::new(2)), args()),
+            Instruction::from_storage_write(register(3), Register2(Register::new(3)), args()),
+            // heap[7..39] = r2
+            Instruction::from_heap_write(
+                register(3).into(),
+                Register2(Register::new(2)),
+                None,
+                args(),
+                false,
+            ),
+            // r4 = heap[7..39] (tainted)
+            Instruction::from_heap_read(register(3).into(), register(4), None, args()),
+            Instruction::from_storage_write(register(4), Register2(r0), args()),
+            Instruction::from_ret(register(0), None, args()),
+        ];
+        let infos = vec![
+            info(Opcode::Add, register(1).into(), 0, 2),
+            info(Opcode::Add, Immediate1(7).into(), 0, 3),
+            info(Opcode::StorageWrite, register(3).into(), 2, 0),
+            info(Opcode::StorageWrite, register(3).into(), 3, 0),
+            info(Opcode::HeapWrite, register(3).into(), 2, 0),
+            info(Opcode::HeapRead, register(3).into(), 0, 4),
+            info(Opcode::StorageWrite, register(4).into(), 0, 0),
+            info(Opcode::Ret(ReturnType::Normal), register(0).into(), 0, 0),
+        ];
+        let program = Program::from_raw(instructions, vec![]);
+
+        let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = TestWorld::new(&[(address, program)]);
+        let program = initial_decommit(&mut world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[1, 2, 3],
+            100_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+
+        let mut tracer = TaintTracer::new().with_program(address, infos);
+        let end = vm.run(&mut world, &mut tracer);
+        assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+
+        let writes: Vec<_> = tracer
+            .storage_writes()
+            .iter()
+            .map(|write| (write.pc, write.is_key_tainted, write.is_value_tainted))
+            .collect();
+        assert_eq!(writes, [(2, false, true), (6, true, false)]);
+        assert_eq!(tracer.storage_writes()[0].key, 7.into());
+        assert!(tracer.calls().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, ops::Range};
 
 use primitive_types::{H160, U256};
 use zksync_vm2_interface::{
@@ -22,6 +22,9 @@ impl<T: Tracer, W: World<T>> StateInterface for VirtualMachine<T, W> {
     }
 
     fn set_register(&mut self, register: u8, value: U256, is_pointer: bool) {
+        if register == 0 {
+            return;
+        }
         self.state.registers[register as usize] = value;
 
         self.state.register_pointer_flags &= !(1 << register);
@@ -70,6 +73,13 @@ impl<T: Tracer, W: World<T>> StateInterface for VirtualMachine<T, W> {
         panic!("Callframe index out of bounds")
     }
 
+    fn gas_remaining(&mut self) -> u32 {
+        std::iter::once(&self.state.current_frame)
+            .chain(&self.state.previous_frames)
+            .map(Callframe::contained_gas)
+            .fold(0, u32::saturating_add)
+    }
+
     fn read_heap_byte(&self, heap: HeapId, index: u32) -> u8 {
         self.state.heaps[heap].read_byte(index)
     }
@@ -78,6 +88,10 @@ impl<T: Tracer, W: World<T>> StateInterface for VirtualMachine<T, W> {
         self.state.heaps[heap].read_u256(index)
     }
 
+    fn read_heap_range(&self, heap: HeapId, range: Range<u32>) -> Vec<u8> {
+        self.state.heaps[heap].read_range_big_endian(range)
+    }
+
     fn write_heap_u256(&mut self, heap: HeapId, index: u32, value: U256) {
         self.state.heaps.write_u256(heap, index, value);
     }
@@ -243,7 +257,12 @@ impl<T: Tracer, W: World<T>> CallframeInterface for CallframeWrapper<'_, T, W> {
     }
 
     fn read_contract_code(&self, slot: u16) -> U256 {
-        self.frame.program.code_page()[slot as usize]
+        self.frame
+            .program
+            .code_page()
+            .get(usize::from(slot))
+            .copied()
+            .unwrap_or_default()
     }
 
     // The following methods are affected by near calls
@@ -373,12 +392,18 @@ impl<T: Tracer, W: World<T>> StateInterface for VmAndWorld<'_, T, W> {
     fn callframe(&mut self, n: usize) -> impl CallframeInterface + '_ {
         self.vm.callframe(n)
     }
+    fn gas_remaining(&mut self) -> u32 {
+        self.vm.gas_remaining()
+    }
     fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8 {
         self.vm.read_heap_byte(heap, offset)
     }
     fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256 {
         self.vm.read_heap_u256(heap, offset)
     }
+    fn read_heap_range(&self, heap: HeapId, range: Range<u32>) -> Vec<u8> {
+        self.vm.read_heap_range(heap, range)
+    }
     fn write_heap_u256(&mut self, heap: HeapId, offset: u32, value: U256) {
         self.vm.write_heap_u256(heap, offset, value);
     }
@@ -471,6 +496,7 @@ mod test {
                 *counter,
                 false,
                 false,
+                0,
                 HeapId::from_u32_unchecked(5),
                 vm.world_diff.snapshot(),
             );
@@ -1,17 +1,27 @@
-use std::fmt;
+use std::{fmt, iter, mem, sync::Arc};
 
-use primitive_types::H160;
-use zksync_vm2_interface::{opcodes::TypeLevelCallingMode, CallingMode, HeapId, Tracer};
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::{
+    opcodes::TypeLevelCallingMode, CallingMode, HeapId, PanicReason, Tracer,
+};
 
 use crate::{
-    callframe::{Callframe, FrameRemnant},
+    allocator::Allocator,
+    callframe::{Callframe, FrameBufferPool, FrameRemnant},
     decommit::u256_into_address,
-    instruction::ExecutionStatus,
-    stack::StackPool,
+    heap::HeapSnapshot,
+    instruction::{ExecutionStatus, Handler},
+    instruction_handlers::spontaneous_panic,
+    memory::ProgramsInUse,
+    metrics::Metrics,
+    precompiles::PrecompilesOverride,
+    stack::{Stack, StackPool},
     state::{State, StateSnapshot},
     world_diff::{ExternalSnapshot, Snapshot, WorldDiff},
-    ExecutionEnd, Program, World,
+    ExecutionEnd, PanicInfo, Program, RefundPolicy, VirtualMachineBuilder, World,
 };
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::{FatPointer, HistoryStats, SourceLocation};
 
 /// [`VirtualMachine`] settings.
 #[derive(Debug, Clone)]
@@ -25,13 +35,32 @@ pub struct Settings {
 }
 
 /// High-performance out-of-circuit EraVM implementation.
+///
+/// The VM doesn't hold the tracer or the world, so it is `Send` and `Sync` regardless of their types;
+/// e.g., a VM can be created on one thread and executed on another.
 #[derive(Debug)]
 pub struct VirtualMachine<T, W> {
     pub(crate) world_diff: WorldDiff,
     pub(crate) state: State<T, W>,
     pub(crate) settings: Settings,
     pub(crate) stack_pool: StackPool,
+    pub(crate) frame_buffers: FrameBufferPool,
     pub(crate) snapshot: Option<VmSnapshot>,
+    pub(crate) refund_policy: RefundPolicy,
+    /// Whether storage writes and L2-to-L1 messages are charged for pubdata; disabled to match `zk_evm`.
+    pub(crate) charge_for_pubdata: bool,
+    /// Memory limit in bytes; `None` if memory usage is not limited.
+    pub(crate) memory_limit: Option<usize>,
+    /// Programs executed by frames on the call stack. Only tracked if `memory_limit` is set, since memory usage
+    /// is checked before each instruction then; otherwise, it's computed on demand.
+    pub(crate) programs_in_use: Option<ProgramsInUse>,
+    /// Precompiles used instead of ones provided by the world.
+    pub(crate) precompiles: Option<PrecompilesOverride>,
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+    /// Panic recorded by the instruction that has failed, but not yet returned from the frame.
+    pub(crate) pending_panic: Option<PanicInfo>,
+    /// Most recent panic reported to the tracer.
+    pub(crate) last_panic: Option<PanicInfo>,
 }
 
 impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
@@ -43,28 +72,58 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
         calldata: &[u8],
         gas: u32,
         settings: Settings,
+    ) -> Self {
+        Self::with_allocator(address, program, caller, calldata, gas, settings, None)
+    }
+
+    pub(crate) fn with_allocator(
+        address: H160,
+        program: Program<T, W>,
+        caller: H160,
+        calldata: &[u8],
+        gas: u32,
+        settings: Settings,
+        allocator: Option<Arc<dyn Allocator>>,
     ) -> Self {
         let world_diff = WorldDiff::default();
         let world_before_this_frame = world_diff.snapshot();
-        let mut stack_pool = StackPool::default();
+        let mut stack_pool = StackPool::new(allocator.clone());
+        let mut frame_buffers = FrameBufferPool::new(allocator.clone());
+        let mut state = State::new(
+            address,
+            caller,
+            calldata,
+            gas,
+            program,
+            world_before_this_frame,
+            stack_pool.get(),
+            allocator,
+        );
+        frame_buffers.fill(&mut state.current_frame);
 
         Self {
             world_diff,
-            state: State::new(
-                address,
-                caller,
-                calldata,
-                gas,
-                program,
-                world_before_this_frame,
-                stack_pool.get(),
-            ),
+            state,
             settings,
             stack_pool,
+            frame_buffers,
             snapshot: None,
+            refund_policy: RefundPolicy::immediate(),
+            charge_for_pubdata: false,
+            memory_limit: None,
+            programs_in_use: None,
+            precompiles: None,
+            metrics: None,
+            pending_panic: None,
+            last_panic: None,
         }
     }
 
+    /// Creates a builder for a VM instance. This is a less error-prone alternative to [`Self::new()`].
+    pub fn builder() -> VirtualMachineBuilder<T, W> {
+        VirtualMachineBuilder::default()
+    }
+
     /// Provides a reference to the [`World`] diff accumulated by VM execution so far.
     pub fn world_diff(&self) -> &WorldDiff {
         &self.world_diff
@@ -78,13 +137,282 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
         &mut self.world_diff
     }
 
+    /// Marks storage slots as accessed earlier in the batch, so that accessing them is charged at warm rather than
+    /// cold rates. Slots with the `bool` flag set are marked as written, which affects the refund for writing them.
+    /// This is mostly useful in tests, to make gas consumption match executing the same code in a batch.
+    ///
+    /// Like other storage accesses, marking slots is undone by [`Self::rollback()`] if done after [`Self::make_snapshot()`].
+    pub fn warm_up_storage_slots(&mut self, slots: impl IntoIterator<Item = ((H160, U256), bool)>) {
+        for ((contract, key), is_written) in slots {
+            self.world_diff
+                .warm_up_storage_slot(contract, key, is_written);
+        }
+    }
+
+    /// Marks bytecodes with the specified versioned hashes as decommitted earlier in the batch, so that
+    /// calling contracts with these bytecodes doesn't charge for decommitment. Like [`Self::warm_up_storage_slots()`],
+    /// this is mostly useful in tests.
+    pub fn warm_up_bytecodes(&mut self, code_hashes: impl IntoIterator<Item = U256>) {
+        for code_hash in code_hashes {
+            self.world_diff.warm_up_bytecode(code_hash);
+        }
+    }
+
+    /// Returns a copy of the current contents of the specified heap. Snapshots taken at different points of execution
+    /// can be compared using [`heap_diff()`](crate::heap_diff()), e.g. to check which memory a call has touched.
+    /// The snapshot shares heap pages with the VM, which copies a page only when writing to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the heap was never allocated.
+    pub fn heap_snapshot(&self, heap: HeapId) -> HeapSnapshot {
+        HeapSnapshot(self.state.heaps[heap].clone())
+    }
+
+    /// Replaces calldata of the initial frame: writes `calldata` to the calldata heap and sets `r1` to a pointer to it,
+    /// like [`Self::new()`] does. This allows reusing a VM setup with different calldata; it should be called
+    /// before the VM starts executing the initial program.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the VM is not in the initial frame, or if the calldata length doesn't fit into `u32`.
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub fn set_calldata(&mut self, calldata: &[u8]) {
+        assert!(
+            self.state.previous_frames.is_empty(),
+            "calldata can only be set in the initial frame"
+        );
+        let pointer = FatPointer {
+            memory_page: HeapId::FIRST_CALLDATA,
+            offset: 0,
+            start: 0,
+            length: u32::try_from(calldata.len()).expect("calldata length overflow"),
+        };
+        self.state.heaps.replace_calldata(calldata);
+        self.state.registers[1] = pointer.into_u256();
+        self.state.register_pointer_flags |= 1 << 1;
+    }
+
+    /// Writes `words` to consecutive 32-byte slots of the bootloader heap starting from the byte `offset`, e.g. to insert
+    /// a transaction into the bootloader memory between transactions. Writes are recorded like writes made by
+    /// the bootloader, so they are undone by [rolling back](Self::rollback()) a snapshot made before them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the written words don't fit into 32-bit heap addresses.
+    pub fn write_to_bootloader_heap(&mut self, offset: u32, words: &[U256]) {
+        let fits = words
+            .len()
+            .checked_mul(32)
+            .and_then(|len| u32::try_from(len).ok())
+            .and_then(|len| offset.checked_add(len))
+            .is_some();
+        assert!(
+            fits,
+            "bootloader heap write doesn't fit into 32-bit addresses"
+        );
+
+        for (i, &word) in (0_u32..).zip(words) {
+            self.state
+                .heaps
+                .write_u256(HeapId::FIRST, offset + i * 32, word);
+        }
+    }
+
+    /// Returns the gas charged per byte of pubdata produced by storage writes and L2-to-L1 messages.
+    /// This is 0 (i.e., pubdata is free) unless changed by the bootloader or [`Self::set_ergs_per_pubdata_byte()`].
+    /// The rate is only applied if charging is enabled with
+    /// [`VirtualMachineBuilder::charge_for_pubdata()`](crate::VirtualMachineBuilder::charge_for_pubdata()).
+    pub fn ergs_per_pubdata_byte(&self) -> u32 {
+        self.state.ergs_per_pubdata_byte
+    }
+
+    /// Sets the gas charged per byte of pubdata. Like the corresponding kernel instruction,
+    /// this change is undone by [`Self::rollback()`], but not by reverts or panics of individual frames.
+    pub fn set_ergs_per_pubdata_byte(&mut self, value: u32) {
+        self.state.ergs_per_pubdata_byte = value;
+    }
+
+    /// Returns the number of bytes allocated by this VM for heaps, stacks and programs executed by frames
+    /// on the call stack.
+    ///
+    /// Heap pages and stacks freed by returning frames are retained by the VM for reuse and are thus included.
+    /// A program executed by several frames is counted once. Small bookkeeping allocations are not included.
+    pub fn memory_usage(&self) -> usize {
+        let stacks = self.state.previous_frames.len() + 1 + self.stack_pool.len();
+        let program_bytes = match &self.programs_in_use {
+            Some(programs) => programs.bytes(),
+            None => self.programs_on_call_stack().bytes(),
+        };
+        self.state.heaps.allocated_bytes() + stacks * mem::size_of::<Stack>() + program_bytes
+    }
+
+    fn programs_on_call_stack(&self) -> ProgramsInUse {
+        let frames = iter::once(&self.state.current_frame).chain(&self.state.previous_frames);
+        ProgramsInUse::from_programs(frames.map(|frame| &frame.program))
+    }
+
+    /// Returns the memory limit set via [`Self::set_memory_limit()`].
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    /// Sets the limit of [memory usage](Self::memory_usage()) in bytes, or removes the limit if `None` is provided.
+    ///
+    /// The limit is checked when the VM is started or resumed, and after each instruction that may allocate memory
+    /// (heap writes, far calls, decommitments and precompile calls). If it's exceeded, the VM stops with
+    /// [`ExecutionEnd::MemoryLimitExceeded`] before executing the next instruction. The VM state stays consistent,
+    /// so the VM can be resumed after raising the limit.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+        if limit.is_none() {
+            self.programs_in_use = None;
+        } else if self.programs_in_use.is_none() {
+            self.programs_in_use = Some(self.programs_on_call_stack());
+        }
+    }
+
+    /// Returns the source location of the next instruction to be executed in the current frame, according to
+    /// the [source map](Program::with_source_map()) attached to the executed program.
+    ///
+    /// Returns `None` if the program has no source map, or the next instruction is not located in the program
+    /// (e.g., after a panic).
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub fn current_source_location(&self) -> Option<&SourceLocation> {
+        let frame = &self.state.current_frame;
+        let pc = u16::try_from(frame.get_raw_pc()).ok()?;
+        frame.program.instruction(pc)?;
+        frame.program.source_map()?.location(pc)
+    }
+
+    /// Returns information about the most recent frame panic, or `None` if no frame has panicked yet.
+    ///
+    /// The panic is recorded regardless of whether it was handled by a calling frame, so it's not necessarily
+    /// the reason the execution as a whole has failed. If the VM has stopped with [`ExecutionEnd::Panicked`],
+    /// this is the panic of the initial frame. Tracers can observe all panics using [`Tracer::on_panic()`].
+    pub fn last_panic(&self) -> Option<PanicInfo> {
+        self.last_panic
+    }
+
+    /// Records the reason of a panic caused by the instruction preceding the program counter. Instruction handlers
+    /// advance the program counter before executing the instruction logic, so it's the currently executing instruction.
+    ///
+    /// If several reasons are recorded before the frame returns (e.g., a spontaneous panic then running out of gas
+    /// for the panic instruction itself), the first one is kept.
+    pub(crate) fn record_panic(&mut self, reason: PanicReason) {
+        let pc = self.state.current_frame.get_raw_pc() - 1;
+        self.record_panic_at(reason, pc);
+    }
+
+    /// Same as [`Self::record_panic()`], but for the instruction at the specified raw program counter.
+    pub(crate) fn record_panic_at(&mut self, reason: PanicReason, raw_pc: isize) {
+        if self.pending_panic.is_some() {
+            return;
+        }
+        let frame = &self.state.current_frame;
+        let pc = u16::try_from(raw_pc)
+            .ok()
+            .filter(|&pc| frame.program.instruction(pc).is_some());
+        self.pending_panic = Some(PanicInfo {
+            reason,
+            address: frame.code_address,
+            pc,
+        });
+    }
+
+    /// Makes the current frame panic after the currently executing instruction.
+    pub(crate) fn panic_spontaneously(&mut self, reason: PanicReason) {
+        self.record_panic(reason);
+        self.state.current_frame.pc = spontaneous_panic();
+    }
+
+    /// Reports the panic recorded for the frame that has just returned to the tracer.
+    pub(crate) fn report_panic(&mut self, tracer: &mut T) {
+        if let Some(panic) = self.pending_panic.take() {
+            self.last_panic = Some(panic);
+            tracer.on_panic(panic.reason);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_memory_limit_exceeded(&self) -> bool {
+        self.memory_limit
+            .is_some_and(|limit| self.memory_usage() > limit)
+    }
+
+    /// Sets the receiver of metrics reported by this VM, or disables reporting metrics if `None` is provided.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn Metrics>>) {
+        self.metrics = metrics;
+    }
+
+    fn report_metrics(&self, executed_instructions: u64) {
+        if let Some(metrics) = &self.metrics {
+            metrics.instructions_executed(executed_instructions);
+            metrics.heap_allocated(self.state.heaps.allocated_bytes());
+        }
+    }
+
     /// Runs this VM with the specified [`World`] and [`Tracer`] until an end of execution due to a hook, or an error.
     pub fn run(&mut self, world: &mut W, tracer: &mut T) -> ExecutionEnd {
+        if self.metrics.is_some() || cfg!(feature = "invariant_checks") {
+            // Counting instructions slows down execution, so it's only done if necessary.
+            return self.run_with_instruction_limit(world, tracer, &mut u64::MAX);
+        }
+        if self.is_memory_limit_exceeded() {
+            return ExecutionEnd::MemoryLimitExceeded;
+        }
+
         unsafe {
             loop {
-                if let ExecutionStatus::Stopped(end) =
-                    ((*self.state.current_frame.pc).handler)(self, world, tracer)
-                {
+                if let ExecutionStatus::Stopped(end) = self.current_handler()(self, world, tracer) {
+                    return end;
+                }
+            }
+        }
+    }
+
+    /// Same as [`Self::run()`], but executes at most `instruction_budget` instructions. The budget is decreased by
+    /// the number of executed instructions (including ones skipped because of their predicate), so that it can be shared
+    /// among several runs, e.g. when resuming the VM after a hook.
+    ///
+    /// Unlike gas, the budget is not observable by the executed programs. Once it is exhausted, the VM stops
+    /// with [`ExecutionEnd::InstructionLimit`]; the VM state stays consistent, so the VM can be resumed afterwards.
+    pub fn run_with_instruction_limit(
+        &mut self,
+        world: &mut W,
+        tracer: &mut T,
+        instruction_budget: &mut u64,
+    ) -> ExecutionEnd {
+        let initial_budget = *instruction_budget;
+        let end = self.run_with_budget(world, tracer, instruction_budget);
+        self.report_metrics(initial_budget - *instruction_budget);
+        end
+    }
+
+    fn run_with_budget(
+        &mut self,
+        world: &mut W,
+        tracer: &mut T,
+        instruction_budget: &mut u64,
+    ) -> ExecutionEnd {
+        if self.is_memory_limit_exceeded() {
+            return ExecutionEnd::MemoryLimitExceeded;
+        }
+        #[cfg(all(feature = "invariant_checks", not(feature = "single_instruction_test")))]
+        let mut invariant_checker = crate::invariants::InvariantChecker::default();
+        unsafe {
+            loop {
+                if *instruction_budget == 0 {
+                    return ExecutionEnd::InstructionLimit;
+                }
+                *instruction_budget -= 1;
+                #[cfg(all(
+                    feature = "invariant_checks",
+                    not(feature = "single_instruction_test")
+                ))]
+                invariant_checker.check(self);
+
+                if let ExecutionStatus::Stopped(end) = self.current_handler()(self, world, tracer) {
                     return end;
                 }
             }
@@ -104,20 +432,35 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
         gas_limit: u32,
     ) -> Option<(u32, ExecutionEnd)> {
         let minimum_gas = self.state.total_unspent_gas().saturating_sub(gas_limit);
+        if self.is_memory_limit_exceeded() {
+            return Some((
+                self.state.total_unspent_gas() - minimum_gas,
+                ExecutionEnd::MemoryLimitExceeded,
+            ));
+        }
 
+        let mut executed_instructions = 0;
+        #[cfg(all(feature = "invariant_checks", not(feature = "single_instruction_test")))]
+        let mut invariant_checker = crate::invariants::InvariantChecker::default();
         let end = unsafe {
             loop {
-                if let ExecutionStatus::Stopped(end) =
-                    ((*self.state.current_frame.pc).handler)(self, world, tracer)
-                {
-                    break end;
+                executed_instructions += 1;
+                #[cfg(all(
+                    feature = "invariant_checks",
+                    not(feature = "single_instruction_test")
+                ))]
+                invariant_checker.check(self);
+                if let ExecutionStatus::Stopped(end) = self.current_handler()(self, world, tracer) {
+                    break Some(end);
                 }
 
                 if self.state.total_unspent_gas() < minimum_gas {
-                    return None;
+                    break None;
                 }
             }
         };
+        self.report_metrics(executed_instructions);
+        let end = end?;
 
         self.state
             .total_unspent_gas()
@@ -142,6 +485,11 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
             world_snapshot: self.world_diff.external_snapshot(),
             state_snapshot: self.state.snapshot(),
         });
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(
+            tx_number = self.state.transaction_number,
+            "made VM snapshot"
+        );
     }
 
     /// Returns the VM to the state it was in when [`Self::make_snapshot()`] was called.
@@ -163,6 +511,14 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
         self.world_diff.external_rollback(snapshot.world_snapshot);
         self.state.rollback(snapshot.state_snapshot);
         self.delete_history();
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(
+            tx_number = self.state.transaction_number,
+            "rolled back VM snapshot"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.snapshot_rolled_back();
+        }
     }
 
     /// Pops a [previously made](Self::make_snapshot()) snapshot without rolling back to it. This effectively commits
@@ -178,6 +534,32 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
         );
         self.snapshot = None;
         self.delete_history();
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(
+            tx_number = self.state.transaction_number,
+            "popped VM snapshot"
+        );
+    }
+
+    /// Reduces the memory used by the history that allows rolling back the bootloader heaps. Hosts may call this
+    /// periodically (e.g., after each transaction) so that this memory stays bounded by the number of distinct written
+    /// heap offsets rather than growing with the number of heap writes.
+    ///
+    /// If the VM has a snapshot, only the first write to each heap offset since the snapshot is retained; otherwise,
+    /// the heap history is discarded. This doesn't influence the results of [rolling back](Self::rollback()).
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub fn compact_history(&mut self) {
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .map(|snapshot| &snapshot.state_snapshot);
+        self.state.compact_history(snapshot);
+    }
+
+    /// Returns statistics of the history retained to support rolling back the bootloader heaps.
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub fn history_stats(&self) -> HistoryStats {
+        self.state.heaps.history_stats()
     }
 
     /// This must only be called when it is known that the VM cannot be rolled back,
@@ -191,6 +573,27 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
 }
 
 impl<T: Tracer, W> VirtualMachine<T, W> {
+    /// Returns the handler of the instruction that the program counter points to.
+    ///
+    /// # Safety
+    ///
+    /// The program counter must point to an instruction, which holds between executing instructions.
+    #[inline(always)]
+    pub(crate) unsafe fn current_handler(&self) -> Handler<T, W> {
+        let frame = &self.state.current_frame;
+        frame.program.handler(&*frame.pc)
+    }
+
+    /// Credits a refund to the current frame according to the [refund policy](RefundPolicy).
+    pub(crate) fn credit_refund(&mut self, refund: u32) {
+        let frame = &mut self.state.current_frame;
+        if self.refund_policy.is_deferred() {
+            frame.pending_refund = frame.pending_refund.saturating_add(refund);
+        } else {
+            frame.gas = frame.gas.saturating_add(refund);
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn push_frame<M: TypeLevelCallingMode>(
         &mut self,
@@ -200,9 +603,13 @@ impl<T: Tracer, W> VirtualMachine<T, W> {
         exception_handler: u16,
         is_static: bool,
         is_evm_interpreter: bool,
+        shard_id: u8,
         calldata_heap: HeapId,
         world_before_this_frame: Snapshot,
     ) {
+        if let Some(programs) = &mut self.programs_in_use {
+            programs.add(&program);
+        }
         let mut new_frame = Callframe::new(
             if M::VALUE == CallingMode::Delegate {
                 self.state.current_frame.address
@@ -231,6 +638,9 @@ impl<T: Tracer, W> VirtualMachine<T, W> {
             is_evm_interpreter,
             world_before_this_frame,
         );
+        new_frame.shard_id = shard_id;
+        new_frame.caller_shard_id = self.state.current_frame.shard_id;
+        self.frame_buffers.fill(&mut new_frame);
         self.state.context_u128 = 0;
 
         std::mem::swap(&mut new_frame, &mut self.state.current_frame);
@@ -256,10 +666,18 @@ impl<T: Tracer, W> VirtualMachine<T, W> {
                 exception_handler,
                 world_before_this_frame,
                 stack,
+                program,
+                near_calls,
+                heaps_i_am_keeping_alive,
                 ..
             } = frame;
 
             self.stack_pool.recycle(stack);
+            self.frame_buffers
+                .recycle(near_calls, heaps_i_am_keeping_alive);
+            if let Some(programs) = &mut self.programs_in_use {
+                programs.remove(&program);
+            }
 
             self.state
                 .current_frame
@@ -276,6 +694,42 @@ impl<T: Tracer, W> VirtualMachine<T, W> {
     pub(crate) fn start_new_tx(&mut self) {
         self.state.transaction_number = self.state.transaction_number.wrapping_add(1);
         self.world_diff.clear_transient_storage();
+        self.world_diff.clear_storage_read_cache();
+    }
+}
+
+impl<T, W> Clone for VirtualMachine<T, W> {
+    fn clone(&self) -> Self {
+        Self {
+            world_diff: self.world_diff.clone(),
+            state: self.state.clone(),
+            settings: self.settings.clone(),
+            // Pooled stacks and buffers are only reused to avoid allocations, so they don't need to be copied.
+            stack_pool: StackPool::new(self.stack_pool.allocator().cloned()),
+            frame_buffers: FrameBufferPool::new(self.stack_pool.allocator().cloned()),
+            snapshot: self.snapshot.clone(),
+            refund_policy: self.refund_policy,
+            charge_for_pubdata: self.charge_for_pubdata,
+            memory_limit: self.memory_limit,
+            programs_in_use: self.programs_in_use.clone(),
+            precompiles: self.precompiles.clone(),
+            metrics: self.metrics.clone(),
+            pending_panic: self.pending_panic,
+            last_panic: self.last_panic,
+        }
+    }
+}
+
+impl<T, W> Drop for VirtualMachine<T, W> {
+    fn drop(&mut self) {
+        // Near call buffers of active frames are moved to the pool, which releases them to the allocator,
+        // so that they can be reused by other VMs.
+        let frames =
+            std::iter::once(&mut self.state.current_frame).chain(&mut self.state.previous_frames);
+        for frame in frames {
+            self.frame_buffers
+                .recycle(mem::take(&mut frame.near_calls), vec![]);
+        }
     }
 }
 
@@ -288,7 +742,7 @@ impl<T: fmt::Debug, W: fmt::Debug> VirtualMachine<T, W> {
 }
 
 /// Snapshot of a [`VirtualMachine`].
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct VmSnapshot {
     world_snapshot: ExternalSnapshot,
     state_snapshot: StateSnapshot,
@@ -0,0 +1,380 @@
+//! Adapter exposing the VM via an API similar to the `VmInterface` trait of the ZKsync Era server.
+//!
+//! The server drives VMs of all protocol versions through a common trait surface: transactions are pushed into
+//! the bootloader memory, the bootloader is executed until a transaction or the whole batch has ended, and batch-level
+//! snapshots allow to roll back a transaction that shouldn't be included. [`BootloaderVm`] implements this surface
+//! via [`VmInterface`] and [`VmInterfaceHistoryEnabled`] on top of a [`VirtualMachine`] running the bootloader.
+//!
+//! Transactions are pushed as already encoded bootloader memory words, since encoding depends on the server types
+//! and the bootloader version. Likewise, the memory layout of a particular bootloader is described by
+//! [`BootloaderLayout`].
+
+use std::collections::BTreeMap;
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::{L2ToL1Log, Tracer};
+
+use crate::{
+    hooks::{Hook, HookCall, HookLayout},
+    merge_events, ExecutionEnd, MergedEvent, StorageChange, VirtualMachine, World,
+};
+
+/// Bootloader memory layout used by [`BootloaderVm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootloaderLayout {
+    /// Bootloader heap offset at which the first pushed transaction is written. Subsequent transactions are written
+    /// immediately after the previous ones.
+    pub transactions_offset: u32,
+    /// Location of hook parameters.
+    pub hook_params: HookLayout,
+}
+
+/// How far [`VmInterface::execute()`] runs the bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExecutionMode {
+    /// Run until the bootloader signals that the current transaction has ended via [`Hook::TxHasEnded`],
+    /// or until the bootloader stops.
+    OneTx,
+    /// Run until the bootloader stops, i.e. until the whole batch is executed.
+    Batch,
+}
+
+/// Outcome of [`VmInterface::execute()`].
+#[derive(Debug)]
+pub struct VmExecutionResult {
+    /// How the execution has ended. For [`ExecutionMode::OneTx`], this is [`ExecutionEnd::SuspendedOnHook`] with
+    /// [`Hook::TxHasEnded`] unless the bootloader has stopped before that.
+    pub end: ExecutionEnd,
+    /// Known hooks called by the bootloader during the execution, in the order of calls.
+    pub hooks: Vec<HookCall>,
+    /// Events emitted during the execution.
+    pub events: Vec<MergedEvent>,
+    /// L2-to-L1 logs emitted during the execution.
+    pub l2_to_l1_logs: Vec<L2ToL1Log>,
+    /// Storage changes made during the execution.
+    pub storage_changes: BTreeMap<(H160, U256), StorageChange>,
+    /// Gas spent by the bootloader during the execution.
+    pub gas_used: u32,
+}
+
+/// Transaction-level API of a VM driven by the bootloader.
+pub trait VmInterface<T, W> {
+    /// Writes an encoded transaction to the bootloader memory after previously pushed transactions, so that
+    /// the bootloader executes it next. Returns the bootloader heap offset the transaction was written to.
+    fn push_transaction(&mut self, encoded_tx: &[U256]) -> u32;
+
+    /// Executes the bootloader in the specified `mode`, resuming it after hooks that don't end the execution.
+    fn execute(&mut self, world: &mut W, tracer: &mut T, mode: ExecutionMode) -> VmExecutionResult;
+
+    /// Returns the memory provided to the bootloader by [`Self::push_transaction()`], as pairs of 32-byte word
+    /// indices and words, in the order of writes.
+    fn get_bootloader_memory(&self) -> Vec<(usize, U256)>;
+}
+
+/// Batch-level snapshots of a [`VmInterface`].
+pub trait VmInterfaceHistoryEnabled {
+    /// Creates a snapshot of the VM state, including the bootloader memory.
+    fn make_snapshot(&mut self);
+
+    /// Rolls back to the latest snapshot and discards it.
+    fn rollback_to_the_latest_snapshot(&mut self);
+
+    /// Discards the latest snapshot without rolling back to it.
+    fn pop_snapshot_no_rollback(&mut self);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MemorySnapshot {
+    next_tx_offset: u32,
+    memory_len: usize,
+}
+
+/// [`VirtualMachine`] running the bootloader, wrapped in the [`VmInterface`] API.
+///
+/// Like the wrapped VM, the adapter holds at most one snapshot at a time.
+#[derive(Debug)]
+pub struct BootloaderVm<T, W> {
+    vm: VirtualMachine<T, W>,
+    layout: BootloaderLayout,
+    next_tx_offset: u32,
+    memory: Vec<(usize, U256)>,
+    snapshot: Option<MemorySnapshot>,
+}
+
+impl<T: Tracer, W: World<T>> BootloaderVm<T, W> {
+    /// Wraps a VM that is about to start executing the bootloader.
+    pub fn new(vm: VirtualMachine<T, W>, layout: BootloaderLayout) -> Self {
+        Self {
+            vm,
+            layout,
+            next_tx_offset: layout.transactions_offset,
+            memory: vec![],
+            snapshot: None,
+        }
+    }
+
+    /// Returns a reference to the wrapped VM.
+    pub fn vm(&self) -> &VirtualMachine<T, W> {
+        &self.vm
+    }
+
+    /// Returns a mutable reference to the wrapped VM. Snapshots should be managed via [`VmInterfaceHistoryEnabled`]
+    /// rather than the VM, so that they cover the bootloader memory.
+    pub fn vm_mut(&mut self) -> &mut VirtualMachine<T, W> {
+        &mut self.vm
+    }
+
+    /// Unwraps the VM.
+    pub fn into_inner(self) -> VirtualMachine<T, W> {
+        self.vm
+    }
+}
+
+impl<T: Tracer, W: World<T>> VmInterface<T, W> for BootloaderVm<T, W> {
+    /// # Panics
+    ///
+    /// Panics if the transaction doesn't fit into 32-bit bootloader heap addresses.
+    fn push_transaction(&mut self, encoded_tx: &[U256]) -> u32 {
+        let offset = self.next_tx_offset;
+        self.next_tx_offset = encoded_tx
+            .len()
+            .checked_mul(32)
+            .and_then(|len| u32::try_from(len).ok())
+            .and_then(|len| offset.checked_add(len))
+            .expect("transaction doesn't fit into 32-bit addresses");
+        self.vm.write_to_bootloader_heap(offset, encoded_tx);
+
+        let first_word = (offset / 32) as usize;
+        self.memory
+            .extend((first_word..).zip(encoded_tx.iter().copied()));
+        offset
+    }
+
+    fn execute(&mut self, world: &mut W, tracer: &mut T, mode: ExecutionMode) -> VmExecutionResult {
+        let world_snapshot = self.vm.world_diff.snapshot();
+        let gas_before = self.vm.state.total_unspent_gas();
+        let mut hooks = vec![];
+        let end = loop {
+            let end = self.vm.run(world, tracer);
+            let ExecutionEnd::SuspendedOnHook(hook) = end else {
+                break end;
+            };
+            let Some(call) = self.layout.hook_params.decode(hook, &self.vm) else {
+                continue;
+            };
+            let tx_has_ended = call.hook == Hook::TxHasEnded;
+            hooks.push(call);
+            if tx_has_ended && mode == ExecutionMode::OneTx {
+                break end;
+            }
+        };
+
+        let world_diff = &self.vm.world_diff;
+        VmExecutionResult {
+            end,
+            hooks,
+            events: merge_events(world_diff.events_after(&world_snapshot).iter().copied()),
+            l2_to_l1_logs: world_diff.l2_to_l1_logs_after(&world_snapshot).to_vec(),
+            storage_changes: world_diff
+                .get_storage_changes_after(&world_snapshot)
+                .collect(),
+            gas_used: gas_before.saturating_sub(self.vm.state.total_unspent_gas()),
+        }
+    }
+
+    fn get_bootloader_memory(&self) -> Vec<(usize, U256)> {
+        self.memory.clone()
+    }
+}
+
+impl<T: Tracer, W: World<T>> VmInterfaceHistoryEnabled for BootloaderVm<T, W> {
+    /// # Panics
+    ///
+    /// Panics if the VM already has a snapshot, or is not in the bootloader frame.
+    fn make_snapshot(&mut self) {
+        self.vm.make_snapshot();
+        self.snapshot = Some(MemorySnapshot {
+            next_tx_offset: self.next_tx_offset,
+            memory_len: self.memory.len(),
+        });
+    }
+
+    /// # Panics
+    ///
+    /// Panics if there is no snapshot, or the VM is not in the bootloader frame.
+    fn rollback_to_the_latest_snapshot(&mut self) {
+        let snapshot = self
+            .snapshot
+            .take()
+            .expect("`rollback_to_the_latest_snapshot()` called without a snapshot");
+        self.vm.rollback();
+        self.next_tx_offset = snapshot.next_tx_offset;
+        self.memory.truncate(snapshot.memory_len);
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the VM is not in the bootloader frame.
+    fn pop_snapshot_no_rollback(&mut self) {
+        self.vm.pop_snapshot();
+        self.snapshot = None;
+    }
+}
+
+#[cfg(all(test, not(feature = "single_instruction_test")))]
+mod tests {
+    use zkevm_opcode_defs::ethereum_types::Address;
+
+    use super::*;
+    use crate::{
+        addressing_modes::{Arguments, Immediate1, Register, Register1, Register2, SSTORE_COST},
+        testonly::{initial_decommit, TestWorld},
+        Instruction, ModeRequirements, Predicate, Program,
+    };
+
+    const ADDRESS: Address = H160([
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0x01,
+    ]);
+    const HOOK_ADDRESS: u16 = 1_024;
+    const TRANSACTIONS_OFFSET: u16 = 2_048;
+    const FIRST_TX_WORD: usize = TRANSACTIONS_OFFSET as usize / 32;
+
+    /// Bootloader storing each of two transactions (a single word each) to the storage slot equal to
+    /// the transaction index, and signalling the end of every transaction with a hook.
+    fn bootloader() -> Program<(), TestWorld<()>> {
+        let r0 = Register::new(0);
+        let r1 = Register::new(1);
+        let r2 = Register::new(2);
+        let r3 = Register::new(3);
+        let arguments = |gas| Arguments::new(Predicate::Always, gas, ModeRequirements::none());
+        let mut instructions = vec![Instruction::from_add(
+            Immediate1(Hook::TxHasEnded.as_u32().try_into().unwrap()).into(),
+            Register2(r0),
+            Register1(r3).into(),
+            arguments(6),
+            false,
+            false,
+        )];
+        for tx_index in 0..2 {
+            instructions.extend([
+                Instruction::from_heap_read(
+                    Immediate1(TRANSACTIONS_OFFSET + tx_index * 32).into(),
+                    Register1(r1),
+                    None,
+                    arguments(7),
+                ),
+                Instruction::from_add(
+                    Immediate1(tx_index).into(),
+                    Register2(r0),
+                    Register1(r2).into(),
+                    arguments(6),
+                    false,
+                    false,
+                ),
+                Instruction::from_storage_write(
+                    Register1(r2),
+                    Register2(r1),
+                    arguments(SSTORE_COST),
+                ),
+                Instruction::from_heap_write(
+                    Immediate1(HOOK_ADDRESS).into(),
+                    Register2(r3),
+                    None,
+                    arguments(7),
+                    true,
+                ),
+            ]);
+        }
+        instructions.push(Instruction::from_ret(Register1(r0), None, arguments(5)));
+        Program::from_raw(instructions, vec![])
+    }
+
+    fn bootloader_vm() -> (BootloaderVm<(), TestWorld<()>>, TestWorld<()>) {
+        let mut world = TestWorld::new(&[(ADDRESS, bootloader())]);
+        let program = initial_decommit(&mut world, ADDRESS);
+        let vm = VirtualMachine::builder()
+            .address(ADDRESS)
+            .program(program)
+            .gas(1_000_000)
+            .hook_address(HOOK_ADDRESS.into())
+            .build()
+            .unwrap();
+        let layout = BootloaderLayout {
+            transactions_offset: TRANSACTIONS_OFFSET.into(),
+            hook_params: HookLayout::preceding(HOOK_ADDRESS.into()),
+        };
+        (BootloaderVm::new(vm, layout), world)
+    }
+
+    fn stored_value(result: &VmExecutionResult, key: u64) -> Option<U256> {
+        result
+            .storage_changes
+            .get(&(ADDRESS, key.into()))
+            .map(|change| change.after)
+    }
+
+    #[test]
+    fn executing_transactions_one_by_one() {
+        let (mut vm, mut world) = bootloader_vm();
+        assert_eq!(vm.push_transaction(&[11.into()]), 2_048);
+        assert_eq!(vm.push_transaction(&[22.into()]), 2_048 + 32);
+        assert_eq!(
+            vm.get_bootloader_memory(),
+            [(FIRST_TX_WORD, 11.into()), (FIRST_TX_WORD + 1, 22.into())]
+        );
+
+        let tx_has_ended = ExecutionEnd::SuspendedOnHook(Hook::TxHasEnded.as_u32());
+        let result = vm.execute(&mut world, &mut (), ExecutionMode::OneTx);
+        assert_eq!(result.end, tx_has_ended);
+        assert_eq!(result.hooks.len(), 1);
+        assert_eq!(result.hooks[0].hook, Hook::TxHasEnded);
+        assert_eq!(result.storage_changes.len(), 1);
+        assert_eq!(stored_value(&result, 0), Some(11.into()));
+        assert!(result.gas_used >= SSTORE_COST);
+
+        let result = vm.execute(&mut world, &mut (), ExecutionMode::OneTx);
+        assert_eq!(result.end, tx_has_ended);
+        assert_eq!(result.storage_changes.len(), 1);
+        assert_eq!(stored_value(&result, 1), Some(22.into()));
+
+        let result = vm.execute(&mut world, &mut (), ExecutionMode::OneTx);
+        assert_eq!(result.end, ExecutionEnd::ProgramFinished(vec![]));
+        assert!(result.hooks.is_empty());
+        assert!(result.storage_changes.is_empty());
+    }
+
+    #[test]
+    fn executing_batch() {
+        let (mut vm, mut world) = bootloader_vm();
+        vm.push_transaction(&[11.into()]);
+        vm.push_transaction(&[22.into()]);
+
+        let result = vm.execute(&mut world, &mut (), ExecutionMode::Batch);
+        assert_eq!(result.end, ExecutionEnd::ProgramFinished(vec![]));
+        assert_eq!(result.hooks.len(), 2);
+        assert_eq!(stored_value(&result, 0), Some(11.into()));
+        assert_eq!(stored_value(&result, 1), Some(22.into()));
+    }
+
+    #[test]
+    fn rolling_back_transaction() {
+        let (mut vm, mut world) = bootloader_vm();
+        vm.make_snapshot();
+        vm.push_transaction(&[11.into()]);
+        let result = vm.execute(&mut world, &mut (), ExecutionMode::OneTx);
+        assert_eq!(stored_value(&result, 0), Some(11.into()));
+
+        vm.rollback_to_the_latest_snapshot();
+        assert!(vm.get_bootloader_memory().is_empty());
+        assert_eq!(vm.vm().world_diff().get_storage_changes().count(), 0);
+
+        // The replacement transaction is written to the same location and executed from the start.
+        assert_eq!(vm.push_transaction(&[33.into()]), 2_048);
+        vm.make_snapshot();
+        let result = vm.execute(&mut world, &mut (), ExecutionMode::OneTx);
+        assert_eq!(stored_value(&result, 0), Some(33.into()));
+        vm.pop_snapshot_no_rollback();
+        assert_eq!(vm.get_bootloader_memory(), [(FIRST_TX_WORD, 33.into())]);
+    }
+}
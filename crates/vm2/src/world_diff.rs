@@ -1,20 +1,25 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use primitive_types::{H160, U256};
-use zkevm_opcode_defs::system_params::{
-    STORAGE_ACCESS_COLD_READ_COST, STORAGE_ACCESS_COLD_WRITE_COST, STORAGE_ACCESS_WARM_READ_COST,
-    STORAGE_ACCESS_WARM_WRITE_COST,
-};
 use zksync_vm2_interface::{CycleStats, Event, L2ToL1Log, Tracer};
 
 use crate::{
+    decommit::u256_into_address,
+    isa::system_params::{
+        DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW, STORAGE_ACCESS_COLD_READ_COST,
+        STORAGE_ACCESS_COLD_WRITE_COST, STORAGE_ACCESS_WARM_READ_COST,
+        STORAGE_ACCESS_WARM_WRITE_COST,
+    },
+    pubdata::{self, CompressedValue},
     rollback::{Rollback, RollbackableLog, RollbackableMap, RollbackablePod, RollbackableSet},
     StorageInterface, StorageSlot,
 };
 
 /// Pending modifications to the global state that are executed at the end of a block.
 /// In other words, side effects.
-#[derive(Debug, Default)]
+///
+/// This type is `Send` and `Sync`.
+#[derive(Debug, Clone, Default)]
 pub struct WorldDiff {
     // These are rolled back on revert or panic (and when the whole VM is rolled back).
     storage_changes: RollbackableMap<(H160, U256), U256>,
@@ -34,11 +39,29 @@ pub struct WorldDiff {
     read_storage_slots: RollbackableSet<(H160, U256)>,
     written_storage_slots: RollbackableSet<(H160, U256)>,
 
-    // This is never rolled back. It is just a cache to avoid asking these from DB every time.
+    // These are never rolled back. They are just caches to avoid asking these from DB every time.
     storage_initial_values: BTreeMap<(H160, U256), StorageSlot>,
+    /// Storage values read from the world during the current transaction.
+    storage_read_cache: BTreeMap<(H160, U256), U256>,
+    /// Number of storage reads served by `storage_read_cache` instead of the world.
+    saved_storage_reads: u64,
+}
+
+/// Contracts and storage slots accessed by a VM, e.g. to prefetch them or to schedule transactions in parallel.
+/// Returned by [`WorldDiff::access_list()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessList {
+    /// Contracts whose code was looked up, e.g. because they were called. Doesn't include the initial contract
+    /// of the VM since its program is provided when creating the VM.
+    pub contracts: BTreeSet<H160>,
+    /// Storage slots that were read but not written. Includes code lookups for `contracts` in the deployer
+    /// system contract storage.
+    pub read_slots: BTreeSet<(H160, U256)>,
+    /// Storage slots that were written (and possibly read).
+    pub written_slots: BTreeSet<(H160, U256)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct ExternalSnapshot {
     internal_snapshot: Snapshot,
     pub(crate) decommitted_hashes: <RollbackableMap<U256, ()> as Rollback>::Snapshot,
@@ -93,7 +116,25 @@ impl WorldDiff {
         }
 
         self.pubdata_costs.push(0);
-        (self.just_read_storage(world, contract, key), newly_added)
+        (self.read_storage_cached(world, contract, key), newly_added)
+    }
+
+    fn read_storage_cached(
+        &mut self,
+        world: &mut impl StorageInterface,
+        contract: H160,
+        key: U256,
+    ) -> U256 {
+        if let Some(&value) = self.storage_changes.as_ref().get(&(contract, key)) {
+            return value;
+        }
+        if let Some(&value) = self.storage_read_cache.get(&(contract, key)) {
+            self.saved_storage_reads += 1;
+            return value;
+        }
+        let value = world.read_storage_value(contract, key);
+        self.storage_read_cache.insert((contract, key), value);
+        value
     }
 
     /// Reads the value of a storage slot without any extra bookkeeping.
@@ -159,17 +200,56 @@ impl WorldDiff {
         #[allow(clippy::cast_possible_wrap)]
         {
             let pubdata_cost = (update_cost as i32) - (prepaid as i32);
-            self.pubdata.0 += pubdata_cost;
+            // Wrapping so that debug and release builds behave the same.
+            self.pubdata.0 = self.pubdata.0.wrapping_add(pubdata_cost);
             self.storage_refunds.push(refund);
             self.pubdata_costs.push(pubdata_cost);
         }
         refund
     }
 
+    /// Returns all storage slots read or written since the VM was created (or last rolled back).
+    pub(crate) fn accessed_storage_slots(&self) -> impl Iterator<Item = &(H160, U256)> + '_ {
+        self.read_storage_slots.as_ref().iter()
+    }
+
+    /// Returns contracts and storage slots accessed since the VM was created (or last rolled back),
+    /// including accesses made by reverted frames.
+    pub fn access_list(&self) -> AccessList {
+        let deployer_system_contract_address =
+            H160::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        let written_slots = self.written_storage_slots.as_ref().clone();
+        let read_slots = self
+            .read_storage_slots
+            .as_ref()
+            .difference(&written_slots)
+            .copied()
+            .collect();
+        // Code of called contracts is looked up in the deployer storage keyed by the contract address.
+        let contracts = self
+            .read_storage_slots
+            .as_ref()
+            .iter()
+            .filter(|(contract, _)| *contract == deployer_system_contract_address)
+            .map(|&(_, address)| u256_into_address(address))
+            .collect();
+        AccessList {
+            contracts,
+            read_slots,
+            written_slots,
+        }
+    }
+
     pub(crate) fn pubdata(&self) -> i32 {
         self.pubdata.0
     }
 
+    /// Returns the number of storage reads that were served from the VM's cache instead of the [`World`](crate::World).
+    /// Reads of slots written to in the same transaction are not counted since they never need the world.
+    pub fn saved_storage_reads(&self) -> u64 {
+        self.saved_storage_reads
+    }
+
     /// Returns recorded refunds for all storage operations.
     pub fn storage_refunds(&self) -> &[u32] {
         self.storage_refunds.as_ref()
@@ -207,6 +287,13 @@ impl WorldDiff {
             })
     }
 
+    /// Returns the number of pubdata bytes needed to publish each changed storage slot; see [`StorageChange::pubdata_bytes()`].
+    /// Slots are returned in the same order as in [`Self::get_storage_changes()`].
+    pub fn get_storage_changes_pubdata(&self) -> impl Iterator<Item = ((H160, U256), u32)> + '_ {
+        self.get_storage_changes()
+            .map(|(key, change)| (key, change.pubdata_bytes()))
+    }
+
     /// Gets changes for storage slots touched after the specified `snapshot` was created.
     pub fn get_storage_changes_after(
         &self,
@@ -348,9 +435,31 @@ impl WorldDiff {
         self.written_storage_slots.delete_history();
     }
 
+    /// Marks a storage slot as accessed (and optionally written), as if it was accessed earlier in the batch.
+    pub(crate) fn warm_up_storage_slot(&mut self, contract: H160, key: U256, is_written: bool) {
+        self.read_storage_slots.add((contract, key));
+        if is_written {
+            self.written_storage_slots.add((contract, key));
+        }
+    }
+
+    /// Marks a bytecode as decommitted, as if it was decommitted earlier in the batch.
+    pub(crate) fn warm_up_bytecode(&mut self, code_hash: U256) {
+        // Decommitment keys don't include the second byte of the versioned hash (the construction marker).
+        let mut code_hash_bytes = [0; 32];
+        code_hash.to_big_endian(&mut code_hash_bytes);
+        code_hash_bytes[1] = 0;
+        self.decommitted_hashes
+            .insert(U256::from_big_endian(&code_hash_bytes), true);
+    }
+
     pub(crate) fn clear_transient_storage(&mut self) {
         self.transient_storage_changes = RollbackableMap::default();
     }
+
+    pub(crate) fn clear_storage_read_cache(&mut self) {
+        self.storage_read_cache.clear();
+    }
 }
 
 /// Opaque snapshot of a [`WorldDiff`] output by its [eponymous method](WorldDiff::snapshot()).
@@ -377,6 +486,20 @@ pub struct StorageChange {
     pub is_initial: bool,
 }
 
+impl StorageChange {
+    /// Returns the number of pubdata bytes needed to publish this change according to
+    /// the [compression scheme](crate::pubdata). This only makes sense for changes relative to the start of the block,
+    /// such as ones returned by [`WorldDiff::get_storage_changes()`].
+    pub fn pubdata_bytes(&self) -> u32 {
+        pubdata::storage_write_pubdata(self.is_initial, self.before, self.after)
+    }
+
+    /// Returns the compressed encoding of the new slot value.
+    pub fn compressed_value(&self) -> CompressedValue {
+        CompressedValue::new(self.before, self.after)
+    }
+}
+
 const WARM_READ_REFUND: u32 = STORAGE_ACCESS_COLD_READ_COST - STORAGE_ACCESS_WARM_READ_COST;
 const WARM_WRITE_REFUND: u32 = STORAGE_ACCESS_COLD_WRITE_COST - STORAGE_ACCESS_WARM_WRITE_COST;
 const COLD_WRITE_AFTER_WARM_READ_REFUND: u32 = STORAGE_ACCESS_COLD_READ_COST;
@@ -386,7 +509,7 @@ mod tests {
     use proptest::{bits, collection::btree_map, prelude::*};
 
     use super::*;
-    use crate::StorageSlot;
+    use crate::{instruction_handlers::address_into_u256, StorageSlot};
 
     fn test_storage_changes(
         initial_values: &BTreeMap<(H160, U256), StorageSlot>,
@@ -496,6 +619,7 @@ mod tests {
     }
 
     proptest! {
+        #[cfg_attr(miri, ignore)]
         #[test]
         fn storage_changes_work_as_expected(
             initial_values in arbitrary_initial_storage(),
@@ -505,6 +629,7 @@ mod tests {
             test_storage_changes(&initial_values, first_changes, second_changes);
         }
 
+        #[cfg_attr(miri, ignore)]
         #[test]
         fn storage_changes_work_with_constrained_changes(
             initial_values in constrained_initial_storage(),
@@ -515,6 +640,113 @@ mod tests {
         }
     }
 
+    #[test]
+    fn repeated_storage_reads_are_cached() {
+        let contract = H160::repeat_byte(1);
+        let mut world = CountingWorld::default();
+        let mut world_diff = WorldDiff::default();
+
+        for _ in 0..3 {
+            let (value, _) = world_diff.read_storage(&mut world, &mut (), contract, 1.into());
+            assert_eq!(value, 1.into());
+        }
+        assert_eq!(world.value_reads, 1);
+        assert_eq!(world_diff.saved_storage_reads(), 2);
+
+        // Pending writes take precedence over cached values and are rolled back independently.
+        let snapshot = world_diff.snapshot();
+        world_diff.write_storage(&mut world, &mut (), contract, 1.into(), 42.into());
+        let (value, _) = world_diff.read_storage(&mut world, &mut (), contract, 1.into());
+        assert_eq!(value, 42.into());
+        world_diff.rollback(snapshot);
+        let (value, _) = world_diff.read_storage(&mut world, &mut (), contract, 1.into());
+        assert_eq!(value, 1.into());
+        assert_eq!(world.value_reads, 1);
+        assert_eq!(world_diff.saved_storage_reads(), 3);
+
+        world_diff.clear_storage_read_cache();
+        world_diff.read_storage_without_refund(&mut world, &mut (), contract, 1.into());
+        assert_eq!(world.value_reads, 2);
+        assert_eq!(world_diff.saved_storage_reads(), 3);
+    }
+
+    #[test]
+    fn storage_changes_pubdata_is_computed_from_initial_values() {
+        let contract = H160::repeat_byte(1);
+        let mut world = CountingWorld::default();
+        let mut world_diff = WorldDiff::default();
+        // `CountingWorld` slots are filled with their keys and are not initial.
+        world_diff.write_storage(&mut world, &mut (), contract, 1.into(), 2.into());
+        world_diff.write_storage(&mut world, &mut (), contract, 1.into(), 3.into());
+        world_diff.write_storage(&mut world, &mut (), contract, 2.into(), 2.into());
+
+        let pubdata: Vec<_> = world_diff.get_storage_changes_pubdata().collect();
+        assert_eq!(
+            pubdata,
+            [((contract, 1.into()), pubdata::REPEATED_WRITE_KEY_BYTES + 2)]
+        );
+    }
+
+    #[test]
+    fn building_access_list() {
+        let contract = H160::repeat_byte(1);
+        let called_contract = H160::repeat_byte(2);
+        let deployer_system_contract_address =
+            H160::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        let mut world = CountingWorld::default();
+        let mut world_diff = WorldDiff::default();
+
+        world_diff.read_storage(&mut world, &mut (), contract, 1.into());
+        world_diff.read_storage(&mut world, &mut (), contract, 2.into());
+        world_diff.write_storage(&mut world, &mut (), contract, 2.into(), 3.into());
+        // Accesses in rolled back frames are retained.
+        let snapshot = world_diff.snapshot();
+        world_diff.write_storage(&mut world, &mut (), contract, 3.into(), 3.into());
+        world_diff.rollback(snapshot);
+        world_diff.read_storage_without_refund(
+            &mut world,
+            &mut (),
+            deployer_system_contract_address,
+            address_into_u256(called_contract),
+        );
+
+        let access_list = world_diff.access_list();
+        assert_eq!(access_list.contracts, BTreeSet::from([called_contract]));
+        assert_eq!(
+            access_list.read_slots,
+            BTreeSet::from([
+                (contract, 1.into()),
+                (
+                    deployer_system_contract_address,
+                    address_into_u256(called_contract)
+                ),
+            ])
+        );
+        assert_eq!(
+            access_list.written_slots,
+            BTreeSet::from([(contract, 2.into()), (contract, 3.into())])
+        );
+    }
+
+    #[test]
+    fn warming_up_storage_slots() {
+        let contract = H160::repeat_byte(1);
+        let mut world = CountingWorld::default();
+        let mut world_diff = WorldDiff::default();
+        world_diff.warm_up_storage_slot(contract, 1.into(), false);
+        world_diff.warm_up_storage_slot(contract, 2.into(), true);
+
+        let (_, refund) = world_diff.read_storage(&mut world, &mut (), contract, 1.into());
+        assert_eq!(refund, WARM_READ_REFUND);
+        let (_, refund) = world_diff.read_storage(&mut world, &mut (), contract, 3.into());
+        assert_eq!(refund, 0);
+
+        let refund = world_diff.write_storage(&mut world, &mut (), contract, 1.into(), 5.into());
+        assert_eq!(refund, COLD_WRITE_AFTER_WARM_READ_REFUND);
+        let refund = world_diff.write_storage(&mut world, &mut (), contract, 2.into(), 5.into());
+        assert_eq!(refund, WARM_WRITE_REFUND);
+    }
+
     /// Max items in generated initial storage / changes.
     const MAX_ITEMS: usize = 5;
     /// Bit mask for bytes in constrained `U256` / `H160` values.
@@ -564,6 +796,34 @@ mod tests {
         )
     }
 
+    /// Storage where each slot contains its key. Counts value reads.
+    #[derive(Default)]
+    struct CountingWorld {
+        value_reads: usize,
+    }
+
+    impl StorageInterface for CountingWorld {
+        fn read_storage(&mut self, _: H160, key: U256) -> StorageSlot {
+            StorageSlot {
+                value: key,
+                is_write_initial: false,
+            }
+        }
+
+        fn read_storage_value(&mut self, _: H160, key: U256) -> U256 {
+            self.value_reads += 1;
+            key
+        }
+
+        fn cost_of_writing_storage(&mut self, _: StorageSlot, _: U256) -> u32 {
+            0
+        }
+
+        fn is_free_storage_slot(&self, _: &H160, _: &U256) -> bool {
+            false
+        }
+    }
+
     struct NoWorld;
 
     impl StorageInterface for NoWorld {